@@ -0,0 +1,8 @@
+-- Events too large to be sent with `NOTIFY`
+
+create table websocket_events
+(
+    uuid       uuid primary key,
+    event      jsonb       not null,
+    created_at timestamptz not null default now()
+);
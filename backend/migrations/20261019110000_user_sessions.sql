@@ -0,0 +1,11 @@
+-- Open websocket sessions, used to tell the presence of users
+
+create table user_sessions
+(
+    session_id uuid primary key,
+    user_id    uuid        not null references users (uuid) on delete cascade,
+    idle       bool        not null default false,
+    last_seen  timestamptz not null default now()
+);
+
+create index user_sessions_user_id on user_sessions (user_id);
@@ -0,0 +1,7 @@
+alter table users
+    add column display_name      text,
+    add column bio               text,
+    add column pronouns          text,
+    add column timezone          text,
+    add column status_text       text,
+    add column status_expires_at timestamptz;
@@ -0,0 +1,14 @@
+-- Members banned from rooms, and the system messages posted when members are removed
+
+ALTER TYPE message_type ADD VALUE 'room_kick';
+ALTER TYPE message_type ADD VALUE 'room_ban';
+
+create table room_bans
+(
+    room_id    uuid        not null references rooms (uuid) on delete cascade,
+    user_id    uuid        not null references users (uuid) on delete cascade,
+    banned_by  uuid        references users (uuid) on delete set null,
+    reason     text,
+    created_at timestamptz not null default now(),
+    primary key (room_id, user_id)
+);
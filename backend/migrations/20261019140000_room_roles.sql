@@ -0,0 +1,60 @@
+-- Roles replace `has_elevated_permissions`, `permissions` is the bitset of `common::Permissions`
+
+create table room_roles
+(
+    uuid        uuid primary key,
+    room_id     uuid        not null references rooms (uuid) on delete cascade,
+    name        text        not null,
+    permissions bigint      not null default 0,
+    is_default  bool        not null default false,
+    is_admin    bool        not null default false,
+    created_at  timestamptz not null default now(),
+    unique (room_id, name)
+);
+
+create unique index room_roles_default on room_roles (room_id) where is_default;
+create unique index room_roles_admin on room_roles (room_id) where is_admin;
+
+-- Every room has an admin role and a default role for new members
+CREATE OR REPLACE FUNCTION default_roles() RETURNS TRIGGER AS
+$$
+BEGIN
+
+    insert into room_roles (uuid, room_id, name, permissions, is_admin)
+    values (gen_random_uuid(), NEW.uuid, 'Admin', 127, true);
+
+    insert into room_roles (uuid, room_id, name, permissions, is_default)
+    values (gen_random_uuid(), NEW.uuid, 'Member', 3, true);
+
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER default_roles
+    AFTER INSERT
+    ON rooms
+    FOR EACH ROW
+EXECUTE PROCEDURE default_roles();
+
+insert into room_roles (uuid, room_id, name, permissions, is_admin)
+select gen_random_uuid(), uuid, 'Admin', 127, true
+from rooms;
+
+insert into room_roles (uuid, room_id, name, permissions, is_default)
+select gen_random_uuid(), uuid, 'Member', 3, true
+from rooms;
+
+-- Elevated members become admins
+
+alter table room_members
+    add column role_id uuid references room_roles (uuid);
+
+update room_members m
+set role_id = r.uuid
+from room_roles r
+where r.room_id = m.room_id
+  and ((m.has_elevated_permissions and r.is_admin) or (not m.has_elevated_permissions and r.is_default));
+
+alter table room_members
+    alter column role_id set not null,
+    drop column has_elevated_permissions;
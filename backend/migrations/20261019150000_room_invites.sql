@@ -0,0 +1,15 @@
+-- Shareable codes that add whoever redeems them to a room
+
+create table room_invites
+(
+    code       text primary key,
+    room_id    uuid        not null references rooms (uuid) on delete cascade,
+    created_by uuid        references users (uuid) on delete set null,
+    role_id    uuid        references room_roles (uuid) on delete set null,
+    max_uses   int,
+    uses       int         not null default 0,
+    expires_at timestamptz,
+    created_at timestamptz not null default now()
+);
+
+create index room_invites_room_id on room_invites (room_id);
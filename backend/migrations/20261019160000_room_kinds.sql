@@ -0,0 +1,3 @@
+CREATE TYPE room_kind AS ENUM ('room', 'dm', 'group_dm');
+
+ALTER TABLE rooms ADD COLUMN kind room_kind not null default 'room';
@@ -0,0 +1,3 @@
+CREATE TYPE room_visibility AS ENUM ('private', 'public');
+
+ALTER TABLE rooms ADD COLUMN visibility room_visibility not null default 'private';
@@ -0,0 +1,3 @@
+ALTER TYPE message_type ADD VALUE 'room_rename';
+
+ALTER TABLE rooms ADD COLUMN topic text;
@@ -0,0 +1,11 @@
+-- Archived rooms are read-only, deleting a room takes everything in it along
+
+ALTER TABLE rooms ADD COLUMN archived_at timestamptz;
+
+ALTER TABLE messages DROP CONSTRAINT messages_room_fkey;
+ALTER TABLE messages
+    ADD CONSTRAINT messages_room_fkey FOREIGN KEY (room) REFERENCES rooms (uuid) ON DELETE CASCADE;
+
+ALTER TABLE room_members DROP CONSTRAINT room_members_room_id_fkey;
+ALTER TABLE room_members
+    ADD CONSTRAINT room_members_room_id_fkey FOREIGN KEY (room_id) REFERENCES rooms (uuid) ON DELETE CASCADE;
@@ -0,0 +1,41 @@
+-- Rooms have an owner, who can't be removed or demoted. DMs don't have one.
+
+ALTER TABLE rooms
+    ADD COLUMN owner uuid REFERENCES users (uuid) ON DELETE SET NULL;
+
+-- the admin who has been in the room the longest becomes its owner
+UPDATE rooms r
+SET owner = (select m.user_id
+             from room_members m
+                      inner join room_roles ro on ro.uuid = m.role_id
+             where m.room_id = r.uuid
+               and ro.is_admin
+             order by m.joined_at
+             limit 1)
+WHERE r.kind = 'room';
+
+-- Insert room owner in room_member when a room is created.
+-- Triggers run in order of their names, so `default_roles` has made the admin role by now.
+CREATE OR REPLACE FUNCTION owner_as_member() RETURNS TRIGGER AS
+$$
+BEGIN
+
+    IF NEW.owner IS NOT NULL THEN
+        insert into room_members (user_id, room_id, role_id)
+        select NEW.owner, NEW.uuid, ro.uuid
+        from room_roles ro
+        where ro.room_id = NEW.uuid
+          and ro.is_admin;
+    END IF;
+
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+drop trigger if exists owner_as_member on rooms;
+
+CREATE TRIGGER owner_as_member
+    AFTER INSERT
+    ON rooms
+    FOR EACH ROW
+EXECUTE PROCEDURE owner_as_member();
@@ -0,0 +1,32 @@
+-- What members did with their permissions, kept until the room is deleted
+
+create type audit_action as enum (
+    'room_update',
+    'room_archive',
+    'room_unarchive',
+    'ownership_transfer',
+    'member_join',
+    'member_kick',
+    'member_ban',
+    'member_unban',
+    'member_role_update',
+    'role_create',
+    'role_update',
+    'role_delete',
+    'invite_create',
+    'invite_revoke'
+    );
+
+create table audit_log
+(
+    uuid       uuid primary key,
+    room_id    uuid         not null references rooms (uuid) on delete cascade,
+    actor      uuid         not null references users (uuid),
+    -- the member or role the action was done to
+    target     uuid,
+    action     audit_action not null,
+    changes    jsonb        not null default '{}',
+    created_at timestamptz  not null default now()
+);
+
+create index audit_log_room_created_at on audit_log (room_id, created_at desc);
@@ -0,0 +1,7 @@
+-- Seconds members have to wait between their messages, 0 turns slow mode off
+
+ALTER TABLE rooms
+    ADD COLUMN slow_mode int NOT NULL DEFAULT 0 CHECK (slow_mode >= 0);
+
+-- the rate limits look up the latest messages of a user
+CREATE INDEX messages_author_created_at ON messages (author, created_at DESC);
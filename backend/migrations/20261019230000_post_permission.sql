@@ -0,0 +1,6 @@
+-- Who can post in a room, announcement rooms only let their admins post
+
+create type post_permission as enum ('everyone', 'admins');
+
+ALTER TABLE rooms
+    ADD COLUMN post_permission post_permission NOT NULL DEFAULT 'everyone';
@@ -0,0 +1,97 @@
+-- Spaces group rooms into workspaces with their own members and roles.
+-- Their rooms are ordered by `position` in collapsible categories.
+
+create table spaces
+(
+    uuid       uuid primary key,
+    name       text        not null,
+    owner      uuid references users (uuid) on delete set null,
+    created_at timestamptz not null default now()
+);
+
+-- `permissions` is the bitset of `common::Permissions`, like in `room_roles`
+create table space_roles
+(
+    uuid        uuid primary key,
+    space_id    uuid        not null references spaces (uuid) on delete cascade,
+    name        text        not null,
+    permissions bigint      not null default 0,
+    is_default  bool        not null default false,
+    is_admin    bool        not null default false,
+    created_at  timestamptz not null default now(),
+    unique (space_id, name)
+);
+
+create unique index space_roles_default on space_roles (space_id) where is_default;
+create unique index space_roles_admin on space_roles (space_id) where is_admin;
+
+create table space_members
+(
+    space_id  uuid        not null references spaces (uuid) on delete cascade,
+    user_id   uuid        not null references users (uuid) on delete cascade,
+    role_id   uuid        not null references space_roles (uuid),
+    joined_at timestamptz not null default now(),
+    primary key (space_id, user_id)
+);
+
+create table space_categories
+(
+    uuid       uuid primary key,
+    space_id   uuid        not null references spaces (uuid) on delete cascade,
+    name       text        not null,
+    position   int         not null default 0,
+    created_at timestamptz not null default now()
+);
+
+create index space_categories_space on space_categories (space_id);
+
+ALTER TABLE rooms
+    ADD COLUMN space_id    uuid REFERENCES spaces (uuid) ON DELETE CASCADE,
+    ADD COLUMN category_id uuid REFERENCES space_categories (uuid) ON DELETE SET NULL,
+    ADD COLUMN position    int NOT NULL DEFAULT 0;
+
+create index rooms_space on rooms (space_id);
+
+-- Every space has an admin role and a default role for new members
+CREATE OR REPLACE FUNCTION default_space_roles() RETURNS TRIGGER AS
+$$
+BEGIN
+
+    insert into space_roles (uuid, space_id, name, permissions, is_admin)
+    values (gen_random_uuid(), NEW.uuid, 'Admin', 127, true);
+
+    insert into space_roles (uuid, space_id, name, permissions, is_default)
+    values (gen_random_uuid(), NEW.uuid, 'Member', 3, true);
+
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER default_space_roles
+    AFTER INSERT
+    ON spaces
+    FOR EACH ROW
+EXECUTE PROCEDURE default_space_roles();
+
+-- Triggers run in order of their names, so `default_space_roles` has made the admin role by now
+CREATE OR REPLACE FUNCTION space_owner_as_member() RETURNS TRIGGER AS
+$$
+BEGIN
+
+    IF NEW.owner IS NOT NULL THEN
+        insert into space_members (space_id, user_id, role_id)
+        select NEW.uuid, NEW.owner, ro.uuid
+        from space_roles ro
+        where ro.space_id = NEW.uuid
+          and ro.is_admin;
+    END IF;
+
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER space_owner_as_member
+    AFTER INSERT
+    ON spaces
+    FOR EACH ROW
+EXECUTE PROCEDURE space_owner_as_member();
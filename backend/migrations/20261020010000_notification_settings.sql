@@ -0,0 +1,9 @@
+-- How each member wants to be notified about a room, kept server-side so that
+-- every device of the member agrees
+
+create type notification_level as enum ('all', 'mentions', 'nothing');
+
+ALTER TABLE room_members
+    ADD COLUMN notification_level      notification_level NOT NULL DEFAULT 'all',
+    ADD COLUMN muted_until             timestamptz,
+    ADD COLUMN hide_unread_when_muted  boolean            NOT NULL DEFAULT true;
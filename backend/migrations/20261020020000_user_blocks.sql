@@ -0,0 +1,12 @@
+-- Users someone blocked, their messages are collapsed and they can't reach the blocker
+
+create table user_blocks
+(
+    blocker_id uuid        not null references users (uuid) on delete cascade,
+    blocked_id uuid        not null references users (uuid) on delete cascade,
+    created_at timestamptz not null default now(),
+    primary key (blocker_id, blocked_id),
+    check (blocker_id <> blocked_id)
+);
+
+create index user_blocks_blocked_id_idx on user_blocks (blocked_id);
@@ -0,0 +1,28 @@
+-- Messages members reported, triaged by the admins of their room or, for issues
+-- that go beyond it, by instance admins
+
+create type report_scope as enum ('room', 'instance');
+create type report_action as enum ('dismiss', 'delete_message', 'kick', 'ban');
+
+create table reports
+(
+    uuid        uuid primary key,
+    room_id     uuid          not null references rooms (uuid) on delete cascade,
+    -- `null` once the message is deleted, `content` keeps what it said
+    message_id  uuid          references messages (uuid) on delete set null,
+    author_id   uuid          references users (uuid) on delete set null,
+    content     text          not null,
+    reporter_id uuid          references users (uuid) on delete set null,
+    reason      text          not null,
+    scope       report_scope  not null default 'room',
+    created_at  timestamptz   not null default now(),
+    -- set together once the report is resolved
+    action      report_action,
+    resolved_by uuid          references users (uuid) on delete set null,
+    resolved_at timestamptz
+);
+
+create index reports_room_created_at on reports (room_id, created_at desc);
+
+alter type audit_action add value 'message_delete';
+alter type audit_action add value 'report_resolve';
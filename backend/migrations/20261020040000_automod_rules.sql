@@ -0,0 +1,19 @@
+-- Content rules checked against new messages, those without a room apply to all of them
+
+create type automod_rule_kind as enum ('banned_words', 'regex', 'link_allow_list', 'link_deny_list', 'max_mentions');
+create type automod_action as enum ('reject', 'mask', 'flag');
+
+create table automod_rules
+(
+    uuid         uuid primary key,
+    room_id      uuid references rooms (uuid) on delete cascade,
+    kind         automod_rule_kind not null,
+    patterns     text[]            not null default '{}',
+    max_mentions integer,
+    action       automod_action    not null,
+    created_by   uuid              references users (uuid) on delete set null,
+    created_at   timestamptz       not null default now(),
+    check ((kind = 'max_mentions') = (max_mentions is not null))
+);
+
+create index automod_rules_room_id on automod_rules (room_id);
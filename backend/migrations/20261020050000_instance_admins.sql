@@ -0,0 +1,28 @@
+-- Instance admins manage the whole instance: they can suspend and delete users,
+-- delete rooms and force password resets
+
+alter table users
+    add column is_instance_admin       bool not null default false,
+    add column suspended_at            timestamptz,
+    add column suspension_reason       text,
+    add column password_reset_required bool not null default false,
+    -- tokens issued before this don't work anymore
+    add column sessions_revoked_at     timestamptz;
+
+-- deleting a user takes their messages and memberships with them,
+-- what they did in rooms stays in the audit log
+alter table room_members
+    drop constraint room_members_user_id_fkey,
+    add constraint room_members_user_id_fkey
+        foreign key (user_id) references users (uuid) on delete cascade;
+
+alter table messages
+    drop constraint messages_author_fkey,
+    add constraint messages_author_fkey
+        foreign key (author) references users (uuid) on delete cascade;
+
+alter table audit_log
+    alter column actor drop not null,
+    drop constraint audit_log_actor_fkey,
+    add constraint audit_log_actor_fkey
+        foreign key (actor) references users (uuid) on delete set null;
@@ -1,10 +1,13 @@
 use crate::services;
+use chrono::Utc;
+use common::errors::ApiError;
 use common::payloads::JwtToken;
 use common::User;
 use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
 use serde::{Deserialize, Serialize};
 use sqlx::types::Uuid;
 use sqlx::PgConnection;
+use warp::http::StatusCode;
 
 const KEY: &[u8; 6] = b"secret";
 
@@ -13,6 +16,9 @@ pub struct Claims {
     pub subject: String,
     pub company: String,
     pub exp: usize,
+    /// When the token was issued, tokens from before it was added count as the oldest
+    #[serde(default)]
+    pub iat: usize,
 }
 
 pub fn create_jwt(user: &User) -> anyhow::Result<JwtToken> {
@@ -20,6 +26,7 @@ pub fn create_jwt(user: &User) -> anyhow::Result<JwtToken> {
         subject: user.uuid.to_string(),
         company: "waichu".to_owned(),
         exp: 10000000000,
+        iat: Utc::now().timestamp() as usize,
     };
 
     let mut header = Header::new(Algorithm::HS512);
@@ -49,5 +56,46 @@ pub async fn parse_token(db: &mut PgConnection, token: &str) -> anyhow::Result<O
 
     let uuid = Uuid::parse_str(&token_data.claims.subject).unwrap();
 
-    Ok(services::user::get(db, uuid).await?)
+    let user = match services::user::get(&mut *db, uuid).await? {
+        Some(user) => user,
+        None => return Ok(None),
+    };
+    let state = match services::admin::get_state(db, uuid).await? {
+        Some(state) => state,
+        None => return Ok(None),
+    };
+    // before the revocation, so suspended users are told why
+    check_account(&state)?;
+
+    // `iat` only has seconds, so tokens issued in the same second as the revocation still work
+    let is_revoked = matches!(
+        state.sessions_revoked_at,
+        Some(revoked_at) if (token_data.claims.iat as i64) < revoked_at.timestamp()
+    );
+    if is_revoked {
+        return Ok(None);
+    }
+
+    Ok(Some(user))
+}
+
+/// Refuses accounts that can't be used right now, e.g. suspended ones, with a `403 Forbidden`
+pub fn check_account(state: &services::admin::AccountState) -> anyhow::Result<()> {
+    if state.suspended_at.is_some() {
+        let message = match &state.suspension_reason {
+            Some(reason) => format!("this account is suspended: {}", reason),
+            None => "this account is suspended".to_string(),
+        };
+        return Err(ApiError::new_with_message_and_status(&message, StatusCode::FORBIDDEN).into());
+    }
+
+    if state.password_reset_required {
+        return Err(ApiError::new_with_message_and_status(
+            "a password reset is required, choose a new password to sign in",
+            StatusCode::FORBIDDEN,
+        )
+        .into());
+    }
+
+    Ok(())
 }
@@ -1,10 +1,12 @@
-use crate::auth::jwt::create_jwt;
+use crate::auth::jwt::{check_account, create_jwt};
 use crate::auth::BCRYPT_COST;
 use crate::services::user::UserAlreadyExists;
-use crate::utils::{json_body, json_with_status, with_db, with_transaction};
+use crate::utils::{
+    error_reply, from_anyhow, json_body, json_with_status, with_db, with_transaction,
+};
 use crate::{bail_if_err, services};
 use common::errors::ApiError;
-use common::payloads::Credentials;
+use common::payloads::{ChangePassword, Credentials};
 use common::User;
 use sqlx::PgPool;
 use warp::http::StatusCode;
@@ -66,19 +68,82 @@ async fn signin(
         }
     };
 
-    Ok(
-        if bail_if_err!(verify_password(&credentials.password, &user.password)) {
-            let token = bail_if_err!(create_jwt(&user));
+    if !bail_if_err!(verify_password(&credentials.password, &user.password)) {
+        return Ok(ApiError::new_with_message_and_status(
+            "invalid username or password",
+            StatusCode::UNAUTHORIZED,
+        )
+        .into_response());
+    }
 
-            reply::json(&token).into_response()
-        } else {
-            ApiError::new_with_message_and_status(
+    // only told to those who know the password, the user may have been deleted since
+    let state = match bail_if_err!(services::admin::get_state(&mut db, user.uuid).await) {
+        Some(state) => state,
+        None => {
+            return Ok(ApiError::new_with_message_and_status(
                 "invalid username or password",
                 StatusCode::UNAUTHORIZED,
             )
-            .into_response()
-        },
-    )
+            .into_response());
+        }
+    };
+    if let Err(e) = check_account(&state) {
+        return Ok(from_anyhow(e).into_response());
+    }
+
+    let token = bail_if_err!(create_jwt(&user));
+
+    Ok(reply::json(&token).into_response())
+}
+
+/// Changes the password of the user and signs them in, the way to complete a password reset
+async fn change_password(
+    pool: PgPool,
+    data: ChangePassword,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    with_transaction(pool, move |transaction| {
+        Box::pin(async move {
+            if let Err(e) = data.validate() {
+                return Ok(error_reply(StatusCode::BAD_REQUEST, &e));
+            }
+
+            let user = services::user::get_by_username(&mut *transaction, &data.username).await?;
+            let user = match user {
+                Some(user) if verify_password(&data.password, &user.password)? => user,
+                _ => {
+                    return Ok(error_reply(
+                        StatusCode::UNAUTHORIZED,
+                        "invalid username or password",
+                    ))
+                }
+            };
+
+            let state = match services::admin::get_state(&mut *transaction, user.uuid).await? {
+                Some(state) => state,
+                None => {
+                    return Ok(error_reply(
+                        StatusCode::UNAUTHORIZED,
+                        "invalid username or password",
+                    ))
+                }
+            };
+            // a required reset is what this completes, a suspension still stands
+            if state.suspended_at.is_some() {
+                return Ok(error_reply(
+                    StatusCode::FORBIDDEN,
+                    "this account is suspended",
+                ));
+            }
+
+            let password = bcrypt::hash(data.new_password, BCRYPT_COST)?;
+            services::user::set_password(&mut *transaction, &user, &password).await?;
+
+            let token = create_jwt(&user)?;
+
+            Ok(reply::json(&token).into_response())
+        })
+    })
+    .await
 }
 
 pub fn auth(
@@ -92,9 +157,15 @@ pub fn auth(
 
     let signin_route = warp::path!("auth" / "signin")
         .and(warp::post())
-        .and(with_db(pool))
+        .and(with_db(pool.clone()))
         .and(json_body::<Credentials>())
         .and_then(signin);
 
-    signup_route.or(signin_route)
+    let change_password_route = warp::path!("auth" / "password")
+        .and(warp::post())
+        .and(with_db(pool))
+        .and(json_body::<ChangePassword>())
+        .and_then(change_password);
+
+    signup_route.or(signin_route).or(change_password_route)
 }
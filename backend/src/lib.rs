@@ -83,6 +83,61 @@ pub async fn setup_assets_directory() -> anyhow::Result<String> {
     Ok(assets_path)
 }
 
+/// Sets up how websocket events reach the other instances of the backend,
+/// according to the `PUBSUB` environment variable
+pub async fn setup_pubsub(pool: PgPool) -> anyhow::Result<()> {
+    match env::var("PUBSUB").as_deref() {
+        Ok("postgres") => {
+            let pubsub = websocket::pubsub::Postgres::new(pool)
+                .await
+                .context("failed to listen for events")?;
+            websocket::set_pubsub(pubsub).await;
+        }
+        Ok("in-process") | Err(_) => {}
+        Ok(other) => anyhow::bail!("unknown pubsub implementation: {}", other),
+    }
+
+    Ok(())
+}
+
+/// Sets up the websocket heartbeat from the `WS_HEARTBEAT_INTERVAL` and
+/// `WS_HEARTBEAT_TIMEOUT` environment variables
+pub async fn setup_heartbeat() -> anyhow::Result<()> {
+    let config = websocket::heartbeat::HeartbeatConfig::from_env()?;
+    websocket::set_heartbeat_config(config).await;
+    Ok(())
+}
+
+pub async fn setup_rate_limit() -> anyhow::Result<()> {
+    let config = services::rate_limit::MessageRateLimit::from_env()?;
+    services::rate_limit::set_message_rate_limit(config).await;
+    Ok(())
+}
+
+/// Makes the users listed in the `INSTANCE_ADMINS` environment variable, separated by
+/// commas, instance admins. That's how the first one is made, the others can be added
+/// through the admin API.
+pub async fn setup_instance_admins(pool: PgPool) -> anyhow::Result<()> {
+    let usernames = match env::var("INSTANCE_ADMINS") {
+        Ok(usernames) => usernames,
+        Err(_) => return Ok(()),
+    };
+
+    let mut conn = pool.acquire().await?;
+    for username in usernames
+        .split(',')
+        .map(str::trim)
+        .filter(|it| !it.is_empty())
+    {
+        let user = services::user::get_by_username(&mut conn, username)
+            .await?
+            .with_context(|| format!("instance admin `{}` doesn't exist", username))?;
+        services::admin::set_instance_admin(&mut conn, &user, true).await?;
+    }
+
+    Ok(())
+}
+
 pub fn api(pool: PgPool) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
     let prefix = warp::path!("api" / ..);
 
@@ -90,12 +145,36 @@ pub fn api(pool: PgPool) -> impl Filter<Extract = (impl warp::Reply,), Error = R
     let auth = auth::routes(pool.clone());
     let websocket = websocket::route(pool.clone());
     let room = routes::room::routes(pool.clone());
+    let role = routes::role::routes(pool.clone());
+    let invite = routes::invite::routes(pool.clone());
+    let audit_log = routes::audit_log::routes(pool.clone());
+    let space = routes::space::routes(pool.clone());
+    let notification = routes::notification::routes(pool.clone());
+    let report = routes::report::routes(pool.clone());
+    let automod = routes::automod::routes(pool.clone());
     let user = routes::user::routes(pool.clone());
+    let admin = routes::admin::routes(pool.clone());
     let message = routes::message::routes(pool.clone());
     let asset = routes::assets::routes(pool);
 
-    let api =
-        balanced_or_tree!(hello, auth, websocket, room, user, message, asset).recover(handler);
+    let api = balanced_or_tree!(
+        hello,
+        auth,
+        websocket,
+        room,
+        role,
+        invite,
+        audit_log,
+        space,
+        notification,
+        report,
+        automod,
+        user,
+        admin,
+        message,
+        asset
+    )
+    .recover(handler);
     prefix.and(api)
 }
 
@@ -1,7 +1,8 @@
 use anyhow::Context;
 use backend::utils::single_page_application;
 use backend::{
-    balanced_or_tree, debug_boxed, exists, setup_assets_directory, setup_database, setup_logger,
+    balanced_or_tree, debug_boxed, exists, setup_assets_directory, setup_database, setup_heartbeat,
+    setup_instance_admins, setup_logger, setup_pubsub, setup_rate_limit,
 };
 use hyper::Server;
 use std::convert::Infallible;
@@ -43,6 +44,22 @@ async fn main() -> anyhow::Result<()> {
 
     let pool = setup_database().await.context("failed to setup database")?;
 
+    setup_pubsub(pool.clone())
+        .await
+        .context("failed to setup pubsub")?;
+
+    setup_heartbeat()
+        .await
+        .context("failed to setup websocket heartbeat")?;
+
+    setup_rate_limit()
+        .await
+        .context("failed to setup message rate limit")?;
+
+    setup_instance_admins(pool.clone())
+        .await
+        .context("failed to setup instance admins")?;
+
     let dist_dir = env::var("DIST_DIR").context("environment variable `DIST_DIR` not defined")?;
 
     let api = backend::api(pool.clone());
@@ -54,7 +71,7 @@ async fn main() -> anyhow::Result<()> {
     let routes = routes.with(
         warp::cors()
             .allow_any_origin()
-            .allow_methods(vec!["GET", "POST", "OPTIONS"])
+            .allow_methods(vec!["GET", "POST", "PATCH", "DELETE", "OPTIONS"])
             .allow_headers(vec!["authorization", "content-type"]),
     );
 
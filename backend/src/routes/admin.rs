@@ -0,0 +1,544 @@
+use crate::utils::{
+    ensure_instance_admin, error_reply, json_body, json_with_status, with_db, with_transaction,
+    with_transaction_then, AssetExt,
+};
+use crate::{services, value_or_404, websocket};
+use common::payloads::{AdminQuery, CreateAutomodRule, ReportQuery, ResolveReport, SuspendUser};
+use common::{ReportAction, User};
+use sqlx::types::Uuid;
+use sqlx::PgPool;
+use tracing::warn;
+use warp::http::StatusCode;
+use warp::reply::Response;
+use warp::{Filter, Reply};
+
+/// Refuses to act on the admin themselves or on other instance admins, who have to lose
+/// the role first
+fn check_can_moderate(admin: &User, user: &User) -> Option<Response> {
+    if admin.uuid == user.uuid {
+        Some(error_reply(
+            StatusCode::BAD_REQUEST,
+            "you can't do this to your own account",
+        ))
+    } else if user.is_instance_admin {
+        Some(error_reply(
+            StatusCode::FORBIDDEN,
+            "remove their instance admin role first",
+        ))
+    } else {
+        None
+    }
+}
+
+async fn get_stats(pool: PgPool, _admin: User) -> Result<impl warp::Reply, warp::Rejection> {
+    with_transaction(pool, move |conn| {
+        Box::pin(async move {
+            let stats = services::admin::get_stats(&mut *conn).await?;
+
+            Ok(warp::reply::json(&stats).into_response())
+        })
+    })
+    .await
+}
+
+async fn get_users(
+    query: AdminQuery,
+    pool: PgPool,
+    _admin: User,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    if let Err(message) = query.validate() {
+        return Ok(error_reply(StatusCode::BAD_REQUEST, &message));
+    }
+
+    with_transaction(pool, move |conn| {
+        Box::pin(async move {
+            let accounts = services::admin::search_accounts(
+                &mut *conn,
+                query.search.as_deref(),
+                query.offset,
+                query.limit,
+            )
+            .await?;
+
+            Ok(warp::reply::json(&accounts).into_response())
+        })
+    })
+    .await
+    .map(Reply::into_response)
+}
+
+async fn get_user(
+    user: Uuid,
+    pool: PgPool,
+    _admin: User,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    with_transaction(pool, move |conn| {
+        Box::pin(async move {
+            let account = value_or_404!(services::admin::get_account(&mut *conn, user).await?);
+
+            Ok(warp::reply::json(&account).into_response())
+        })
+    })
+    .await
+}
+
+async fn delete_user(
+    user: Uuid,
+    pool: PgPool,
+    admin: User,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let (reply, deleted) = with_transaction_then(pool, move |conn| {
+        Box::pin(async move {
+            let user = match services::user::get(&mut *conn, user).await? {
+                Some(user) => user,
+                None => return Ok((error_reply(StatusCode::NOT_FOUND, "user not found"), None)),
+            };
+            if let Some(reply) = check_can_moderate(&admin, &user) {
+                return Ok((reply, None));
+            }
+
+            services::admin::delete_user(&mut *conn, &user).await?;
+
+            Ok((StatusCode::NO_CONTENT.into_response(), Some(user)))
+        })
+    })
+    .await?;
+
+    if let Some(user) = deleted {
+        services::admin::revoke_sessions(&user, "this account was deleted").await;
+        // a missing file shouldn't keep the user around
+        if let Some(avatar) = user.avatar {
+            if let Err(e) = avatar.delete().await {
+                warn!("failed to delete avatar {}: {}", avatar.uuid, e);
+            }
+        }
+    }
+
+    Ok(reply)
+}
+
+async fn suspend_user(
+    user: Uuid,
+    data: SuspendUser,
+    pool: PgPool,
+    admin: User,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    if let Err(message) = data.validate() {
+        return Ok(error_reply(StatusCode::BAD_REQUEST, &message));
+    }
+
+    let (reply, suspended) = with_transaction_then(pool, move |conn| {
+        Box::pin(async move {
+            let user = match services::user::get(&mut *conn, user).await? {
+                Some(user) => user,
+                None => return Ok((error_reply(StatusCode::NOT_FOUND, "user not found"), None)),
+            };
+            if let Some(reply) = check_can_moderate(&admin, &user) {
+                return Ok((reply, None));
+            }
+
+            let reason = data.reason.as_deref().map(str::trim);
+            let account = services::admin::suspend(&mut *conn, &user, reason).await?;
+
+            Ok((warp::reply::json(&account).into_response(), Some(user)))
+        })
+    })
+    .await?;
+
+    if let Some(user) = suspended {
+        services::admin::revoke_sessions(&user, "this account is suspended").await;
+    }
+
+    Ok(reply)
+}
+
+async fn unsuspend_user(
+    user: Uuid,
+    pool: PgPool,
+    _admin: User,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    with_transaction(pool, move |conn| {
+        Box::pin(async move {
+            let user = value_or_404!(services::user::get(&mut *conn, user).await?);
+
+            let account = services::admin::unsuspend(&mut *conn, &user).await?;
+
+            Ok(warp::reply::json(&account).into_response())
+        })
+    })
+    .await
+}
+
+async fn require_password_reset(
+    user: Uuid,
+    pool: PgPool,
+    admin: User,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let (reply, reset) = with_transaction_then(pool, move |conn| {
+        Box::pin(async move {
+            let user = match services::user::get(&mut *conn, user).await? {
+                Some(user) => user,
+                None => return Ok((error_reply(StatusCode::NOT_FOUND, "user not found"), None)),
+            };
+            if let Some(reply) = check_can_moderate(&admin, &user) {
+                return Ok((reply, None));
+            }
+
+            let account = services::admin::require_password_reset(&mut *conn, &user).await?;
+
+            Ok((warp::reply::json(&account).into_response(), Some(user)))
+        })
+    })
+    .await?;
+
+    if let Some(user) = reset {
+        services::admin::revoke_sessions(&user, "a password reset is required").await;
+    }
+
+    Ok(reply)
+}
+
+async fn add_instance_admin(
+    user: Uuid,
+    pool: PgPool,
+    _admin: User,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    with_transaction(pool, move |conn| {
+        Box::pin(async move {
+            let user = value_or_404!(services::user::get(&mut *conn, user).await?);
+
+            let account = services::admin::set_instance_admin(&mut *conn, &user, true).await?;
+
+            Ok(warp::reply::json(&account).into_response())
+        })
+    })
+    .await
+}
+
+async fn remove_instance_admin(
+    user: Uuid,
+    pool: PgPool,
+    admin: User,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    with_transaction(pool, move |conn| {
+        Box::pin(async move {
+            let user = value_or_404!(services::user::get(&mut *conn, user).await?);
+            // there would be nobody left to give it back otherwise
+            if admin.uuid == user.uuid {
+                return Ok(error_reply(
+                    StatusCode::BAD_REQUEST,
+                    "you can't remove your own instance admin role",
+                ));
+            }
+
+            let account = services::admin::set_instance_admin(&mut *conn, &user, false).await?;
+
+            Ok(warp::reply::json(&account).into_response())
+        })
+    })
+    .await
+}
+
+async fn get_rooms(
+    query: AdminQuery,
+    pool: PgPool,
+    _admin: User,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    if let Err(message) = query.validate() {
+        return Ok(error_reply(StatusCode::BAD_REQUEST, &message));
+    }
+
+    with_transaction(pool, move |conn| {
+        Box::pin(async move {
+            let rooms = services::admin::search_rooms(
+                &mut *conn,
+                query.search.as_deref(),
+                query.offset,
+                query.limit,
+            )
+            .await?;
+
+            Ok(warp::reply::json(&rooms).into_response())
+        })
+    })
+    .await
+    .map(Reply::into_response)
+}
+
+async fn delete_room(
+    room: Uuid,
+    pool: PgPool,
+    _admin: User,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let (reply, (icon, deleted)) = with_transaction_then(pool, move |conn| {
+        Box::pin(async move {
+            let room = match services::room::get(&mut *conn, room).await? {
+                Some(room) => room,
+                None => {
+                    let reply = error_reply(StatusCode::NOT_FOUND, "room not found");
+                    return Ok((reply, (None, None)));
+                }
+            };
+
+            let deleted = services::room::delete(&mut *conn, &room).await?;
+
+            Ok((
+                StatusCode::NO_CONTENT.into_response(),
+                (room.icon, Some(deleted)),
+            ))
+        })
+    })
+    .await?;
+    websocket::send_deferred(deleted).await;
+
+    // a missing file shouldn't keep the room around
+    if let Some(icon) = icon {
+        if let Err(e) = icon.delete().await {
+            warn!("failed to delete icon {}: {}", icon.uuid, e);
+        }
+    }
+
+    Ok(reply)
+}
+
+async fn get_reports(
+    query: ReportQuery,
+    pool: PgPool,
+    _admin: User,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    with_transaction(pool, move |conn| {
+        Box::pin(async move {
+            let reports = services::report::get_for_instance(&mut *conn, query.resolved).await?;
+
+            Ok(warp::reply::json(&reports).into_response())
+        })
+    })
+    .await
+}
+
+/// Closes a report escalated to instance admins, who can only act on the message itself.
+///
+/// Authors who need more than that can be suspended.
+async fn resolve_report(
+    report: Uuid,
+    data: ResolveReport,
+    pool: PgPool,
+    admin: User,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    with_transaction(pool, move |conn| {
+        Box::pin(async move {
+            let report = value_or_404!(services::report::get_instance(&mut *conn, report).await?);
+            if report.is_resolved() {
+                return Ok(error_reply(
+                    StatusCode::CONFLICT,
+                    "this report was already resolved",
+                ));
+            }
+
+            match data.action {
+                ReportAction::Dismiss => {}
+                ReportAction::DeleteMessage => {
+                    let room = services::room::get(&mut *conn, report.room).await?;
+                    // it may have been deleted since, which is what was asked anyway
+                    if let (Some(room), Some(message)) = (room, report.message) {
+                        let message = services::message::get(&mut *conn, &room, message).await?;
+                        if let Some(message) = message {
+                            services::message::delete(&mut *conn, &message, &admin).await?;
+                        }
+                    }
+                }
+                ReportAction::Kick | ReportAction::Ban => {
+                    return Ok(error_reply(
+                        StatusCode::BAD_REQUEST,
+                        "kicks and bans are up to the admins of the room, suspend the author instead",
+                    ));
+                }
+            }
+
+            let report =
+                services::report::resolve(&mut *conn, &report, data.action, &admin).await?;
+
+            Ok(warp::reply::json(&report).into_response())
+        })
+    })
+    .await
+}
+
+async fn get_automod_rules(
+    pool: PgPool,
+    _admin: User,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    with_transaction(pool, move |conn| {
+        Box::pin(async move {
+            let rules = services::automod::get_all(&mut *conn, None).await?;
+
+            Ok(warp::reply::json(&rules).into_response())
+        })
+    })
+    .await
+}
+
+async fn create_automod_rule(
+    data: CreateAutomodRule,
+    pool: PgPool,
+    admin: User,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let valid = data
+        .validate()
+        .and_then(|_| services::automod::validate_regexes(&data));
+    if let Err(message) = valid {
+        return Ok(error_reply(StatusCode::BAD_REQUEST, &message));
+    }
+
+    with_transaction(pool, move |conn| {
+        Box::pin(async move {
+            let rule = services::automod::create(&mut *conn, None, &data, Some(&admin)).await?;
+
+            Ok(json_with_status(StatusCode::CREATED, &rule))
+        })
+    })
+    .await
+    .map(Reply::into_response)
+}
+
+async fn delete_automod_rule(
+    rule: Uuid,
+    pool: PgPool,
+    _admin: User,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    with_transaction(pool, move |conn| {
+        Box::pin(async move {
+            let rule = value_or_404!(
+                services::automod::get(&mut *conn, None, rule).await?,
+                "this rule doesn't exist"
+            );
+            services::automod::delete(&mut *conn, &rule).await?;
+
+            Ok(StatusCode::NO_CONTENT.into_response())
+        })
+    })
+    .await
+}
+
+pub fn routes(
+    db: PgPool,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    let get_stats_route = warp::path!("admin" / "stats")
+        .and(warp::get())
+        .and(with_db(db.clone()))
+        .and(ensure_instance_admin(db.clone()))
+        .and_then(get_stats);
+
+    let get_users_route = warp::path!("admin" / "users")
+        .and(warp::get())
+        .and(warp::query::<AdminQuery>())
+        .and(with_db(db.clone()))
+        .and(ensure_instance_admin(db.clone()))
+        .and_then(get_users);
+
+    let get_user_route = warp::path!("admin" / "users" / Uuid)
+        .and(warp::get())
+        .and(with_db(db.clone()))
+        .and(ensure_instance_admin(db.clone()))
+        .and_then(get_user);
+
+    let delete_user_route = warp::path!("admin" / "users" / Uuid)
+        .and(warp::delete())
+        .and(with_db(db.clone()))
+        .and(ensure_instance_admin(db.clone()))
+        .and_then(delete_user);
+
+    let suspend_user_route = warp::path!("admin" / "users" / Uuid / "suspension")
+        .and(warp::put())
+        .and(json_body::<SuspendUser>())
+        .and(with_db(db.clone()))
+        .and(ensure_instance_admin(db.clone()))
+        .and_then(suspend_user);
+
+    let unsuspend_user_route = warp::path!("admin" / "users" / Uuid / "suspension")
+        .and(warp::delete())
+        .and(with_db(db.clone()))
+        .and(ensure_instance_admin(db.clone()))
+        .and_then(unsuspend_user);
+
+    let password_reset_route = warp::path!("admin" / "users" / Uuid / "password-reset")
+        .and(warp::post())
+        .and(with_db(db.clone()))
+        .and(ensure_instance_admin(db.clone()))
+        .and_then(require_password_reset);
+
+    let add_instance_admin_route = warp::path!("admin" / "users" / Uuid / "instance-admin")
+        .and(warp::put())
+        .and(with_db(db.clone()))
+        .and(ensure_instance_admin(db.clone()))
+        .and_then(add_instance_admin);
+
+    let remove_instance_admin_route = warp::path!("admin" / "users" / Uuid / "instance-admin")
+        .and(warp::delete())
+        .and(with_db(db.clone()))
+        .and(ensure_instance_admin(db.clone()))
+        .and_then(remove_instance_admin);
+
+    let get_rooms_route = warp::path!("admin" / "rooms")
+        .and(warp::get())
+        .and(warp::query::<AdminQuery>())
+        .and(with_db(db.clone()))
+        .and(ensure_instance_admin(db.clone()))
+        .and_then(get_rooms);
+
+    let delete_room_route = warp::path!("admin" / "rooms" / Uuid)
+        .and(warp::delete())
+        .and(with_db(db.clone()))
+        .and(ensure_instance_admin(db.clone()))
+        .and_then(delete_room);
+
+    let get_reports_route = warp::path!("admin" / "reports")
+        .and(warp::get())
+        .and(warp::query::<ReportQuery>())
+        .and(with_db(db.clone()))
+        .and(ensure_instance_admin(db.clone()))
+        .and_then(get_reports);
+
+    let resolve_report_route = warp::path!("admin" / "reports" / Uuid / "resolve")
+        .and(warp::post())
+        .and(json_body::<ResolveReport>())
+        .and(with_db(db.clone()))
+        .and(ensure_instance_admin(db.clone()))
+        .and_then(resolve_report);
+
+    let get_automod_rules_route = warp::path!("admin" / "automod-rules")
+        .and(warp::get())
+        .and(with_db(db.clone()))
+        .and(ensure_instance_admin(db.clone()))
+        .and_then(get_automod_rules);
+
+    let create_automod_rule_route = warp::path!("admin" / "automod-rules")
+        .and(warp::post())
+        .and(json_body::<CreateAutomodRule>())
+        .and(with_db(db.clone()))
+        .and(ensure_instance_admin(db.clone()))
+        .and_then(create_automod_rule);
+
+    let delete_automod_rule_route = warp::path!("admin" / "automod-rules" / Uuid)
+        .and(warp::delete())
+        .and(with_db(db.clone()))
+        .and(ensure_instance_admin(db))
+        .and_then(delete_automod_rule);
+
+    get_stats_route
+        .or(get_users_route)
+        .or(get_user_route)
+        .or(delete_user_route)
+        .or(suspend_user_route)
+        .or(unsuspend_user_route)
+        .or(password_reset_route)
+        .or(add_instance_admin_route)
+        .or(remove_instance_admin_route)
+        .or(get_rooms_route)
+        .or(delete_room_route)
+        .or(get_reports_route)
+        .or(resolve_report_route)
+        .or(get_automod_rules_route)
+        .or(create_automod_rule_route)
+        .or(delete_automod_rule_route)
+}
@@ -0,0 +1,44 @@
+use crate::services::permissions;
+use crate::utils::{ensure_authorized, error_reply, with_db, with_transaction};
+use crate::{services, value_or_404};
+use common::payloads::AuditLogQuery;
+use common::User;
+use sqlx::types::Uuid;
+use sqlx::PgPool;
+use warp::http::StatusCode;
+use warp::{Filter, Reply};
+
+async fn get_audit_log(
+    room: Uuid,
+    query: AuditLogQuery,
+    pool: PgPool,
+    user: User,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    if let Err(message) = query.validate() {
+        return Ok(error_reply(StatusCode::BAD_REQUEST, &message));
+    }
+
+    with_transaction(pool, move |conn| {
+        Box::pin(async move {
+            let room = value_or_404!(services::room::get(&mut *conn, room).await?);
+            permissions::check_admin(&mut *conn, &room, &user).await?;
+
+            let entries = services::audit_log::get(&mut *conn, &room, &query).await?;
+
+            Ok(warp::reply::json(&entries).into_response())
+        })
+    })
+    .await
+    .map(Reply::into_response)
+}
+
+pub fn routes(
+    db: PgPool,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path!("rooms" / Uuid / "audit-log")
+        .and(warp::get())
+        .and(warp::query::<AuditLogQuery>())
+        .and(with_db(db.clone()))
+        .and(ensure_authorized(db))
+        .and_then(get_audit_log)
+}
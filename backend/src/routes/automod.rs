@@ -0,0 +1,105 @@
+use crate::services::permissions;
+use crate::utils::{
+    ensure_authorized, error_reply, json_body, json_with_status, with_db, with_transaction,
+};
+use crate::{services, value_or_404};
+use common::payloads::CreateAutomodRule;
+use common::{Permissions, User};
+use sqlx::types::Uuid;
+use sqlx::PgPool;
+use warp::http::StatusCode;
+use warp::{Filter, Reply};
+
+async fn get_rules(
+    room: Uuid,
+    pool: PgPool,
+    user: User,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    with_transaction(pool, move |conn| {
+        Box::pin(async move {
+            let room = value_or_404!(services::room::get(&mut *conn, room).await?);
+            permissions::check(&mut *conn, &room, &user, Permissions::MANAGE_ROOM).await?;
+
+            let rules = services::automod::get_all(&mut *conn, Some(&room)).await?;
+
+            Ok(warp::reply::json(&rules).into_response())
+        })
+    })
+    .await
+}
+
+async fn create_rule(
+    room: Uuid,
+    data: CreateAutomodRule,
+    pool: PgPool,
+    user: User,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let valid = data
+        .validate()
+        .and_then(|_| services::automod::validate_regexes(&data));
+    if let Err(message) = valid {
+        return Ok(error_reply(StatusCode::BAD_REQUEST, &message));
+    }
+
+    with_transaction(pool, move |conn| {
+        Box::pin(async move {
+            let room = value_or_404!(services::room::get(&mut *conn, room).await?);
+            permissions::check(&mut *conn, &room, &user, Permissions::MANAGE_ROOM).await?;
+
+            let rule =
+                services::automod::create(&mut *conn, Some(&room), &data, Some(&user)).await?;
+
+            Ok(json_with_status(StatusCode::CREATED, &rule))
+        })
+    })
+    .await
+    .map(Reply::into_response)
+}
+
+async fn delete_rule(
+    room: Uuid,
+    rule: Uuid,
+    pool: PgPool,
+    user: User,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    with_transaction(pool, move |conn| {
+        Box::pin(async move {
+            let room = value_or_404!(services::room::get(&mut *conn, room).await?);
+            permissions::check(&mut *conn, &room, &user, Permissions::MANAGE_ROOM).await?;
+
+            let rule = value_or_404!(
+                services::automod::get(&mut *conn, Some(&room), rule).await?,
+                "this rule doesn't exist in the room"
+            );
+            services::automod::delete(&mut *conn, &rule).await?;
+
+            Ok(StatusCode::NO_CONTENT.into_response())
+        })
+    })
+    .await
+}
+
+pub fn routes(
+    db: PgPool,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    let get_rules_route = warp::path!("rooms" / Uuid / "automod-rules")
+        .and(warp::get())
+        .and(with_db(db.clone()))
+        .and(ensure_authorized(db.clone()))
+        .and_then(get_rules);
+
+    let create_rule_route = warp::path!("rooms" / Uuid / "automod-rules")
+        .and(warp::post())
+        .and(json_body::<CreateAutomodRule>())
+        .and(with_db(db.clone()))
+        .and(ensure_authorized(db.clone()))
+        .and_then(create_rule);
+
+    let delete_rule_route = warp::path!("rooms" / Uuid / "automod-rules" / Uuid)
+        .and(warp::delete())
+        .and(with_db(db.clone()))
+        .and(ensure_authorized(db))
+        .and_then(delete_rule);
+
+    get_rules_route.or(create_rule_route).or(delete_rule_route)
+}
@@ -0,0 +1,188 @@
+use crate::services::permissions;
+use crate::utils::{
+    ensure_authorized, error_reply, json_body, json_with_status, with_db, with_transaction,
+    with_transaction_deferred,
+};
+use crate::{services, value_or_404};
+use common::payloads::CreateInvite;
+use common::{Permissions, RoomKind, User, MAX_GROUP_DM_MEMBERS};
+use sqlx::types::Uuid;
+use sqlx::PgPool;
+use warp::http::StatusCode;
+use warp::{Filter, Reply};
+
+async fn create_invite(
+    room: Uuid,
+    data: CreateInvite,
+    pool: PgPool,
+    user: User,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    if let Err(message) = data.validate() {
+        return Ok(error_reply(StatusCode::BAD_REQUEST, &message));
+    }
+
+    with_transaction(pool, move |conn| {
+        Box::pin(async move {
+            let room = value_or_404!(services::room::get(&mut *conn, room).await?);
+            permissions::check(&mut *conn, &room, &user, Permissions::INVITE).await?;
+            if room.kind == RoomKind::Dm {
+                return Ok(error_reply(
+                    StatusCode::BAD_REQUEST,
+                    "DMs can't have invites, start a group DM instead",
+                ));
+            }
+
+            if let Some(role) = data.role {
+                let role = value_or_404!(
+                    services::role::get(&mut *conn, &room, role).await?,
+                    "this role doesn't exist in the room"
+                );
+                permissions::check_can_assign(&mut *conn, &room, &user, &role).await?;
+            }
+
+            let invite = services::invite::create(&mut *conn, &room, &user, &data).await?;
+
+            Ok(json_with_status(StatusCode::CREATED, &invite))
+        })
+    })
+    .await
+    .map(Reply::into_response)
+}
+
+async fn get_invites(
+    room: Uuid,
+    pool: PgPool,
+    user: User,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    with_transaction(pool, move |conn| {
+        Box::pin(async move {
+            let room = value_or_404!(services::room::get(&mut *conn, room).await?);
+            permissions::check(&mut *conn, &room, &user, Permissions::MANAGE_ROOM).await?;
+
+            let invites = services::invite::get_all(&mut *conn, &room).await?;
+
+            Ok(warp::reply::json(&invites).into_response())
+        })
+    })
+    .await
+}
+
+async fn delete_invite(
+    room: Uuid,
+    code: String,
+    pool: PgPool,
+    user: User,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    with_transaction(pool, move |conn| {
+        Box::pin(async move {
+            let room = value_or_404!(services::room::get(&mut *conn, room).await?);
+            permissions::check(&mut *conn, &room, &user, Permissions::MANAGE_ROOM).await?;
+
+            if !services::invite::delete(&mut *conn, &room, &code, &user).await? {
+                return Ok(error_reply(StatusCode::NOT_FOUND, "invite not found"));
+            }
+
+            Ok(StatusCode::NO_CONTENT.into_response())
+        })
+    })
+    .await
+}
+
+async fn redeem_invite(
+    code: String,
+    pool: PgPool,
+    user: User,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    with_transaction_deferred(pool, move |conn, messages| {
+        Box::pin(async move {
+            let invite = value_or_404!(
+                services::invite::get(&mut *conn, &code).await?,
+                "invite not found"
+            );
+            let room = value_or_404!(services::room::get(&mut *conn, invite.room).await?);
+
+            // already being a member doesn't use the invite up
+            if services::room::user_in_room(&mut *conn, &room, &user).await? {
+                return Ok(warp::reply::json(&room).into_response());
+            }
+
+            match room.kind {
+                RoomKind::Room => {}
+                RoomKind::Dm => {
+                    return Ok(error_reply(
+                        StatusCode::BAD_REQUEST,
+                        "members can't be added to a DM",
+                    ))
+                }
+                RoomKind::GroupDm => {
+                    let members = services::room::get_member_ids(&mut *conn, &room).await?;
+                    if members.len() >= MAX_GROUP_DM_MEMBERS {
+                        return Ok(error_reply(
+                            StatusCode::BAD_REQUEST,
+                            &format!(
+                                "group DMs can have at most {} members",
+                                MAX_GROUP_DM_MEMBERS
+                            ),
+                        ));
+                    }
+                }
+            }
+
+            if !services::invite::consume(&mut *conn, &code).await? {
+                return Ok(error_reply(StatusCode::GONE, "this invite has expired"));
+            }
+
+            // the role may have been deleted since
+            let role = match invite.role {
+                Some(role) => services::role::get(&mut *conn, &room, role).await?,
+                None => None,
+            };
+            let role = match role {
+                Some(role) => role,
+                None => services::role::get_default(&mut *conn, &room).await?,
+            };
+            let (_, joined) = services::room::join(&mut *conn, &room, &user, &role, &user).await?;
+            messages.push(joined);
+            if room.kind == RoomKind::GroupDm {
+                services::room::refresh_dm_name(&mut *conn, &room).await?;
+            }
+
+            Ok(json_with_status(StatusCode::CREATED, &room))
+        })
+    })
+    .await
+}
+
+pub fn routes(
+    db: PgPool,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    let create_invite_route = warp::path!("rooms" / Uuid / "invites")
+        .and(warp::post())
+        .and(json_body::<CreateInvite>())
+        .and(with_db(db.clone()))
+        .and(ensure_authorized(db.clone()))
+        .and_then(create_invite);
+
+    let get_invites_route = warp::path!("rooms" / Uuid / "invites")
+        .and(warp::get())
+        .and(with_db(db.clone()))
+        .and(ensure_authorized(db.clone()))
+        .and_then(get_invites);
+
+    let delete_invite_route = warp::path!("rooms" / Uuid / "invites" / String)
+        .and(warp::delete())
+        .and(with_db(db.clone()))
+        .and(ensure_authorized(db.clone()))
+        .and_then(delete_invite);
+
+    let redeem_invite_route = warp::path!("invites" / String)
+        .and(warp::post())
+        .and(with_db(db.clone()))
+        .and(ensure_authorized(db))
+        .and_then(redeem_invite);
+
+    create_invite_route
+        .or(get_invites_route)
+        .or(delete_invite_route)
+        .or(redeem_invite_route)
+}
@@ -1,10 +1,11 @@
 use crate::services;
+use crate::services::permissions;
 use crate::utils::{
     ensure_authorized, error_reply, json_body, json_with_status, with_db, with_transaction,
 };
 use crate::value_or_404;
 use common::payloads::CreateMessage;
-use common::{Message, User};
+use common::{Message, Permissions, RoomKind, User};
 use sqlx::types::Uuid;
 use sqlx::PgPool;
 use warp::http::StatusCode;
@@ -25,12 +26,25 @@ async fn create_message(
                 ));
             }
             let room = value_or_404!(services::room::get(conn, room_id).await?);
-            if !services::room::user_in_room(conn, &room, &user).await? {
+            permissions::check_can_post(conn, &room, &user).await?;
+            if room.is_archived() {
                 return Ok(error_reply(
                     StatusCode::FORBIDDEN,
-                    "you do not have permission to message here",
+                    "this room is archived and can't be posted in",
                 ));
-            };
+            }
+            if room.kind == RoomKind::Dm {
+                let members = services::room::get_member_ids(conn, &room).await?;
+                for member in members.into_iter().filter(|it| *it != user.uuid) {
+                    if services::block::has_blocked(conn, member, user.uuid).await? {
+                        return Ok(error_reply(
+                            StatusCode::FORBIDDEN,
+                            "you can't message this user",
+                        ));
+                    }
+                }
+            }
+            services::rate_limit::check_message(conn, &room, &user).await?;
 
             let message = Message::new(user, room, data.content);
             let message = services::message::create(conn, message).await?;
@@ -49,14 +63,9 @@ async fn get_messages(
     with_transaction(pool, move |conn| {
         Box::pin(async move {
             let room = value_or_404!(services::room::get(conn, room_id).await?);
-            if !services::room::user_in_room(conn, &room, &user).await? {
-                return Ok(error_reply(
-                    StatusCode::FORBIDDEN,
-                    "you must be in the room to get its messages",
-                ));
-            };
+            permissions::check(conn, &room, &user, Permissions::NONE).await?;
 
-            let messages = services::message::get_all(conn, &room).await?;
+            let messages = services::message::get_all(conn, &room, &user).await?;
 
             let status = if messages.is_empty() {
                 StatusCode::NO_CONTENT
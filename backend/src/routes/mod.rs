@@ -1,4 +1,12 @@
+pub mod admin;
 pub mod assets;
+pub mod audit_log;
+pub mod automod;
+pub mod invite;
 pub mod message;
+pub mod notification;
+pub mod report;
+pub mod role;
 pub mod room;
+pub mod space;
 pub mod user;
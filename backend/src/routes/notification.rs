@@ -0,0 +1,82 @@
+use crate::utils::{ensure_authorized, error_reply, json_body, with_db, with_transaction};
+use crate::{services, value_or_404};
+use common::payloads::UpdateNotificationSettings;
+use common::User;
+use sqlx::types::Uuid;
+use sqlx::PgPool;
+use warp::http::StatusCode;
+use warp::{Filter, Reply};
+
+async fn get_notification_settings(
+    room: Uuid,
+    pool: PgPool,
+    user: User,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    with_transaction(pool, move |conn| {
+        Box::pin(async move {
+            let room = value_or_404!(services::room::get(&mut *conn, room).await?);
+            let settings = value_or_404!(
+                services::notification::get(&mut *conn, &room, &user).await?,
+                "you aren't a member of this room"
+            );
+
+            Ok(warp::reply::json(&settings).into_response())
+        })
+    })
+    .await
+}
+
+async fn update_notification_settings(
+    room: Uuid,
+    data: UpdateNotificationSettings,
+    pool: PgPool,
+    user: User,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    if let Err(message) = data.validate() {
+        return Ok(error_reply(StatusCode::BAD_REQUEST, &message));
+    }
+
+    with_transaction(pool, move |conn| {
+        Box::pin(async move {
+            let room = value_or_404!(services::room::get(&mut *conn, room).await?);
+            let mut settings = value_or_404!(
+                services::notification::get(&mut *conn, &room, &user).await?,
+                "you aren't a member of this room"
+            );
+
+            if let Some(level) = data.level {
+                settings.level = level;
+            }
+            if let Some(muted_until) = data.muted_until {
+                settings.muted_until = muted_until;
+            }
+            if let Some(hide_unread_when_muted) = data.hide_unread_when_muted {
+                settings.hide_unread_when_muted = hide_unread_when_muted;
+            }
+            services::notification::update(&mut *conn, &settings, &user).await?;
+
+            Ok(warp::reply::json(&settings).into_response())
+        })
+    })
+    .await
+    .map(Reply::into_response)
+}
+
+pub fn routes(
+    db: PgPool,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    let get_notification_settings_route = warp::path!("rooms" / Uuid / "notifications")
+        .and(warp::get())
+        .and(with_db(db.clone()))
+        .and(ensure_authorized(db.clone()))
+        .and_then(get_notification_settings);
+
+    let update_notification_settings_route = warp::path!("rooms" / Uuid / "notifications")
+        .and(warp::patch())
+        .and(json_body::<UpdateNotificationSettings>())
+        .and(with_db(db.clone()))
+        .and(ensure_authorized(db))
+        .and_then(update_notification_settings);
+
+    get_notification_settings_route.or(update_notification_settings_route)
+}
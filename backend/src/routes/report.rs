@@ -0,0 +1,192 @@
+use crate::routes::room::check_can_remove;
+use crate::services::permissions;
+use crate::utils::{
+    ensure_authorized, error_reply, json_body, json_with_status, with_db, with_transaction,
+    with_transaction_deferred,
+};
+use crate::{services, value_or_404};
+use common::payloads::{CreateReport, ReportQuery, ResolveReport};
+use common::{Permissions, ReportAction, ReportScope, User};
+use sqlx::types::Uuid;
+use sqlx::PgPool;
+use warp::http::StatusCode;
+use warp::{Filter, Reply};
+
+async fn create_report(
+    room: Uuid,
+    message: Uuid,
+    data: CreateReport,
+    pool: PgPool,
+    user: User,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    if let Err(message) = data.validate() {
+        return Ok(error_reply(StatusCode::BAD_REQUEST, &message));
+    }
+
+    with_transaction(pool, move |conn| {
+        Box::pin(async move {
+            let room = value_or_404!(services::room::get(&mut *conn, room).await?);
+            permissions::check(&mut *conn, &room, &user, Permissions::NONE).await?;
+
+            let message = value_or_404!(
+                services::message::get(&mut *conn, &room, message).await?,
+                "this message doesn't exist in the room"
+            );
+            if message.author.uuid == user.uuid {
+                return Ok(error_reply(
+                    StatusCode::BAD_REQUEST,
+                    "you can't report your own message",
+                ));
+            }
+            if services::report::has_open(&mut *conn, &message, &user).await? {
+                return Ok(error_reply(
+                    StatusCode::CONFLICT,
+                    "you already reported this message",
+                ));
+            }
+
+            let report = services::report::create(
+                &mut *conn,
+                &message,
+                Some(&user),
+                data.reason.trim(),
+                data.scope,
+            )
+            .await?;
+
+            Ok(json_with_status(StatusCode::CREATED, &report))
+        })
+    })
+    .await
+    .map(Reply::into_response)
+}
+
+async fn get_reports(
+    room: Uuid,
+    query: ReportQuery,
+    pool: PgPool,
+    user: User,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    with_transaction(pool, move |conn| {
+        Box::pin(async move {
+            let room = value_or_404!(services::room::get(&mut *conn, room).await?);
+            permissions::check_admin(&mut *conn, &room, &user).await?;
+
+            let reports = services::report::get_for_room(&mut *conn, &room, query.resolved).await?;
+
+            Ok(warp::reply::json(&reports).into_response())
+        })
+    })
+    .await
+}
+
+/// Carries out the action and closes the report
+async fn resolve_report(
+    room: Uuid,
+    report: Uuid,
+    data: ResolveReport,
+    pool: PgPool,
+    user: User,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    with_transaction_deferred(pool, move |conn, messages| {
+        Box::pin(async move {
+            let room = value_or_404!(services::room::get(&mut *conn, room).await?);
+            permissions::check_admin(&mut *conn, &room, &user).await?;
+
+            let report = value_or_404!(
+                services::report::get(&mut *conn, &room, report)
+                    .await?
+                    .filter(|it| it.scope == ReportScope::Room),
+                "this report doesn't exist in the room"
+            );
+            if report.is_resolved() {
+                return Ok(error_reply(
+                    StatusCode::CONFLICT,
+                    "this report was already resolved",
+                ));
+            }
+
+            match data.action {
+                ReportAction::Dismiss => {}
+                ReportAction::DeleteMessage => {
+                    // it may have been deleted since, which is what was asked anyway
+                    if let Some(message) = report.message {
+                        let message = services::message::get(&mut *conn, &room, message).await?;
+                        if let Some(message) = message {
+                            services::message::delete(&mut *conn, &message, &user).await?;
+                        }
+                    }
+                }
+                ReportAction::Kick | ReportAction::Ban => {
+                    let author = value_or_404!(
+                        report.author,
+                        "the author of the message doesn't exist anymore"
+                    );
+                    let permission = if data.action == ReportAction::Kick {
+                        Permissions::KICK
+                    } else {
+                        Permissions::BAN
+                    };
+                    let reply =
+                        check_can_remove(&mut *conn, &room, &user, author, permission).await?;
+                    if let Some(reply) = reply {
+                        return Ok(reply);
+                    }
+
+                    let author = value_or_404!(services::user::get(&mut *conn, author).await?);
+                    let reason = data.reason.as_deref().unwrap_or(&report.reason);
+                    if data.action == ReportAction::Ban {
+                        let left =
+                            services::room::ban(&mut *conn, &room, &author, &user, Some(reason))
+                                .await?;
+                        messages.extend(left);
+                    } else if services::room::user_in_room(&mut *conn, &room, &author).await? {
+                        let left =
+                            services::room::kick(&mut *conn, &room, &author, &user, Some(reason))
+                                .await?;
+                        messages.extend(left);
+                    } else {
+                        return Ok(error_reply(
+                            StatusCode::BAD_REQUEST,
+                            "the author isn't a member of the room anymore",
+                        ));
+                    }
+                }
+            }
+
+            let report = services::report::resolve(&mut *conn, &report, data.action, &user).await?;
+
+            Ok(warp::reply::json(&report).into_response())
+        })
+    })
+    .await
+}
+
+pub fn routes(
+    db: PgPool,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    let create_report_route = warp::path!("rooms" / Uuid / "messages" / Uuid / "reports")
+        .and(warp::post())
+        .and(json_body::<CreateReport>())
+        .and(with_db(db.clone()))
+        .and(ensure_authorized(db.clone()))
+        .and_then(create_report);
+
+    let get_reports_route = warp::path!("rooms" / Uuid / "reports")
+        .and(warp::get())
+        .and(warp::query::<ReportQuery>())
+        .and(with_db(db.clone()))
+        .and(ensure_authorized(db.clone()))
+        .and_then(get_reports);
+
+    let resolve_report_route = warp::path!("rooms" / Uuid / "reports" / Uuid / "resolve")
+        .and(warp::post())
+        .and(json_body::<ResolveReport>())
+        .and(with_db(db.clone()))
+        .and(ensure_authorized(db))
+        .and_then(resolve_report);
+
+    create_report_route
+        .or(get_reports_route)
+        .or(resolve_report_route)
+}
@@ -0,0 +1,218 @@
+use crate::services::permissions;
+use crate::utils::{
+    ensure_authorized, error_reply, json_body, json_with_status, with_db, with_transaction,
+};
+use crate::{services, value_or_404};
+use common::payloads::{CreateRole, SetMemberRole, UpdateRole};
+use common::{Permissions, User};
+use sqlx::types::Uuid;
+use sqlx::PgPool;
+use warp::http::StatusCode;
+use warp::{Filter, Reply};
+
+async fn get_roles(
+    room: Uuid,
+    pool: PgPool,
+    user: User,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    with_transaction(pool, move |conn| {
+        Box::pin(async move {
+            let room = value_or_404!(services::room::get(&mut *conn, room).await?);
+            permissions::check(&mut *conn, &room, &user, Permissions::NONE).await?;
+
+            let roles = services::role::get_all(&mut *conn, &room).await?;
+
+            Ok(warp::reply::json(&roles).into_response())
+        })
+    })
+    .await
+}
+
+async fn create_role(
+    room: Uuid,
+    data: CreateRole,
+    pool: PgPool,
+    user: User,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    if let Err(message) = data.validate() {
+        return Ok(error_reply(StatusCode::BAD_REQUEST, &message));
+    }
+
+    with_transaction(pool, move |conn| {
+        Box::pin(async move {
+            let room = value_or_404!(services::room::get(&mut *conn, room).await?);
+            // nobody can hand out permissions they don't have themselves
+            let needed = Permissions::MANAGE_ROLES | data.permissions;
+            permissions::check(&mut *conn, &room, &user, needed).await?;
+
+            let role = services::role::create(
+                &mut *conn,
+                &room,
+                data.name.trim(),
+                data.permissions,
+                &user,
+            )
+            .await?;
+
+            Ok(json_with_status(StatusCode::CREATED, &role))
+        })
+    })
+    .await
+    .map(Reply::into_response)
+}
+
+async fn update_role(
+    room: Uuid,
+    role: Uuid,
+    data: UpdateRole,
+    pool: PgPool,
+    user: User,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    if let Err(message) = data.validate() {
+        return Ok(error_reply(StatusCode::BAD_REQUEST, &message));
+    }
+
+    with_transaction(pool, move |conn| {
+        Box::pin(async move {
+            let room = value_or_404!(services::room::get(&mut *conn, room).await?);
+            permissions::check(&mut *conn, &room, &user, Permissions::MANAGE_ROLES).await?;
+
+            let mut role = value_or_404!(services::role::get(&mut *conn, &room, role).await?);
+            if role.is_admin {
+                return Ok(error_reply(
+                    StatusCode::BAD_REQUEST,
+                    "the admin role can't be changed",
+                ));
+            }
+
+            if let Some(name) = data.name {
+                role.name = name.trim().to_string();
+            }
+            if let Some(new_permissions) = data.permissions {
+                let needed = role.permissions | new_permissions;
+                permissions::check(&mut *conn, &room, &user, needed).await?;
+                role.permissions = new_permissions;
+            }
+            let role = services::role::update(&mut *conn, &room, role, &user).await?;
+
+            Ok(warp::reply::json(&role).into_response())
+        })
+    })
+    .await
+    .map(Reply::into_response)
+}
+
+async fn delete_role(
+    room: Uuid,
+    role: Uuid,
+    pool: PgPool,
+    user: User,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    with_transaction(pool, move |conn| {
+        Box::pin(async move {
+            let room = value_or_404!(services::room::get(&mut *conn, room).await?);
+            permissions::check(&mut *conn, &room, &user, Permissions::MANAGE_ROLES).await?;
+
+            let role = value_or_404!(services::role::get(&mut *conn, &room, role).await?);
+            if role.is_admin || role.is_default {
+                return Ok(error_reply(
+                    StatusCode::BAD_REQUEST,
+                    "the admin and default roles can't be deleted",
+                ));
+            }
+            // deleting a role demotes its holders, so it needs the role's permissions too
+            permissions::check_can_assign(&mut *conn, &room, &user, &role).await?;
+
+            services::role::delete(&mut *conn, &room, &role, &user).await?;
+
+            Ok(StatusCode::NO_CONTENT.into_response())
+        })
+    })
+    .await
+}
+
+async fn set_member_role(
+    room: Uuid,
+    member: Uuid,
+    data: SetMemberRole,
+    pool: PgPool,
+    user: User,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    with_transaction(pool, move |conn| {
+        Box::pin(async move {
+            let room = value_or_404!(services::room::get(&mut *conn, room).await?);
+            permissions::check(&mut *conn, &room, &user, Permissions::MANAGE_ROLES).await?;
+
+            if member == user.uuid {
+                return Ok(error_reply(
+                    StatusCode::BAD_REQUEST,
+                    "you can't change your own role",
+                ));
+            }
+            if room.is_owner(member) {
+                return Ok(error_reply(
+                    StatusCode::FORBIDDEN,
+                    "the owner is always an admin, transfer the room to change that",
+                ));
+            }
+
+            let current = value_or_404!(
+                services::role::get_member_role(&mut *conn, &room, member).await?,
+                "this user isn't a member of the room"
+            );
+            let role = value_or_404!(services::role::get(&mut *conn, &room, data.role).await?);
+
+            // roles can only be given or taken away by those who have every permission in them
+            permissions::check_can_assign(&mut *conn, &room, &user, &role).await?;
+            permissions::check_can_assign(&mut *conn, &room, &user, &current).await?;
+
+            services::role::set_member_role(&mut *conn, &room, member, &role, &user).await?;
+
+            Ok(warp::reply::json(&role).into_response())
+        })
+    })
+    .await
+}
+
+pub fn routes(
+    db: PgPool,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    let get_roles_route = warp::path!("rooms" / Uuid / "roles")
+        .and(warp::get())
+        .and(with_db(db.clone()))
+        .and(ensure_authorized(db.clone()))
+        .and_then(get_roles);
+
+    let create_role_route = warp::path!("rooms" / Uuid / "roles")
+        .and(warp::post())
+        .and(json_body::<CreateRole>())
+        .and(with_db(db.clone()))
+        .and(ensure_authorized(db.clone()))
+        .and_then(create_role);
+
+    let update_role_route = warp::path!("rooms" / Uuid / "roles" / Uuid)
+        .and(warp::patch())
+        .and(json_body::<UpdateRole>())
+        .and(with_db(db.clone()))
+        .and(ensure_authorized(db.clone()))
+        .and_then(update_role);
+
+    let delete_role_route = warp::path!("rooms" / Uuid / "roles" / Uuid)
+        .and(warp::delete())
+        .and(with_db(db.clone()))
+        .and(ensure_authorized(db.clone()))
+        .and_then(delete_role);
+
+    let set_member_role_route = warp::path!("rooms" / Uuid / "members" / Uuid / "role")
+        .and(warp::put())
+        .and(json_body::<SetMemberRole>())
+        .and(with_db(db.clone()))
+        .and(ensure_authorized(db))
+        .and_then(set_member_role);
+
+    get_roles_route
+        .or(create_role_route)
+        .or(update_role_route)
+        .or(delete_role_route)
+        .or(set_member_role_route)
+}
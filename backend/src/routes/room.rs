@@ -1,40 +1,200 @@
+use crate::services::permissions;
 use crate::utils::{
     ensure_authorized, error_reply, json_body, json_with_status, with_db, with_transaction,
-    AssetExt,
+    with_transaction_deferred, with_transaction_then, AssetExt,
 };
 use crate::{bail_if_err, bail_if_err_or_404, update_fields, value_or_404};
-use crate::{services, utils};
-use common::payloads::{CreateRoom, JoinMembers};
-use common::{Asset, Room, RoomMember, User};
+use crate::{services, utils, websocket};
+use common::payloads::{
+    CreateRoom, DirectoryEntry, DirectoryQuery, JoinMembers, OpenDm, RemoveMember,
+    TransferOwnership, UpdateRoom,
+};
+use common::{
+    Asset, Message, MessageType, Permissions, PostPermission, Presence, Room, RoomKind, RoomMember,
+    RoomVisibility, User, MAX_GROUP_DM_MEMBERS,
+};
 use sqlx::types::Uuid;
-use sqlx::PgPool;
+use sqlx::{PgConnection, PgPool};
+use tracing::warn;
 use warp::http::StatusCode;
+use warp::reply::Response;
 use warp::{Filter, Reply};
 
 async fn get_room(
     room_id: Uuid,
     pool: PgPool,
-    _: User,
+    user: User,
 ) -> Result<impl warp::Reply, warp::Rejection> {
     let mut conn = bail_if_err!(pool.acquire().await.map_err(anyhow::Error::from));
     let room = bail_if_err_or_404!(services::room::get(&mut conn, room_id).await);
+
+    // private rooms don't exist as far as outsiders are concerned,
+    // neither do the public rooms of spaces the user isn't in
+    if !bail_if_err!(can_see(&mut conn, &room, &user).await) {
+        return Ok(error_reply(
+            StatusCode::NOT_FOUND,
+            "requested resource was not found",
+        ));
+    }
+
     Ok(warp::reply::json(&room).into_response())
 }
 
+/// Whether the user is a member of the room, or could join it by themselves
+async fn can_see(conn: &mut PgConnection, room: &Room, user: &User) -> anyhow::Result<bool> {
+    if services::room::user_in_room(&mut *conn, room, user).await? {
+        return Ok(true);
+    }
+
+    if !room.is_public() {
+        return Ok(false);
+    }
+    match room.space {
+        Some(space) => services::space::user_in_space(conn, space, user).await,
+        None => Ok(true),
+    }
+}
+
+async fn get_public_rooms(
+    query: DirectoryQuery,
+    pool: PgPool,
+    user: User,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    if let Err(message) = query.validate() {
+        return Ok(error_reply(StatusCode::BAD_REQUEST, &message));
+    }
+
+    let mut conn = bail_if_err!(pool.acquire().await.map_err(anyhow::Error::from));
+    if let Some(space) = query.space {
+        if !bail_if_err!(services::space::user_in_space(&mut conn, space, &user).await) {
+            return Ok(error_reply(
+                StatusCode::FORBIDDEN,
+                "either this space doesn't exist or you aren't a member of it",
+            ));
+        }
+    }
+    let search = query
+        .search
+        .as_deref()
+        .map(str::trim)
+        .filter(|it| !it.is_empty());
+    let rooms = bail_if_err!(
+        services::room::search_public(&mut conn, query.space, search, query.offset, query.limit)
+            .await
+    );
+
+    let entries = rooms
+        .into_iter()
+        .map(|(room, member_count)| DirectoryEntry { room, member_count })
+        .collect::<Vec<_>>();
+
+    Ok(warp::reply::json(&entries).into_response())
+}
+
 async fn create_room(
     data: CreateRoom,
     pool: PgPool,
     user: User,
 ) -> Result<impl warp::Reply, warp::Rejection> {
-    with_transaction(pool, |conn| {
+    with_transaction_deferred(pool, move |conn, messages| {
         Box::pin(async move {
-            let room = Room::new(&data.name);
+            let mut room = Room::new(&data.name);
+            room.visibility = data.visibility;
+            room.owner = Some(user.uuid);
+            match data.space {
+                Some(space) => {
+                    let space = value_or_404!(services::space::get(&mut *conn, space).await?);
+                    permissions::check_space(&mut *conn, &space, &user, Permissions::MANAGE_ROOM)
+                        .await?;
+                    if let Some(category) = data.category {
+                        if !space.categories.iter().any(|it| it.uuid == category) {
+                            return Ok(error_reply(
+                                StatusCode::NOT_FOUND,
+                                "this category doesn't exist in the space",
+                            ));
+                        }
+                    }
+                    room.space = Some(space.uuid);
+                    room.category = data.category;
+                }
+                None if data.category.is_some() => {
+                    return Ok(error_reply(
+                        StatusCode::BAD_REQUEST,
+                        "categories are only found in spaces",
+                    ));
+                }
+                None => {}
+            }
             println!("creating room uuid: {}", room.uuid);
-            let room = services::room::create(&mut *conn, room).await?;
+            // the owner is joined along with the room
+            let (room, joined) = services::room::create(&mut *conn, room).await?;
+            messages.extend(joined);
             println!("created room");
 
-            services::room::join(&mut *conn, &room, &user, true).await?;
-            println!("joined room");
+            Ok(json_with_status(StatusCode::CREATED, &room))
+        })
+    })
+    .await
+}
+
+async fn open_dm(
+    data: OpenDm,
+    pool: PgPool,
+    user: User,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    if let Err(message) = data.validate() {
+        return Ok(error_reply(StatusCode::BAD_REQUEST, &message));
+    }
+    if data.members.contains(&user.uuid) {
+        return Ok(error_reply(
+            StatusCode::BAD_REQUEST,
+            "you can't start a conversation with yourself",
+        ));
+    }
+
+    with_transaction_deferred(pool, move |conn, messages| {
+        Box::pin(async move {
+            let mut users = Vec::with_capacity(data.members.len() + 1);
+            users.push(user.clone());
+            for member in data.members {
+                users.push(value_or_404!(
+                    services::user::get(&mut *conn, member).await?
+                ));
+                if services::block::has_blocked(&mut *conn, member, user.uuid).await? {
+                    return Ok(error_reply(
+                        StatusCode::FORBIDDEN,
+                        "you can't message this user",
+                    ));
+                }
+            }
+
+            let kind = if users.len() == 2 {
+                // there's only ever one DM between two users
+                let dm = services::room::find_dm(&mut *conn, user.uuid, users[1].uuid).await?;
+                if let Some(room) = dm {
+                    return Ok(warp::reply::json(&room).into_response());
+                }
+                RoomKind::Dm
+            } else {
+                RoomKind::GroupDm
+            };
+
+            let room = Room::new_with_kind(&services::room::dm_name(&users), kind);
+            let (room, joined) = services::room::create(&mut *conn, room).await?;
+            messages.extend(joined);
+
+            let default = services::role::get_default(&mut *conn, &room).await?;
+            for member in users {
+                // whoever starts a group DM gets to manage it
+                let role = if kind == RoomKind::GroupDm && member.uuid == user.uuid {
+                    services::role::get_admin(&mut *conn, &room).await?
+                } else {
+                    default.clone()
+                };
+                let (_, joined) =
+                    services::room::join(&mut *conn, &room, &member, &role, &user).await?;
+                messages.push(joined);
+            }
 
             Ok(json_with_status(StatusCode::CREATED, &room))
         })
@@ -48,24 +208,92 @@ async fn join_room(
     pool: PgPool,
     user: User,
 ) -> Result<impl warp::Reply, warp::Rejection> {
-    with_transaction(pool, move |conn| {
+    with_transaction_deferred(pool, move |conn, messages| {
         Box::pin(async move {
             let room = services::room::get(&mut *conn, room).await?;
             let room = value_or_404!(room);
 
-            let is_in_room = services::room::user_in_room(&mut *conn, &room, &user).await?;
+            permissions::check(&mut *conn, &room, &user, Permissions::INVITE).await?;
 
-            if !is_in_room {
+            match room.kind {
+                RoomKind::Room => {}
+                RoomKind::Dm => {
+                    return Ok(error_reply(
+                        StatusCode::BAD_REQUEST,
+                        "members can't be added to a DM, start a group DM instead",
+                    ))
+                }
+                RoomKind::GroupDm => {
+                    let members = services::room::get_member_ids(&mut *conn, &room).await?;
+                    if members.len() >= MAX_GROUP_DM_MEMBERS {
+                        return Ok(error_reply(
+                            StatusCode::BAD_REQUEST,
+                            &format!(
+                                "group DMs can have at most {} members",
+                                MAX_GROUP_DM_MEMBERS
+                            ),
+                        ));
+                    }
+                }
+            }
+
+            let role = match data.role {
+                Some(role) => value_or_404!(
+                    services::role::get(&mut *conn, &room, role).await?,
+                    "this role doesn't exist in the room"
+                ),
+                None => services::role::get_default(&mut *conn, &room).await?,
+            };
+            permissions::check_can_assign(&mut *conn, &room, &user, &role).await?;
+
+            let added = value_or_404!(services::user::get(&mut *conn, data.member).await?);
+            if services::block::has_blocked(&mut *conn, added.uuid, user.uuid).await? {
                 return Ok(error_reply(
                     StatusCode::FORBIDDEN,
-                    "either this room doesn't exist or you don't have permission to join this room",
+                    "you can't add this user",
                 ));
             }
+            let (member, joined) =
+                services::room::join(&mut *conn, &room, &added, &role, &user).await?;
+            messages.push(joined);
+            if room.kind == RoomKind::GroupDm {
+                services::room::refresh_dm_name(&mut *conn, &room).await?;
+            }
 
-            let user = value_or_404!(services::user::get(&mut *conn, data.member).await?);
-            let member =
-                services::room::join(&mut *conn, &room, &user, data.with_elevated_permissions)
-                    .await?;
+            Ok(json_with_status(StatusCode::CREATED, &member))
+        })
+    })
+    .await
+}
+
+/// Joins a public room as its default role
+async fn join_public_room(
+    room: Uuid,
+    pool: PgPool,
+    user: User,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    with_transaction_deferred(pool, move |conn, messages| {
+        Box::pin(async move {
+            let room = value_or_404!(services::room::get(&mut *conn, room).await?);
+            if !can_see(&mut *conn, &room, &user).await? {
+                // same as `get_room`, private rooms are kept hidden
+                return Ok(error_reply(
+                    StatusCode::NOT_FOUND,
+                    "requested resource was not found",
+                ));
+            }
+
+            if services::room::user_in_room(&mut *conn, &room, &user).await? {
+                return Ok(error_reply(
+                    StatusCode::CONFLICT,
+                    "you're already a member of this room",
+                ));
+            }
+
+            let role = services::role::get_default(&mut *conn, &room).await?;
+            let (member, joined) =
+                services::room::join(&mut *conn, &room, &user, &role, &user).await?;
+            messages.push(joined);
 
             Ok(json_with_status(StatusCode::CREATED, &member))
         })
@@ -83,16 +311,16 @@ async fn get_room_members(
             let room = services::room::get(&mut *conn, room).await?;
             let room = value_or_404!(room);
 
-            let is_in_room = services::room::user_in_room(&mut *conn, &room, &user).await?;
+            permissions::check(&mut *conn, &room, &user, Permissions::NONE).await?;
 
-            if !is_in_room {
-                return Ok(error_reply(
-                    StatusCode::FORBIDDEN,
-                    "either this room doesn't exist or you don't have permission to see it",
-                ));
+            let mut users = services::room::get_room_members(&mut *conn, room).await?;
+            // the presence of blocked users is kept from those who blocked them
+            let blocked = services::block::get_blocked_ids(conn, user.uuid).await?;
+            for member in users.iter_mut() {
+                if blocked.contains(&member.user.uuid) {
+                    member.presence = Presence::Offline;
+                }
             }
-
-            let users = services::room::get_room_members(conn, room).await?;
             Ok(if users.is_empty() {
                 json_with_status(StatusCode::NO_CONTENT, &Vec::<RoomMember>::new())
             } else {
@@ -103,42 +331,384 @@ async fn get_room_members(
     .await
 }
 
-pub async fn room_icon(
+async fn leave_room(
     room: Uuid,
     pool: PgPool,
     user: User,
-    asset: Asset,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    with_transaction_deferred(pool, move |conn, messages| {
+        Box::pin(async move {
+            let room = value_or_404!(services::room::get(&mut *conn, room).await?);
+            permissions::check(&mut *conn, &room, &user, Permissions::NONE).await?;
+
+            if room.kind == RoomKind::Dm {
+                return Ok(error_reply(StatusCode::BAD_REQUEST, "DMs can't be left"));
+            }
+            if room.is_owner(user.uuid) {
+                return Ok(error_reply(
+                    StatusCode::BAD_REQUEST,
+                    "you own this room, transfer it to someone else before leaving",
+                ));
+            }
+
+            messages.extend(services::room::leave(&mut *conn, &room, &user).await?);
+
+            Ok(StatusCode::NO_CONTENT.into_response())
+        })
+    })
+    .await
+}
+
+/// Checks that `user` may kick or ban `member` from the room,
+/// returning the reply to send if they may not.
+///
+/// The owner can't be removed, admins can only be removed by the owner, and only
+/// admins can remove the members who have the permission being used.
+pub(crate) async fn check_can_remove(
+    conn: &mut PgConnection,
+    room: &Room,
+    user: &User,
+    member: Uuid,
+    permission: Permissions,
+) -> anyhow::Result<Option<Response>> {
+    permissions::check(&mut *conn, room, user, permission).await?;
+
+    if member == user.uuid {
+        return Ok(Some(error_reply(
+            StatusCode::BAD_REQUEST,
+            "you can't remove yourself, leave the room instead",
+        )));
+    }
+
+    if room.is_owner(member) {
+        return Ok(Some(error_reply(
+            StatusCode::FORBIDDEN,
+            "the owner can't be removed from the room",
+        )));
+    }
+
+    let role = match services::role::get_member_role(&mut *conn, room, member).await? {
+        Some(role) => role,
+        None => return Ok(None),
+    };
+
+    if role.is_admin && !room.is_owner(user.uuid) {
+        return Ok(Some(error_reply(
+            StatusCode::FORBIDDEN,
+            "only the owner can remove admins from the room",
+        )));
+    }
+
+    let is_admin = services::role::get_member_role(&mut *conn, room, user.uuid)
+        .await?
+        .map_or(false, |role| role.is_admin);
+    if role.permissions().contains(permission) && !is_admin {
+        return Ok(Some(error_reply(
+            StatusCode::FORBIDDEN,
+            "only admins can remove members with this permission",
+        )));
+    }
+
+    Ok(None)
+}
+
+async fn kick_member(
+    room: Uuid,
+    data: RemoveMember,
+    pool: PgPool,
+    user: User,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    with_transaction_deferred(pool, move |conn, messages| {
+        Box::pin(async move {
+            let room = value_or_404!(services::room::get(&mut *conn, room).await?);
+
+            let reply =
+                check_can_remove(&mut *conn, &room, &user, data.member, Permissions::KICK).await?;
+            if let Some(reply) = reply {
+                return Ok(reply);
+            }
+
+            let member = value_or_404!(services::user::get(&mut *conn, data.member).await?);
+            if !services::room::user_in_room(&mut *conn, &room, &member).await? {
+                return Ok(error_reply(
+                    StatusCode::NOT_FOUND,
+                    "this user isn't a member of the room",
+                ));
+            }
+
+            let left =
+                services::room::kick(&mut *conn, &room, &member, &user, data.reason.as_deref())
+                    .await?;
+            messages.extend(left);
+
+            Ok(StatusCode::NO_CONTENT.into_response())
+        })
+    })
+    .await
+}
+
+async fn ban_member(
+    room: Uuid,
+    data: RemoveMember,
+    pool: PgPool,
+    user: User,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    with_transaction_deferred(pool, move |conn, messages| {
+        Box::pin(async move {
+            let room = value_or_404!(services::room::get(&mut *conn, room).await?);
+
+            let reply =
+                check_can_remove(&mut *conn, &room, &user, data.member, Permissions::BAN).await?;
+            if let Some(reply) = reply {
+                return Ok(reply);
+            }
+
+            let member = value_or_404!(services::user::get(&mut *conn, data.member).await?);
+            let left =
+                services::room::ban(&mut *conn, &room, &member, &user, data.reason.as_deref())
+                    .await?;
+            messages.extend(left);
+
+            Ok(StatusCode::NO_CONTENT.into_response())
+        })
+    })
+    .await
+}
+
+async fn unban_member(
+    room: Uuid,
+    member: Uuid,
+    pool: PgPool,
+    user: User,
 ) -> Result<impl warp::Reply, warp::Rejection> {
+    with_transaction(pool, move |conn| {
+        Box::pin(async move {
+            let room = value_or_404!(services::room::get(&mut *conn, room).await?);
+
+            permissions::check(&mut *conn, &room, &user, Permissions::BAN).await?;
+
+            if !services::room::unban(&mut *conn, &room, member, &user).await? {
+                return Ok(error_reply(
+                    StatusCode::NOT_FOUND,
+                    "this user isn't banned from the room",
+                ));
+            }
+
+            Ok(StatusCode::NO_CONTENT.into_response())
+        })
+    })
+    .await
+}
+
+async fn update_room(
+    room: Uuid,
+    data: UpdateRoom,
+    pool: PgPool,
+    user: User,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    if let Err(message) = data.validate() {
+        return Ok(error_reply(StatusCode::BAD_REQUEST, &message));
+    }
+
     with_transaction(pool, move |conn| {
         Box::pin(async move {
             let mut room = value_or_404!(services::room::get(&mut *conn, room).await?);
-            let is_in_room = services::room::user_in_room(&mut *conn, &room, &user).await?;
+            permissions::check(&mut *conn, &room, &user, Permissions::MANAGE_ROOM).await?;
+
+            if room.is_dm() {
+                if data.name.is_some() {
+                    return Ok(error_reply(
+                        StatusCode::BAD_REQUEST,
+                        "DMs are named after their members",
+                    ));
+                }
+                if data.visibility == Some(RoomVisibility::Public) {
+                    return Ok(error_reply(StatusCode::BAD_REQUEST, "DMs can't be public"));
+                }
+                if data.post_permission == Some(PostPermission::Admins) {
+                    return Ok(error_reply(
+                        StatusCode::BAD_REQUEST,
+                        "DMs can't be announcement rooms",
+                    ));
+                }
+            }
+
+            let renamed_to = data
+                .name
+                .map(|name| name.trim().to_string())
+                .filter(|name| *name != room.name);
+            if let Some(name) = &renamed_to {
+                room.name = name.clone();
+            }
+            if let Some(topic) = data.topic {
+                room.topic = topic.map(|topic| topic.trim().to_string());
+            }
+            if let Some(visibility) = data.visibility {
+                room.visibility = visibility;
+            }
+            if let Some(slow_mode) = data.slow_mode {
+                room.slow_mode = slow_mode;
+            }
+            if let Some(post_permission) = data.post_permission {
+                room.post_permission = post_permission;
+            }
+
+            let room = services::room::update(&mut *conn, room, &user).await?;
+            services::room::broadcast_update(&mut *conn, room.clone()).await?;
+
+            if let Some(name) = renamed_to {
+                let message =
+                    Message::new_with_type(user, room.clone(), name, MessageType::RoomRename);
+                services::message::create(&mut *conn, message).await?;
+            }
+
+            Ok(warp::reply::json(&room).into_response())
+        })
+    })
+    .await
+    .map(Reply::into_response)
+}
+
+async fn archive_room(
+    room: Uuid,
+    archived: bool,
+    pool: PgPool,
+    user: User,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    with_transaction(pool, move |conn| {
+        Box::pin(async move {
+            let room = value_or_404!(services::room::get(&mut *conn, room).await?);
+            permissions::check_admin(&mut *conn, &room, &user).await?;
+
+            let room = services::room::set_archived(&mut *conn, &room, archived, &user).await?;
+            services::room::broadcast_update(&mut *conn, room.clone()).await?;
+
+            Ok(warp::reply::json(&room).into_response())
+        })
+    })
+    .await
+}
+
+async fn delete_room(
+    room: Uuid,
+    pool: PgPool,
+    user: User,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let (reply, (icon, deleted)) = with_transaction_then(pool, move |conn| {
+        Box::pin(async move {
+            let room = match services::room::get(&mut *conn, room).await? {
+                Some(room) => room,
+                None => {
+                    let reply = error_reply(StatusCode::NOT_FOUND, "room not found");
+                    return Ok((reply, (None, None)));
+                }
+            };
+            permissions::check_owner(&mut *conn, &room, &user).await?;
+
+            let deleted = services::room::delete(&mut *conn, &room).await?;
+
+            Ok((
+                StatusCode::NO_CONTENT.into_response(),
+                (room.icon, Some(deleted)),
+            ))
+        })
+    })
+    .await?;
+    websocket::send_deferred(deleted).await;
+
+    // a missing file shouldn't keep the room around
+    if let Some(icon) = icon {
+        if let Err(e) = icon.delete().await {
+            warn!("failed to delete icon {}: {}", icon.uuid, e);
+        }
+    }
+
+    Ok(reply)
+}
 
-            if !is_in_room {
+async fn transfer_ownership(
+    room: Uuid,
+    data: TransferOwnership,
+    pool: PgPool,
+    user: User,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    with_transaction(pool, move |conn| {
+        Box::pin(async move {
+            let room = value_or_404!(services::room::get(&mut *conn, room).await?);
+            if room.is_dm() {
                 return Ok(error_reply(
-                    StatusCode::FORBIDDEN,
-                    "either this room doesn't exist or you don't have permission to see it",
+                    StatusCode::BAD_REQUEST,
+                    "DMs don't have an owner",
                 ));
             }
+            permissions::check_owner(&mut *conn, &room, &user).await?;
 
-            if let Some(asset) = room.icon {
-                room.icon = None;
-                room = services::room::update(conn, room).await?;
-                services::asset::delete(conn, &asset).await?;
-                asset.delete().await?;
+            if data.owner == user.uuid {
+                return Ok(error_reply(
+                    StatusCode::BAD_REQUEST,
+                    "you already own this room",
+                ));
+            }
+            let owner = value_or_404!(services::user::get(&mut *conn, data.owner).await?);
+            if !services::room::user_in_room(&mut *conn, &room, &owner).await? {
+                return Ok(error_reply(
+                    StatusCode::NOT_FOUND,
+                    "this user isn't a member of the room",
+                ));
             }
 
+            let room = services::room::transfer_ownership(&mut *conn, &room, &owner, &user).await?;
+            services::room::broadcast_update(&mut *conn, room.clone()).await?;
+
+            Ok(warp::reply::json(&room).into_response())
+        })
+    })
+    .await
+}
+
+pub async fn room_icon(
+    room: Uuid,
+    pool: PgPool,
+    user: User,
+    asset: Asset,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let (reply, old_icon) = with_transaction_then(pool, move |conn| {
+        Box::pin(async move {
+            let mut room = match services::room::get(&mut *conn, room).await? {
+                Some(room) => room,
+                None => return Ok((error_reply(StatusCode::NOT_FOUND, "room not found"), None)),
+            };
+            permissions::check(&mut *conn, &room, &user, Permissions::MANAGE_ROOM).await?;
+
+            let old_icon = room.icon.take();
             let asset = services::asset::create(conn, asset).await?;
 
             update_fields!(room => icon = Some(asset.clone()));
-            let room = services::room::update(conn, room).await?;
+            let room = services::room::update(conn, room, &user).await?;
+            services::room::broadcast_update(conn, room.clone()).await?;
+
+            // the old icon can only go once the room doesn't point to it anymore
+            if let Some(old_icon) = &old_icon {
+                services::asset::delete(conn, old_icon).await?;
+            }
 
             asset.save().await?;
 
-            Ok(warp::reply::json(&room.icon.unwrap()).into_response())
+            Ok((
+                warp::reply::json(&room.icon.unwrap()).into_response(),
+                old_icon,
+            ))
         })
     })
-    .await
+    .await?;
+
+    if let Some(icon) = old_icon {
+        if let Err(e) = icon.delete().await {
+            warn!("failed to delete icon {}: {}", icon.uuid, e);
+        }
+    }
+
+    Ok(reply)
 }
 
 pub fn routes(
@@ -150,6 +720,44 @@ pub fn routes(
         .and(ensure_authorized(db.clone()))
         .and_then(get_room);
 
+    let get_public_rooms_route = warp::path!("rooms" / "public")
+        .and(warp::get())
+        .and(warp::query::<DirectoryQuery>())
+        .and(with_db(db.clone()))
+        .and(ensure_authorized(db.clone()))
+        .and_then(get_public_rooms);
+
+    let update_room_route = warp::path!("rooms" / Uuid)
+        .and(warp::patch())
+        .and(json_body::<UpdateRoom>())
+        .and(with_db(db.clone()))
+        .and(ensure_authorized(db.clone()))
+        .and_then(update_room);
+
+    let delete_room_route = warp::path!("rooms" / Uuid)
+        .and(warp::delete())
+        .and(with_db(db.clone()))
+        .and(ensure_authorized(db.clone()))
+        .and_then(delete_room);
+
+    let archive_room_route = warp::path!("rooms" / Uuid / "archive")
+        .and(
+            warp::put()
+                .map(|| true)
+                .or(warp::delete().map(|| false))
+                .unify(),
+        )
+        .and(with_db(db.clone()))
+        .and(ensure_authorized(db.clone()))
+        .and_then(archive_room);
+
+    let transfer_ownership_route = warp::path!("rooms" / Uuid / "owner")
+        .and(warp::put())
+        .and(json_body::<TransferOwnership>())
+        .and(with_db(db.clone()))
+        .and(ensure_authorized(db.clone()))
+        .and_then(transfer_ownership);
+
     let create_room_route = warp::path!("rooms")
         .and(warp::post())
         .and(json_body::<CreateRoom>())
@@ -157,6 +765,13 @@ pub fn routes(
         .and(ensure_authorized(db.clone()))
         .and_then(create_room);
 
+    let open_dm_route = warp::path!("dms")
+        .and(warp::post())
+        .and(json_body::<OpenDm>())
+        .and(with_db(db.clone()))
+        .and(ensure_authorized(db.clone()))
+        .and_then(open_dm);
+
     let join_room_route = warp::path!("rooms" / Uuid / "join")
         .and(warp::post())
         .and(json_body::<JoinMembers>())
@@ -164,12 +779,44 @@ pub fn routes(
         .and(ensure_authorized(db.clone()))
         .and_then(join_room);
 
+    let join_public_room_route = warp::path!("rooms" / Uuid / "members" / "me")
+        .and(warp::post())
+        .and(with_db(db.clone()))
+        .and(ensure_authorized(db.clone()))
+        .and_then(join_public_room);
+
     let get_room_members_route = warp::path!("rooms" / Uuid / "members")
         .and(warp::get())
         .and(with_db(db.clone()))
         .and(ensure_authorized(db.clone()))
         .and_then(get_room_members);
 
+    let leave_room_route = warp::path!("rooms" / Uuid / "leave")
+        .and(warp::post())
+        .and(with_db(db.clone()))
+        .and(ensure_authorized(db.clone()))
+        .and_then(leave_room);
+
+    let kick_member_route = warp::path!("rooms" / Uuid / "kick")
+        .and(warp::post())
+        .and(json_body::<RemoveMember>())
+        .and(with_db(db.clone()))
+        .and(ensure_authorized(db.clone()))
+        .and_then(kick_member);
+
+    let ban_member_route = warp::path!("rooms" / Uuid / "bans")
+        .and(warp::post())
+        .and(json_body::<RemoveMember>())
+        .and(with_db(db.clone()))
+        .and(ensure_authorized(db.clone()))
+        .and_then(ban_member);
+
+    let unban_member_route = warp::path!("rooms" / Uuid / "bans" / Uuid)
+        .and(warp::delete())
+        .and(with_db(db.clone()))
+        .and(ensure_authorized(db.clone()))
+        .and_then(unban_member);
+
     let room_icon_route = warp::path!("rooms" / Uuid / "icon")
         .and(warp::put())
         .and(with_db(db.clone()))
@@ -177,9 +824,20 @@ pub fn routes(
         .and(utils::multipart())
         .and_then(room_icon);
 
-    get_room_route
+    get_public_rooms_route
+        .or(get_room_route)
+        .or(update_room_route)
+        .or(delete_room_route)
+        .or(archive_room_route)
+        .or(transfer_ownership_route)
         .or(create_room_route)
+        .or(open_dm_route)
         .or(join_room_route)
+        .or(join_public_room_route)
         .or(get_room_members_route)
+        .or(leave_room_route)
+        .or(kick_member_route)
+        .or(ban_member_route)
+        .or(unban_member_route)
         .or(room_icon_route)
 }
@@ -0,0 +1,371 @@
+use crate::services::permissions;
+use crate::utils::{
+    ensure_authorized, error_reply, json_body, json_with_status, with_db, with_transaction,
+    with_transaction_deferred,
+};
+use crate::{services, value_or_404};
+use common::payloads::{CreateCategory, CreateSpace, JoinMembers, MoveRoom, UpdateCategory};
+use common::{Permissions, Space, User};
+use sqlx::types::Uuid;
+use sqlx::PgPool;
+use warp::http::StatusCode;
+use warp::{Filter, Reply};
+
+async fn create_space(
+    data: CreateSpace,
+    pool: PgPool,
+    user: User,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    if let Err(message) = data.validate() {
+        return Ok(error_reply(StatusCode::BAD_REQUEST, &message));
+    }
+
+    with_transaction_deferred(pool, move |conn, messages| {
+        Box::pin(async move {
+            let mut space = Space::new(data.name.trim());
+            space.owner = Some(user.uuid);
+            let (space, joined) = services::space::create(&mut *conn, space).await?;
+            messages.extend(joined);
+
+            Ok(json_with_status(StatusCode::CREATED, &space))
+        })
+    })
+    .await
+    .map(Reply::into_response)
+}
+
+async fn get_spaces(pool: PgPool, user: User) -> Result<impl warp::Reply, warp::Rejection> {
+    with_transaction(pool, move |conn| {
+        Box::pin(async move {
+            let spaces = services::space::get_with_user(&mut *conn, &user).await?;
+
+            Ok(warp::reply::json(&spaces).into_response())
+        })
+    })
+    .await
+}
+
+async fn get_space(
+    space: Uuid,
+    pool: PgPool,
+    user: User,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    with_transaction(pool, move |conn| {
+        Box::pin(async move {
+            let space = value_or_404!(services::space::get(&mut *conn, space).await?);
+            permissions::check_space(&mut *conn, &space, &user, Permissions::NONE).await?;
+
+            Ok(warp::reply::json(&space).into_response())
+        })
+    })
+    .await
+}
+
+async fn get_space_members(
+    space: Uuid,
+    pool: PgPool,
+    user: User,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    with_transaction(pool, move |conn| {
+        Box::pin(async move {
+            let space = value_or_404!(services::space::get(&mut *conn, space).await?);
+            permissions::check_space(&mut *conn, &space, &user, Permissions::NONE).await?;
+
+            let members = services::space::get_members(&mut *conn, &space).await?;
+
+            Ok(warp::reply::json(&members).into_response())
+        })
+    })
+    .await
+}
+
+/// Adds a member to the space, who also joins its public rooms
+async fn add_space_member(
+    space: Uuid,
+    data: JoinMembers,
+    pool: PgPool,
+    user: User,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    with_transaction_deferred(pool, move |conn, messages| {
+        Box::pin(async move {
+            let space = value_or_404!(services::space::get(&mut *conn, space).await?);
+            permissions::check_space(&mut *conn, &space, &user, Permissions::INVITE).await?;
+
+            let role = match data.role {
+                Some(role) => value_or_404!(
+                    services::space::get_role(&mut *conn, &space, role).await?,
+                    "this role doesn't exist in the space"
+                ),
+                None => services::space::get_default_role(&mut *conn, &space).await?,
+            };
+            // the same rule as in rooms, nobody can hand out permissions they don't have
+            if !role.is_default {
+                let needed = Permissions::MANAGE_ROLES | role.permissions();
+                permissions::check_space(&mut *conn, &space, &user, needed).await?;
+            }
+
+            let added = value_or_404!(services::user::get(&mut *conn, data.member).await?);
+            if services::block::has_blocked(&mut *conn, added.uuid, user.uuid).await? {
+                return Ok(error_reply(
+                    StatusCode::FORBIDDEN,
+                    "you can't add this user",
+                ));
+            }
+            if services::space::user_in_space(&mut *conn, space.uuid, &added).await? {
+                return Ok(error_reply(
+                    StatusCode::CONFLICT,
+                    "this user is already a member of the space",
+                ));
+            }
+
+            let (member, joined) =
+                services::space::join(&mut *conn, &space, &added, &role, &user).await?;
+            messages.extend(joined);
+
+            Ok(json_with_status(StatusCode::CREATED, &member))
+        })
+    })
+    .await
+}
+
+async fn leave_space(
+    space: Uuid,
+    pool: PgPool,
+    user: User,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    with_transaction_deferred(pool, move |conn, messages| {
+        Box::pin(async move {
+            let space = value_or_404!(services::space::get(&mut *conn, space).await?);
+            permissions::check_space(&mut *conn, &space, &user, Permissions::NONE).await?;
+
+            if space.is_owner(user.uuid) {
+                return Ok(error_reply(
+                    StatusCode::BAD_REQUEST,
+                    "you own this space and can't leave it",
+                ));
+            }
+            if services::space::owns_rooms(&mut *conn, &space, &user).await? {
+                return Ok(error_reply(
+                    StatusCode::BAD_REQUEST,
+                    "you own rooms in this space, transfer them to someone else before leaving",
+                ));
+            }
+
+            messages.extend(services::space::leave(&mut *conn, &space, &user).await?);
+
+            Ok(StatusCode::NO_CONTENT.into_response())
+        })
+    })
+    .await
+}
+
+async fn create_category(
+    space: Uuid,
+    data: CreateCategory,
+    pool: PgPool,
+    user: User,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    if let Err(message) = data.validate() {
+        return Ok(error_reply(StatusCode::BAD_REQUEST, &message));
+    }
+
+    with_transaction(pool, move |conn| {
+        Box::pin(async move {
+            let space = value_or_404!(services::space::get(&mut *conn, space).await?);
+            permissions::check_space(&mut *conn, &space, &user, Permissions::MANAGE_ROOM).await?;
+
+            let category =
+                services::space::create_category(&mut *conn, &space, data.name.trim()).await?;
+            services::space::broadcast_update(&mut *conn, &space).await?;
+
+            Ok(json_with_status(StatusCode::CREATED, &category))
+        })
+    })
+    .await
+    .map(Reply::into_response)
+}
+
+async fn update_category(
+    space: Uuid,
+    category: Uuid,
+    data: UpdateCategory,
+    pool: PgPool,
+    user: User,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    if let Err(message) = data.validate() {
+        return Ok(error_reply(StatusCode::BAD_REQUEST, &message));
+    }
+
+    with_transaction(pool, move |conn| {
+        Box::pin(async move {
+            let space = value_or_404!(services::space::get(&mut *conn, space).await?);
+            permissions::check_space(&mut *conn, &space, &user, Permissions::MANAGE_ROOM).await?;
+
+            let mut category = value_or_404!(
+                space
+                    .categories
+                    .iter()
+                    .find(|it| it.uuid == category)
+                    .cloned(),
+                "this category doesn't exist in the space"
+            );
+            if let Some(name) = data.name {
+                category.name = name.trim().to_string();
+            }
+            if let Some(position) = data.position {
+                category.position = position;
+            }
+            services::space::update_category(&mut *conn, &category).await?;
+            services::space::broadcast_update(&mut *conn, &space).await?;
+
+            Ok(warp::reply::json(&category).into_response())
+        })
+    })
+    .await
+    .map(Reply::into_response)
+}
+
+async fn delete_category(
+    space: Uuid,
+    category: Uuid,
+    pool: PgPool,
+    user: User,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    with_transaction(pool, move |conn| {
+        Box::pin(async move {
+            let space = value_or_404!(services::space::get(&mut *conn, space).await?);
+            permissions::check_space(&mut *conn, &space, &user, Permissions::MANAGE_ROOM).await?;
+
+            let category = value_or_404!(
+                space.categories.iter().find(|it| it.uuid == category),
+                "this category doesn't exist in the space"
+            );
+            services::space::delete_category(&mut *conn, category).await?;
+            services::space::broadcast_update(&mut *conn, &space).await?;
+
+            Ok(StatusCode::NO_CONTENT.into_response())
+        })
+    })
+    .await
+}
+
+/// Moves a room to another category of its space, or to another position in its own
+async fn move_room(
+    room: Uuid,
+    data: MoveRoom,
+    pool: PgPool,
+    user: User,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    with_transaction(pool, move |conn| {
+        Box::pin(async move {
+            let room = value_or_404!(services::room::get(&mut *conn, room).await?);
+            // arranging the space is up to its managers, whether or not they're in the room
+            let space = match room.space {
+                Some(space) => value_or_404!(services::space::get(&mut *conn, space).await?),
+                None => {
+                    return Ok(error_reply(
+                        StatusCode::BAD_REQUEST,
+                        "this room isn't in a space",
+                    ))
+                }
+            };
+            permissions::check_space(&mut *conn, &space, &user, Permissions::MANAGE_ROOM).await?;
+
+            if let Some(category) = data.category {
+                if !space.categories.iter().any(|it| it.uuid == category) {
+                    return Ok(error_reply(
+                        StatusCode::NOT_FOUND,
+                        "this category doesn't exist in the space",
+                    ));
+                }
+            }
+
+            let room =
+                services::space::move_room(&mut *conn, &room, data.category, data.position).await?;
+            services::room::broadcast_update(&mut *conn, room.clone()).await?;
+
+            Ok(warp::reply::json(&room).into_response())
+        })
+    })
+    .await
+}
+
+pub fn routes(
+    db: PgPool,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    let create_space_route = warp::path!("spaces")
+        .and(warp::post())
+        .and(json_body::<CreateSpace>())
+        .and(with_db(db.clone()))
+        .and(ensure_authorized(db.clone()))
+        .and_then(create_space);
+
+    let get_spaces_route = warp::path!("spaces")
+        .and(warp::get())
+        .and(with_db(db.clone()))
+        .and(ensure_authorized(db.clone()))
+        .and_then(get_spaces);
+
+    let get_space_route = warp::path!("spaces" / Uuid)
+        .and(warp::get())
+        .and(with_db(db.clone()))
+        .and(ensure_authorized(db.clone()))
+        .and_then(get_space);
+
+    let get_space_members_route = warp::path!("spaces" / Uuid / "members")
+        .and(warp::get())
+        .and(with_db(db.clone()))
+        .and(ensure_authorized(db.clone()))
+        .and_then(get_space_members);
+
+    let add_space_member_route = warp::path!("spaces" / Uuid / "members")
+        .and(warp::post())
+        .and(json_body::<JoinMembers>())
+        .and(with_db(db.clone()))
+        .and(ensure_authorized(db.clone()))
+        .and_then(add_space_member);
+
+    let leave_space_route = warp::path!("spaces" / Uuid / "leave")
+        .and(warp::post())
+        .and(with_db(db.clone()))
+        .and(ensure_authorized(db.clone()))
+        .and_then(leave_space);
+
+    let create_category_route = warp::path!("spaces" / Uuid / "categories")
+        .and(warp::post())
+        .and(json_body::<CreateCategory>())
+        .and(with_db(db.clone()))
+        .and(ensure_authorized(db.clone()))
+        .and_then(create_category);
+
+    let update_category_route = warp::path!("spaces" / Uuid / "categories" / Uuid)
+        .and(warp::patch())
+        .and(json_body::<UpdateCategory>())
+        .and(with_db(db.clone()))
+        .and(ensure_authorized(db.clone()))
+        .and_then(update_category);
+
+    let delete_category_route = warp::path!("spaces" / Uuid / "categories" / Uuid)
+        .and(warp::delete())
+        .and(with_db(db.clone()))
+        .and(ensure_authorized(db.clone()))
+        .and_then(delete_category);
+
+    let move_room_route = warp::path!("rooms" / Uuid / "position")
+        .and(warp::put())
+        .and(json_body::<MoveRoom>())
+        .and(with_db(db.clone()))
+        .and(ensure_authorized(db))
+        .and_then(move_room);
+
+    create_space_route
+        .or(get_spaces_route)
+        .or(get_space_route)
+        .or(get_space_members_route)
+        .or(add_space_member_route)
+        .or(leave_space_route)
+        .or(create_category_route)
+        .or(update_category_route)
+        .or(delete_category_route)
+        .or(move_room_route)
+}
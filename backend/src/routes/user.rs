@@ -1,9 +1,13 @@
-use crate::utils::{ensure_authorized, with_db, with_transaction, AssetExt};
-use crate::{bail_if_err, bail_if_err_or_404, update_fields};
+use crate::utils::{
+    ensure_authorized, error_reply, json_body, with_db, with_transaction, AssetExt,
+};
+use crate::{bail_if_err, bail_if_err_or_404, update_fields, value_or_404};
 use crate::{services, utils};
+use common::payloads::UpdateProfile;
 use common::{Asset, User};
 use sqlx::types::Uuid;
 use sqlx::PgPool;
+use warp::http::StatusCode;
 use warp::{Filter, Reply};
 
 async fn get_user(uuid: Uuid, pool: PgPool) -> Result<impl warp::Reply, warp::Rejection> {
@@ -58,6 +62,106 @@ async fn update_avatar(
     .await
 }
 
+async fn update_profile(
+    data: UpdateProfile,
+    pool: PgPool,
+    mut user: User,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    if let Err(message) = data.validate() {
+        return Ok(error_reply(StatusCode::BAD_REQUEST, &message));
+    }
+
+    with_transaction(pool, |conn| {
+        Box::pin(async move {
+            let UpdateProfile {
+                display_name,
+                bio,
+                pronouns,
+                timezone,
+                status,
+            } = data;
+
+            if let Some(display_name) = display_name {
+                user.display_name = display_name;
+            }
+            if let Some(bio) = bio {
+                user.bio = bio;
+            }
+            if let Some(pronouns) = pronouns {
+                user.pronouns = pronouns;
+            }
+            if let Some(timezone) = timezone {
+                user.timezone = timezone;
+            }
+            if let Some(status) = status {
+                user.status = status;
+            }
+
+            let user = services::user::update(conn, user).await?;
+
+            Ok(warp::reply::json(&user).into_response())
+        })
+    })
+    .await
+    .map(Reply::into_response)
+}
+
+async fn get_blocks(pool: PgPool, user: User) -> Result<impl warp::Reply, warp::Rejection> {
+    with_transaction(pool, move |conn| {
+        Box::pin(async move {
+            let blocked = services::block::get_blocked(&mut *conn, &user).await?;
+
+            Ok(warp::reply::json(&blocked).into_response())
+        })
+    })
+    .await
+}
+
+/// Blocks the user, which is fine to do again
+async fn block_user(
+    blocked: Uuid,
+    pool: PgPool,
+    user: User,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    if blocked == user.uuid {
+        return Ok(error_reply(
+            StatusCode::BAD_REQUEST,
+            "you can't block yourself",
+        ));
+    }
+
+    with_transaction(pool, move |conn| {
+        Box::pin(async move {
+            let blocked = value_or_404!(services::user::get(&mut *conn, blocked).await?);
+            services::block::block(&mut *conn, &user, &blocked).await?;
+
+            Ok(StatusCode::NO_CONTENT.into_response())
+        })
+    })
+    .await
+    .map(Reply::into_response)
+}
+
+async fn unblock_user(
+    blocked: Uuid,
+    pool: PgPool,
+    user: User,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    with_transaction(pool, move |conn| {
+        Box::pin(async move {
+            if !services::block::unblock(&mut *conn, &user, blocked).await? {
+                return Ok(error_reply(
+                    StatusCode::NOT_FOUND,
+                    "this user isn't blocked",
+                ));
+            }
+
+            Ok(StatusCode::NO_CONTENT.into_response())
+        })
+    })
+    .await
+}
+
 pub fn routes(
     db: PgPool,
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
@@ -76,15 +180,44 @@ pub fn routes(
         .and(ensure_authorized(db.clone()))
         .and_then(get_me);
 
+    let update_profile_route = warp::path!("users" / "me")
+        .and(warp::patch())
+        .and(json_body::<UpdateProfile>())
+        .and(with_db(db.clone()))
+        .and(ensure_authorized(db.clone()))
+        .and_then(update_profile);
+
     let update_avatar_route = warp::path!("users" / "me" / "avatar")
         .and(warp::put())
         .and(with_db(db.clone()))
-        .and(ensure_authorized(db))
+        .and(ensure_authorized(db.clone()))
         .and(utils::multipart())
         .and_then(update_avatar);
 
+    let get_blocks_route = warp::path!("users" / "me" / "blocks")
+        .and(warp::get())
+        .and(with_db(db.clone()))
+        .and(ensure_authorized(db.clone()))
+        .and_then(get_blocks);
+
+    let block_user_route = warp::path!("users" / "me" / "blocks" / Uuid)
+        .and(warp::put())
+        .and(with_db(db.clone()))
+        .and(ensure_authorized(db.clone()))
+        .and_then(block_user);
+
+    let unblock_user_route = warp::path!("users" / "me" / "blocks" / Uuid)
+        .and(warp::delete())
+        .and(with_db(db.clone()))
+        .and(ensure_authorized(db))
+        .and_then(unblock_user);
+
     get_me_route
         .or(get_user_route)
         .or(get_by_username_route)
+        .or(update_profile_route)
         .or(update_avatar_route)
+        .or(get_blocks_route)
+        .or(block_user_route)
+        .or(unblock_user_route)
 }
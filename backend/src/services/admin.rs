@@ -0,0 +1,337 @@
+use crate::services;
+use crate::utils::contains_pattern;
+use crate::websocket;
+use chrono::{DateTime, Utc};
+use common::websocket::{ErrorCode, ErrorPayload};
+use common::{
+    InstanceStats, PostPermission, Room, RoomKind, RoomOverview, RoomVisibility, User, UserAccount,
+};
+use sqlx::types::Uuid;
+use sqlx::PgConnection;
+use tracing::{debug, instrument};
+
+/// What instance admins decided about an account
+#[derive(Debug)]
+pub struct AccountState {
+    pub suspended_at: Option<DateTime<Utc>>,
+    pub suspension_reason: Option<String>,
+    pub password_reset_required: bool,
+    /// Tokens issued before this are refused
+    pub sessions_revoked_at: Option<DateTime<Utc>>,
+}
+
+pub async fn get_state(db: &mut PgConnection, user: Uuid) -> anyhow::Result<Option<AccountState>> {
+    let state = sqlx::query!(
+        "
+            select suspended_at, suspension_reason, password_reset_required, sessions_revoked_at
+            from users
+            where uuid = $1;
+        ",
+        user
+    )
+    .fetch_optional(db)
+    .await?
+    .map(|it| AccountState {
+        suspended_at: it.suspended_at,
+        suspension_reason: it.suspension_reason,
+        password_reset_required: it.password_reset_required,
+        sessions_revoked_at: it.sessions_revoked_at,
+    });
+
+    Ok(state)
+}
+
+pub async fn get_account(db: &mut PgConnection, uuid: Uuid) -> anyhow::Result<Option<UserAccount>> {
+    let user = match services::user::get(&mut *db, uuid).await? {
+        Some(user) => user,
+        None => return Ok(None),
+    };
+    // the user may have been deleted in between
+    let state = match get_state(db, uuid).await? {
+        Some(state) => state,
+        None => return Ok(None),
+    };
+
+    Ok(Some(UserAccount {
+        user,
+        suspended_at: state.suspended_at,
+        suspension_reason: state.suspension_reason,
+        password_reset_required: state.password_reset_required,
+    }))
+}
+
+/// A page of the accounts whose username or display name contains `search`, the oldest first
+#[instrument]
+pub async fn search_accounts(
+    db: &mut PgConnection,
+    search: Option<&str>,
+    offset: i64,
+    limit: i64,
+) -> anyhow::Result<Vec<UserAccount>> {
+    debug!("searching accounts");
+    let pattern = search.map(contains_pattern);
+
+    let ids = sqlx::query!(
+        "
+            select uuid
+            from users
+            where $1::text is null
+               or username ilike $1
+               or display_name ilike $1
+            order by created_at, username
+            offset $2 limit $3;
+        ",
+        pattern,
+        offset,
+        limit
+    )
+    .fetch_all(&mut *db)
+    .await?;
+
+    let mut accounts = Vec::with_capacity(ids.len());
+    for it in ids {
+        accounts.extend(get_account(&mut *db, it.uuid).await?);
+    }
+
+    Ok(accounts)
+}
+
+/// Suspends the user, refusing the tokens they have.
+///
+/// They can't sign in again until they're unsuspended. Their websockets are left to
+/// [`revoke_sessions`] once this is committed.
+#[instrument]
+pub async fn suspend(
+    db: &mut PgConnection,
+    user: &User,
+    reason: Option<&str>,
+) -> anyhow::Result<UserAccount> {
+    debug!("suspending user");
+    sqlx::query!(
+        "
+            update users
+            set suspended_at        = now(),
+                suspension_reason   = $2,
+                sessions_revoked_at = now()
+            where uuid = $1;
+        ",
+        user.uuid,
+        reason
+    )
+    .execute(&mut *db)
+    .await?;
+
+    get_account(db, user.uuid)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("updated user doesn't exist"))
+}
+
+#[instrument]
+pub async fn unsuspend(db: &mut PgConnection, user: &User) -> anyhow::Result<UserAccount> {
+    debug!("unsuspending user");
+    sqlx::query!(
+        "
+            update users
+            set suspended_at      = null,
+                suspension_reason = null
+            where uuid = $1;
+        ",
+        user.uuid
+    )
+    .execute(&mut *db)
+    .await?;
+
+    get_account(db, user.uuid)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("updated user doesn't exist"))
+}
+
+/// Refuses the tokens the user has, they have to choose a new password to sign in again.
+///
+/// It isn't a lockout, the current password is all it takes to choose the new one.
+/// Their websockets are left to [`revoke_sessions`] once this is committed.
+#[instrument]
+pub async fn require_password_reset(
+    db: &mut PgConnection,
+    user: &User,
+) -> anyhow::Result<UserAccount> {
+    debug!("requiring password reset");
+    sqlx::query!(
+        "
+            update users
+            set password_reset_required = true,
+                sessions_revoked_at     = now()
+            where uuid = $1;
+        ",
+        user.uuid
+    )
+    .execute(&mut *db)
+    .await?;
+
+    get_account(db, user.uuid)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("updated user doesn't exist"))
+}
+
+#[instrument]
+pub async fn set_instance_admin(
+    db: &mut PgConnection,
+    user: &User,
+    is_instance_admin: bool,
+) -> anyhow::Result<UserAccount> {
+    debug!("setting instance admin");
+    sqlx::query!(
+        "update users set is_instance_admin = $2 where uuid = $1;",
+        user.uuid,
+        is_instance_admin
+    )
+    .execute(&mut *db)
+    .await?;
+
+    get_account(db, user.uuid)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("updated user doesn't exist"))
+}
+
+/// Ids of the instance admins, used to address websocket messages
+pub async fn get_instance_admin_ids(db: &mut PgConnection) -> anyhow::Result<Vec<Uuid>> {
+    let admins = sqlx::query!("select uuid from users where is_instance_admin;")
+        .fetch_all(db)
+        .await?
+        .into_iter()
+        .map(|it| it.uuid)
+        .collect();
+
+    Ok(admins)
+}
+
+/// Deletes the user along with their messages and memberships.
+///
+/// Rooms they own are left without an owner. The other members only see the messages go
+/// once they load the room again. Only the row of the avatar is deleted, its file is left
+/// to the caller and the websockets to [`revoke_sessions`] once this is committed.
+#[instrument]
+pub async fn delete_user(db: &mut PgConnection, user: &User) -> anyhow::Result<()> {
+    debug!("deleting user");
+    let group_dms = services::room::get_with_user(&mut *db, user)
+        .await?
+        .into_iter()
+        .filter(|room| room.kind == RoomKind::GroupDm)
+        .collect::<Vec<_>>();
+
+    sqlx::query!("delete from users where uuid = $1;", user.uuid)
+        .execute(&mut *db)
+        .await?;
+    if let Some(avatar) = &user.avatar {
+        services::asset::delete(&mut *db, avatar).await?;
+    }
+
+    for room in group_dms.iter() {
+        services::room::refresh_dm_name(&mut *db, room).await?;
+    }
+
+    Ok(())
+}
+
+/// A page of every room of the instance, DMs included, the newest first
+#[instrument]
+pub async fn search_rooms(
+    db: &mut PgConnection,
+    search: Option<&str>,
+    offset: i64,
+    limit: i64,
+) -> anyhow::Result<Vec<RoomOverview>> {
+    debug!("searching rooms");
+    let pattern = search.map(contains_pattern);
+
+    let res = sqlx::query!(
+        r#"
+            select r.uuid, r.name, r.created_at, r.icon, r.topic, r.archived_at, r.owner,
+                r.slow_mode, r.space_id, r.category_id, r.position,
+                r.kind as "kind: RoomKind",
+                r.visibility as "visibility: RoomVisibility",
+                r.post_permission as "post_permission: PostPermission",
+                (select count(*) from room_members m where m.room_id = r.uuid) as "member_count!",
+                (select count(*) from messages m where m.room = r.uuid) as "message_count!"
+            from rooms r
+            where $1::text is null or r.name ilike $1
+            order by r.created_at desc
+            offset $2 limit $3;
+        "#,
+        pattern,
+        offset,
+        limit
+    )
+    .fetch_all(&mut *db)
+    .await?;
+
+    let mut rooms = Vec::with_capacity(res.len());
+    for room in res {
+        rooms.push(RoomOverview {
+            room: Room {
+                uuid: room.uuid,
+                name: room.name,
+                created_at: room.created_at,
+                icon: services::asset::get_from_option(db, room.icon).await?,
+                kind: room.kind,
+                visibility: room.visibility,
+                topic: room.topic,
+                archived_at: room.archived_at,
+                owner: room.owner,
+                slow_mode: room.slow_mode,
+                post_permission: room.post_permission,
+                space: room.space_id,
+                category: room.category_id,
+                position: room.position,
+            },
+            member_count: room.member_count,
+            message_count: room.message_count,
+        });
+    }
+
+    Ok(rooms)
+}
+
+pub async fn get_stats(db: &mut PgConnection) -> anyhow::Result<InstanceStats> {
+    let stats = sqlx::query!(
+        r#"
+            select (select count(*) from users) as "users!",
+                   (select count(*) from users where suspended_at is not null) as "suspended_users!",
+                   (select count(*) from rooms) as "rooms!",
+                   (select count(*) from spaces) as "spaces!",
+                   (select count(*) from messages) as "messages!",
+                   (select count(*)
+                    from messages
+                    where created_at > now() - interval '1 day') as "recent_messages!",
+                   (select count(distinct user_id)
+                    from user_sessions
+                    where last_seen > now() - interval '1 minute') as "online_users!",
+                   (select count(*)
+                    from reports
+                    where scope = 'instance'
+                      and resolved_at is null) as "open_reports!";
+        "#
+    )
+    .fetch_one(db)
+    .await?;
+
+    Ok(InstanceStats {
+        users: stats.users,
+        suspended_users: stats.suspended_users,
+        rooms: stats.rooms,
+        spaces: stats.spaces,
+        messages: stats.messages,
+        recent_messages: stats.recent_messages,
+        online_users: stats.online_users,
+        open_reports: stats.open_reports,
+    })
+}
+
+/// Closes the websockets of the user, their tokens have to be refused by then
+pub async fn revoke_sessions(user: &User, reason: &str) {
+    websocket::disconnect_users(
+        vec![user.uuid],
+        ErrorPayload::new(ErrorCode::SessionRevoked, reason),
+    )
+    .await;
+}
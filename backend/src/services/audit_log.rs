@@ -0,0 +1,92 @@
+use common::payloads::AuditLogQuery;
+use common::{AuditAction, AuditLogEntry, Room, User};
+use serde_json::{json, Map, Value};
+use sqlx::types::Uuid;
+use sqlx::PgConnection;
+use tracing::{debug, instrument};
+
+/// Adds an entry to the audit log of the room.
+///
+/// The log goes along with the room when it's deleted.
+#[instrument]
+pub async fn record(
+    db: &mut PgConnection,
+    room: Uuid,
+    actor: &User,
+    action: AuditAction,
+    target: Option<Uuid>,
+    changes: Value,
+) -> anyhow::Result<()> {
+    debug!("recording audit log entry");
+    sqlx::query!(
+        "
+            insert into audit_log (uuid, room_id, actor, target, action, changes)
+            values ($1, $2, $3, $4, $5, $6);
+        ",
+        Uuid::new_v4(),
+        room,
+        actor.uuid,
+        target,
+        action as _,
+        changes
+    )
+    .execute(db)
+    .await?;
+
+    Ok(())
+}
+
+/// `{"field": {"old": .., "new": ..}}` for each of the fields whose value changed
+pub fn diff(fields: Vec<(&str, Value, Value)>) -> Value {
+    let changed = fields
+        .into_iter()
+        .filter(|(_, old, new)| old != new)
+        .map(|(field, old, new)| (field.to_string(), json!({ "old": old, "new": new })))
+        .collect::<Map<_, _>>();
+
+    Value::Object(changed)
+}
+
+/// A page of the audit log of the room, the newest entries first
+#[instrument]
+pub async fn get(
+    db: &mut PgConnection,
+    room: &Room,
+    query: &AuditLogQuery,
+) -> anyhow::Result<Vec<AuditLogEntry>> {
+    debug!("fetching audit log");
+    let entries = sqlx::query!(
+        r#"
+            select uuid, room_id, actor, target, changes, created_at,
+                action as "action: AuditAction"
+            from audit_log
+            where room_id = $1
+              and ($2::audit_action is null or action = $2)
+              and ($3::uuid is null or actor = $3)
+              and ($4::uuid is null or target = $4)
+            order by created_at desc
+            offset $5 limit $6;
+        "#,
+        room.uuid,
+        query.action as _,
+        query.actor,
+        query.target,
+        query.offset,
+        query.limit
+    )
+    .fetch_all(db)
+    .await?;
+
+    Ok(entries
+        .into_iter()
+        .map(|entry| AuditLogEntry {
+            uuid: entry.uuid,
+            room: entry.room_id,
+            actor: entry.actor,
+            target: entry.target,
+            action: entry.action,
+            changes: entry.changes,
+            created_at: entry.created_at,
+        })
+        .collect())
+}
@@ -0,0 +1,320 @@
+use common::errors::ApiError;
+use common::payloads::CreateAutomodRule;
+use common::{
+    parse_mentions, AutomodAction, AutomodRule, AutomodRuleKind, ReportScope, Room, User,
+};
+use lazy_static::lazy_static;
+use regex::Regex;
+use sqlx::types::Uuid;
+use sqlx::PgConnection;
+use std::ops::Range;
+use tracing::{debug, instrument};
+use warp::http::StatusCode;
+
+lazy_static! {
+    // the host is everything up to the path, the query or the fragment
+    static ref LINK: Regex = Regex::new(r"(?i)\bhttps?://([^\s/?#<>]+)[^\s<>]*").unwrap();
+}
+
+macro_rules! rule {
+    ($value:expr) => {{
+        let value = $value;
+        AutomodRule {
+            uuid: value.uuid,
+            room: value.room_id,
+            kind: value.kind,
+            patterns: value.patterns,
+            max_mentions: value.max_mentions,
+            action: value.action,
+            created_by: value.created_by,
+            created_at: value.created_at,
+        }
+    }};
+}
+
+/// Creates a rule for the room, or for every room of the instance without one
+#[instrument]
+pub async fn create(
+    db: &mut PgConnection,
+    room: Option<&Room>,
+    data: &CreateAutomodRule,
+    created_by: Option<&User>,
+) -> anyhow::Result<AutomodRule> {
+    debug!("creating automod rule");
+    let patterns = data
+        .patterns
+        .iter()
+        .map(|it| it.trim().to_string())
+        .collect::<Vec<_>>();
+
+    let inserted = sqlx::query!(
+        r#"
+            insert into automod_rules (uuid, room_id, kind, patterns, max_mentions, action,
+                                       created_by)
+            values ($1, $2, $3, $4, $5, $6, $7)
+            returning uuid, room_id, kind as "kind: AutomodRuleKind", patterns, max_mentions,
+                action as "action: AutomodAction", created_by, created_at;
+        "#,
+        Uuid::new_v4(),
+        room.map(|it| it.uuid),
+        data.kind as _,
+        &patterns,
+        data.max_mentions,
+        data.action as _,
+        created_by.map(|it| it.uuid)
+    )
+    .fetch_one(db)
+    .await?;
+
+    Ok(rule!(inserted))
+}
+
+/// The rule of the room, or of the instance without one
+pub async fn get(
+    db: &mut PgConnection,
+    room: Option<&Room>,
+    uuid: Uuid,
+) -> anyhow::Result<Option<AutomodRule>> {
+    let rule = sqlx::query!(
+        r#"
+            select uuid, room_id, kind as "kind: AutomodRuleKind", patterns, max_mentions,
+                action as "action: AutomodAction", created_by, created_at
+            from automod_rules
+            where uuid = $1
+              and room_id is not distinct from $2;
+        "#,
+        uuid,
+        room.map(|it| it.uuid)
+    )
+    .fetch_optional(db)
+    .await?
+    .map(|it| rule!(it));
+
+    Ok(rule)
+}
+
+/// The rules of the room, or those of the instance without one
+pub async fn get_all(
+    db: &mut PgConnection,
+    room: Option<&Room>,
+) -> anyhow::Result<Vec<AutomodRule>> {
+    let rules = sqlx::query!(
+        r#"
+            select uuid, room_id, kind as "kind: AutomodRuleKind", patterns, max_mentions,
+                action as "action: AutomodAction", created_by, created_at
+            from automod_rules
+            where room_id is not distinct from $1
+            order by created_at;
+        "#,
+        room.map(|it| it.uuid)
+    )
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .map(|it| rule!(it))
+    .collect();
+
+    Ok(rules)
+}
+
+/// The rules messages posted in the room go through, those of the instance first
+pub async fn get_applicable(
+    db: &mut PgConnection,
+    room: &Room,
+) -> anyhow::Result<Vec<AutomodRule>> {
+    let rules = sqlx::query!(
+        r#"
+            select uuid, room_id, kind as "kind: AutomodRuleKind", patterns, max_mentions,
+                action as "action: AutomodAction", created_by, created_at
+            from automod_rules
+            where room_id is null
+               or room_id = $1
+            order by room_id nulls first, created_at;
+        "#,
+        room.uuid
+    )
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .map(|it| rule!(it))
+    .collect();
+
+    Ok(rules)
+}
+
+#[instrument]
+pub async fn delete(db: &mut PgConnection, rule: &AutomodRule) -> anyhow::Result<()> {
+    debug!("deleting automod rule");
+    sqlx::query!("delete from automod_rules where uuid = $1;", rule.uuid)
+        .execute(db)
+        .await?;
+
+    Ok(())
+}
+
+/// Checks that the regexes of a new rule compile and can't match an empty string,
+/// naming the first one that doesn't
+pub fn validate_regexes(data: &CreateAutomodRule) -> Result<(), String> {
+    if data.kind != AutomodRuleKind::Regex {
+        return Ok(());
+    }
+
+    data.patterns.iter().try_for_each(|pattern| {
+        let regex =
+            Regex::new(pattern.trim()).map_err(|_| format!("`{}` isn't a valid regex", pattern))?;
+        if regex.is_match("") {
+            return Err(format!("`{}` matches empty text", pattern));
+        }
+        Ok(())
+    })
+}
+
+/// A rule that flagged a message, it's reported once the message is posted
+#[derive(Debug)]
+pub struct Flag {
+    /// Room rules are reviewed by the admins of the room, instance rules by instance admins
+    pub scope: ReportScope,
+    pub reason: String,
+}
+
+/// A message the rules let through
+#[derive(Debug)]
+pub struct Checked {
+    /// The content with what the rules mask replaced
+    pub content: String,
+    pub flags: Vec<Flag>,
+}
+
+/// Runs the content of a message through the rules.
+///
+/// Rejecting and flagging rules look at what was written, masking rules go last so that
+/// they can't hide anything from the others. Fails with a `400` if a rule rejects the message.
+pub fn check(rules: &[AutomodRule], content: &str) -> anyhow::Result<Checked> {
+    if let Some(rule) = broken_by(rules, AutomodAction::Reject, content)?.first() {
+        let message = format!("your message contains {}", rule.describe());
+        return Err(
+            ApiError::new_with_message_and_status(&message, StatusCode::BAD_REQUEST).into(),
+        );
+    }
+
+    let flags = broken_by(rules, AutomodAction::Flag, content)?
+        .into_iter()
+        .map(|rule| Flag {
+            scope: match rule.room {
+                Some(_) => ReportScope::Room,
+                None => ReportScope::Instance,
+            },
+            reason: format!("automod: the message contains {}", rule.describe()),
+        })
+        .collect();
+
+    let mut content = content.to_string();
+    for rule in rules.iter().filter(|it| it.action == AutomodAction::Mask) {
+        let found = violations(rule, &content)?;
+        content = mask(&content, found);
+    }
+
+    Ok(Checked { content, flags })
+}
+
+/// The rules with the action that the content breaks
+fn broken_by<'a>(
+    rules: &'a [AutomodRule],
+    action: AutomodAction,
+    content: &str,
+) -> anyhow::Result<Vec<&'a AutomodRule>> {
+    let mut broken = vec![];
+    for rule in rules.iter().filter(|it| it.action == action) {
+        if !violations(rule, content)?.is_empty() {
+            broken.push(rule);
+        }
+    }
+
+    Ok(broken)
+}
+
+/// Where the content breaks the rule, empty if it doesn't
+fn violations(rule: &AutomodRule, content: &str) -> anyhow::Result<Vec<Range<usize>>> {
+    let found = match rule.kind {
+        AutomodRuleKind::BannedWords => {
+            let words = rule
+                .patterns
+                .iter()
+                .map(|it| regex::escape(it))
+                .collect::<Vec<_>>()
+                .join("|");
+            let regex = Regex::new(&format!(r"(?i)\b(?:{})\b", words))?;
+            regex.find_iter(content).map(|it| it.range()).collect()
+        }
+        AutomodRuleKind::Regex => {
+            let mut found = vec![];
+            for pattern in &rule.patterns {
+                let regex = Regex::new(pattern)?;
+                // a pattern like `x*` matches between every character otherwise
+                found.extend(
+                    regex
+                        .find_iter(content)
+                        .map(|it| it.range())
+                        .filter(|it| !it.is_empty()),
+                );
+            }
+            found
+        }
+        AutomodRuleKind::LinkAllowList | AutomodRuleKind::LinkDenyList => {
+            let denied = rule.kind == AutomodRuleKind::LinkDenyList;
+            LINK.captures_iter(content)
+                .filter(|link| {
+                    let host = host(&link[1]);
+                    let listed = rule.patterns.iter().any(|it| is_on_domain(&host, it));
+                    listed == denied
+                })
+                .filter_map(|link| link.get(0).map(|it| it.range()))
+                .collect()
+        }
+        AutomodRuleKind::MaxMentions => {
+            let max = rule.max_mentions.unwrap_or(0).max(0) as usize;
+            parse_mentions(content)
+                .skip(max)
+                .map(|(range, _)| range)
+                .collect()
+        }
+    };
+
+    Ok(found)
+}
+
+/// The host of a link without the credentials and the port, `user@Example.com:80` is `example.com`
+fn host(authority: &str) -> String {
+    let host = authority.rsplit('@').next().unwrap_or(authority);
+    let host = host.split(':').next().unwrap_or(host);
+    host.trim_end_matches('.').to_lowercase()
+}
+
+/// Whether the host is the domain or one of its subdomains
+fn is_on_domain(host: &str, domain: &str) -> bool {
+    let domain = domain
+        .trim_start_matches("*.")
+        .trim_matches('.')
+        .to_lowercase();
+    host == domain || host.ends_with(&format!(".{}", domain))
+}
+
+/// Replaces every character in the ranges with `*`, they may overlap
+fn mask(content: &str, mut ranges: Vec<Range<usize>>) -> String {
+    ranges.sort_by_key(|it| it.start);
+
+    let mut masked = String::with_capacity(content.len());
+    let mut end = 0;
+    for range in ranges {
+        let start = range.start.max(end);
+        if start >= range.end {
+            continue;
+        }
+        masked.push_str(&content[end..start]);
+        masked.extend(content[start..range.end].chars().map(|_| '*'));
+        end = range.end;
+    }
+    masked.push_str(&content[end..]);
+
+    masked
+}
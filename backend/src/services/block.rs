@@ -0,0 +1,120 @@
+use crate::services;
+use common::User;
+use sqlx::types::Uuid;
+use sqlx::PgConnection;
+use tracing::{debug, instrument};
+
+/// Blocks the user, blocking them again does nothing
+#[instrument]
+pub async fn block(db: &mut PgConnection, blocker: &User, blocked: &User) -> anyhow::Result<()> {
+    debug!("blocking user");
+    sqlx::query!(
+        "
+            insert into user_blocks(blocker_id, blocked_id)
+            values ($1, $2)
+            on conflict do nothing;
+        ",
+        blocker.uuid,
+        blocked.uuid
+    )
+    .execute(db)
+    .await?;
+
+    Ok(())
+}
+
+/// Unblocks the user, returning whether they were blocked
+#[instrument]
+pub async fn unblock(db: &mut PgConnection, blocker: &User, blocked: Uuid) -> anyhow::Result<bool> {
+    debug!("unblocking user");
+    let result = sqlx::query!(
+        "
+            delete
+            from user_blocks
+            where blocker_id = $1
+              and blocked_id = $2;
+        ",
+        blocker.uuid,
+        blocked
+    )
+    .execute(db)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// The users the user blocked, the most recently blocked first
+pub async fn get_blocked(db: &mut PgConnection, user: &User) -> anyhow::Result<Vec<User>> {
+    let ids = sqlx::query!(
+        "
+            select blocked_id
+            from user_blocks
+            where blocker_id = $1
+            order by created_at desc;
+        ",
+        user.uuid
+    )
+    .fetch_all(&mut *db)
+    .await?;
+
+    let mut users = Vec::with_capacity(ids.len());
+    for it in ids {
+        if let Some(user) = services::user::get(&mut *db, it.blocked_id).await? {
+            users.push(user);
+        }
+    }
+
+    Ok(users)
+}
+
+pub async fn get_blocked_ids(db: &mut PgConnection, user: Uuid) -> anyhow::Result<Vec<Uuid>> {
+    let ids = sqlx::query!(
+        "select blocked_id from user_blocks where blocker_id = $1;",
+        user
+    )
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .map(|it| it.blocked_id)
+    .collect();
+
+    Ok(ids)
+}
+
+/// The users who blocked the user
+pub async fn get_blocker_ids(db: &mut PgConnection, user: Uuid) -> anyhow::Result<Vec<Uuid>> {
+    let ids = sqlx::query!(
+        "select blocker_id from user_blocks where blocked_id = $1;",
+        user
+    )
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .map(|it| it.blocker_id)
+    .collect();
+
+    Ok(ids)
+}
+
+pub async fn has_blocked(
+    db: &mut PgConnection,
+    blocker: Uuid,
+    blocked: Uuid,
+) -> anyhow::Result<bool> {
+    Ok(sqlx::query!(
+        "
+            select exists(
+                select 1
+                from user_blocks
+                where blocker_id = $1
+                  and blocked_id = $2
+            ) as has_blocked;
+        ",
+        blocker,
+        blocked
+    )
+    .fetch_one(db)
+    .await?
+    .has_blocked
+    .unwrap_or(false))
+}
@@ -0,0 +1,170 @@
+use crate::services;
+use common::payloads::CreateInvite;
+use common::{AuditAction, Invite, Room, User};
+use serde_json::json;
+use sqlx::types::Uuid;
+use sqlx::PgConnection;
+use tracing::{debug, instrument};
+
+const CODE_LENGTH: usize = 10;
+const CODE_ALPHABET: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+macro_rules! invite {
+    ($row:expr) => {{
+        let row = $row;
+        Invite {
+            code: row.code,
+            room: row.room_id,
+            created_by: row.created_by,
+            role: row.role_id,
+            max_uses: row.max_uses,
+            uses: row.uses,
+            expires_at: row.expires_at,
+            created_at: row.created_at,
+        }
+    }};
+}
+
+/// A short random code, short enough to be typed out from a link
+fn generate_code() -> String {
+    let mut bits = Uuid::new_v4().as_u128();
+    let base = CODE_ALPHABET.len() as u128;
+
+    (0..CODE_LENGTH)
+        .map(|_| {
+            let c = CODE_ALPHABET[(bits % base) as usize] as char;
+            bits /= base;
+            c
+        })
+        .collect()
+}
+
+#[instrument]
+pub async fn create(
+    db: &mut PgConnection,
+    room: &Room,
+    created_by: &User,
+    data: &CreateInvite,
+) -> anyhow::Result<Invite> {
+    debug!("creating invite");
+    let invite = sqlx::query!(
+        "
+            insert into room_invites (code, room_id, created_by, role_id, max_uses, expires_at)
+            values ($1, $2, $3, $4, $5, $6)
+            returning *;
+        ",
+        generate_code(),
+        room.uuid,
+        created_by.uuid,
+        data.role,
+        data.max_uses,
+        data.expires_at
+    )
+    .fetch_one(&mut *db)
+    .await?;
+    let invite = invite!(invite);
+
+    services::audit_log::record(
+        db,
+        room.uuid,
+        created_by,
+        AuditAction::InviteCreate,
+        None,
+        json!({
+            "code": invite.code,
+            "role": invite.role,
+            "max_uses": invite.max_uses,
+            "expires_at": invite.expires_at,
+        }),
+    )
+    .await?;
+
+    Ok(invite)
+}
+
+#[instrument]
+pub async fn get(db: &mut PgConnection, code: &str) -> anyhow::Result<Option<Invite>> {
+    debug!("fetching invite");
+    let invite = sqlx::query!("select * from room_invites where code = $1;", code)
+        .fetch_optional(db)
+        .await?;
+
+    Ok(invite.map(|invite| invite!(invite)))
+}
+
+#[instrument]
+pub async fn get_all(db: &mut PgConnection, room: &Room) -> anyhow::Result<Vec<Invite>> {
+    debug!("fetching invites");
+    let invites = sqlx::query!(
+        "
+            select *
+            from room_invites
+            where room_id = $1
+            order by created_at desc;
+        ",
+        room.uuid
+    )
+    .fetch_all(db)
+    .await?;
+
+    Ok(invites.into_iter().map(|invite| invite!(invite)).collect())
+}
+
+/// Deletes the invite, returning whether it existed
+#[instrument]
+pub async fn delete(
+    db: &mut PgConnection,
+    room: &Room,
+    code: &str,
+    deleted_by: &User,
+) -> anyhow::Result<bool> {
+    debug!("deleting invite");
+    let result = sqlx::query!(
+        "
+            delete from room_invites
+            where room_id = $1
+              and code = $2;
+        ",
+        room.uuid,
+        code
+    )
+    .execute(&mut *db)
+    .await?;
+
+    let deleted = result.rows_affected() > 0;
+    if deleted {
+        services::audit_log::record(
+            db,
+            room.uuid,
+            deleted_by,
+            AuditAction::InviteRevoke,
+            None,
+            json!({ "code": code }),
+        )
+        .await?;
+    }
+
+    Ok(deleted)
+}
+
+/// Counts a use of the invite, returning `false` if it has expired or ran out of uses.
+///
+/// The check is done by the update itself so concurrent redemptions can't go over the limit.
+#[instrument]
+pub async fn consume(db: &mut PgConnection, code: &str) -> anyhow::Result<bool> {
+    debug!("consuming invite");
+    let result = sqlx::query!(
+        "
+            update room_invites
+            set uses = uses + 1
+            where code = $1
+              and (max_uses is null or uses < max_uses)
+              and (expires_at is null or expires_at > now());
+        ",
+        code
+    )
+    .execute(db)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
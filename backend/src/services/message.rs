@@ -1,9 +1,14 @@
-use crate::websocket;
+use crate::websocket::pubsub::Recipients;
+use crate::{services, websocket};
 use common::websocket::{MessagePayload, OpCode};
-use common::{Asset, Message, MessageType, Room, User};
+use common::{Asset, AuditAction, Message, MessageType, Room, User};
+use serde_json::json;
+use sqlx::types::Uuid;
 use sqlx::PgConnection;
 use std::sync::Arc;
+use tracing::{debug, instrument};
 
+/// Posts the message, those members write go through the automod rules of the room first
 pub async fn create(db: &mut PgConnection, message: Message) -> anyhow::Result<Message> {
     let Message {
         uuid,
@@ -12,8 +17,17 @@ pub async fn create(db: &mut PgConnection, message: Message) -> anyhow::Result<M
         content,
         created_at: _,
         type_,
+        blocked: _,
     } = message;
 
+    let (content, flags) = if type_ == MessageType::Default {
+        let rules = services::automod::get_applicable(&mut *db, &room).await?;
+        let checked = services::automod::check(&rules, &content)?;
+        (checked.content, checked.flags)
+    } else {
+        (content, vec![])
+    };
+
     let inserted = sqlx::query!(
         r#"
             insert into messages(uuid, author, room, content, type)
@@ -26,7 +40,7 @@ pub async fn create(db: &mut PgConnection, message: Message) -> anyhow::Result<M
         content,
         type_ as _,
     )
-    .fetch_one(db)
+    .fetch_one(&mut *db)
     .await?;
 
     let message = Message {
@@ -36,21 +50,131 @@ pub async fn create(db: &mut PgConnection, message: Message) -> anyhow::Result<M
         content: inserted.content,
         created_at: inserted.created_at,
         type_: inserted.type_,
+        blocked: false,
     };
 
+    let members = services::room::get_member_ids(&mut *db, &message.room).await?;
+    let blockers = services::block::get_blocker_ids(db, message.author.uuid).await?;
+    let (blockers, members): (Vec<Uuid>, Vec<Uuid>) =
+        members.into_iter().partition(|it| blockers.contains(it));
     websocket::send_message(
-        Arc::new(MessagePayload {
+        MessagePayload {
             op: OpCode::MessageCreate,
             data: message.clone(), // maybe find a way to do this without cloning
-        }),
-        |_uuid| true, /* TODO only send to users in a room */
+        },
+        Recipients::Users(members),
     )
     .await;
+    if !blockers.is_empty() {
+        websocket::send_message(
+            MessagePayload {
+                op: OpCode::MessageCreate,
+                data: collapse(message.clone()),
+            },
+            Recipients::Users(blockers),
+        )
+        .await;
+    }
+
+    for flag in flags {
+        services::report::create(&mut *db, &message, None, &flag.reason, flag.scope).await?;
+    }
 
     Ok(message)
 }
 
-pub async fn get_all(conn: &mut PgConnection, room: &Room) -> anyhow::Result<Vec<Message>> {
+/// The message of the room, `None` if there's no such message in it
+pub async fn get(
+    db: &mut PgConnection,
+    room: &Room,
+    uuid: Uuid,
+) -> anyhow::Result<Option<Message>> {
+    let returned = sqlx::query!(
+        r#"
+            select uuid, author, content, created_at, type as "type_: MessageType"
+            from messages
+            where uuid = $1
+              and room = $2;
+        "#,
+        uuid,
+        room.uuid
+    )
+    .fetch_optional(&mut *db)
+    .await?;
+
+    let value = match returned {
+        Some(value) => value,
+        None => return Ok(None),
+    };
+    let author = services::user::get(db, value.author)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("author of the message doesn't exist"))?;
+
+    Ok(Some(Message {
+        uuid: value.uuid,
+        author,
+        room: room.clone(),
+        content: value.content,
+        created_at: value.created_at,
+        type_: value.type_,
+        blocked: false,
+    }))
+}
+
+/// Deletes the message and takes it out of the room for everyone in it
+#[instrument]
+pub async fn delete(
+    db: &mut PgConnection,
+    message: &Message,
+    deleted_by: &User,
+) -> anyhow::Result<()> {
+    debug!("deleting message");
+    sqlx::query!("delete from messages where uuid = $1;", message.uuid)
+        .execute(&mut *db)
+        .await?;
+
+    services::audit_log::record(
+        &mut *db,
+        message.room.uuid,
+        deleted_by,
+        AuditAction::MessageDelete,
+        Some(message.author.uuid),
+        json!({ "content": message.content }),
+    )
+    .await?;
+
+    // clients only need to know which message it was
+    let members = services::room::get_member_ids(db, &message.room).await?;
+    websocket::send_message(
+        MessagePayload {
+            op: OpCode::MessageDelete,
+            data: Message {
+                content: String::new(),
+                ..message.clone()
+            },
+        },
+        Recipients::Users(members),
+    )
+    .await;
+
+    Ok(())
+}
+
+/// What those who blocked the author get instead of the message
+fn collapse(mut message: Message) -> Message {
+    message.content = String::new();
+    message.blocked = true;
+    message
+}
+
+/// The messages of the room as `viewer` sees them, with those of users they blocked collapsed
+pub async fn get_all(
+    conn: &mut PgConnection,
+    room: &Room,
+    viewer: &User,
+) -> anyhow::Result<Vec<Message>> {
+    let blocked = services::block::get_blocked_ids(&mut *conn, viewer.uuid).await?;
+
     let returned = sqlx::query!(
         r#"
 select messages.uuid,
@@ -63,6 +187,13 @@ select messages.uuid,
        u.password    as author_password,
        u.created_at  as author_created_at,
        u.avatar      as author_avatar,
+       u.display_name      as author_display_name,
+       u.bio               as author_bio,
+       u.pronouns          as author_pronouns,
+       u.timezone          as author_timezone,
+       u.status_text       as author_status_text,
+       u.status_expires_at as author_status_expires_at,
+       u.is_instance_admin as author_is_instance_admin,
        a.uuid        as "asset_uuid?",
        a.created_at  as "asset_created_at?"
 from messages
@@ -93,11 +224,28 @@ order by messages.created_at desc ;
                     }),
                     None => None,
                 },
+                display_name: value.author_display_name,
+                bio: value.author_bio,
+                pronouns: value.author_pronouns,
+                timezone: value.author_timezone,
+                status: services::user::status(
+                    value.author_status_text,
+                    value.author_status_expires_at,
+                ),
+                is_instance_admin: value.author_is_instance_admin,
             },
             room: room.clone(),
             content: value.content,
             created_at: value.created_at,
             type_: value.type_,
+            blocked: false,
+        })
+        .map(|message| {
+            if blocked.contains(&message.author.uuid) {
+                collapse(message)
+            } else {
+                message
+            }
         })
         .collect::<Vec<Message>>();
 
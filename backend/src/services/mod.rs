@@ -1,4 +1,16 @@
+pub mod admin;
 pub mod asset;
+pub mod audit_log;
+pub mod automod;
+pub mod block;
+pub mod invite;
 pub mod message;
+pub mod notification;
+pub mod permissions;
+pub mod presence;
+pub mod rate_limit;
+pub mod report;
+pub mod role;
 pub mod room;
+pub mod space;
 pub mod user;
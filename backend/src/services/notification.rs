@@ -0,0 +1,105 @@
+use crate::websocket;
+use crate::websocket::pubsub::Recipients;
+use common::websocket::{MessagePayload, OpCode};
+use common::{NotificationLevel, NotificationSettings, Room, User};
+use sqlx::PgConnection;
+use tracing::{debug, instrument};
+
+/// The notification settings of the user for the room, `None` if they aren't in it
+pub async fn get(
+    db: &mut PgConnection,
+    room: &Room,
+    user: &User,
+) -> anyhow::Result<Option<NotificationSettings>> {
+    let settings = sqlx::query!(
+        r#"
+            select room_id,
+                   notification_level as "notification_level: NotificationLevel",
+                   muted_until,
+                   hide_unread_when_muted
+            from room_members
+            where room_id = $1
+              and user_id = $2;
+        "#,
+        room.uuid,
+        user.uuid
+    )
+    .fetch_optional(db)
+    .await?
+    .map(|it| NotificationSettings {
+        room: it.room_id,
+        level: it.notification_level,
+        muted_until: it.muted_until,
+        hide_unread_when_muted: it.hide_unread_when_muted,
+    });
+
+    Ok(settings)
+}
+
+/// The notification settings of every room the user is in
+pub async fn get_with_user(
+    db: &mut PgConnection,
+    user: &User,
+) -> anyhow::Result<Vec<NotificationSettings>> {
+    let settings = sqlx::query!(
+        r#"
+            select room_id,
+                   notification_level as "notification_level: NotificationLevel",
+                   muted_until,
+                   hide_unread_when_muted
+            from room_members
+            where user_id = $1;
+        "#,
+        user.uuid
+    )
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .map(|it| NotificationSettings {
+        room: it.room_id,
+        level: it.notification_level,
+        muted_until: it.muted_until,
+        hide_unread_when_muted: it.hide_unread_when_muted,
+    })
+    .collect();
+
+    Ok(settings)
+}
+
+/// Saves the settings and sends them to the other sessions of the user
+#[instrument]
+pub async fn update(
+    db: &mut PgConnection,
+    settings: &NotificationSettings,
+    user: &User,
+) -> anyhow::Result<()> {
+    debug!("updating notification settings");
+    sqlx::query!(
+        "
+            update room_members
+            set notification_level     = $3,
+                muted_until            = $4,
+                hide_unread_when_muted = $5
+            where room_id = $1
+              and user_id = $2;
+        ",
+        settings.room,
+        user.uuid,
+        settings.level as _,
+        settings.muted_until,
+        settings.hide_unread_when_muted
+    )
+    .execute(db)
+    .await?;
+
+    websocket::send_message(
+        MessagePayload {
+            op: OpCode::NotificationSettingsUpdate,
+            data: settings.clone(),
+        },
+        Recipients::Users(vec![user.uuid]),
+    )
+    .await;
+
+    Ok(())
+}
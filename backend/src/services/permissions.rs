@@ -0,0 +1,133 @@
+use crate::services;
+use common::errors::ApiError;
+use common::{Permissions, Role, Room, Space, User};
+use sqlx::types::Uuid;
+use sqlx::PgConnection;
+use warp::http::StatusCode;
+
+/// What the user is allowed to do in the room, `None` if they aren't a member
+pub async fn get(
+    db: &mut PgConnection,
+    room: &Room,
+    user: Uuid,
+) -> anyhow::Result<Option<Permissions>> {
+    let role = services::role::get_member_role(db, room, user).await?;
+    Ok(role.map(|role| role.permissions()))
+}
+
+/// Fails with a `403` unless the user is a member of the room with all of the given permissions.
+///
+/// Checking for [`Permissions::NONE`] only checks that they're a member.
+pub async fn check(
+    db: &mut PgConnection,
+    room: &Room,
+    user: &User,
+    permissions: Permissions,
+) -> anyhow::Result<()> {
+    let message = match get(db, room, user.uuid).await? {
+        Some(granted) if granted.contains(permissions) => return Ok(()),
+        Some(granted) => format!(
+            "you need the following permissions in this room: {}",
+            permissions & !granted
+        ),
+        None => "either this room doesn't exist or you aren't a member of it".to_string(),
+    };
+
+    Err(ApiError::new_with_message_and_status(&message, StatusCode::FORBIDDEN).into())
+}
+
+/// Fails with a `403` unless the user is a member of the space with all of the given permissions.
+///
+/// Checking for [`Permissions::NONE`] only checks that they're a member.
+pub async fn check_space(
+    db: &mut PgConnection,
+    space: &Space,
+    user: &User,
+    permissions: Permissions,
+) -> anyhow::Result<()> {
+    let granted = services::space::get_member_role(db, space, user.uuid)
+        .await?
+        .map(|role| role.permissions());
+    let message = match granted {
+        Some(granted) if granted.contains(permissions) => return Ok(()),
+        Some(granted) => format!(
+            "you need the following permissions in this space: {}",
+            permissions & !granted
+        ),
+        None => "either this space doesn't exist or you aren't a member of it".to_string(),
+    };
+
+    Err(ApiError::new_with_message_and_status(&message, StatusCode::FORBIDDEN).into())
+}
+
+/// Fails with a `403` unless the user may give the role to a member.
+///
+/// Anyone who can add members can give the default role, the admin role needs
+/// an admin, other roles need [`Permissions::MANAGE_ROLES`] and every permission they grant.
+pub async fn check_can_assign(
+    db: &mut PgConnection,
+    room: &Room,
+    user: &User,
+    role: &Role,
+) -> anyhow::Result<()> {
+    if role.is_default {
+        return Ok(());
+    }
+    if role.is_admin {
+        return check_admin(db, room, user).await;
+    }
+
+    check(
+        db,
+        room,
+        user,
+        Permissions::MANAGE_ROLES | role.permissions(),
+    )
+    .await
+}
+
+/// Fails with a `403` unless the user is an admin of the room
+pub async fn check_admin(db: &mut PgConnection, room: &Room, user: &User) -> anyhow::Result<()> {
+    match services::role::get_member_role(db, room, user.uuid).await? {
+        Some(role) if role.is_admin => Ok(()),
+        _ => Err(ApiError::new_with_message_and_status(
+            "only admins of the room can do this",
+            StatusCode::FORBIDDEN,
+        )
+        .into()),
+    }
+}
+
+/// Fails with a `403` unless the user owns the room.
+///
+/// Rooms without an owner, like group DMs, leave it to their admins.
+pub async fn check_owner(db: &mut PgConnection, room: &Room, user: &User) -> anyhow::Result<()> {
+    match room.owner {
+        Some(owner) if owner == user.uuid => Ok(()),
+        Some(_) => Err(ApiError::new_with_message_and_status(
+            "only the owner of the room can do this",
+            StatusCode::FORBIDDEN,
+        )
+        .into()),
+        None => check_admin(db, room, user).await,
+    }
+}
+
+/// Fails with a `403` unless the user can send messages in the room.
+///
+/// Announcement rooms also need them to be an admin.
+pub async fn check_can_post(db: &mut PgConnection, room: &Room, user: &User) -> anyhow::Result<()> {
+    check(db, room, user, Permissions::SEND_MESSAGES).await?;
+    if !room.is_announcement() {
+        return Ok(());
+    }
+
+    match services::role::get_member_role(db, room, user.uuid).await? {
+        Some(role) if role.is_admin => Ok(()),
+        _ => Err(ApiError::new_with_message_and_status(
+            "only admins can post in this room",
+            StatusCode::FORBIDDEN,
+        )
+        .into()),
+    }
+}
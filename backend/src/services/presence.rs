@@ -0,0 +1,141 @@
+use crate::websocket::pubsub::Recipients;
+use crate::{services, websocket};
+use common::websocket::{MessagePayload, OpCode, PresenceUpdatePayload};
+use common::Presence;
+use sqlx::types::Uuid;
+use sqlx::PgConnection;
+use tracing::{debug, instrument};
+
+/// Presence from whether any of the user's live sessions is active,
+/// `None` meaning there are no live sessions
+pub(crate) fn from_active(active: Option<bool>) -> Presence {
+    match active {
+        Some(true) => Presence::Online,
+        Some(false) => Presence::Idle,
+        None => Presence::Offline,
+    }
+}
+
+/// Presence of the user across all of their sessions.
+///
+/// Sessions not seen for a minute were left behind by an instance that went away.
+#[instrument]
+pub async fn get(db: &mut PgConnection, user: Uuid) -> anyhow::Result<Presence> {
+    let active = sqlx::query!(
+        "
+            select bool_or(not idle) as active
+            from user_sessions
+            where user_id = $1
+              and last_seen > now() - interval '1 minute';
+        ",
+        user
+    )
+    .fetch_one(db)
+    .await?
+    .active;
+
+    Ok(from_active(active))
+}
+
+#[instrument]
+pub async fn connect(db: &mut PgConnection, session: Uuid, user: Uuid) -> anyhow::Result<()> {
+    debug!("adding session");
+    let before = get(&mut *db, user).await?;
+
+    sqlx::query!("delete from user_sessions where last_seen < now() - interval '1 minute';")
+        .execute(&mut *db)
+        .await?;
+
+    sqlx::query!(
+        "
+            insert into user_sessions(session_id, user_id)
+            values ($1, $2);
+        ",
+        session,
+        user
+    )
+    .execute(&mut *db)
+    .await?;
+
+    notify_if_changed(db, user, before).await
+}
+
+#[instrument]
+pub async fn set_idle(
+    db: &mut PgConnection,
+    session: Uuid,
+    user: Uuid,
+    idle: bool,
+) -> anyhow::Result<()> {
+    debug!("updating session idle state");
+    let before = get(&mut *db, user).await?;
+
+    sqlx::query!(
+        "
+            update user_sessions
+            set idle      = $1,
+                last_seen = now()
+            where session_id = $2;
+        ",
+        idle,
+        session
+    )
+    .execute(&mut *db)
+    .await?;
+
+    notify_if_changed(db, user, before).await
+}
+
+/// Marks the session as still alive
+pub async fn refresh(db: &mut PgConnection, session: Uuid) -> anyhow::Result<()> {
+    sqlx::query!(
+        "update user_sessions set last_seen = now() where session_id = $1;",
+        session
+    )
+    .execute(db)
+    .await?;
+
+    Ok(())
+}
+
+#[instrument]
+pub async fn disconnect(db: &mut PgConnection, session: Uuid, user: Uuid) -> anyhow::Result<()> {
+    debug!("removing session");
+    let before = get(&mut *db, user).await?;
+
+    sqlx::query!("delete from user_sessions where session_id = $1;", session)
+        .execute(&mut *db)
+        .await?;
+
+    notify_if_changed(db, user, before).await
+}
+
+async fn notify_if_changed(
+    db: &mut PgConnection,
+    user: Uuid,
+    before: Presence,
+) -> anyhow::Result<()> {
+    let presence = get(&mut *db, user).await?;
+    if presence == before {
+        return Ok(());
+    }
+
+    debug!("sending presence update websocket notification");
+    let recipients = services::user::get_ids_sharing_room(&mut *db, user).await?;
+    // those who blocked the user don't get to see when they're around
+    let blockers = services::block::get_blocker_ids(db, user).await?;
+    let recipients = recipients
+        .into_iter()
+        .filter(|it| !blockers.contains(it))
+        .collect();
+    websocket::send_message(
+        MessagePayload {
+            op: OpCode::PresenceUpdate,
+            data: PresenceUpdatePayload { user, presence },
+        },
+        Recipients::Users(recipients),
+    )
+    .await;
+
+    Ok(())
+}
@@ -0,0 +1,146 @@
+use crate::services;
+use anyhow::Context;
+use common::errors::ApiError;
+use common::{Permissions, Room, User};
+use lazy_static::lazy_static;
+use sqlx::PgConnection;
+use std::env;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tracing::{debug, instrument};
+
+/// How many messages a user can send, in all rooms together, within the window
+#[derive(Debug, Copy, Clone)]
+pub struct MessageRateLimit {
+    pub messages: i64,
+    pub window: Duration,
+}
+
+impl Default for MessageRateLimit {
+    fn default() -> Self {
+        Self {
+            messages: 10,
+            window: Duration::from_secs(10),
+        }
+    }
+}
+
+impl MessageRateLimit {
+    /// Reads the `MESSAGE_RATE_LIMIT` and `MESSAGE_RATE_LIMIT_WINDOW` environment variables,
+    /// a number of messages and seconds, falling back to the defaults for the missing ones
+    pub fn from_env() -> anyhow::Result<Self> {
+        let mut config = Self::default();
+
+        if let Ok(messages) = env::var("MESSAGE_RATE_LIMIT") {
+            config.messages = messages.parse().context("invalid `MESSAGE_RATE_LIMIT`")?;
+        }
+        if let Ok(window) = env::var("MESSAGE_RATE_LIMIT_WINDOW") {
+            config.window = Duration::from_secs(
+                window
+                    .parse()
+                    .context("invalid `MESSAGE_RATE_LIMIT_WINDOW`")?,
+            );
+        }
+
+        anyhow::ensure!(
+            config.messages > 0,
+            "the message rate limit must allow at least one message"
+        );
+
+        Ok(config)
+    }
+}
+
+lazy_static! {
+    static ref MESSAGE_RATE_LIMIT: RwLock<MessageRateLimit> =
+        RwLock::new(MessageRateLimit::default());
+}
+
+/// Sets how fast users can send messages from now on
+pub async fn set_message_rate_limit(config: MessageRateLimit) {
+    *MESSAGE_RATE_LIMIT.write().await = config;
+}
+
+/// Fails with a `429` if the user has to wait before sending a message in the room.
+///
+/// Slow mode doesn't hold back the members who can change it. The messages of the user are
+/// serialized until the transaction ends, so it has to insert the message as well.
+#[instrument]
+pub async fn check_message(db: &mut PgConnection, room: &Room, user: &User) -> anyhow::Result<()> {
+    let limit = *MESSAGE_RATE_LIMIT.read().await;
+
+    // otherwise concurrent messages would all pass the checks before any of them is inserted
+    sqlx::query!(
+        "select pg_advisory_xact_lock(hashtext('message_rate_limit'), hashtext($1::uuid::text));",
+        user.uuid
+    )
+    .execute(&mut *db)
+    .await?;
+
+    // the oldest of the messages that count towards the limit
+    let retry_after = sqlx::query!(
+        r#"
+            select extract(epoch from created_at + make_interval(secs => $3) - now())::float8
+                as "retry_after!"
+            from messages
+            where author = $1
+              and type = 'default'
+            order by created_at desc
+            offset $2 limit 1;
+        "#,
+        user.uuid,
+        limit.messages - 1,
+        limit.window.as_secs_f64()
+    )
+    .fetch_optional(&mut *db)
+    .await?
+    .map(|row| row.retry_after);
+
+    if let Some(retry_after) = retry_after.filter(|it| *it > 0.0) {
+        debug!("user is sending messages too fast");
+        return Err(ApiError::new_rate_limited(
+            "you're sending messages too fast",
+            Duration::from_secs_f64(retry_after),
+        )
+        .into());
+    }
+
+    if room.slow_mode == 0 {
+        return Ok(());
+    }
+    let exempt = services::permissions::get(&mut *db, room, user.uuid)
+        .await?
+        .map_or(false, |it| it.contains(Permissions::MANAGE_ROOM));
+    if exempt {
+        return Ok(());
+    }
+
+    let retry_after = sqlx::query!(
+        r#"
+            select extract(epoch from max(created_at) + make_interval(secs => $3) - now())::float8
+                as retry_after
+            from messages
+            where room = $1
+              and author = $2
+              and type = 'default';
+        "#,
+        room.uuid,
+        user.uuid,
+        room.slow_mode as f64
+    )
+    .fetch_one(db)
+    .await?
+    .retry_after;
+
+    match retry_after {
+        Some(retry_after) if retry_after > 0.0 => Err(ApiError::new_rate_limited(
+            &format!(
+                "slow mode is on, you can send a message every {} seconds",
+                room.slow_mode
+            ),
+            Duration::from_secs_f64(retry_after),
+        )
+        .into()),
+        _ => Ok(()),
+    }
+}
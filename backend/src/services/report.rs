@@ -0,0 +1,266 @@
+use crate::websocket::pubsub::Recipients;
+use crate::{services, websocket};
+use common::websocket::{MessagePayload, OpCode};
+use common::{AuditAction, Message, Report, ReportAction, ReportScope, Room, User};
+use serde_json::json;
+use sqlx::types::Uuid;
+use sqlx::PgConnection;
+use tracing::{debug, instrument};
+
+macro_rules! report {
+    ($value:expr) => {{
+        let value = $value;
+        Report {
+            uuid: value.uuid,
+            room: value.room_id,
+            message: value.message_id,
+            author: value.author_id,
+            content: value.content,
+            reporter: value.reporter_id,
+            reason: value.reason,
+            scope: value.scope,
+            created_at: value.created_at,
+            action: value.action,
+            resolved_by: value.resolved_by,
+            resolved_at: value.resolved_at,
+        }
+    }};
+}
+
+/// Reports the message, its content is kept in case it gets deleted.
+///
+/// Messages automod flagged don't have a reporter.
+#[instrument]
+pub async fn create(
+    db: &mut PgConnection,
+    message: &Message,
+    reporter: Option<&User>,
+    reason: &str,
+    scope: ReportScope,
+) -> anyhow::Result<Report> {
+    debug!("creating report");
+    let inserted = sqlx::query!(
+        r#"
+            insert into reports (uuid, room_id, message_id, author_id, content,
+                                 reporter_id, reason, scope)
+            values ($1, $2, $3, $4, $5, $6, $7, $8)
+            returning uuid, room_id, message_id, author_id, content, reporter_id, reason,
+                scope as "scope: ReportScope", created_at,
+                action as "action: ReportAction", resolved_by, resolved_at;
+        "#,
+        Uuid::new_v4(),
+        message.room.uuid,
+        message.uuid,
+        message.author.uuid,
+        message.content,
+        reporter.map(|it| it.uuid),
+        reason,
+        scope as _
+    )
+    .fetch_one(&mut *db)
+    .await?;
+    let report = report!(inserted);
+
+    // instance-wide reports aren't up to the room's admins
+    let admins = match report.scope {
+        ReportScope::Room => get_admin_ids(db, &message.room).await?,
+        ReportScope::Instance => services::admin::get_instance_admin_ids(db).await?,
+    };
+    websocket::send_message(
+        MessagePayload {
+            op: OpCode::ReportCreate,
+            data: report.clone(),
+        },
+        Recipients::Users(admins),
+    )
+    .await;
+
+    Ok(report)
+}
+
+pub async fn get(db: &mut PgConnection, room: &Room, uuid: Uuid) -> anyhow::Result<Option<Report>> {
+    let report = sqlx::query!(
+        r#"
+            select uuid, room_id, message_id, author_id, content, reporter_id, reason,
+                scope as "scope: ReportScope", created_at,
+                action as "action: ReportAction", resolved_by, resolved_at
+            from reports
+            where uuid = $1
+              and room_id = $2;
+        "#,
+        uuid,
+        room.uuid
+    )
+    .fetch_optional(db)
+    .await?
+    .map(|it| report!(it));
+
+    Ok(report)
+}
+
+/// The reports the admins of the room handle, the newest first
+pub async fn get_for_room(
+    db: &mut PgConnection,
+    room: &Room,
+    resolved: bool,
+) -> anyhow::Result<Vec<Report>> {
+    let reports = sqlx::query!(
+        r#"
+            select uuid, room_id, message_id, author_id, content, reporter_id, reason,
+                scope as "scope: ReportScope", created_at,
+                action as "action: ReportAction", resolved_by, resolved_at
+            from reports
+            where room_id = $1
+              and scope = 'room'
+              and (resolved_at is not null) = $2
+            order by created_at desc;
+        "#,
+        room.uuid,
+        resolved
+    )
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .map(|it| report!(it))
+    .collect();
+
+    Ok(reports)
+}
+
+/// A report escalated to instance admins, whichever room it's in
+pub async fn get_instance(db: &mut PgConnection, uuid: Uuid) -> anyhow::Result<Option<Report>> {
+    let report = sqlx::query!(
+        r#"
+            select uuid, room_id, message_id, author_id, content, reporter_id, reason,
+                scope as "scope: ReportScope", created_at,
+                action as "action: ReportAction", resolved_by, resolved_at
+            from reports
+            where uuid = $1
+              and scope = 'instance';
+        "#,
+        uuid
+    )
+    .fetch_optional(db)
+    .await?
+    .map(|it| report!(it));
+
+    Ok(report)
+}
+
+/// The reports instance admins handle, the newest first
+pub async fn get_for_instance(
+    db: &mut PgConnection,
+    resolved: bool,
+) -> anyhow::Result<Vec<Report>> {
+    let reports = sqlx::query!(
+        r#"
+            select uuid, room_id, message_id, author_id, content, reporter_id, reason,
+                scope as "scope: ReportScope", created_at,
+                action as "action: ReportAction", resolved_by, resolved_at
+            from reports
+            where scope = 'instance'
+              and (resolved_at is not null) = $1
+            order by created_at desc;
+        "#,
+        resolved
+    )
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .map(|it| report!(it))
+    .collect();
+
+    Ok(reports)
+}
+
+/// Whether the user already reported the message and nobody looked at it yet
+pub async fn has_open(
+    db: &mut PgConnection,
+    message: &Message,
+    reporter: &User,
+) -> anyhow::Result<bool> {
+    Ok(sqlx::query!(
+        "
+            select exists(
+                select 1
+                from reports
+                where message_id = $1
+                  and reporter_id = $2
+                  and resolved_at is null
+            ) as has_open;
+        ",
+        message.uuid,
+        reporter.uuid
+    )
+    .fetch_one(db)
+    .await?
+    .has_open
+    .unwrap_or(false))
+}
+
+/// Marks the report as resolved with the action, which has to be carried out already.
+///
+/// The resolution stays on the report and goes in the audit log of the room.
+#[instrument]
+pub async fn resolve(
+    db: &mut PgConnection,
+    report: &Report,
+    action: ReportAction,
+    resolved_by: &User,
+) -> anyhow::Result<Report> {
+    debug!("resolving report");
+    let updated = sqlx::query!(
+        r#"
+            update reports
+            set action      = $2,
+                resolved_by = $3,
+                resolved_at = now()
+            where uuid = $1
+            returning uuid, room_id, message_id, author_id, content, reporter_id, reason,
+                scope as "scope: ReportScope", created_at,
+                action as "action: ReportAction", resolved_by, resolved_at;
+        "#,
+        report.uuid,
+        action as _,
+        resolved_by.uuid
+    )
+    .fetch_one(&mut *db)
+    .await?;
+
+    services::audit_log::record(
+        db,
+        report.room,
+        resolved_by,
+        AuditAction::ReportResolve,
+        report.author,
+        json!({
+            "report": report.uuid,
+            "action": action,
+            "reason": report.reason,
+        }),
+    )
+    .await?;
+
+    Ok(report!(updated))
+}
+
+/// The members of the room with an admin role
+async fn get_admin_ids(db: &mut PgConnection, room: &Room) -> anyhow::Result<Vec<Uuid>> {
+    let admins = sqlx::query!(
+        "
+            select m.user_id
+            from room_members m
+                inner join room_roles r on r.uuid = m.role_id
+            where m.room_id = $1
+              and r.is_admin;
+        ",
+        room.uuid
+    )
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .map(|it| it.user_id)
+    .collect();
+
+    Ok(admins)
+}
@@ -0,0 +1,301 @@
+use crate::services;
+use common::errors::ApiError;
+use common::{AuditAction, Permissions, Role, Room, User};
+use serde_json::json;
+use sqlx::postgres::PgDatabaseError;
+use sqlx::types::Uuid;
+use sqlx::PgConnection;
+use tracing::{debug, instrument};
+use warp::http::StatusCode;
+
+macro_rules! role {
+    ($row:expr) => {{
+        let row = $row;
+        Role {
+            uuid: row.uuid,
+            room: row.room_id,
+            name: row.name,
+            permissions: Permissions::from_bits_truncate(row.permissions),
+            is_default: row.is_default,
+            is_admin: row.is_admin,
+            created_at: row.created_at,
+        }
+    }};
+}
+
+#[instrument]
+pub async fn get(db: &mut PgConnection, room: &Room, uuid: Uuid) -> anyhow::Result<Option<Role>> {
+    debug!("fetching role");
+    let role = sqlx::query!(
+        "
+            select *
+            from room_roles
+            where room_id = $1
+              and uuid = $2;
+        ",
+        room.uuid,
+        uuid
+    )
+    .fetch_optional(db)
+    .await?;
+
+    Ok(role.map(|role| role!(role)))
+}
+
+#[instrument]
+pub async fn get_all(db: &mut PgConnection, room: &Room) -> anyhow::Result<Vec<Role>> {
+    debug!("fetching roles");
+    let roles = sqlx::query!(
+        "
+            select *
+            from room_roles
+            where room_id = $1
+            order by is_admin desc, created_at;
+        ",
+        room.uuid
+    )
+    .fetch_all(db)
+    .await?;
+
+    Ok(roles.into_iter().map(|role| role!(role)).collect())
+}
+
+/// The role of the members who join without being assigned one
+pub async fn get_default(db: &mut PgConnection, room: &Room) -> anyhow::Result<Role> {
+    let role = sqlx::query!(
+        "
+            select *
+            from room_roles
+            where room_id = $1
+              and is_default;
+        ",
+        room.uuid
+    )
+    .fetch_one(db)
+    .await?;
+
+    Ok(role!(role))
+}
+
+/// The role with every permission, given to the creator of the room
+pub async fn get_admin(db: &mut PgConnection, room: &Room) -> anyhow::Result<Role> {
+    let role = sqlx::query!(
+        "
+            select *
+            from room_roles
+            where room_id = $1
+              and is_admin;
+        ",
+        room.uuid
+    )
+    .fetch_one(db)
+    .await?;
+
+    Ok(role!(role))
+}
+
+/// The role of the user in the room, `None` if they aren't a member
+pub async fn get_member_role(
+    db: &mut PgConnection,
+    room: &Room,
+    user: Uuid,
+) -> anyhow::Result<Option<Role>> {
+    let role = sqlx::query!(
+        "
+            select r.*
+            from room_members m
+                inner join room_roles r on r.uuid = m.role_id
+            where m.room_id = $1
+              and m.user_id = $2;
+        ",
+        room.uuid,
+        user
+    )
+    .fetch_optional(db)
+    .await?;
+
+    Ok(role.map(|role| role!(role)))
+}
+
+#[instrument]
+pub async fn create(
+    db: &mut PgConnection,
+    room: &Room,
+    name: &str,
+    permissions: Permissions,
+    created_by: &User,
+) -> anyhow::Result<Role> {
+    debug!("creating role");
+    let role = sqlx::query!(
+        "
+            insert into room_roles (uuid, room_id, name, permissions)
+            values ($1, $2, $3, $4)
+            returning *;
+        ",
+        Uuid::new_v4(),
+        room.uuid,
+        name,
+        permissions.bits()
+    )
+    .fetch_one(&mut *db)
+    .await
+    .map_err(name_taken)?;
+    let role = role!(role);
+
+    services::audit_log::record(
+        db,
+        room.uuid,
+        created_by,
+        AuditAction::RoleCreate,
+        Some(role.uuid),
+        json!({ "name": role.name, "permissions": role.permissions }),
+    )
+    .await?;
+
+    Ok(role)
+}
+
+#[instrument]
+pub async fn update(
+    db: &mut PgConnection,
+    room: &Room,
+    role: Role,
+    updated_by: &User,
+) -> anyhow::Result<Role> {
+    debug!("updating role");
+    let old = get(&mut *db, room, role.uuid)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("role to update doesn't exist"))?;
+
+    let role = sqlx::query!(
+        "
+            update room_roles
+            set name        = $1,
+                permissions = $2
+            where uuid = $3
+            returning *;
+        ",
+        role.name,
+        role.permissions.bits(),
+        role.uuid
+    )
+    .fetch_one(&mut *db)
+    .await
+    .map_err(name_taken)?;
+    let role = role!(role);
+
+    services::audit_log::record(
+        db,
+        room.uuid,
+        updated_by,
+        AuditAction::RoleUpdate,
+        Some(role.uuid),
+        services::audit_log::diff(vec![
+            ("name", json!(old.name), json!(role.name)),
+            (
+                "permissions",
+                json!(old.permissions),
+                json!(role.permissions),
+            ),
+        ]),
+    )
+    .await?;
+
+    Ok(role)
+}
+
+/// Deletes the role, its members are given the default role
+#[instrument]
+pub async fn delete(
+    db: &mut PgConnection,
+    room: &Room,
+    role: &Role,
+    deleted_by: &User,
+) -> anyhow::Result<()> {
+    debug!("deleting role");
+    let default = get_default(&mut *db, room).await?;
+
+    sqlx::query!(
+        "
+            update room_members
+            set role_id = $1
+            where role_id = $2;
+        ",
+        default.uuid,
+        role.uuid
+    )
+    .execute(&mut *db)
+    .await?;
+
+    sqlx::query!("delete from room_roles where uuid = $1;", role.uuid)
+        .execute(&mut *db)
+        .await?;
+
+    services::audit_log::record(
+        db,
+        room.uuid,
+        deleted_by,
+        AuditAction::RoleDelete,
+        Some(role.uuid),
+        json!({ "name": role.name }),
+    )
+    .await?;
+
+    Ok(())
+}
+
+#[instrument]
+pub async fn set_member_role(
+    db: &mut PgConnection,
+    room: &Room,
+    user: Uuid,
+    role: &Role,
+    set_by: &User,
+) -> anyhow::Result<()> {
+    debug!("setting member role");
+    let old = get_member_role(&mut *db, room, user).await?;
+
+    sqlx::query!(
+        "
+            update room_members
+            set role_id = $1
+            where room_id = $2
+              and user_id = $3;
+        ",
+        role.uuid,
+        room.uuid,
+        user
+    )
+    .execute(&mut *db)
+    .await?;
+
+    services::audit_log::record(
+        db,
+        room.uuid,
+        set_by,
+        AuditAction::MemberRoleUpdate,
+        Some(user),
+        services::audit_log::diff(vec![(
+            "role",
+            json!(old.map(|it| it.name)),
+            json!(role.name),
+        )]),
+    )
+    .await?;
+
+    Ok(())
+}
+
+fn name_taken(e: sqlx::Error) -> anyhow::Error {
+    if let sqlx::Error::Database(error) = &e {
+        let error = error.downcast_ref::<PgDatabaseError>();
+        if error.code() == "23505" {
+            return ApiError::new_with_message_and_status(
+                "a role with this name already exists",
+                StatusCode::CONFLICT,
+            )
+            .into();
+        }
+    }
+    e.into()
+}
@@ -1,43 +1,102 @@
+use crate::utils::contains_pattern;
+use crate::websocket::pubsub::Recipients;
+use crate::websocket::Deferred;
 use crate::{services, websocket};
+use common::errors::ApiError;
 use common::websocket::{MessagePayload, OpCode};
-use common::{Message, MessageType, Room, RoomMember, User};
+use common::{
+    AuditAction, Message, MessageType, Permissions, PostPermission, Role, Room, RoomKind,
+    RoomMember, RoomVisibility, User,
+};
+use serde_json::json;
 use sqlx::types::Uuid;
 use sqlx::PgConnection;
-use std::sync::Arc;
 use tracing::{debug, error, instrument};
+use warp::http::StatusCode;
 
+/// Creates the room, along with the `RoomJoin` of its owner to send once it's committed
 #[instrument]
-pub async fn create(db: &mut PgConnection, room: Room) -> anyhow::Result<Room> {
-    let Room { name, uuid, .. } = room;
+pub async fn create(db: &mut PgConnection, room: Room) -> anyhow::Result<(Room, Option<Deferred>)> {
+    let Room {
+        name,
+        uuid,
+        kind,
+        visibility,
+        owner,
+        space,
+        category,
+        ..
+    } = room;
     debug!("creating room");
+    // the `owner_as_member` trigger makes the owner an admin of the room,
+    // rooms of a space go at the end of their category
     let room = sqlx::query!(
-        "
-            insert into rooms(name, uuid)
-            values ($1, $2)
-            returning *;
-        ",
+        r#"
+            insert into rooms(name, uuid, kind, visibility, owner, space_id, category_id, position)
+            values ($1, $2, $3, $4, $5, $6, $7,
+                    (select coalesce(max(position) + 1, 0)
+                     from rooms
+                     where $6::uuid is not null
+                       and space_id = $6
+                       and category_id is not distinct from $7))
+            returning uuid, name, created_at, kind as "kind: RoomKind",
+                visibility as "visibility: RoomVisibility", topic, archived_at, owner,
+                slow_mode, post_permission as "post_permission: PostPermission",
+                space_id, category_id, position;
+        "#,
         name,
-        uuid
+        uuid,
+        kind as _,
+        visibility as _,
+        owner,
+        space,
+        category,
     )
-    .fetch_one(db)
+    .fetch_one(&mut *db)
     .await?;
-    Ok(Room {
+    let room = Room {
         uuid: room.uuid,
         name: room.name,
         created_at: room.created_at,
         icon: None,
-    })
+        kind: room.kind,
+        visibility: room.visibility,
+        topic: room.topic,
+        archived_at: room.archived_at,
+        owner: room.owner,
+        slow_mode: room.slow_mode,
+        post_permission: room.post_permission,
+        space: room.space_id,
+        category: room.category_id,
+        position: room.position,
+    };
+
+    let joined = match room.owner {
+        Some(owner) => {
+            let owner = services::user::get(&mut *db, owner)
+                .await?
+                .ok_or_else(|| anyhow::anyhow!("owner of the room doesn't exist"))?;
+            Some(announce_join(db, &room, &owner).await?)
+        }
+        None => None,
+    };
+
+    Ok((room, joined))
 }
 
 #[instrument]
 pub async fn get(db: &mut PgConnection, uuid: Uuid) -> anyhow::Result<Option<Room>> {
     debug!("fetching room");
     let result = sqlx::query!(
-        "
-            select *
+        r#"
+            select uuid, name, created_at, icon, topic, archived_at, owner, slow_mode,
+                space_id, category_id, position,
+                kind as "kind: RoomKind",
+                visibility as "visibility: RoomVisibility",
+                post_permission as "post_permission: PostPermission"
             from rooms
             where uuid = $1;
-        ",
+        "#,
         uuid
     )
     .fetch_optional(&mut *db)
@@ -49,6 +108,16 @@ pub async fn get(db: &mut PgConnection, uuid: Uuid) -> anyhow::Result<Option<Roo
             name: room.name,
             created_at: room.created_at,
             icon: services::asset::get_from_option(db, room.icon).await?,
+            kind: room.kind,
+            visibility: room.visibility,
+            topic: room.topic,
+            archived_at: room.archived_at,
+            owner: room.owner,
+            slow_mode: room.slow_mode,
+            post_permission: room.post_permission,
+            space: room.space_id,
+            category: room.category_id,
+            position: room.position,
         })),
         Ok(None) => {
             debug!("room not found");
@@ -61,45 +130,65 @@ pub async fn get(db: &mut PgConnection, uuid: Uuid) -> anyhow::Result<Option<Roo
     }
 }
 
+/// Adds the user to the room, the `RoomJoin` it returns is to be sent once that's committed
 #[instrument]
 pub async fn join(
     db: &mut PgConnection,
     room: &Room,
     user: &User,
-    has_elevated_perms: bool,
-) -> anyhow::Result<RoomMember> {
+    role: &Role,
+    added_by: &User,
+) -> anyhow::Result<(RoomMember, Deferred)> {
     debug!("joining room");
+    if is_banned(&mut *db, room, user.uuid).await? {
+        return Err(ApiError::new_with_message_and_status(
+            "user is banned from this room",
+            StatusCode::FORBIDDEN,
+        )
+        .into());
+    }
+
     let ret = sqlx::query!(
         "
-            insert into room_members(room_id, user_id, has_elevated_permissions)
+            insert into room_members(room_id, user_id, role_id)
             values ($1, $2, $3)
-            returning *;
+            returning joined_at;
         ",
         room.uuid,
         user.uuid,
-        has_elevated_perms
+        role.uuid
     )
     .fetch_one(&mut *db)
     .await?;
     let member = RoomMember {
         room: room.clone(),
         user: user.clone(),
-        has_elevated_permissions: ret.has_elevated_permissions,
+        role: role.clone(),
         joined_at: ret.joined_at,
+        presence: services::presence::get(&mut *db, user.uuid).await?,
     };
 
-    debug!("sending room join websocket notification");
-    // notify user
-    websocket::send_message(
-        Arc::new(MessagePayload {
-            op: OpCode::RoomJoin,
-            data: room.clone(), // maybe find a way to do this without cloning
-        }),
-        |uuid| uuid == user.uuid,
+    services::audit_log::record(
+        &mut *db,
+        room.uuid,
+        added_by,
+        AuditAction::MemberJoin,
+        Some(user.uuid),
+        json!({ "role": role.name }),
     )
-    .await;
+    .await?;
+
+    let joined = announce_join(db, room, user).await?;
+    Ok((member, joined))
+}
 
-    debug!("sending room join message for creator");
+/// Tells the room the user joined, and returns the `RoomJoin` letting them know they're in it
+async fn announce_join(
+    db: &mut PgConnection,
+    room: &Room,
+    user: &User,
+) -> anyhow::Result<Deferred> {
+    debug!("sending room join message");
     // send the message that user joined
     services::message::create(
         db,
@@ -111,7 +200,8 @@ pub async fn join(
         ),
     )
     .await?;
-    Ok(member)
+
+    Deferred::new(OpCode::RoomJoin, room, Recipients::Users(vec![user.uuid]))
 }
 
 pub async fn user_in_room(db: &mut PgConnection, room: &Room, user: &User) -> anyhow::Result<bool> {
@@ -131,16 +221,434 @@ pub async fn user_in_room(db: &mut PgConnection, room: &Room, user: &User) -> an
     .unwrap_or(false))
 }
 
+#[instrument]
+pub async fn leave(
+    db: &mut PgConnection,
+    room: &Room,
+    user: &User,
+) -> anyhow::Result<Option<Deferred>> {
+    debug!("leaving room");
+    remove_member(db, room, user, "", MessageType::RoomLeave).await
+}
+
+#[instrument]
+pub async fn kick(
+    db: &mut PgConnection,
+    room: &Room,
+    user: &User,
+    kicked_by: &User,
+    reason: Option<&str>,
+) -> anyhow::Result<Option<Deferred>> {
+    debug!("kicking member");
+    services::audit_log::record(
+        &mut *db,
+        room.uuid,
+        kicked_by,
+        AuditAction::MemberKick,
+        Some(user.uuid),
+        json!({ "reason": reason }),
+    )
+    .await?;
+
+    remove_member(db, room, user, reason.unwrap_or(""), MessageType::RoomKick).await
+}
+
+/// Bans the user from the room, removing them from it if they're a member
+#[instrument]
+pub async fn ban(
+    db: &mut PgConnection,
+    room: &Room,
+    user: &User,
+    banned_by: &User,
+    reason: Option<&str>,
+) -> anyhow::Result<Option<Deferred>> {
+    debug!("banning user");
+    sqlx::query!(
+        "
+            insert into room_bans(room_id, user_id, banned_by, reason)
+            values ($1, $2, $3, $4)
+            on conflict (room_id, user_id) do update
+                set banned_by  = excluded.banned_by,
+                    reason     = excluded.reason,
+                    created_at = now();
+        ",
+        room.uuid,
+        user.uuid,
+        banned_by.uuid,
+        reason
+    )
+    .execute(&mut *db)
+    .await?;
+
+    services::audit_log::record(
+        &mut *db,
+        room.uuid,
+        banned_by,
+        AuditAction::MemberBan,
+        Some(user.uuid),
+        json!({ "reason": reason }),
+    )
+    .await?;
+
+    remove_member(db, room, user, reason.unwrap_or(""), MessageType::RoomBan).await
+}
+
+/// Lifts the ban of the user, returning whether they were banned
+#[instrument]
+pub async fn unban(
+    db: &mut PgConnection,
+    room: &Room,
+    user: Uuid,
+    unbanned_by: &User,
+) -> anyhow::Result<bool> {
+    debug!("unbanning user");
+    let result = sqlx::query!(
+        "
+            delete from room_bans
+            where room_id = $1
+              and user_id = $2;
+        ",
+        room.uuid,
+        user
+    )
+    .execute(&mut *db)
+    .await?;
+
+    let unbanned = result.rows_affected() > 0;
+    if unbanned {
+        services::audit_log::record(
+            db,
+            room.uuid,
+            unbanned_by,
+            AuditAction::MemberUnban,
+            Some(user),
+            json!({}),
+        )
+        .await?;
+    }
+
+    Ok(unbanned)
+}
+
+pub async fn is_banned(db: &mut PgConnection, room: &Room, user: Uuid) -> anyhow::Result<bool> {
+    Ok(sqlx::query!(
+        "
+            select exists(select 1 from room_bans where room_id = $1 and user_id = $2) as is_banned;
+        ",
+        room.uuid,
+        user
+    )
+    .fetch_one(db)
+    .await?
+    .is_banned
+    .unwrap_or(false))
+}
+
+/// Removes the user from the room and posts the system message of the given type.
+///
+/// The content of the message is the reason of the removal, if any. If the user was a member,
+/// the `RoomLeave` letting them know is returned to be sent once that's committed.
+async fn remove_member(
+    db: &mut PgConnection,
+    room: &Room,
+    user: &User,
+    reason: &str,
+    type_: MessageType,
+) -> anyhow::Result<Option<Deferred>> {
+    let removed = sqlx::query!(
+        "
+            delete from room_members
+            where room_id = $1
+              and user_id = $2;
+        ",
+        room.uuid,
+        user.uuid
+    )
+    .execute(&mut *db)
+    .await?
+    .rows_affected()
+        > 0;
+
+    if removed && room.kind == RoomKind::GroupDm {
+        refresh_dm_name(&mut *db, room).await?;
+    }
+
+    services::message::create(
+        db,
+        Message::new_with_type(user.clone(), room.clone(), reason.to_string(), type_),
+    )
+    .await?;
+
+    if !removed {
+        return Ok(None);
+    }
+    let left = Deferred::new(OpCode::RoomLeave, room, Recipients::Users(vec![user.uuid]))?;
+    Ok(Some(left))
+}
+
+/// A page of the public rooms of the space, or of those outside of spaces if it's `None`.
+///
+/// The ones with the most members come first.
+#[instrument]
+pub async fn search_public(
+    db: &mut PgConnection,
+    space: Option<Uuid>,
+    search: Option<&str>,
+    offset: i64,
+    limit: i64,
+) -> anyhow::Result<Vec<(Room, i64)>> {
+    debug!("searching public rooms");
+    let pattern = search.map(contains_pattern);
+
+    let res = sqlx::query!(
+        r#"
+            select r.uuid, r.name, r.created_at, r.icon, r.topic, r.archived_at, r.owner,
+                r.slow_mode, r.space_id, r.category_id, r.position,
+                r.kind as "kind: RoomKind",
+                r.visibility as "visibility: RoomVisibility",
+                r.post_permission as "post_permission: PostPermission",
+                (select count(*) from room_members m where m.room_id = r.uuid) as "member_count!"
+            from rooms r
+            where r.visibility = 'public'
+              and r.space_id is not distinct from $4
+              and ($1::text is null or r.name ilike $1)
+            order by "member_count!" desc, r.created_at desc
+            offset $2 limit $3;
+        "#,
+        pattern,
+        offset,
+        limit,
+        space
+    )
+    .fetch_all(&mut *db)
+    .await?;
+
+    let mut rooms = Vec::with_capacity(res.len());
+    for room in res {
+        rooms.push((
+            Room {
+                uuid: room.uuid,
+                name: room.name,
+                created_at: room.created_at,
+                icon: services::asset::get_from_option(db, room.icon).await?,
+                kind: room.kind,
+                visibility: room.visibility,
+                topic: room.topic,
+                archived_at: room.archived_at,
+                owner: room.owner,
+                slow_mode: room.slow_mode,
+                post_permission: room.post_permission,
+                space: room.space_id,
+                category: room.category_id,
+                position: room.position,
+            },
+            room.member_count,
+        ));
+    }
+
+    Ok(rooms)
+}
+
+/// The 1:1 DM between the two users, if they have one
+pub async fn find_dm(
+    db: &mut PgConnection,
+    user: Uuid,
+    other: Uuid,
+) -> anyhow::Result<Option<Room>> {
+    let found = sqlx::query!(
+        "
+            select r.uuid
+            from rooms r
+            where r.kind = 'dm'
+              and exists(select 1 from room_members m where m.room_id = r.uuid and m.user_id = $1)
+              and exists(select 1 from room_members m where m.room_id = r.uuid and m.user_id = $2)
+            limit 1;
+        ",
+        user,
+        other
+    )
+    .fetch_optional(&mut *db)
+    .await?;
+
+    match found {
+        Some(found) => get(db, found.uuid).await,
+        None => Ok(None),
+    }
+}
+
+/// The name of a DM between the given users
+pub fn dm_name(users: &[User]) -> String {
+    let mut names = users
+        .iter()
+        .map(|user| user.username.as_str())
+        .collect::<Vec<_>>();
+    names.sort_unstable();
+    names.join(", ")
+}
+
+/// Renames a group DM after the users that are in it now, letting its members know
+pub async fn refresh_dm_name(db: &mut PgConnection, room: &Room) -> anyhow::Result<()> {
+    sqlx::query!(
+        "
+            update rooms
+            set name = coalesce((select string_agg(u.username, ', ' order by u.username)
+                                 from room_members m
+                                          inner join users u on u.uuid = m.user_id
+                                 where m.room_id = $1), '')
+            where uuid = $1;
+        ",
+        room.uuid
+    )
+    .execute(&mut *db)
+    .await?;
+
+    if let Some(room) = get(&mut *db, room.uuid).await? {
+        broadcast_update(db, room).await?;
+    }
+
+    Ok(())
+}
+
+/// Archives the room, or brings it back if `archived` is false
+#[instrument]
+pub async fn set_archived(
+    db: &mut PgConnection,
+    room: &Room,
+    archived: bool,
+    archived_by: &User,
+) -> anyhow::Result<Room> {
+    debug!("archiving room");
+    sqlx::query!(
+        "
+            update rooms
+            set archived_at = case when $2 then coalesce(archived_at, now()) end
+            where uuid = $1;
+        ",
+        room.uuid,
+        archived
+    )
+    .execute(&mut *db)
+    .await?;
+
+    if room.is_archived() != archived {
+        let action = if archived {
+            AuditAction::RoomArchive
+        } else {
+            AuditAction::RoomUnarchive
+        };
+        services::audit_log::record(&mut *db, room.uuid, archived_by, action, None, json!({}))
+            .await?;
+    }
+
+    get(db, room.uuid).await.map(|it| it.unwrap())
+}
+
+/// Deletes the room along with everything in it.
+///
+/// Only the row of the icon is deleted, its file is left to the caller, as is sending the
+/// returned `RoomDelete` to the members once that's committed.
+#[instrument]
+pub async fn delete(db: &mut PgConnection, room: &Room) -> anyhow::Result<Deferred> {
+    debug!("deleting room");
+    let members = get_member_ids(&mut *db, room).await?;
+
+    sqlx::query!("delete from rooms where uuid = $1;", room.uuid)
+        .execute(&mut *db)
+        .await?;
+    if let Some(icon) = &room.icon {
+        services::asset::delete(&mut *db, icon).await?;
+    }
+
+    Deferred::new(OpCode::RoomDelete, room, Recipients::Users(members))
+}
+
+/// Makes the member the owner of the room, giving them the admin role.
+///
+/// The previous owner stays an admin.
+#[instrument]
+pub async fn transfer_ownership(
+    db: &mut PgConnection,
+    room: &Room,
+    owner: &User,
+    transferred_by: &User,
+) -> anyhow::Result<Room> {
+    debug!("transferring room ownership");
+    let admin = services::role::get_admin(&mut *db, room).await?;
+    services::role::set_member_role(&mut *db, room, owner.uuid, &admin, transferred_by).await?;
+
+    sqlx::query!(
+        "
+            update rooms
+            set owner = $2
+            where uuid = $1;
+        ",
+        room.uuid,
+        owner.uuid
+    )
+    .execute(&mut *db)
+    .await?;
+
+    services::audit_log::record(
+        &mut *db,
+        room.uuid,
+        transferred_by,
+        AuditAction::OwnershipTransfer,
+        Some(owner.uuid),
+        services::audit_log::diff(vec![("owner", json!(room.owner), json!(owner.uuid))]),
+    )
+    .await?;
+
+    get(db, room.uuid).await.map(|it| it.unwrap())
+}
+
+/// Sends the room to its members as a `RoomUpdate`
+pub async fn broadcast_update(db: &mut PgConnection, room: Room) -> anyhow::Result<()> {
+    let members = get_member_ids(db, &room).await?;
+    websocket::send_message(
+        MessagePayload {
+            op: OpCode::RoomUpdate,
+            data: room,
+        },
+        Recipients::Users(members),
+    )
+    .await;
+
+    Ok(())
+}
+
+/// Ids of the users in the room, used to address websocket messages
+pub async fn get_member_ids(db: &mut PgConnection, room: &Room) -> anyhow::Result<Vec<Uuid>> {
+    let members = sqlx::query!(
+        "
+            select user_id
+            from room_members
+            where room_id = $1;
+        ",
+        room.uuid
+    )
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .map(|member| member.user_id)
+    .collect();
+
+    Ok(members)
+}
+
 #[instrument]
 pub async fn get_with_user(db: &mut PgConnection, user: &User) -> anyhow::Result<Vec<Room>> {
     debug!("getting room with user");
     let res = sqlx::query!(
-        "
-            select r.*
+        r#"
+            select r.uuid, r.name, r.created_at, r.icon, r.topic, r.archived_at, r.owner,
+                r.slow_mode, r.space_id, r.category_id, r.position,
+                r.kind as "kind: RoomKind",
+                r.visibility as "visibility: RoomVisibility",
+                r.post_permission as "post_permission: PostPermission"
             from room_members
                 left join rooms r on r.uuid = room_members.room_id
             where user_id = $1;
-        ",
+        "#,
         user.uuid
     )
     .fetch_all(&mut *db)
@@ -153,6 +661,16 @@ pub async fn get_with_user(db: &mut PgConnection, user: &User) -> anyhow::Result
             name: room.name,
             created_at: room.created_at,
             icon: services::asset::get_from_option(db, room.icon).await?,
+            kind: room.kind,
+            visibility: room.visibility,
+            topic: room.topic,
+            archived_at: room.archived_at,
+            owner: room.owner,
+            slow_mode: room.slow_mode,
+            post_permission: room.post_permission,
+            space: room.space_id,
+            category: room.category_id,
+            position: room.position,
         });
     }
 
@@ -172,12 +690,29 @@ pub async fn get_room_members(
                    u.password   as user_password,
                    u.created_at as user_created_at,
                    u.avatar as user_avatar,
-                   has_elevated_permissions,
-                   joined_at
+                   u.display_name as user_display_name,
+                   u.bio as user_bio,
+                   u.pronouns as user_pronouns,
+                   u.timezone as user_timezone,
+                   u.status_text as user_status_text,
+                   u.status_expires_at as user_status_expires_at,
+                   u.is_instance_admin as user_is_instance_admin,
+                   ro.uuid as role_uuid,
+                   ro.name as role_name,
+                   ro.permissions as role_permissions,
+                   ro.is_default as role_is_default,
+                   ro.is_admin as role_is_admin,
+                   ro.created_at as role_created_at,
+                   joined_at,
+                   (select bool_or(not s.idle)
+                    from user_sessions s
+                    where s.user_id = u.uuid
+                      and s.last_seen > now() - interval '1 minute') as active
             from room_members
             left join rooms r on r.uuid = room_members.room_id
             left join users u on u.uuid = room_members.user_id
-            where room_id = $1;
+            inner join room_roles ro on ro.uuid = room_members.role_id
+            where room_members.room_id = $1;
         ",
         room.uuid
     )
@@ -194,10 +729,28 @@ pub async fn get_room_members(
                 password: value.user_password,
                 created_at: value.user_created_at,
                 avatar: services::asset::get_from_option(db, value.user_avatar).await?,
+                display_name: value.user_display_name,
+                bio: value.user_bio,
+                pronouns: value.user_pronouns,
+                timezone: value.user_timezone,
+                status: services::user::status(
+                    value.user_status_text,
+                    value.user_status_expires_at,
+                ),
+                is_instance_admin: value.user_is_instance_admin,
+            },
+            role: Role {
+                uuid: value.role_uuid,
+                room: room.uuid,
+                name: value.role_name,
+                permissions: Permissions::from_bits_truncate(value.role_permissions),
+                is_default: value.role_is_default,
+                is_admin: value.role_is_admin,
+                created_at: value.role_created_at,
             },
             room: room.clone(),
-            has_elevated_permissions: value.has_elevated_permissions,
             joined_at: value.joined_at,
+            presence: services::presence::from_active(value.active),
         })
     }
 
@@ -206,30 +759,71 @@ pub async fn get_room_members(
     Ok(mapped)
 }
 
+/// Saves the name, topic, visibility, slow mode, post permission and icon of the room
 #[instrument]
-pub async fn update(db: &mut PgConnection, room: Room) -> anyhow::Result<Room> {
+pub async fn update(db: &mut PgConnection, room: Room, updated_by: &User) -> anyhow::Result<Room> {
     debug!("updating room");
+    let old = get(&mut *db, room.uuid)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("room to update doesn't exist"))?;
+
     let Room {
-        uuid, name, icon, ..
+        uuid,
+        name,
+        icon,
+        topic,
+        visibility,
+        slow_mode,
+        post_permission,
+        ..
     } = room;
 
     let icon = icon.map(|it| it.uuid);
 
-    sqlx::query_as!(
-        Room,
+    sqlx::query!(
         "
 update rooms
-set name     = $1,
-    icon     = $2
-where uuid = $3;
+set name            = $1,
+    icon            = $2,
+    topic           = $3,
+    visibility      = $4,
+    slow_mode       = $5,
+    post_permission = $6
+where uuid = $7;
         ",
         name,
         icon,
+        topic,
+        visibility as _,
+        slow_mode,
+        post_permission as _,
         uuid,
     )
     .execute(&mut *db)
     .await?;
     debug!("room updated");
 
-    get(db, uuid).await.map(|it| it.unwrap())
+    let room = get(&mut *db, uuid).await?.unwrap();
+    let changes = services::audit_log::diff(vec![
+        ("name", json!(old.name), json!(room.name)),
+        ("topic", json!(old.topic), json!(room.topic)),
+        ("visibility", json!(old.visibility), json!(room.visibility)),
+        ("slow_mode", json!(old.slow_mode), json!(room.slow_mode)),
+        (
+            "post_permission",
+            json!(old.post_permission),
+            json!(room.post_permission),
+        ),
+        (
+            "icon",
+            json!(old.icon.map(|it| it.uuid)),
+            json!(room.icon.as_ref().map(|it| it.uuid)),
+        ),
+    ]);
+    if changes != json!({}) {
+        services::audit_log::record(db, uuid, updated_by, AuditAction::RoomUpdate, None, changes)
+            .await?;
+    }
+
+    Ok(room)
 }
@@ -0,0 +1,533 @@
+use crate::websocket::pubsub::Recipients;
+use crate::websocket::Deferred;
+use crate::{services, websocket};
+use common::websocket::{MessagePayload, OpCode};
+use common::{Permissions, Room, Space, SpaceCategory, SpaceMember, SpaceRole, User};
+use sqlx::types::Uuid;
+use sqlx::PgConnection;
+use tracing::{debug, instrument};
+
+macro_rules! space_role {
+    ($row:expr) => {{
+        let row = $row;
+        SpaceRole {
+            uuid: row.uuid,
+            space: row.space_id,
+            name: row.name,
+            permissions: Permissions::from_bits_truncate(row.permissions),
+            is_default: row.is_default,
+            is_admin: row.is_admin,
+            created_at: row.created_at,
+        }
+    }};
+}
+
+/// Creates the space, along with the `SpaceJoin` of its owner to send once it's committed
+#[instrument]
+pub async fn create(
+    db: &mut PgConnection,
+    space: Space,
+) -> anyhow::Result<(Space, Option<Deferred>)> {
+    debug!("creating space");
+    // the `space_owner_as_member` trigger makes the owner an admin of the space
+    sqlx::query!(
+        "
+            insert into spaces (uuid, name, owner)
+            values ($1, $2, $3);
+        ",
+        space.uuid,
+        space.name,
+        space.owner
+    )
+    .execute(&mut *db)
+    .await?;
+
+    let space = get(&mut *db, space.uuid)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("created space doesn't exist"))?;
+    let joined = match space.owner {
+        Some(owner) => Some(announce_join(&space, owner)?),
+        None => None,
+    };
+
+    Ok((space, joined))
+}
+
+#[instrument]
+pub async fn get(db: &mut PgConnection, uuid: Uuid) -> anyhow::Result<Option<Space>> {
+    debug!("fetching space");
+    let space = sqlx::query!(
+        "
+            select *
+            from spaces
+            where uuid = $1;
+        ",
+        uuid
+    )
+    .fetch_optional(&mut *db)
+    .await?;
+
+    Ok(match space {
+        Some(space) => Some(Space {
+            uuid: space.uuid,
+            name: space.name,
+            owner: space.owner,
+            created_at: space.created_at,
+            categories: get_categories(db, space.uuid).await?,
+        }),
+        None => None,
+    })
+}
+
+/// The spaces the user is a member of
+#[instrument]
+pub async fn get_with_user(db: &mut PgConnection, user: &User) -> anyhow::Result<Vec<Space>> {
+    debug!("fetching spaces of user");
+    let res = sqlx::query!(
+        "
+            select s.*
+            from space_members m
+                inner join spaces s on s.uuid = m.space_id
+            where m.user_id = $1
+            order by m.joined_at;
+        ",
+        user.uuid
+    )
+    .fetch_all(&mut *db)
+    .await?;
+
+    let mut spaces = Vec::with_capacity(res.len());
+    for space in res {
+        spaces.push(Space {
+            uuid: space.uuid,
+            name: space.name,
+            owner: space.owner,
+            created_at: space.created_at,
+            categories: get_categories(&mut *db, space.uuid).await?,
+        });
+    }
+
+    Ok(spaces)
+}
+
+async fn get_categories(db: &mut PgConnection, space: Uuid) -> anyhow::Result<Vec<SpaceCategory>> {
+    let categories = sqlx::query!(
+        "
+            select *
+            from space_categories
+            where space_id = $1
+            order by position, created_at;
+        ",
+        space
+    )
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .map(|category| SpaceCategory {
+        uuid: category.uuid,
+        space: category.space_id,
+        name: category.name,
+        position: category.position,
+    })
+    .collect();
+
+    Ok(categories)
+}
+
+/// The role of the user in the space, `None` if they aren't a member
+pub async fn get_member_role(
+    db: &mut PgConnection,
+    space: &Space,
+    user: Uuid,
+) -> anyhow::Result<Option<SpaceRole>> {
+    let role = sqlx::query!(
+        "
+            select r.*
+            from space_members m
+                inner join space_roles r on r.uuid = m.role_id
+            where m.space_id = $1
+              and m.user_id = $2;
+        ",
+        space.uuid,
+        user
+    )
+    .fetch_optional(db)
+    .await?;
+
+    Ok(role.map(|role| space_role!(role)))
+}
+
+pub async fn get_role(
+    db: &mut PgConnection,
+    space: &Space,
+    uuid: Uuid,
+) -> anyhow::Result<Option<SpaceRole>> {
+    let role = sqlx::query!(
+        "
+            select *
+            from space_roles
+            where space_id = $1
+              and uuid = $2;
+        ",
+        space.uuid,
+        uuid
+    )
+    .fetch_optional(db)
+    .await?;
+
+    Ok(role.map(|role| space_role!(role)))
+}
+
+/// The role of the members who join without being assigned one
+pub async fn get_default_role(db: &mut PgConnection, space: &Space) -> anyhow::Result<SpaceRole> {
+    let role = sqlx::query!(
+        "
+            select *
+            from space_roles
+            where space_id = $1
+              and is_default;
+        ",
+        space.uuid
+    )
+    .fetch_one(db)
+    .await?;
+
+    Ok(space_role!(role))
+}
+
+pub async fn user_in_space(
+    db: &mut PgConnection,
+    space: Uuid,
+    user: &User,
+) -> anyhow::Result<bool> {
+    let member = sqlx::query!(
+        "
+            select user_id
+            from space_members
+            where space_id = $1
+              and user_id = $2;
+        ",
+        space,
+        user.uuid
+    )
+    .fetch_optional(db)
+    .await?;
+
+    Ok(member.is_some())
+}
+
+#[instrument]
+pub async fn get_members(db: &mut PgConnection, space: &Space) -> anyhow::Result<Vec<SpaceMember>> {
+    debug!("fetching space members");
+    let res = sqlx::query!(
+        "
+            select m.user_id, m.joined_at, r.*
+            from space_members m
+                inner join space_roles r on r.uuid = m.role_id
+            where m.space_id = $1
+            order by m.joined_at;
+        ",
+        space.uuid
+    )
+    .fetch_all(&mut *db)
+    .await?;
+
+    let mut members = Vec::with_capacity(res.len());
+    for member in res {
+        let user = services::user::get(&mut *db, member.user_id)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("member of the space doesn't exist"))?;
+        let joined_at = member.joined_at;
+        members.push(SpaceMember {
+            user,
+            space: space.uuid,
+            role: space_role!(member),
+            joined_at,
+        });
+    }
+
+    Ok(members)
+}
+
+/// Ids of the users in the space, used to address websocket messages
+pub async fn get_member_ids(db: &mut PgConnection, space: &Space) -> anyhow::Result<Vec<Uuid>> {
+    let members = sqlx::query!(
+        "
+            select user_id
+            from space_members
+            where space_id = $1;
+        ",
+        space.uuid
+    )
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .map(|member| member.user_id)
+    .collect();
+
+    Ok(members)
+}
+
+/// Adds the user to the space and to its public rooms they aren't banned from.
+///
+/// The `SpaceJoin` and `RoomJoin`s letting them know are to be sent once that's committed.
+#[instrument]
+pub async fn join(
+    db: &mut PgConnection,
+    space: &Space,
+    user: &User,
+    role: &SpaceRole,
+    added_by: &User,
+) -> anyhow::Result<(SpaceMember, Vec<Deferred>)> {
+    debug!("joining space");
+    let ret = sqlx::query!(
+        "
+            insert into space_members (space_id, user_id, role_id)
+            values ($1, $2, $3)
+            returning joined_at;
+        ",
+        space.uuid,
+        user.uuid,
+        role.uuid
+    )
+    .fetch_one(&mut *db)
+    .await?;
+    let mut joined = vec![announce_join(space, user.uuid)?];
+
+    let rooms = sqlx::query!(
+        "
+            select r.uuid
+            from rooms r
+            where r.space_id = $1
+              and r.visibility = 'public'
+              and r.archived_at is null
+              and not exists(select 1
+                             from room_members m
+                             where m.room_id = r.uuid
+                               and m.user_id = $2)
+              and not exists(select 1
+                             from room_bans b
+                             where b.room_id = r.uuid
+                               and b.user_id = $2);
+        ",
+        space.uuid,
+        user.uuid
+    )
+    .fetch_all(&mut *db)
+    .await?;
+    for room in rooms {
+        let room = services::room::get(&mut *db, room.uuid)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("room of the space doesn't exist"))?;
+        let role = services::role::get_default(&mut *db, &room).await?;
+        let (_, room_joined) = services::room::join(&mut *db, &room, user, &role, added_by).await?;
+        joined.push(room_joined);
+    }
+
+    let member = SpaceMember {
+        user: user.clone(),
+        space: space.uuid,
+        role: role.clone(),
+        joined_at: ret.joined_at,
+    };
+    Ok((member, joined))
+}
+
+/// The `SpaceJoin` letting the user know they're in the space now
+fn announce_join(space: &Space, user: Uuid) -> anyhow::Result<Deferred> {
+    Deferred::new(OpCode::SpaceJoin, space, Recipients::Users(vec![user]))
+}
+
+/// Removes the user from the space along with all of its rooms.
+///
+/// The `RoomLeave`s and `SpaceLeave` letting them know are to be sent once that's committed.
+#[instrument]
+pub async fn leave(
+    db: &mut PgConnection,
+    space: &Space,
+    user: &User,
+) -> anyhow::Result<Vec<Deferred>> {
+    debug!("leaving space");
+    let rooms = sqlx::query!(
+        "
+            select m.room_id
+            from room_members m
+                inner join rooms r on r.uuid = m.room_id
+            where r.space_id = $1
+              and m.user_id = $2;
+        ",
+        space.uuid,
+        user.uuid
+    )
+    .fetch_all(&mut *db)
+    .await?;
+    let mut left = Vec::new();
+    for room in rooms {
+        let room = services::room::get(&mut *db, room.room_id)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("room of the space doesn't exist"))?;
+        left.extend(services::room::leave(&mut *db, &room, user).await?);
+    }
+
+    sqlx::query!(
+        "
+            delete
+            from space_members
+            where space_id = $1
+              and user_id = $2;
+        ",
+        space.uuid,
+        user.uuid
+    )
+    .execute(&mut *db)
+    .await?;
+
+    left.push(Deferred::new(
+        OpCode::SpaceLeave,
+        space,
+        Recipients::Users(vec![user.uuid]),
+    )?);
+
+    Ok(left)
+}
+
+/// Whether the user owns any room of the space, they'd be left without one if they left it
+pub async fn owns_rooms(db: &mut PgConnection, space: &Space, user: &User) -> anyhow::Result<bool> {
+    let room = sqlx::query!(
+        "
+            select uuid
+            from rooms
+            where space_id = $1
+              and owner = $2
+            limit 1;
+        ",
+        space.uuid,
+        user.uuid
+    )
+    .fetch_optional(db)
+    .await?;
+
+    Ok(room.is_some())
+}
+
+/// Adds a category at the end of the space
+#[instrument]
+pub async fn create_category(
+    db: &mut PgConnection,
+    space: &Space,
+    name: &str,
+) -> anyhow::Result<SpaceCategory> {
+    debug!("creating category");
+    let category = sqlx::query!(
+        "
+            insert into space_categories (uuid, space_id, name, position)
+            values ($1, $2, $3,
+                    (select coalesce(max(position) + 1, 0)
+                     from space_categories
+                     where space_id = $2))
+            returning *;
+        ",
+        Uuid::new_v4(),
+        space.uuid,
+        name
+    )
+    .fetch_one(db)
+    .await?;
+
+    Ok(SpaceCategory {
+        uuid: category.uuid,
+        space: category.space_id,
+        name: category.name,
+        position: category.position,
+    })
+}
+
+#[instrument]
+pub async fn update_category(
+    db: &mut PgConnection,
+    category: &SpaceCategory,
+) -> anyhow::Result<()> {
+    debug!("updating category");
+    sqlx::query!(
+        "
+            update space_categories
+            set name     = $1,
+                position = $2
+            where uuid = $3;
+        ",
+        category.name,
+        category.position,
+        category.uuid
+    )
+    .execute(db)
+    .await?;
+
+    Ok(())
+}
+
+/// Deletes the category, its rooms are moved above the categories
+#[instrument]
+pub async fn delete_category(
+    db: &mut PgConnection,
+    category: &SpaceCategory,
+) -> anyhow::Result<()> {
+    debug!("deleting category");
+    sqlx::query!(
+        "
+            delete
+            from space_categories
+            where uuid = $1;
+        ",
+        category.uuid
+    )
+    .execute(db)
+    .await?;
+
+    Ok(())
+}
+
+/// Puts the room in the category of its space at the given position
+#[instrument]
+pub async fn move_room(
+    db: &mut PgConnection,
+    room: &Room,
+    category: Option<Uuid>,
+    position: i32,
+) -> anyhow::Result<Room> {
+    debug!("moving room");
+    sqlx::query!(
+        "
+            update rooms
+            set category_id = $1,
+                position    = $2
+            where uuid = $3;
+        ",
+        category,
+        position,
+        room.uuid
+    )
+    .execute(&mut *db)
+    .await?;
+
+    services::room::get(db, room.uuid)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("moved room doesn't exist"))
+}
+
+/// Sends the space as it is now to its members, after its categories change
+pub async fn broadcast_update(db: &mut PgConnection, space: &Space) -> anyhow::Result<()> {
+    let space = get(&mut *db, space.uuid)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("updated space doesn't exist"))?;
+    let members = get_member_ids(db, &space).await?;
+    websocket::send_message(
+        MessagePayload {
+            op: OpCode::SpaceUpdate,
+            data: space,
+        },
+        Recipients::Users(members),
+    )
+    .await;
+
+    Ok(())
+}
@@ -1,12 +1,13 @@
 use crate::websocket;
+use crate::websocket::pubsub::Recipients;
+use chrono::{DateTime, Utc};
 use common::websocket::{MessagePayload, OpCode};
-use common::{Asset, User};
+use common::{Asset, CustomStatus, User};
 use serde::export::Formatter;
 use sqlx::postgres::PgDatabaseError;
 use sqlx::types::Uuid;
 use sqlx::PgConnection;
 use std::fmt;
-use std::sync::Arc;
 use tracing::instrument;
 use tracing::{debug, error};
 
@@ -20,6 +21,13 @@ select users.username as user_username,
        users.password as user_password,
        users.created_at as user_created_at,
        users.avatar as "user_avatar?",
+       users.display_name as user_display_name,
+       users.bio as user_bio,
+       users.pronouns as user_pronouns,
+       users.timezone as user_timezone,
+       users.status_text as user_status_text,
+       users.status_expires_at as user_status_expires_at,
+       users.is_instance_admin as user_is_instance_admin,
        assets.uuid as "asset_uuid?",
        assets.created_at as "asset_created_at?"
 from users
@@ -45,6 +53,12 @@ where "# + $sel_col
                     }),
                     None => None,
                 },
+                display_name: res.user_display_name,
+                bio: res.user_bio,
+                pronouns: res.user_pronouns,
+                timezone: res.user_timezone,
+                status: status(res.user_status_text, res.user_status_expires_at),
+                is_instance_admin: res.user_is_instance_admin,
             })),
             Ok(None) => Ok(None),
             Err(e) => {
@@ -98,6 +112,12 @@ pub async fn create(db: &mut PgConnection, user: User) -> anyhow::Result<User> {
                 password: res.password,
                 created_at: res.created_at,
                 avatar: None,
+                display_name: None,
+                bio: None,
+                pronouns: None,
+                timezone: None,
+                status: None,
+                is_instance_admin: res.is_instance_admin,
             };
             debug!("created user: uuid: {}", user.uuid);
             Ok(user)
@@ -138,21 +158,41 @@ pub async fn update(db: &mut PgConnection, user: User) -> anyhow::Result<User> {
         uuid,
         username,
         avatar,
+        display_name,
+        bio,
+        pronouns,
+        timezone,
+        status,
         ..
     } = user;
 
     let avatar = avatar.map(|it| it.uuid);
+    let (status_text, status_expires_at) = match status {
+        Some(status) => (Some(status.text), status.expires_at),
+        None => (None, None),
+    };
 
-    sqlx::query_as!(
-        User,
+    sqlx::query!(
         "
 update users
-set username   = $1,
-    avatar     = $2
-where uuid = $3;
+set username          = $1,
+    avatar            = $2,
+    display_name      = $3,
+    bio               = $4,
+    pronouns          = $5,
+    timezone          = $6,
+    status_text       = $7,
+    status_expires_at = $8
+where uuid = $9;
         ",
         username,
         avatar,
+        display_name,
+        bio,
+        pronouns,
+        timezone,
+        status_text,
+        status_expires_at,
         uuid,
     )
     .execute(&mut *db)
@@ -160,14 +200,81 @@ where uuid = $3;
 
     let new_user = get(db, uuid).await.map(|it| it.unwrap())?;
 
+    // everyone who can see the user has to know about the change
+    let recipients = get_ids_sharing_room(db, new_user.uuid).await?;
     websocket::send_message(
-        Arc::new(MessagePayload {
+        MessagePayload {
             op: OpCode::UserUpdate,
             data: new_user.clone(),
-        }),
-        |uuid| uuid == new_user.uuid,
+        },
+        Recipients::Users(recipients),
     )
     .await;
 
     Ok(new_user)
 }
+
+/// Sets the bcrypt hash of the user's password, which completes a required password reset
+#[instrument(skip(password))]
+pub async fn set_password(
+    db: &mut PgConnection,
+    user: &User,
+    password: &str,
+) -> anyhow::Result<()> {
+    debug!("setting password");
+    sqlx::query!(
+        "
+            update users
+            set password                = $2,
+                password_reset_required = false
+            where uuid = $1;
+        ",
+        user.uuid,
+        password
+    )
+    .execute(db)
+    .await?;
+
+    Ok(())
+}
+
+/// The status stored in the `status_*` columns, unless it has expired
+pub(crate) fn status(
+    text: Option<String>,
+    expires_at: Option<DateTime<Utc>>,
+) -> Option<CustomStatus> {
+    let status = CustomStatus {
+        text: text?,
+        expires_at,
+    };
+
+    if status.is_expired() {
+        None
+    } else {
+        Some(status)
+    }
+}
+
+/// Ids of the user and of everyone sharing a room with them
+pub async fn get_ids_sharing_room(db: &mut PgConnection, uuid: Uuid) -> anyhow::Result<Vec<Uuid>> {
+    let mut users: Vec<Uuid> = sqlx::query!(
+        "
+            select distinct others.user_id
+            from room_members mine
+                join room_members others on others.room_id = mine.room_id
+            where mine.user_id = $1;
+        ",
+        uuid
+    )
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .map(|member| member.user_id)
+    .collect();
+
+    if !users.contains(&uuid) {
+        users.push(uuid);
+    }
+
+    Ok(users)
+}
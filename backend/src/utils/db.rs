@@ -1,6 +1,9 @@
 use crate::bail_if_err;
+use crate::utils::from_anyhow;
+use crate::websocket::{self, Deferred};
 use futures::future::BoxFuture;
 use sqlx::{Connection, PgPool, Postgres};
+use warp::reply::Response;
 use warp::{Rejection, Reply};
 
 pub type Transaction<'c> = sqlx::Transaction<'c, Postgres>;
@@ -19,3 +22,66 @@ where
     let ret = bail_if_err!(ret);
     Ok(ret.into_response())
 }
+
+/// Like [`with_transaction`], but the callback also returns a value that's only handed back
+/// once the transaction is committed, for what can't be rolled back like deleting files.
+///
+/// It's the default value of `T` when the transaction failed.
+pub async fn with_transaction_then<F, R, T>(
+    pool: PgPool,
+    callback: F,
+) -> Result<(Response, T), Rejection>
+where
+    for<'c> F: FnOnce(&'c mut Transaction) -> BoxFuture<'c, anyhow::Result<(R, T)>>
+        + 'static
+        + Send
+        + Sync,
+    R: Reply,
+    T: Default + Send,
+{
+    let mut conn = match pool.acquire().await {
+        Ok(conn) => conn,
+        Err(e) => return Ok((from_anyhow(e.into()).into_response(), T::default())),
+    };
+    let ret: anyhow::Result<(R, T)> = conn.transaction(|db| callback(db)).await;
+
+    match ret {
+        Ok((reply, value)) => Ok((reply.into_response(), value)),
+        Err(e) => Ok((from_anyhow(e).into_response(), T::default())),
+    }
+}
+
+/// Like [`with_transaction`], but the callback collects the websocket messages it produces,
+/// which are only sent once the transaction is committed.
+pub async fn with_transaction_deferred<F, R>(
+    pool: PgPool,
+    callback: F,
+) -> Result<Response, Rejection>
+where
+    for<'c> F: FnOnce(&'c mut Transaction, &'c mut Vec<Deferred>) -> BoxFuture<'c, anyhow::Result<R>>
+        + 'static
+        + Send
+        + Sync,
+    R: Reply,
+{
+    let (reply, messages) = with_transaction_then(pool, move |conn| {
+        Box::pin(async move {
+            let mut messages = Vec::new();
+            let reply = callback(&mut *conn, &mut messages).await?;
+            Ok((reply, messages))
+        })
+    })
+    .await?;
+    websocket::send_deferred(messages).await;
+
+    Ok(reply)
+}
+
+/// An `ilike` pattern matching values containing `search`, which is matched literally
+pub fn contains_pattern(search: &str) -> String {
+    let escaped = search
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{}%", escaped)
+}
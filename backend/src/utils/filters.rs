@@ -1,4 +1,5 @@
 use crate::auth::parse_token;
+use crate::utils::from_anyhow;
 use common::errors::ApiError;
 use common::User;
 use serde::Deserialize;
@@ -32,7 +33,7 @@ pub fn ensure_authorized(
 
             let user = parse_token(&mut conn, &token)
                 .await
-                .map_err(|e| ApiError::new_with_message(&e.to_string()).into_rejection())?;
+                .map_err(|e| from_anyhow(e).into_rejection())?;
 
             let user = match user {
                 Some(user) => user,
@@ -48,6 +49,23 @@ pub fn ensure_authorized(
         })
 }
 
+/// Like [`ensure_authorized`], but refuses users who aren't instance admins
+pub fn ensure_instance_admin(
+    pool: PgPool,
+) -> impl Filter<Extract = (User,), Error = warp::Rejection> + Clone {
+    ensure_authorized(pool).and_then(|user: User| async move {
+        if user.is_instance_admin {
+            Ok(user)
+        } else {
+            Err(ApiError::new_with_message_and_status(
+                "only instance admins can do this",
+                StatusCode::FORBIDDEN,
+            )
+            .into_rejection())
+        }
+    })
+}
+
 pub fn single_page_application(
     dist_dir: impl Into<PathBuf>,
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
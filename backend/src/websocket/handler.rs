@@ -1,147 +1,235 @@
-use super::{HEARTBEATS, USERS};
-use crate::websocket::models::WsSession;
+use super::{HEARTBEAT_CONFIG, SESSIONS};
+use crate::websocket::heartbeat::{Heartbeat, Stopped};
+use crate::websocket::models::{ConnectionParams, WsSession};
 use crate::{auth, services};
-use anyhow::{anyhow, Context};
-use common::websocket::{AuthenticatePayload, AuthenticatedPayload, MessagePayload, OpCode};
-use futures::{FutureExt, StreamExt};
-use serde::Serialize;
+use common::errors::ApiError;
+use common::websocket::{
+    AuthenticatePayload, AuthenticatedPayload, ErrorCode, ErrorPayload, MessagePayload, OpCode,
+    UpdatePresencePayload, PROTOCOL_VERSION,
+};
+use futures::StreamExt;
+use serde::de::DeserializeOwned;
 use serde_json::Value;
-use sqlx::types::Uuid;
 use sqlx::PgPool;
-use std::sync::Arc;
-use tokio::sync::mpsc;
-use tokio::time::{Duration, Instant};
-use tokio_stream::wrappers::UnboundedReceiverStream;
+use tracing::{debug, error};
 use warp::filters::ws::WebSocket;
 use warp::ws::Message;
 
-/// How often heartbeat pings are sent
-const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
-
-/// How long before lack of client response causes a timeout
-const CLIENT_TIMEOUT: Duration = Duration::from_secs(10);
-
-#[derive(Serialize)]
-struct ResponseMessage {}
-
-async fn heartbeat(session_id: Uuid, tx: Arc<mpsc::UnboundedSender<Result<Message, warp::Error>>>) {
-    if tx.send(Ok(Message::ping(""))).is_err() {
-        return user_disconnected(session_id).await;
-    };
-    loop {
-        let hb = HEARTBEATS.read().await;
-        if let Some(hb) = hb.get(&session_id) {
-            if Instant::now().duration_since(*hb) > CLIENT_TIMEOUT {
-                // heartbeat timed out
-                println!("Websocket Client heartbeat failed, disconnecting!");
-
-                // disconnect user
-                let _ = tx.send(Ok(Message::close()));
-                user_disconnected(session_id).await;
-
-                // don't try to send a ping
-                return;
-            }
-
-            println!("Sending heartbeat ping");
-            if tx.send(Ok(Message::ping(""))).is_err() {
-                user_disconnected(session_id).await;
-            };
-
-            tokio::time::sleep(HEARTBEAT_INTERVAL).await;
-        }
-    }
-}
-
-pub async fn user_connected(pool: PgPool, ws: WebSocket) -> anyhow::Result<()> {
+pub async fn user_connected(
+    pool: PgPool,
+    ws: WebSocket,
+    params: ConnectionParams,
+) -> anyhow::Result<()> {
     // Split the socket into a sender and receive of messages.
     let (user_ws_tx, mut user_ws_rx) = ws.split();
 
-    // Use an unbounded channel to handle buffering and flushing of messages
-    // to the websocket...
-    let (tx, rx) = mpsc::unbounded_channel();
-    let rx = UnboundedReceiverStream::new(rx);
-    tokio::task::spawn(rx.forward(user_ws_tx).map(|result| {
-        if let Err(e) = result {
-            eprintln!("websocket send error: {}", e);
-        }
-    }));
+    // Create our own websocket session, its messages are buffered in a bounded
+    // queue and flushed to the websocket by a separate task
+    let heartbeat = Heartbeat::new(*HEARTBEAT_CONFIG.read().await);
+    let mut session = WsSession::new(pool, user_ws_tx, params.encoding, heartbeat.clone());
+
+    let version = params.v.unwrap_or(PROTOCOL_VERSION);
+    if version != PROTOCOL_VERSION {
+        let error = ErrorPayload::new(
+            ErrorCode::UnsupportedVersion,
+            &format!("unsupported protocol version: {}", version),
+        );
+        send_error(&session, error)?;
+        return Ok(());
+    }
 
-    // Create our own websocket session
-    let mut session = WsSession::new(pool, tx);
+    // pings the client for as long as the session is open
+    let pinger = session.clone();
+    let mut heartbeat_task =
+        tokio::task::spawn(heartbeat.run(move || pinger.queue(Message::ping(""))));
 
     // listen to messages
-    while let Some(result) = user_ws_rx.next().await {
-        let msg = result?;
+    loop {
+        let result = tokio::select! {
+            result = user_ws_rx.next() => match result {
+                Some(result) => result,
+                None => break,
+            },
+            // the writer closes the socket itself
+            _ = session.evicted() => break,
+            stopped = &mut heartbeat_task => {
+                if let Ok(Stopped::TimedOut) = stopped {
+                    debug!("websocket session {} timed out", session.id);
+                    let _ = session.queue(Message::close_with(1001u16, "heartbeat timed out"));
+                }
+                break;
+            }
+        };
+
+        let msg = match result {
+            Ok(msg) => msg,
+            Err(e) => {
+                debug!("websocket receive error: {}", e);
+                break;
+            }
+        };
         println!("Websocket message received {:?}", msg);
 
         if let Err(e) = user_message(&mut session, msg).await {
-            // TODO codes
-            session
-                .tx
-                .send(Ok(Message::close_with(0_u16, e.to_string())))?;
-            break;
+            let error = to_error_payload(e);
+            let is_fatal = error.code.is_fatal();
+
+            if send_error(&session, error).is_err() || is_fatal {
+                break;
+            }
         };
     }
 
     // user_ws_rx stream will keep processing as long as the user stays
     // connected. Once they disconnect, then...
-    user_disconnected(session.id).await;
+    heartbeat_task.abort();
+    user_disconnected(&session).await;
     Ok(())
 }
 
-async fn user_message(session: &mut WsSession, message: Message) -> anyhow::Result<()> {
-    if message.is_text() {
-        let message = message
-            .to_str()
-            .map_err(|_| anyhow!("failed to parse message as string"))?;
+/// Sends the error frame and, if the error is fatal, closes the connection with its code
+fn send_error(session: &WsSession, error: ErrorPayload) -> anyhow::Result<()> {
+    session.send(&MessagePayload {
+        op: error.code.op(),
+        data: error.clone(),
+    })?;
 
-        let json =
-            serde_json::from_str::<MessagePayload<Value>>(message).context("invalid json found")?;
+    if error.code.is_fatal() {
+        session.queue(Message::close_with(error.code, error.message))?;
+    }
+
+    Ok(())
+}
+
+fn to_error_payload(e: anyhow::Error) -> ErrorPayload {
+    match e.downcast::<ErrorPayload>() {
+        Ok(error) => error,
+        Err(e) => {
+            error!("error while handling websocket message: {}", e);
+            ErrorPayload::new(ErrorCode::Internal, "internal server error")
+        }
+    }
+}
+
+fn parse_data<T: DeserializeOwned>(data: Value) -> Result<T, ErrorPayload> {
+    serde_json::from_value(data)
+        .map_err(|e| ErrorPayload::new(ErrorCode::InvalidData, &e.to_string()))
+}
+
+async fn user_message(session: &mut WsSession, message: Message) -> anyhow::Result<()> {
+    if message.is_text() || message.is_binary() {
+        let json = session
+            .encoding
+            .decode::<MessagePayload<Value>>(message.as_bytes())
+            .map_err(|e| ErrorPayload::new(ErrorCode::MalformedFrame, &e.to_string()))?;
 
         match json.op {
             OpCode::Authenticate => {
-                let token = serde_json::from_value::<AuthenticatePayload>(json.data)?.token;
+                if session.user.is_some() {
+                    return Err(ErrorPayload::new(
+                        ErrorCode::AlreadyAuthenticated,
+                        "connection is already authenticated",
+                    )
+                    .into());
+                }
+
+                let token = parse_data::<AuthenticatePayload>(json.data)?.token;
 
                 let mut db = session.pool.begin().await?;
 
-                let user = auth::parse_token(&mut db, &token).await?;
+                // refused accounts, e.g. suspended ones, fail like invalid tokens
+                let user = auth::parse_token(&mut db, &token)
+                    .await
+                    .map_err(|e| match e.downcast::<ApiError>() {
+                        Ok(e) => {
+                            ErrorPayload::new(ErrorCode::AuthenticationFailed, &e.message).into()
+                        }
+                        Err(e) => e,
+                    })?;
                 let user = match user {
                     Some(user) => user,
                     None => {
-                        return Err(anyhow!("no user found"));
+                        return Err(ErrorPayload::new(
+                            ErrorCode::AuthenticationFailed,
+                            "invalid token",
+                        )
+                        .into());
                     }
                 };
                 session.set_user(&user);
                 // maybe arc this clone?
-                USERS.write().await.insert(user.uuid, session.clone());
-
-                tokio::task::spawn(heartbeat(session.id, session.tx.clone()));
+                SESSIONS.write().await.insert(session.id, session.clone());
 
+                let user_id = user.uuid;
                 let rooms = services::room::get_with_user(&mut db, &user).await?;
+                let spaces = services::space::get_with_user(&mut db, &user).await?;
+                let notification_settings =
+                    services::notification::get_with_user(&mut db, &user).await?;
                 let payload = MessagePayload {
                     op: OpCode::Authenticated,
-                    data: AuthenticatedPayload { me: user, rooms },
+                    data: AuthenticatedPayload {
+                        me: user,
+                        rooms,
+                        spaces,
+                        notification_settings,
+                    },
                 };
-
                 session.send(&payload)?;
+
+                // after `Authenticated` so it's the first thing the client receives
+                services::presence::connect(&mut db, session.id, user_id).await?;
+                db.commit().await?;
+            }
+            OpCode::UpdatePresence => {
+                let user = match session.user {
+                    Some(user) => user,
+                    None => {
+                        return Err(ErrorPayload::new(
+                            ErrorCode::NotAuthenticated,
+                            "connection isn't authenticated",
+                        )
+                        .into())
+                    }
+                };
+                let idle = parse_data::<UpdatePresencePayload>(json.data)?.idle;
+
+                let mut conn = session.pool.acquire().await?;
+                services::presence::set_idle(&mut conn, session.id, user, idle).await?;
+            }
+            op => {
+                return Err(ErrorPayload::new(
+                    ErrorCode::UnknownOp,
+                    &format!("invalid op code: {}", u32::from(op)),
+                )
+                .into())
             }
-            _ => return Err(anyhow!("invalid OP code")),
         }
     } else if message.is_pong() {
-        let hb = Instant::now();
-        session.hb = hb;
-        HEARTBEATS.write().await.insert(session.id, hb);
-        println!("~~meat~~ beaten at {:?}", hb);
+        session.heartbeat.beat();
+
+        if session.user.is_some() {
+            let mut conn = session.pool.acquire().await?;
+            services::presence::refresh(&mut conn, session.id).await?;
+        }
     };
 
     Ok(())
 }
 
-async fn user_disconnected(uuid: Uuid) {
-    eprintln!("good bye user: {}", uuid);
+async fn user_disconnected(session: &WsSession) {
+    eprintln!("good bye session: {}", session.id);
 
-    // Stream closed up, so remove from the user list and heartbeats
-    USERS.write().await.remove(&uuid);
-    HEARTBEATS.write().await.remove(&uuid);
+    // Stream closed up, so remove from the session list
+    SESSIONS.write().await.remove(&session.id);
+
+    if let Some(user) = session.user {
+        let result = match session.pool.acquire().await {
+            Ok(mut conn) => services::presence::disconnect(&mut conn, session.id, user).await,
+            Err(e) => Err(e.into()),
+        };
+
+        if let Err(e) = result {
+            error!("failed to remove session {}: {}", session.id, e);
+        }
+    }
 }
@@ -0,0 +1,101 @@
+use anyhow::Context;
+use std::env;
+use std::sync::{Arc, Mutex};
+use tokio::time::{Duration, Instant};
+
+/// How often the clients are pinged and how long they have to answer
+#[derive(Debug, Copy, Clone)]
+pub struct HeartbeatConfig {
+    pub interval: Duration,
+    pub timeout: Duration,
+}
+
+impl Default for HeartbeatConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(5),
+            timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+impl HeartbeatConfig {
+    /// Reads the `WS_HEARTBEAT_INTERVAL` and `WS_HEARTBEAT_TIMEOUT` environment variables,
+    /// both in seconds, falling back to the defaults for the missing ones
+    pub fn from_env() -> anyhow::Result<Self> {
+        let mut config = Self::default();
+
+        if let Ok(interval) = env::var("WS_HEARTBEAT_INTERVAL") {
+            config.interval = Duration::from_secs(
+                interval
+                    .parse()
+                    .context("invalid `WS_HEARTBEAT_INTERVAL`")?,
+            );
+        }
+        if let Ok(timeout) = env::var("WS_HEARTBEAT_TIMEOUT") {
+            config.timeout =
+                Duration::from_secs(timeout.parse().context("invalid `WS_HEARTBEAT_TIMEOUT`")?);
+        }
+
+        anyhow::ensure!(
+            config.interval > Duration::from_secs(0),
+            "heartbeat interval must not be zero"
+        );
+
+        Ok(config)
+    }
+}
+
+/// Why a heartbeat stopped
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Stopped {
+    /// The client didn't answer the pings in time
+    TimedOut,
+    /// The ping couldn't be sent because the session is closed
+    Closed,
+}
+
+/// Tracks whether a client still answers its pings.
+///
+/// Clones share the time of the last answer, so the session can record
+/// pongs while [`Heartbeat::run`] pings from its own task.
+#[derive(Debug, Clone)]
+pub struct Heartbeat {
+    config: HeartbeatConfig,
+    last_beat: Arc<Mutex<Instant>>,
+}
+
+impl Heartbeat {
+    pub fn new(config: HeartbeatConfig) -> Self {
+        Self {
+            config,
+            last_beat: Arc::new(Mutex::new(Instant::now())),
+        }
+    }
+
+    /// Records that the client answered
+    pub fn beat(&self) {
+        *self.last_beat.lock().unwrap() = Instant::now();
+    }
+
+    /// Pings the client every interval until it stops answering or the ping can't be sent
+    pub async fn run<F>(self, mut ping: F) -> Stopped
+    where
+        F: FnMut() -> anyhow::Result<()>,
+    {
+        let mut interval = tokio::time::interval(self.config.interval);
+
+        loop {
+            interval.tick().await;
+
+            let last_beat = *self.last_beat.lock().unwrap();
+            if last_beat.elapsed() > self.config.timeout {
+                return Stopped::TimedOut;
+            }
+
+            if ping().is_err() {
+                return Stopped::Closed;
+            }
+        }
+    }
+}
@@ -1,66 +1,191 @@
 mod handler;
+pub mod heartbeat;
 mod models;
+pub mod pubsub;
 
-use crate::utils::with_db;
-use crate::websocket::models::WsSession;
-use common::websocket::MessagePayload;
+use crate::utils::{ensure_instance_admin, with_db};
+use crate::websocket::heartbeat::HeartbeatConfig;
+use crate::websocket::models::{
+    ConnectionParams, QueueMetrics, WsSession, OUTBOUND_QUEUE_CAPACITY, SLOW_CONSUMER_EVICTIONS,
+};
+use crate::websocket::pubsub::{Event, InProcess, PubSub, Recipients};
+use common::websocket::{ErrorPayload, MessagePayload, OpCode};
+use common::User;
 use futures::future::BoxFuture;
 use lazy_static::lazy_static;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use sqlx::types::Uuid;
 use sqlx::PgPool;
 use std::collections::HashMap;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use tokio::sync::RwLock;
-use tokio::time::Instant;
+use tracing::error;
 use warp::Filter;
 
-type Heartbeats = Arc<RwLock<HashMap<Uuid, Instant>>>;
-type Users = Arc<RwLock<HashMap<Uuid, WsSession>>>;
+/// Authenticated sessions of this instance, keyed by the session id
+type Sessions = Arc<RwLock<HashMap<Uuid, WsSession>>>;
 
 lazy_static! {
-    static ref HEARTBEATS: Heartbeats = Heartbeats::default();
-    static ref USERS: Users = Users::default();
+    static ref SESSIONS: Sessions = Sessions::default();
+    static ref PUBSUB: RwLock<Arc<dyn PubSub>> = RwLock::new(Arc::new(InProcess));
+    static ref HEARTBEAT_CONFIG: RwLock<HeartbeatConfig> = RwLock::new(HeartbeatConfig::default());
+}
+
+/// Sets the pub/sub implementation events are published with
+pub async fn set_pubsub(pubsub: impl PubSub + 'static) {
+    *PUBSUB.write().await = Arc::new(pubsub);
+}
+
+/// Sets how the clients connecting from now on are pinged
+pub async fn set_heartbeat_config(config: HeartbeatConfig) {
+    *HEARTBEAT_CONFIG.write().await = config;
 }
 
 pub fn route(
     pool: PgPool,
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
-    warp::path!("ws")
+    // they tell how loaded the instance is, so they're for its admins only
+    let metrics = warp::path!("ws" / "metrics")
+        .and(warp::get())
+        .and(ensure_instance_admin(pool.clone()))
+        .and_then(get_queue_metrics);
+
+    let connect = warp::path!("ws")
         // The `ws()` filter will prepare Websocket handshake...
         .and(warp::ws())
+        .and(warp::query::<ConnectionParams>())
         .and(with_db(pool))
-        .map(|ws: warp::ws::Ws, db: PgPool| {
+        .map(|ws: warp::ws::Ws, params: ConnectionParams, db: PgPool| {
             // This will call our function if the handshake succeeds.
             ws.on_upgrade(move |socket| async {
                 // probably a bad idea to ignore this
                 // but whatever
-                let _ = handler::user_connected(db, socket).await;
+                let _ = handler::user_connected(db, socket, params).await;
             })
-        })
+        });
+
+    metrics.or(connect)
 }
 
+async fn get_queue_metrics(_admin: User) -> Result<impl warp::Reply, warp::Rejection> {
+    let sessions = SESSIONS.read().await;
+    let depths = sessions.values().map(WsSession::queued);
+
+    let metrics = QueueMetrics {
+        sessions: sessions.len(),
+        capacity: OUTBOUND_QUEUE_CAPACITY,
+        queued: depths.clone().sum(),
+        max_queued: depths.max().unwrap_or(0),
+        slow_consumer_evictions: SLOW_CONSUMER_EVICTIONS.load(Ordering::Relaxed),
+    };
+
+    Ok(warp::reply::json(&metrics))
+}
+
+/// Publishes the message to the recipients, whichever instance they're connected to
 pub(crate) fn send_message<'a, T>(
-    message: Arc<MessagePayload<T>>,
-    send_to_predicate: impl Fn(Uuid) -> bool + Send + Sync + 'a,
+    message: MessagePayload<T>,
+    recipients: Recipients,
 ) -> BoxFuture<'a, ()>
 where
-    T: Serialize + for<'de> Deserialize<'de> + Send + Sync + std::fmt::Debug + 'a,
+    T: Serialize + for<'de> Deserialize<'de> + Send + Sync + 'a,
 {
     Box::pin(async move {
-        println!("send 1");
-        let users = USERS.read().await;
-
-        for (uuid, session) in users.iter() {
-            println!("send 2 uuid: {}", uuid);
-            if send_to_predicate(*uuid) {
-                println!("send 2.5 uuid: {}, message: {:?}", uuid, &*message);
-                if session.send(&message).is_err() {
-                    // add notification
-                };
-            } else {
-                // add notification
+        let payload = match serde_json::to_value(&message.data) {
+            Ok(data) => MessagePayload {
+                op: message.op,
+                data,
+            },
+            Err(e) => {
+                error!("failed to serialize websocket message: {}", e);
+                return;
             }
+        };
+
+        let pubsub = Arc::clone(&*PUBSUB.read().await);
+        let event = Event::Dispatch {
+            recipients,
+            payload,
+        };
+
+        if let Err(e) = pubsub.publish(event).await {
+            error!("failed to publish websocket message: {}", e);
         }
     })
 }
+
+/// A message that's published only once the transaction it was produced in has committed,
+/// so clients never hear of changes that end up rolled back
+#[derive(Debug)]
+pub struct Deferred {
+    message: MessagePayload<Value>,
+    recipients: Recipients,
+}
+
+impl Deferred {
+    pub fn new<T: Serialize>(op: OpCode, data: &T, recipients: Recipients) -> anyhow::Result<Self> {
+        Ok(Deferred {
+            message: MessagePayload {
+                op,
+                data: serde_json::to_value(data)?,
+            },
+            recipients,
+        })
+    }
+}
+
+/// Publishes the messages in the order they were produced in
+pub async fn send_deferred(messages: impl IntoIterator<Item = Deferred>) {
+    for Deferred {
+        message,
+        recipients,
+    } in messages
+    {
+        send_message(message, recipients).await;
+    }
+}
+
+/// Closes the sessions of the users, whichever instance they're connected to
+pub(crate) async fn disconnect_users(users: Vec<Uuid>, error: ErrorPayload) {
+    let pubsub = Arc::clone(&*PUBSUB.read().await);
+
+    if let Err(e) = pubsub.publish(Event::Disconnect { users, error }).await {
+        error!("failed to publish websocket disconnect: {}", e);
+    }
+}
+
+/// Delivers a published event to the sessions connected to this instance
+pub(crate) async fn deliver(event: Event) {
+    match event {
+        Event::Dispatch {
+            recipients,
+            payload,
+        } => {
+            let sessions = SESSIONS.read().await;
+
+            for session in sessions.values() {
+                let user = match session.user {
+                    Some(user) => user,
+                    None => continue,
+                };
+
+                if recipients.contains(user) {
+                    // an error means the session is closing, it'll remove itself
+                    let _ = session.send(&payload);
+                }
+            }
+        }
+        Event::Disconnect { users, error } => {
+            let sessions = SESSIONS.read().await;
+
+            let closed = sessions
+                .values()
+                .filter(|session| matches!(session.user, Some(user) if users.contains(&user)));
+            for session in closed {
+                session.close(error.clone());
+            }
+        }
+    }
+}
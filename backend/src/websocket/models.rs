@@ -1,29 +1,86 @@
+use crate::websocket::heartbeat::Heartbeat;
+use common::websocket::{Encoding, ErrorCode, ErrorPayload, Frame};
 use common::User;
-use serde::Serialize;
+use futures::stream::SplitSink;
+use futures::SinkExt;
+use serde::{Deserialize, Serialize};
 use sqlx::types::Uuid;
 use sqlx::PgPool;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
-use tokio::sync::mpsc::UnboundedSender;
-use tokio::time::Instant;
-use warp::ws::Message;
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::{mpsc, watch};
+use tokio::time::Duration;
+use tracing::{debug, warn};
+use warp::ws::{Message, WebSocket};
+
+/// How many messages can wait to be written to a socket before its client
+/// is considered too slow and disconnected
+pub const OUTBOUND_QUEUE_CAPACITY: usize = 256;
+
+/// How long a client the server disconnects gets to receive the close frame
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Number of sessions disconnected for being too slow since the server started
+pub(crate) static SLOW_CONSUMER_EVICTIONS: AtomicUsize = AtomicUsize::new(0);
+
+/// Query parameters accepted when opening the websocket
+#[derive(Debug, Deserialize)]
+pub struct ConnectionParams {
+    /// Protocol version the client speaks, the current one if not provided
+    pub v: Option<u32>,
+    /// Encoding of the frames, JSON if not provided
+    #[serde(default)]
+    pub encoding: Encoding,
+}
+
+/// Depth of the outbound queues of the sessions connected to this instance
+#[derive(Debug, Serialize)]
+pub struct QueueMetrics {
+    pub sessions: usize,
+    pub capacity: usize,
+    pub queued: usize,
+    pub max_queued: usize,
+    pub slow_consumer_evictions: usize,
+}
 
 #[derive(Clone)]
 pub struct WsSession {
     pub id: Uuid,
-    pub hb: Instant,
+    pub heartbeat: Heartbeat,
     pub pool: PgPool,
-    pub tx: Arc<UnboundedSender<Result<Message, warp::Error>>>,
     pub user: Option<Uuid>,
+    pub encoding: Encoding,
+    tx: mpsc::Sender<Message>,
+    queued: Arc<AtomicUsize>,
+    evict_tx: Arc<watch::Sender<Option<ErrorPayload>>>,
+    evict_rx: watch::Receiver<Option<ErrorPayload>>,
 }
 
 impl WsSession {
-    pub fn new(pool: PgPool, tx: UnboundedSender<Result<Message, warp::Error>>) -> Self {
+    /// Creates the session and spawns the task writing its messages to the socket
+    pub fn new(
+        pool: PgPool,
+        ws_tx: SplitSink<WebSocket, Message>,
+        encoding: Encoding,
+        heartbeat: Heartbeat,
+    ) -> Self {
+        let (tx, rx) = mpsc::channel(OUTBOUND_QUEUE_CAPACITY);
+        let (evict_tx, evict_rx) = watch::channel(None);
+        let queued = Arc::new(AtomicUsize::new(0));
+
+        tokio::task::spawn(write(ws_tx, rx, evict_rx.clone(), queued.clone()));
+
         Self {
             id: Uuid::new_v4(),
-            hb: Instant::now(),
+            heartbeat,
             pool,
-            tx: Arc::new(tx),
             user: None,
+            encoding,
+            tx,
+            queued,
+            evict_tx: Arc::new(evict_tx),
+            evict_rx,
         }
     }
 
@@ -35,8 +92,108 @@ impl WsSession {
     where
         T: Serialize + Send + Sync,
     {
-        let text = serde_json::to_string(payload)?;
-        self.tx.send(Ok(Message::text(text)))?;
-        Ok(())
+        let message = match self.encoding.encode(payload)? {
+            Frame::Text(text) => Message::text(text),
+            Frame::Binary(bytes) => Message::binary(bytes),
+        };
+        self.queue(message)
+    }
+
+    /// Queues the message to be written to the socket.
+    ///
+    /// If the queue is full the client isn't keeping up, so it's evicted rather
+    /// than letting the queue grow.
+    pub fn queue(&self, message: Message) -> anyhow::Result<()> {
+        // counted before sending so the writer can never take it below zero
+        self.queued.fetch_add(1, Ordering::Relaxed);
+
+        match self.tx.try_send(message) {
+            Ok(()) => Ok(()),
+            Err(e) => {
+                self.queued.fetch_sub(1, Ordering::Relaxed);
+
+                if let TrySendError::Full(_) = e {
+                    self.evict();
+                }
+                Err(anyhow::anyhow!("websocket session {} is closed", self.id))
+            }
+        }
+    }
+
+    /// Number of messages waiting to be written to the socket
+    pub fn queued(&self) -> usize {
+        self.queued.load(Ordering::Relaxed)
+    }
+
+    /// Resolves once the server has closed the session, see [`WsSession::close`]
+    pub async fn evicted(&self) {
+        let mut evict_rx = self.evict_rx.clone();
+        // the sender lives as long as the session so this can't fail
+        let _ = evict_rx.changed().await;
+    }
+
+    /// Disconnects the client, the close frame carries the code and message of the error
+    pub fn close(&self, error: ErrorPayload) {
+        if self.evict_rx.borrow().is_some() {
+            return;
+        }
+
+        let _ = self.evict_tx.send(Some(error));
+    }
+
+    fn evict(&self) {
+        if self.evict_rx.borrow().is_some() {
+            return;
+        }
+
+        warn!(
+            "websocket session {} has {} queued messages, evicting it",
+            self.id,
+            self.queued()
+        );
+        SLOW_CONSUMER_EVICTIONS.fetch_add(1, Ordering::Relaxed);
+
+        self.close(ErrorPayload::new(
+            ErrorCode::SlowConsumer,
+            "too many messages queued, reconnect to resync",
+        ));
+    }
+}
+
+/// Writes the queued messages to the socket until the session is dropped or closed
+async fn write(
+    mut ws_tx: SplitSink<WebSocket, Message>,
+    mut rx: mpsc::Receiver<Message>,
+    mut evict_rx: watch::Receiver<Option<ErrorPayload>>,
+    queued: Arc<AtomicUsize>,
+) {
+    loop {
+        // once the session is dropped `changed` fails, the queue still has to be flushed then
+        let message = tokio::select! {
+            message = rx.recv() => message,
+            Ok(()) = evict_rx.changed() => break,
+        };
+        let message = match message {
+            Some(message) => message,
+            None => return,
+        };
+        queued.fetch_sub(1, Ordering::Relaxed);
+
+        // a stalled client can block this forever, so eviction has to interrupt it
+        tokio::select! {
+            result = ws_tx.send(message) => {
+                if let Err(e) = result {
+                    debug!("websocket send error: {}", e);
+                    return;
+                }
+            }
+            Ok(()) = evict_rx.changed() => break,
+        }
+    }
+
+    let error = evict_rx.borrow().clone();
+    if let Some(error) = error {
+        let close = Message::close_with(error.code, error.message);
+        let _ = tokio::time::timeout(CLOSE_TIMEOUT, ws_tx.send(close)).await;
     }
 }
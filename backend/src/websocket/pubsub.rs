@@ -0,0 +1,173 @@
+use super::deliver;
+use common::websocket::{ErrorPayload, MessagePayload};
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::postgres::PgListener;
+use sqlx::types::Uuid;
+use sqlx::PgPool;
+use tokio::time::Duration;
+use tracing::{debug, error};
+
+/// Postgres channel the events are sent on
+const CHANNEL: &str = "waichu_events";
+
+/// `NOTIFY` payloads must be shorter than 8000 bytes, larger events are stored in
+/// the `websocket_events` table and only their id is sent.
+const MAX_NOTIFY_PAYLOAD: usize = 7900;
+
+/// How long to wait before listening again after losing the connection
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+/// Users an event should be delivered to
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum Recipients {
+    All,
+    Users(Vec<Uuid>),
+}
+
+impl Recipients {
+    pub fn contains(&self, user: Uuid) -> bool {
+        match self {
+            Recipients::All => true,
+            Recipients::Users(users) => users.contains(&user),
+        }
+    }
+}
+
+/// An event published to every backend instance, each of which delivers it
+/// to its own websocket sessions
+#[derive(Serialize, Deserialize, Debug)]
+pub enum Event {
+    Dispatch {
+        recipients: Recipients,
+        payload: MessagePayload<Value>,
+    },
+    /// Closes every session of the users, e.g. once they're suspended
+    Disconnect {
+        users: Vec<Uuid>,
+        error: ErrorPayload,
+    },
+}
+
+pub trait PubSub: Send + Sync {
+    fn publish(&self, event: Event) -> BoxFuture<anyhow::Result<()>>;
+}
+
+/// Delivers the events directly to the sessions of this process.
+///
+/// Only suitable when a single instance of the backend is running.
+pub struct InProcess;
+
+impl PubSub for InProcess {
+    fn publish(&self, event: Event) -> BoxFuture<anyhow::Result<()>> {
+        Box::pin(async move {
+            deliver(event).await;
+            Ok(())
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+enum Notification {
+    Event(Event),
+    Stored(Uuid),
+}
+
+/// Fans the events out to every instance using Postgres' `LISTEN/NOTIFY`
+pub struct Postgres {
+    pool: PgPool,
+}
+
+impl Postgres {
+    /// Starts listening for events and returns the publisher
+    pub async fn new(pool: PgPool) -> anyhow::Result<Self> {
+        let mut listener = PgListener::connect_with(&pool).await?;
+        listener.listen(CHANNEL).await?;
+
+        tokio::task::spawn(listen(pool.clone(), listener));
+
+        Ok(Self { pool })
+    }
+}
+
+impl PubSub for Postgres {
+    fn publish(&self, event: Event) -> BoxFuture<anyhow::Result<()>> {
+        Box::pin(async move {
+            let notification = Notification::Event(event);
+            let mut payload = serde_json::to_string(&notification)?;
+
+            if payload.len() > MAX_NOTIFY_PAYLOAD {
+                debug!("event too large to notify, storing it");
+                let uuid = store(&self.pool, serde_json::to_value(&notification)?).await?;
+                payload = serde_json::to_string(&Notification::Stored(uuid))?;
+            }
+
+            sqlx::query("select pg_notify($1, $2);")
+                .bind(CHANNEL)
+                .bind(payload)
+                .execute(&self.pool)
+                .await?;
+
+            Ok(())
+        })
+    }
+}
+
+async fn store(pool: &PgPool, notification: Value) -> anyhow::Result<Uuid> {
+    let mut tx = pool.begin().await?;
+
+    // every instance has had plenty of time to read these
+    sqlx::query!("delete from websocket_events where created_at < now() - interval '1 minute';")
+        .execute(&mut tx)
+        .await?;
+
+    let uuid = sqlx::query!(
+        "insert into websocket_events (uuid, event) values ($1, $2) returning uuid;",
+        Uuid::new_v4(),
+        notification,
+    )
+    .fetch_one(&mut tx)
+    .await?
+    .uuid;
+
+    tx.commit().await?;
+
+    Ok(uuid)
+}
+
+async fn load(pool: &PgPool, uuid: Uuid) -> anyhow::Result<Event> {
+    let stored = sqlx::query!("select event from websocket_events where uuid = $1;", uuid)
+        .fetch_one(pool)
+        .await?;
+
+    match serde_json::from_value(stored.event)? {
+        Notification::Event(event) => Ok(event),
+        Notification::Stored(_) => Err(anyhow::anyhow!("stored event points to another one")),
+    }
+}
+
+async fn listen(pool: PgPool, mut listener: PgListener) {
+    loop {
+        let notification = match listener.recv().await {
+            Ok(notification) => notification,
+            Err(e) => {
+                // the listener reconnects on the next call
+                error!("error receiving event notification: {}", e);
+                tokio::time::sleep(RECONNECT_DELAY).await;
+                continue;
+            }
+        };
+
+        let event = match serde_json::from_str::<Notification>(notification.payload()) {
+            Ok(Notification::Event(event)) => Ok(event),
+            Ok(Notification::Stored(uuid)) => load(&pool, uuid).await,
+            Err(e) => Err(e.into()),
+        };
+
+        match event {
+            Ok(event) => deliver(event).await,
+            Err(e) => error!("failed to read event notification: {}", e),
+        }
+    }
+}
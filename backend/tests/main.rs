@@ -1,3 +1,4 @@
 mod routes;
 mod utils;
+mod websocket;
 pub use utils::*;
@@ -0,0 +1,376 @@
+use crate::{create_authenticated_user, create_room, db, join_user, send_message};
+use backend::services;
+use common::payloads::{ChangePassword, Credentials, JwtToken, SuspendUser};
+use common::{InstanceStats, RoomOverview, User, UserAccount};
+use sqlx::PgConnection;
+use warp::http::StatusCode;
+use warp::test::request;
+
+async fn create_instance_admin(conn: &mut PgConnection, username: &str) -> (User, String) {
+    let (admin, token) = create_authenticated_user(&mut *conn, username, "password").await;
+    let account = services::admin::set_instance_admin(conn, &admin, true)
+        .await
+        .expect("failed to make instance admin");
+
+    (account.user, token)
+}
+
+fn credentials(username: &str, password: &str) -> Credentials {
+    Credentials {
+        username: username.to_string(),
+        password: password.to_string(),
+    }
+}
+
+#[tokio::test]
+async fn test_only_instance_admins() {
+    db(|pool| {
+        Box::pin(async move {
+            let mut conn = pool.acquire().await.expect("can't acquire pool");
+            let (_, admin_token) = create_instance_admin(&mut conn, "admin").await;
+            let (_, user_token) = create_authenticated_user(&mut conn, "user", "password").await;
+
+            let api = backend::api(pool.clone());
+
+            let resp = request()
+                .method("GET")
+                .path("/api/admin/stats")
+                .header("Authorization", &user_token)
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+            let resp = request()
+                .method("GET")
+                .path("/api/admin/stats")
+                .header("Authorization", &admin_token)
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::OK);
+            let stats = serde_json::from_slice::<InstanceStats>(resp.body())
+                .expect("failed to parse response");
+            assert_eq!(stats.users, 2);
+            assert_eq!(stats.suspended_users, 0);
+
+            // the flag is part of the user
+            let resp = request()
+                .method("GET")
+                .path("/api/users/me")
+                .header("Authorization", &admin_token)
+                .reply(&api)
+                .await;
+            let me = serde_json::from_slice::<User>(resp.body()).expect("failed to parse response");
+            assert!(me.is_instance_admin);
+        })
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_search_users() {
+    db(|pool| {
+        Box::pin(async move {
+            let mut conn = pool.acquire().await.expect("can't acquire pool");
+            let (_, admin_token) = create_instance_admin(&mut conn, "admin").await;
+            let (alice, _) = create_authenticated_user(&mut conn, "alice", "password").await;
+            create_authenticated_user(&mut conn, "bob", "password").await;
+
+            let api = backend::api(pool.clone());
+
+            let resp = request()
+                .method("GET")
+                .path("/api/admin/users")
+                .header("Authorization", &admin_token)
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::OK);
+            let accounts = serde_json::from_slice::<Vec<UserAccount>>(resp.body())
+                .expect("failed to parse response");
+            assert_eq!(accounts.len(), 3);
+
+            let resp = request()
+                .method("GET")
+                .path("/api/admin/users?search=ali")
+                .header("Authorization", &admin_token)
+                .reply(&api)
+                .await;
+            let accounts = serde_json::from_slice::<Vec<UserAccount>>(resp.body())
+                .expect("failed to parse response");
+            assert_eq!(accounts.len(), 1);
+            assert_eq!(accounts[0].user, alice);
+            assert!(!accounts[0].is_suspended());
+
+            let resp = request()
+                .method("GET")
+                .path("/api/admin/users?limit=0")
+                .header("Authorization", &admin_token)
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        })
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_suspend_user() {
+    db(|pool| {
+        Box::pin(async move {
+            let mut conn = pool.acquire().await.expect("can't acquire pool");
+            let (admin, admin_token) = create_instance_admin(&mut conn, "admin").await;
+            let (other_admin, _) = create_instance_admin(&mut conn, "other_admin").await;
+            let (user, user_token) = create_authenticated_user(&mut conn, "user", "password").await;
+
+            let api = backend::api(pool.clone());
+            let suspend = |user: &User| {
+                request()
+                    .method("PUT")
+                    .path(&format!("/api/admin/users/{}/suspension", user.uuid))
+                    .header("Authorization", &admin_token)
+                    .json(&SuspendUser {
+                        reason: Some("spam".to_string()),
+                    })
+            };
+
+            assert_eq!(
+                suspend(&admin).reply(&api).await.status(),
+                StatusCode::BAD_REQUEST
+            );
+            assert_eq!(
+                suspend(&other_admin).reply(&api).await.status(),
+                StatusCode::FORBIDDEN
+            );
+
+            let resp = suspend(&user).reply(&api).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+            let account = serde_json::from_slice::<UserAccount>(resp.body())
+                .expect("failed to parse response");
+            assert!(account.is_suspended());
+            assert_eq!(account.suspension_reason.as_deref(), Some("spam"));
+
+            // their token stops working right away
+            let resp = request()
+                .method("GET")
+                .path("/api/users/me")
+                .header("Authorization", &user_token)
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+            let resp = request()
+                .method("POST")
+                .path("/api/auth/signin")
+                .json(&credentials("user", "password"))
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+            let resp = request()
+                .method("DELETE")
+                .path(&format!("/api/admin/users/{}/suspension", user.uuid))
+                .header("Authorization", &admin_token)
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::OK);
+
+            let resp = request()
+                .method("POST")
+                .path("/api/auth/signin")
+                .json(&credentials("user", "password"))
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::OK);
+        })
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_password_reset() {
+    db(|pool| {
+        Box::pin(async move {
+            let mut conn = pool.acquire().await.expect("can't acquire pool");
+            let (_, admin_token) = create_instance_admin(&mut conn, "admin").await;
+            let (user, user_token) = create_authenticated_user(&mut conn, "user", "password").await;
+
+            let api = backend::api(pool.clone());
+
+            let resp = request()
+                .method("POST")
+                .path(&format!("/api/admin/users/{}/password-reset", user.uuid))
+                .header("Authorization", &admin_token)
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::OK);
+            let account = serde_json::from_slice::<UserAccount>(resp.body())
+                .expect("failed to parse response");
+            assert!(account.password_reset_required);
+
+            let resp = request()
+                .method("GET")
+                .path("/api/users/me")
+                .header("Authorization", &user_token)
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+            let resp = request()
+                .method("POST")
+                .path("/api/auth/signin")
+                .json(&credentials("user", "password"))
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+            let change_password = |password: &str, new_password: &str| {
+                request()
+                    .method("POST")
+                    .path("/api/auth/password")
+                    .json(&ChangePassword {
+                        username: "user".to_string(),
+                        password: password.to_string(),
+                        new_password: new_password.to_string(),
+                    })
+            };
+
+            assert_eq!(
+                change_password("wrong", "new_password")
+                    .reply(&api)
+                    .await
+                    .status(),
+                StatusCode::UNAUTHORIZED
+            );
+            assert_eq!(
+                change_password("password", "password")
+                    .reply(&api)
+                    .await
+                    .status(),
+                StatusCode::BAD_REQUEST
+            );
+
+            let resp = change_password("password", "new_password")
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::OK);
+            let jwt =
+                serde_json::from_slice::<JwtToken>(resp.body()).expect("failed to parse response");
+
+            let resp = request()
+                .method("GET")
+                .path("/api/users/me")
+                .header("Authorization", &jwt.token)
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::OK);
+
+            let resp = request()
+                .method("POST")
+                .path("/api/auth/signin")
+                .json(&credentials("user", "new_password"))
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::OK);
+        })
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_delete_user() {
+    db(|pool| {
+        Box::pin(async move {
+            let mut conn = pool.acquire().await.expect("can't acquire pool");
+            let (admin, admin_token) = create_instance_admin(&mut conn, "admin").await;
+            let (user, _) = create_authenticated_user(&mut conn, "user", "password").await;
+            let room = create_room(&mut conn, "room_name").await;
+            join_user(&mut conn, &admin, &room, true).await;
+            join_user(&mut conn, &user, &room, false).await;
+            send_message(&mut conn, "hello", &user, &room).await;
+
+            let api = backend::api(pool.clone());
+
+            let resp = request()
+                .method("DELETE")
+                .path(&format!("/api/admin/users/{}", admin.uuid))
+                .header("Authorization", &admin_token)
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+            let resp = request()
+                .method("DELETE")
+                .path(&format!("/api/admin/users/{}", user.uuid))
+                .header("Authorization", &admin_token)
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+
+            let resp = request()
+                .method("GET")
+                .path(&format!("/api/admin/users/{}", user.uuid))
+                .header("Authorization", &admin_token)
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+            // their messages and membership went with them
+            let members = services::room::get_member_ids(&mut conn, &room)
+                .await
+                .unwrap();
+            assert_eq!(members, vec![admin.uuid]);
+            let messages = services::message::get_all(&mut conn, &room, &admin)
+                .await
+                .unwrap();
+            assert!(messages.iter().all(|it| it.author.uuid != user.uuid));
+        })
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_manage_rooms() {
+    db(|pool| {
+        Box::pin(async move {
+            let mut conn = pool.acquire().await.expect("can't acquire pool");
+            let (_, admin_token) = create_instance_admin(&mut conn, "admin").await;
+            let (user, _) = create_authenticated_user(&mut conn, "user", "password").await;
+            let room = create_room(&mut conn, "general").await;
+            create_room(&mut conn, "random").await;
+            join_user(&mut conn, &user, &room, false).await;
+
+            let api = backend::api(pool.clone());
+
+            let resp = request()
+                .method("GET")
+                .path("/api/admin/rooms?search=gen")
+                .header("Authorization", &admin_token)
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::OK);
+            let rooms = serde_json::from_slice::<Vec<RoomOverview>>(resp.body())
+                .expect("failed to parse response");
+            assert_eq!(rooms.len(), 1);
+            assert_eq!(rooms[0].room.uuid, room.uuid);
+            assert_eq!(rooms[0].member_count, 1);
+
+            let resp = request()
+                .method("DELETE")
+                .path(&format!("/api/admin/rooms/{}", room.uuid))
+                .header("Authorization", &admin_token)
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+
+            let resp = request()
+                .method("GET")
+                .path("/api/admin/stats")
+                .header("Authorization", &admin_token)
+                .reply(&api)
+                .await;
+            let stats = serde_json::from_slice::<InstanceStats>(resp.body())
+                .expect("failed to parse response");
+            assert_eq!(stats.rooms, 1);
+        })
+    })
+    .await
+}
@@ -0,0 +1,97 @@
+use crate::{create_authenticated_user, create_room, db, join_user};
+use common::payloads::{RemoveMember, UpdateRoom};
+use common::{AuditAction, AuditLogEntry};
+use serde_json::json;
+use warp::http::StatusCode;
+use warp::test::request;
+
+#[tokio::test]
+async fn test_audit_log() {
+    db(|pool| {
+        Box::pin(async move {
+            let mut conn = pool.acquire().await.expect("can't acquire pool");
+
+            let (admin, admin_token) =
+                create_authenticated_user(&mut conn, "admin", "password").await;
+            let (member, member_token) =
+                create_authenticated_user(&mut conn, "member", "password").await;
+            let room = create_room(&mut conn, "room_name").await;
+            join_user(&mut conn, &admin, &room, true).await;
+            join_user(&mut conn, &member, &room, false).await;
+
+            let api = backend::api(pool.clone());
+
+            let resp = request()
+                .method("PATCH")
+                .path(&format!("/api/rooms/{}", room.uuid))
+                .header("Authorization", &admin_token)
+                .json(&UpdateRoom {
+                    name: Some("new_name".to_string()),
+                    ..Default::default()
+                })
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::OK);
+
+            let resp = request()
+                .method("POST")
+                .path(&format!("/api/rooms/{}/kick", room.uuid))
+                .header("Authorization", &admin_token)
+                .json(&RemoveMember {
+                    member: member.uuid,
+                    reason: Some("spam".to_string()),
+                })
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+
+            let audit_log = |token: &str, query: &str| {
+                request()
+                    .method("GET")
+                    .path(&format!("/api/rooms/{}/audit-log{}", room.uuid, query))
+                    .header("Authorization", token)
+            };
+
+            let resp = audit_log(&member_token, "").reply(&api).await;
+            assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+            let resp = audit_log(&admin_token, "?limit=0").reply(&api).await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+            let resp = audit_log(&admin_token, "").reply(&api).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+            let entries = serde_json::from_slice::<Vec<AuditLogEntry>>(resp.body())
+                .expect("failed to parse response");
+            // the newest come first
+            assert_eq!(entries[0].action, AuditAction::MemberKick);
+            assert_eq!(entries[0].actor, Some(admin.uuid));
+            assert_eq!(entries[0].target, Some(member.uuid));
+            assert_eq!(entries[0].changes, json!({ "reason": "spam" }));
+            assert_eq!(entries[1].action, AuditAction::RoomUpdate);
+            assert_eq!(
+                entries[1].changes,
+                json!({ "name": { "old": "room_name", "new": "new_name" } })
+            );
+
+            let resp = audit_log(&admin_token, &format!("?target={}", member.uuid))
+                .reply(&api)
+                .await;
+            let entries = serde_json::from_slice::<Vec<AuditLogEntry>>(resp.body())
+                .expect("failed to parse response");
+            let actions = entries.iter().map(|it| it.action).collect::<Vec<_>>();
+            assert_eq!(
+                actions,
+                vec![AuditAction::MemberKick, AuditAction::MemberJoin]
+            );
+
+            let resp = audit_log(&admin_token, "?action=member_join&limit=1")
+                .reply(&api)
+                .await;
+            let entries = serde_json::from_slice::<Vec<AuditLogEntry>>(resp.body())
+                .expect("failed to parse response");
+            assert_eq!(entries.len(), 1);
+            assert_eq!(entries[0].target, Some(member.uuid));
+        })
+    })
+    .await
+}
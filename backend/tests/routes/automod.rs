@@ -0,0 +1,234 @@
+use crate::{create_authenticated_user, create_room, db, join_user};
+use backend::services;
+use common::payloads::{CreateAutomodRule, CreateMessage};
+use common::{AutomodAction, AutomodRule, AutomodRuleKind, Message, Report};
+use warp::http::StatusCode;
+use warp::test::request;
+
+#[tokio::test]
+async fn test_manage_automod_rules() {
+    db(|pool| {
+        Box::pin(async move {
+            let mut conn = pool.acquire().await.expect("can't acquire pool");
+
+            let (admin, admin_token) =
+                create_authenticated_user(&mut conn, "admin", "password").await;
+            let (member, member_token) =
+                create_authenticated_user(&mut conn, "member", "password").await;
+            let room = create_room(&mut conn, "room_name").await;
+            join_user(&mut conn, &admin, &room, true).await;
+            join_user(&mut conn, &member, &room, false).await;
+
+            let api = backend::api(pool.clone());
+            let path = format!("/api/rooms/{}/automod-rules", room.uuid);
+            let data = CreateAutomodRule {
+                kind: AutomodRuleKind::BannedWords,
+                patterns: vec!["darn".to_string()],
+                max_mentions: None,
+                action: AutomodAction::Mask,
+            };
+
+            let resp = request()
+                .method("POST")
+                .path(&path)
+                .header("Authorization", &member_token)
+                .json(&data)
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+            let resp = request()
+                .method("POST")
+                .path(&path)
+                .header("Authorization", &admin_token)
+                .json(&CreateAutomodRule {
+                    kind: AutomodRuleKind::Regex,
+                    patterns: vec!["(unclosed".to_string()],
+                    max_mentions: None,
+                    action: AutomodAction::Mask,
+                })
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+            // it would match everything
+            let resp = request()
+                .method("POST")
+                .path(&path)
+                .header("Authorization", &admin_token)
+                .json(&CreateAutomodRule {
+                    kind: AutomodRuleKind::Regex,
+                    patterns: vec!["x*".to_string()],
+                    ..data
+                })
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+            let resp = request()
+                .method("POST")
+                .path(&path)
+                .header("Authorization", &admin_token)
+                .json(&CreateAutomodRule {
+                    kind: AutomodRuleKind::MaxMentions,
+                    patterns: vec![],
+                    max_mentions: None,
+                    action: AutomodAction::Flag,
+                })
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+            let resp = request()
+                .method("POST")
+                .path(&path)
+                .header("Authorization", &admin_token)
+                .json(&CreateAutomodRule {
+                    kind: AutomodRuleKind::BannedWords,
+                    patterns: vec!["darn".to_string()],
+                    max_mentions: None,
+                    action: AutomodAction::Mask,
+                })
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::CREATED);
+            let rule = serde_json::from_slice::<AutomodRule>(resp.body())
+                .expect("failed to parse response");
+            assert_eq!(rule.room, Some(room.uuid));
+
+            let resp = request()
+                .method("GET")
+                .path(&path)
+                .header("Authorization", &admin_token)
+                .reply(&api)
+                .await;
+            let rules = serde_json::from_slice::<Vec<AutomodRule>>(resp.body())
+                .expect("failed to parse response");
+            assert_eq!(rules, vec![rule.clone()]);
+
+            let resp = request()
+                .method("DELETE")
+                .path(&format!("{}/{}", path, rule.uuid))
+                .header("Authorization", &admin_token)
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+
+            let rules = services::automod::get_all(&mut conn, Some(&room))
+                .await
+                .expect("failed to fetch rules");
+            assert!(rules.is_empty());
+        })
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn test_automod_actions() {
+    db(|pool| {
+        Box::pin(async move {
+            let mut conn = pool.acquire().await.expect("can't acquire pool");
+
+            let (admin, admin_token) =
+                create_authenticated_user(&mut conn, "admin", "password").await;
+            let (member, member_token) =
+                create_authenticated_user(&mut conn, "member", "password").await;
+            let room = create_room(&mut conn, "room_name").await;
+            join_user(&mut conn, &admin, &room, true).await;
+            join_user(&mut conn, &member, &room, false).await;
+
+            let rules = vec![
+                CreateAutomodRule {
+                    kind: AutomodRuleKind::BannedWords,
+                    patterns: vec!["darn".to_string()],
+                    max_mentions: None,
+                    action: AutomodAction::Mask,
+                },
+                CreateAutomodRule {
+                    kind: AutomodRuleKind::LinkDenyList,
+                    patterns: vec!["spam.example".to_string()],
+                    max_mentions: None,
+                    action: AutomodAction::Reject,
+                },
+                CreateAutomodRule {
+                    kind: AutomodRuleKind::MaxMentions,
+                    patterns: vec![],
+                    max_mentions: Some(2),
+                    action: AutomodAction::Flag,
+                },
+            ];
+            for rule in &rules {
+                services::automod::create(&mut conn, Some(&room), rule, Some(&admin))
+                    .await
+                    .expect("failed to create rule");
+            }
+            // applies to every room, `^` only ever matches empty text which doesn't count
+            services::automod::create(
+                &mut conn,
+                None,
+                &CreateAutomodRule {
+                    kind: AutomodRuleKind::Regex,
+                    patterns: vec![r"(?i)free\s+money".to_string(), "^".to_string()],
+                    max_mentions: None,
+                    action: AutomodAction::Reject,
+                },
+                None,
+            )
+            .await
+            .expect("failed to create rule");
+
+            let api = backend::api(pool.clone());
+            let path = format!("/api/rooms/{}/messages", room.uuid);
+            let send = |content: &str| {
+                request()
+                    .method("POST")
+                    .path(&path)
+                    .header("Authorization", &member_token)
+                    .json(&CreateMessage {
+                        content: content.to_string(),
+                    })
+            };
+
+            // mask
+            let resp = send("well Darn it, darnation").reply(&api).await;
+            assert_eq!(resp.status(), StatusCode::CREATED);
+            let message =
+                serde_json::from_slice::<Message>(resp.body()).expect("failed to parse response");
+            assert_eq!(message.content, "well **** it, darnation");
+
+            // reject
+            let resp = send("look at https://cdn.spam.example/offer")
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+            let resp = send("look at https://example.com/spam.example")
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::CREATED);
+            let resp = send("get FREE  money now").reply(&api).await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+            // flag
+            let resp = send("@admin @member hi").reply(&api).await;
+            assert_eq!(resp.status(), StatusCode::CREATED);
+            let resp = send("@admin @member @admin hi").reply(&api).await;
+            assert_eq!(resp.status(), StatusCode::CREATED);
+            let flagged =
+                serde_json::from_slice::<Message>(resp.body()).expect("failed to parse response");
+            assert_eq!(flagged.content, "@admin @member @admin hi");
+
+            let resp = request()
+                .method("GET")
+                .path(&format!("/api/rooms/{}/reports", room.uuid))
+                .header("Authorization", &admin_token)
+                .reply(&api)
+                .await;
+            let reports = serde_json::from_slice::<Vec<Report>>(resp.body())
+                .expect("failed to parse response");
+            assert_eq!(reports.len(), 1);
+            assert_eq!(reports[0].message, Some(flagged.uuid));
+            assert_eq!(reports[0].reporter, None);
+        })
+    })
+    .await;
+}
@@ -0,0 +1,138 @@
+use crate::{create_authenticated_user, create_user, db};
+use common::payloads::{JoinMembers, OpenDm};
+use common::{Room, RoomKind, RoomMember};
+use sqlx::types::Uuid;
+use warp::http::StatusCode;
+use warp::test::request;
+
+#[tokio::test]
+async fn test_open_dm() {
+    db(|pool| {
+        Box::pin(async {
+            let mut conn = pool.acquire().await.expect("can't acquire pool");
+
+            let (bob, bob_token) = create_authenticated_user(&mut conn, "bob", "password").await;
+            let (alice, alice_token) =
+                create_authenticated_user(&mut conn, "alice", "password").await;
+            let carol = create_user(&mut conn, "carol", "password").await;
+
+            let api = backend::api(pool);
+
+            let open = |token: &str, members: Vec<Uuid>| {
+                request()
+                    .method("POST")
+                    .path("/api/dms")
+                    .header("Authorization", token)
+                    .json(&OpenDm { members })
+            };
+
+            let resp = open(&bob_token, vec![alice.uuid]).reply(&api).await;
+            assert_eq!(resp.status(), StatusCode::CREATED);
+            let room =
+                serde_json::from_slice::<Room>(resp.body()).expect("failed to parse response");
+            assert_eq!(room.kind, RoomKind::Dm);
+            assert_eq!(room.name, "alice, bob");
+
+            // the other user gets the same room back
+            let resp = open(&alice_token, vec![bob.uuid]).reply(&api).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+            let existing =
+                serde_json::from_slice::<Room>(resp.body()).expect("failed to parse response");
+            assert_eq!(existing, room);
+
+            let resp = open(&bob_token, vec![bob.uuid]).reply(&api).await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+            let resp = request()
+                .method("POST")
+                .path(&format!("/api/rooms/{}/join", room.uuid))
+                .header("Authorization", &bob_token)
+                .json(&JoinMembers {
+                    member: carol.uuid,
+                    role: None,
+                })
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+            let resp = request()
+                .method("POST")
+                .path(&format!("/api/rooms/{}/leave", room.uuid))
+                .header("Authorization", &bob_token)
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        })
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_open_group_dm() {
+    db(|pool| {
+        Box::pin(async {
+            let mut conn = pool.acquire().await.expect("can't acquire pool");
+
+            let (bob, bob_token) = create_authenticated_user(&mut conn, "bob", "password").await;
+            let alice = create_user(&mut conn, "alice", "password").await;
+            let carol = create_user(&mut conn, "carol", "password").await;
+            let dave = create_user(&mut conn, "dave", "password").await;
+
+            let api = backend::api(pool);
+
+            let resp = request()
+                .method("POST")
+                .path("/api/dms")
+                .header("Authorization", &bob_token)
+                .json(&OpenDm {
+                    members: vec![carol.uuid, alice.uuid],
+                })
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::CREATED);
+            let room =
+                serde_json::from_slice::<Room>(resp.body()).expect("failed to parse response");
+            assert_eq!(room.kind, RoomKind::GroupDm);
+            assert_eq!(room.name, "alice, bob, carol");
+
+            let resp = request()
+                .method("GET")
+                .path(&format!("/api/rooms/{}/members", room.uuid))
+                .header("Authorization", &bob_token)
+                .reply(&api)
+                .await;
+            let members = serde_json::from_slice::<Vec<RoomMember>>(resp.body())
+                .expect("failed to parse response");
+            assert_eq!(members.len(), 3);
+            let me = members
+                .iter()
+                .find(|it| it.user.uuid == bob.uuid)
+                .expect("creator isn't a member");
+            assert!(me.role.is_admin);
+
+            // the name follows the members
+            let resp = request()
+                .method("POST")
+                .path(&format!("/api/rooms/{}/join", room.uuid))
+                .header("Authorization", &bob_token)
+                .json(&JoinMembers {
+                    member: dave.uuid,
+                    role: None,
+                })
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::CREATED);
+
+            let resp = request()
+                .method("GET")
+                .path(&format!("/api/rooms/{}", room.uuid))
+                .header("Authorization", &bob_token)
+                .reply(&api)
+                .await;
+            let room =
+                serde_json::from_slice::<Room>(resp.body()).expect("failed to parse response");
+            assert_eq!(room.name, "alice, bob, carol, dave");
+        })
+    })
+    .await
+}
@@ -0,0 +1,343 @@
+use crate::{create_authenticated_user, create_room, db, join_user};
+use common::payloads::{CreateInvite, CreateRole, OpenDm, RemoveMember};
+use common::{Invite, Permissions, Role, Room, RoomKind, RoomMember, User, MAX_GROUP_DM_MEMBERS};
+use sqlx::types::Uuid;
+use sqlx::PgPool;
+use warp::http::StatusCode;
+use warp::test::request;
+use warp::{Filter, Reply};
+
+async fn create_invite<F>(api: &F, token: &str, room: &Room, data: &CreateInvite) -> Invite
+where
+    F: Filter + 'static,
+    F::Extract: Reply + Send,
+{
+    let resp = request()
+        .method("POST")
+        .path(&format!("/api/rooms/{}/invites", room.uuid))
+        .header("Authorization", token)
+        .json(data)
+        .reply(api)
+        .await;
+    assert_eq!(resp.status(), StatusCode::CREATED);
+
+    serde_json::from_slice::<Invite>(resp.body()).expect("failed to parse response")
+}
+
+async fn redeem<F>(api: &F, token: &str, invite: &Invite) -> StatusCode
+where
+    F: Filter + 'static,
+    F::Extract: Reply + Send,
+{
+    request()
+        .method("POST")
+        .path(&format!("/api/invites/{}", invite.code))
+        .header("Authorization", token)
+        .reply(api)
+        .await
+        .status()
+}
+
+async fn find_member(pool: &PgPool, room: &Room, token: &str, user: &User) -> Option<RoomMember> {
+    let resp = request()
+        .method("GET")
+        .path(&format!("/api/rooms/{}/members", room.uuid))
+        .header("Authorization", token)
+        .reply(&backend::api(pool.clone()))
+        .await;
+
+    let members =
+        serde_json::from_slice::<Vec<RoomMember>>(resp.body()).expect("failed to parse response");
+    members.into_iter().find(|it| it.user.uuid == user.uuid)
+}
+
+#[tokio::test]
+async fn test_redeem_invite() {
+    db(|pool| {
+        Box::pin(async move {
+            let mut conn = pool.acquire().await.expect("can't acquire pool");
+
+            let (admin, admin_token) =
+                create_authenticated_user(&mut conn, "admin", "password").await;
+            let (first, first_token) =
+                create_authenticated_user(&mut conn, "first", "password").await;
+            let (second, second_token) =
+                create_authenticated_user(&mut conn, "second", "password").await;
+            let room = create_room(&mut conn, "room_name").await;
+            join_user(&mut conn, &admin, &room, true).await;
+
+            let api = backend::api(pool.clone());
+
+            let invite = create_invite(
+                &api,
+                &admin_token,
+                &room,
+                &CreateInvite {
+                    max_uses: Some(1),
+                    ..Default::default()
+                },
+            )
+            .await;
+
+            assert_eq!(
+                redeem(&api, &first_token, &invite).await,
+                StatusCode::CREATED
+            );
+            let joined = find_member(&pool, &room, &admin_token, &first)
+                .await
+                .expect("user didn't join");
+            assert!(joined.role.is_default);
+
+            // members redeeming it again don't use it up
+            assert_eq!(redeem(&api, &first_token, &invite).await, StatusCode::OK);
+
+            assert_eq!(redeem(&api, &second_token, &invite).await, StatusCode::GONE);
+            assert!(find_member(&pool, &room, &admin_token, &second)
+                .await
+                .is_none());
+        })
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_expired_invite() {
+    db(|pool| {
+        Box::pin(async move {
+            let mut conn = pool.acquire().await.expect("can't acquire pool");
+
+            let (admin, admin_token) =
+                create_authenticated_user(&mut conn, "admin", "password").await;
+            let (_, token) = create_authenticated_user(&mut conn, "user", "password").await;
+            let room = create_room(&mut conn, "room_name").await;
+            join_user(&mut conn, &admin, &room, true).await;
+
+            let api = backend::api(pool.clone());
+
+            let invite = create_invite(&api, &admin_token, &room, &Default::default()).await;
+            sqlx::query("update room_invites set expires_at = now() - interval '1 minute';")
+                .execute(&mut conn)
+                .await
+                .expect("failed to expire invite");
+
+            assert_eq!(redeem(&api, &token, &invite).await, StatusCode::GONE);
+        })
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_invite_with_role() {
+    db(|pool| {
+        Box::pin(async move {
+            let mut conn = pool.acquire().await.expect("can't acquire pool");
+
+            let (admin, admin_token) =
+                create_authenticated_user(&mut conn, "admin", "password").await;
+            let (member_user, member_token) =
+                create_authenticated_user(&mut conn, "member", "password").await;
+            let (user, token) = create_authenticated_user(&mut conn, "user", "password").await;
+            let room = create_room(&mut conn, "room_name").await;
+            join_user(&mut conn, &admin, &room, true).await;
+            join_user(&mut conn, &member_user, &room, false).await;
+
+            let api = backend::api(pool.clone());
+
+            let resp = request()
+                .method("POST")
+                .path(&format!("/api/rooms/{}/roles", room.uuid))
+                .header("Authorization", &admin_token)
+                .json(&CreateRole {
+                    name: "Moderator".to_string(),
+                    permissions: Permissions::DEFAULT | Permissions::KICK,
+                })
+                .reply(&api)
+                .await;
+            let role =
+                serde_json::from_slice::<Role>(resp.body()).expect("failed to parse response");
+
+            let data = CreateInvite {
+                role: Some(role.uuid),
+                ..Default::default()
+            };
+
+            // ordinary members can invite, but not with a role
+            let resp = request()
+                .method("POST")
+                .path(&format!("/api/rooms/{}/invites", room.uuid))
+                .header("Authorization", &member_token)
+                .json(&data)
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+            let invite = create_invite(&api, &admin_token, &room, &data).await;
+            assert_eq!(redeem(&api, &token, &invite).await, StatusCode::CREATED);
+
+            let joined = find_member(&pool, &room, &admin_token, &user)
+                .await
+                .expect("user didn't join");
+            assert_eq!(joined.role, role);
+        })
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_list_and_revoke_invites() {
+    db(|pool| {
+        Box::pin(async move {
+            let mut conn = pool.acquire().await.expect("can't acquire pool");
+
+            let (admin, admin_token) =
+                create_authenticated_user(&mut conn, "admin", "password").await;
+            let (member_user, member_token) =
+                create_authenticated_user(&mut conn, "member", "password").await;
+            let (_, token) = create_authenticated_user(&mut conn, "user", "password").await;
+            let room = create_room(&mut conn, "room_name").await;
+            join_user(&mut conn, &admin, &room, true).await;
+            join_user(&mut conn, &member_user, &room, false).await;
+
+            let api = backend::api(pool.clone());
+
+            let invite = create_invite(&api, &member_token, &room, &Default::default()).await;
+
+            let list = |token: &str| {
+                request()
+                    .method("GET")
+                    .path(&format!("/api/rooms/{}/invites", room.uuid))
+                    .header("Authorization", token)
+            };
+
+            let resp = list(&member_token).reply(&api).await;
+            assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+            let resp = list(&admin_token).reply(&api).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+            let invites = serde_json::from_slice::<Vec<Invite>>(resp.body())
+                .expect("failed to parse response");
+            assert_eq!(invites, vec![invite.clone()]);
+
+            let resp = request()
+                .method("DELETE")
+                .path(&format!("/api/rooms/{}/invites/{}", room.uuid, invite.code))
+                .header("Authorization", &admin_token)
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+
+            assert_eq!(redeem(&api, &token, &invite).await, StatusCode::NOT_FOUND);
+        })
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_banned_user_cant_redeem_invite() {
+    db(|pool| {
+        Box::pin(async move {
+            let mut conn = pool.acquire().await.expect("can't acquire pool");
+
+            let (admin, admin_token) =
+                create_authenticated_user(&mut conn, "admin", "password").await;
+            let (user, token) = create_authenticated_user(&mut conn, "user", "password").await;
+            let room = create_room(&mut conn, "room_name").await;
+            join_user(&mut conn, &admin, &room, true).await;
+
+            let api = backend::api(pool.clone());
+
+            let resp = request()
+                .method("POST")
+                .path(&format!("/api/rooms/{}/bans", room.uuid))
+                .header("Authorization", &admin_token)
+                .json(&RemoveMember {
+                    member: user.uuid,
+                    reason: None,
+                })
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+
+            let invite = create_invite(
+                &api,
+                &admin_token,
+                &room,
+                &CreateInvite {
+                    max_uses: Some(1),
+                    ..Default::default()
+                },
+            )
+            .await;
+
+            assert_eq!(redeem(&api, &token, &invite).await, StatusCode::FORBIDDEN);
+
+            // the failed attempt didn't use the invite up
+            let resp = request()
+                .method("GET")
+                .path(&format!("/api/rooms/{}/invites", room.uuid))
+                .header("Authorization", &admin_token)
+                .reply(&api)
+                .await;
+            let invites = serde_json::from_slice::<Vec<Invite>>(resp.body())
+                .expect("failed to parse response");
+            assert_eq!(invites[0].uses, 0);
+        })
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_dm_invites() {
+    db(|pool| {
+        Box::pin(async move {
+            let mut conn = pool.acquire().await.expect("can't acquire pool");
+
+            let (_, bob_token) = create_authenticated_user(&mut conn, "bob", "password").await;
+            let mut others = Vec::new();
+            for i in 0..MAX_GROUP_DM_MEMBERS {
+                others.push(
+                    create_authenticated_user(&mut conn, &format!("user{}", i), "password").await,
+                );
+            }
+
+            let api = backend::api(pool.clone());
+
+            let open = |members: Vec<Uuid>| {
+                request()
+                    .method("POST")
+                    .path("/api/dms")
+                    .header("Authorization", &bob_token)
+                    .json(&OpenDm { members })
+            };
+
+            let resp = open(vec![others[0].0.uuid]).reply(&api).await;
+            let dm = serde_json::from_slice::<Room>(resp.body()).expect("failed to parse response");
+            let resp = request()
+                .method("POST")
+                .path(&format!("/api/rooms/{}/invites", dm.uuid))
+                .header("Authorization", &bob_token)
+                .json(&CreateInvite::default())
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+            // one spot left
+            let members = others[..MAX_GROUP_DM_MEMBERS - 2]
+                .iter()
+                .map(|(user, _)| user.uuid)
+                .collect();
+            let resp = open(members).reply(&api).await;
+            let group =
+                serde_json::from_slice::<Room>(resp.body()).expect("failed to parse response");
+            assert_eq!(group.kind, RoomKind::GroupDm);
+
+            let invite = create_invite(&api, &bob_token, &group, &Default::default()).await;
+            let (_, token) = &others[MAX_GROUP_DM_MEMBERS - 2];
+            assert_eq!(redeem(&api, token, &invite).await, StatusCode::CREATED);
+            let (user, token) = &others[MAX_GROUP_DM_MEMBERS - 1];
+            assert_eq!(redeem(&api, token, &invite).await, StatusCode::BAD_REQUEST);
+            assert!(find_member(&pool, &group, &bob_token, user).await.is_none());
+        })
+    })
+    .await
+}
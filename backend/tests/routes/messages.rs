@@ -1,6 +1,10 @@
-use crate::{create_authenticated_user, create_room, create_room_with_user, db, send_message};
-use common::payloads::CreateMessage;
-use common::{Message, MessageType};
+use crate::{
+    create_authenticated_user, create_room, create_room_with_user, db, join_user, send_message,
+};
+use backend::services::rate_limit::MessageRateLimit;
+use common::errors::ApiError;
+use common::payloads::{CreateMessage, UpdateRoom};
+use common::{Message, MessageType, PostPermission, Room};
 use warp::http::StatusCode;
 use warp::test::request;
 
@@ -156,3 +160,195 @@ async fn test_create_blank_message_fails() {
     })
     .await
 }
+
+#[tokio::test]
+async fn test_slow_mode() {
+    db(|pool| {
+        Box::pin(async {
+            let mut conn = pool.acquire().await.expect("can't acquire pool");
+
+            let (admin, admin_token) =
+                create_authenticated_user(&mut conn, "admin", "password").await;
+            let (member, member_token) =
+                create_authenticated_user(&mut conn, "member", "password").await;
+            let room = create_room(&mut conn, "room_name").await;
+            join_user(&mut conn, &admin, &room, true).await;
+            join_user(&mut conn, &member, &room, false).await;
+
+            let api = backend::api(pool);
+
+            let resp = request()
+                .method("PATCH")
+                .path(&format!("/api/rooms/{}", room.uuid))
+                .header("Authorization", &admin_token)
+                .json(&UpdateRoom {
+                    slow_mode: Some(60),
+                    ..Default::default()
+                })
+                .reply(&api)
+                .await;
+            let updated =
+                serde_json::from_slice::<Room>(resp.body()).expect("failed to parse response");
+            assert_eq!(updated.slow_mode, 60);
+
+            let post = |token: &str| {
+                request()
+                    .method("POST")
+                    .path(&format!("/api/rooms/{}/messages", room.uuid))
+                    .header("Authorization", token)
+                    .json(&CreateMessage {
+                        content: "message_content".to_string(),
+                    })
+            };
+
+            let resp = post(&member_token).reply(&api).await;
+            assert_eq!(resp.status(), StatusCode::CREATED);
+
+            let resp = post(&member_token).reply(&api).await;
+            assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+            assert!(resp.headers().contains_key("retry-after"));
+            let error =
+                serde_json::from_slice::<ApiError>(resp.body()).expect("failed to parse response");
+            let retry_after = error.retry_after.expect("no cooldown given");
+            assert!(retry_after > 0.0 && retry_after <= 60.0);
+
+            // those who can change the slow mode aren't held by it
+            for _ in 0..2 {
+                let resp = post(&admin_token).reply(&api).await;
+                assert_eq!(resp.status(), StatusCode::CREATED);
+            }
+        })
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_announcement_room() {
+    db(|pool| {
+        Box::pin(async {
+            let mut conn = pool.acquire().await.expect("can't acquire pool");
+
+            let (admin, admin_token) =
+                create_authenticated_user(&mut conn, "admin", "password").await;
+            let (member, member_token) =
+                create_authenticated_user(&mut conn, "member", "password").await;
+            let room = create_room(&mut conn, "room_name").await;
+            join_user(&mut conn, &admin, &room, true).await;
+            join_user(&mut conn, &member, &room, false).await;
+
+            let api = backend::api(pool);
+
+            let resp = request()
+                .method("PATCH")
+                .path(&format!("/api/rooms/{}", room.uuid))
+                .header("Authorization", &admin_token)
+                .json(&UpdateRoom {
+                    post_permission: Some(PostPermission::Admins),
+                    ..Default::default()
+                })
+                .reply(&api)
+                .await;
+            let updated =
+                serde_json::from_slice::<Room>(resp.body()).expect("failed to parse response");
+            assert!(updated.is_announcement());
+
+            let post = |token: &str| {
+                request()
+                    .method("POST")
+                    .path(&format!("/api/rooms/{}/messages", room.uuid))
+                    .header("Authorization", token)
+                    .json(&CreateMessage {
+                        content: "message_content".to_string(),
+                    })
+            };
+
+            let resp = post(&member_token).reply(&api).await;
+            assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+            let resp = post(&admin_token).reply(&api).await;
+            assert_eq!(resp.status(), StatusCode::CREATED);
+
+            // members can still read what the admins post
+            let resp = request()
+                .method("GET")
+                .path(&format!("/api/rooms/{}/messages", room.uuid))
+                .header("Authorization", &member_token)
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::OK);
+            let messages = serde_json::from_slice::<Vec<Message>>(resp.body())
+                .expect("failed to parse response");
+            assert!(messages
+                .iter()
+                .any(|message| message.content == "message_content"));
+        })
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_message_rate_limit() {
+    db(|pool| {
+        Box::pin(async {
+            let mut conn = pool.acquire().await.expect("can't acquire pool");
+
+            let (user, token) = create_authenticated_user(&mut conn, "user", "password").await;
+            let (room, _) = create_room_with_user(&mut conn, "room_name", &user, true).await;
+
+            let api = backend::api(pool);
+
+            let post = || {
+                request()
+                    .method("POST")
+                    .path(&format!("/api/rooms/{}/messages", room.uuid))
+                    .header("Authorization", &token)
+                    .json(&CreateMessage {
+                        content: "message_content".to_string(),
+                    })
+            };
+
+            for _ in 0..MessageRateLimit::default().messages {
+                let resp = post().reply(&api).await;
+                assert_eq!(resp.status(), StatusCode::CREATED);
+            }
+
+            let resp = post().reply(&api).await;
+            assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+        })
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_concurrent_messages_share_the_rate_limit() {
+    db(|pool| {
+        Box::pin(async {
+            let mut conn = pool.acquire().await.expect("can't acquire pool");
+
+            let (user, token) = create_authenticated_user(&mut conn, "user", "password").await;
+            let (room, _) = create_room_with_user(&mut conn, "room_name", &user, true).await;
+
+            let api = backend::api(pool);
+
+            let limit = MessageRateLimit::default().messages;
+            let posts = (0..limit * 2).map(|_| {
+                request()
+                    .method("POST")
+                    .path(&format!("/api/rooms/{}/messages", room.uuid))
+                    .header("Authorization", &token)
+                    .json(&CreateMessage {
+                        content: "message_content".to_string(),
+                    })
+                    .reply(&api)
+            });
+            let resps = futures::future::join_all(posts).await;
+
+            let created = resps
+                .iter()
+                .filter(|it| it.status() == StatusCode::CREATED)
+                .count();
+            assert_eq!(created as i64, limit);
+        })
+    })
+    .await
+}
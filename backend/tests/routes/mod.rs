@@ -1,4 +1,14 @@
+mod admin;
+mod audit_log;
 mod auth;
+mod automod;
+mod dms;
+mod invites;
 mod messages;
+mod notifications;
+mod reports;
+mod roles;
 mod room;
+mod spaces;
 mod users;
+mod websocket;
@@ -0,0 +1,100 @@
+use crate::{create_authenticated_user, create_room, db, join_user};
+use chrono::{Duration, Utc};
+use common::payloads::UpdateNotificationSettings;
+use common::{NotificationLevel, NotificationSettings};
+use serde_json::json;
+use warp::http::StatusCode;
+use warp::test::request;
+
+#[tokio::test]
+async fn test_notification_settings() {
+    db(|pool| {
+        Box::pin(async move {
+            let mut conn = pool.acquire().await.expect("can't acquire pool");
+
+            let (member, member_token) =
+                create_authenticated_user(&mut conn, "member", "password").await;
+            let (_, other_token) = create_authenticated_user(&mut conn, "other", "password").await;
+            let room = create_room(&mut conn, "room_name").await;
+            join_user(&mut conn, &member, &room, false).await;
+
+            let api = backend::api(pool.clone());
+            let path = format!("/api/rooms/{}/notifications", room.uuid);
+
+            let resp = request()
+                .method("GET")
+                .path(&path)
+                .header("Authorization", &member_token)
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::OK);
+            let settings = serde_json::from_slice::<NotificationSettings>(resp.body())
+                .expect("failed to parse response");
+            assert_eq!(settings, NotificationSettings::new(room.uuid));
+
+            // only members have settings for the room
+            let resp = request()
+                .method("GET")
+                .path(&path)
+                .header("Authorization", &other_token)
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+            let resp = request()
+                .method("PATCH")
+                .path(&path)
+                .header("Authorization", &member_token)
+                .json(&UpdateNotificationSettings {
+                    muted_until: Some(Some(Utc::now() - Duration::hours(1))),
+                    ..Default::default()
+                })
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+            let muted_until = Utc::now() + Duration::hours(1);
+            let resp = request()
+                .method("PATCH")
+                .path(&path)
+                .header("Authorization", &member_token)
+                .json(&UpdateNotificationSettings {
+                    level: Some(NotificationLevel::Mentions),
+                    muted_until: Some(Some(muted_until)),
+                    hide_unread_when_muted: Some(false),
+                })
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::OK);
+
+            // they're stored, not just echoed back
+            let resp = request()
+                .method("GET")
+                .path(&path)
+                .header("Authorization", &member_token)
+                .reply(&api)
+                .await;
+            let settings = serde_json::from_slice::<NotificationSettings>(resp.body())
+                .expect("failed to parse response");
+            assert_eq!(settings.level, NotificationLevel::Mentions);
+            assert!(settings.is_muted());
+            assert!(settings.counts_unread());
+
+            // `null` unmutes, the other fields stay
+            let resp = request()
+                .method("PATCH")
+                .path(&path)
+                .header("Authorization", &member_token)
+                .json(&json!({ "muted_until": null }))
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::OK);
+            let settings = serde_json::from_slice::<NotificationSettings>(resp.body())
+                .expect("failed to parse response");
+            assert_eq!(settings.muted_until, None);
+            assert_eq!(settings.level, NotificationLevel::Mentions);
+            assert!(!settings.hide_unread_when_muted);
+        })
+    })
+    .await;
+}
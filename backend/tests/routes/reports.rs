@@ -0,0 +1,165 @@
+use crate::{create_authenticated_user, create_room, db, join_user, send_message};
+use backend::services;
+use common::payloads::{CreateReport, ResolveReport};
+use common::{Report, ReportAction, ReportScope};
+use warp::http::StatusCode;
+use warp::test::request;
+
+#[tokio::test]
+async fn test_report_and_resolve() {
+    db(|pool| {
+        Box::pin(async move {
+            let mut conn = pool.acquire().await.expect("can't acquire pool");
+
+            let (admin, admin_token) =
+                create_authenticated_user(&mut conn, "admin", "password").await;
+            let (member, member_token) =
+                create_authenticated_user(&mut conn, "member", "password").await;
+            let (spammer, spammer_token) =
+                create_authenticated_user(&mut conn, "spammer", "password").await;
+            let room = create_room(&mut conn, "room_name").await;
+            join_user(&mut conn, &admin, &room, true).await;
+            join_user(&mut conn, &member, &room, false).await;
+            join_user(&mut conn, &spammer, &room, false).await;
+            let message = send_message(&mut conn, "buy cheap stuff", &spammer, &room).await;
+
+            let api = backend::api(pool.clone());
+            let report_path = format!("/api/rooms/{}/messages/{}/reports", room.uuid, message.uuid);
+            let data = CreateReport {
+                reason: "spam".to_string(),
+                scope: ReportScope::Room,
+            };
+
+            let resp = request()
+                .method("POST")
+                .path(&report_path)
+                .header("Authorization", &spammer_token)
+                .json(&data)
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+            let resp = request()
+                .method("POST")
+                .path(&report_path)
+                .header("Authorization", &member_token)
+                .json(&data)
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::CREATED);
+            let report =
+                serde_json::from_slice::<Report>(resp.body()).expect("failed to parse response");
+            assert_eq!(report.content, "buy cheap stuff");
+            assert_eq!(report.author, Some(spammer.uuid));
+
+            let resp = request()
+                .method("POST")
+                .path(&report_path)
+                .header("Authorization", &member_token)
+                .json(&data)
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::CONFLICT);
+
+            // the queue is for admins only
+            let queue_path = format!("/api/rooms/{}/reports", room.uuid);
+            let resp = request()
+                .method("GET")
+                .path(&queue_path)
+                .header("Authorization", &member_token)
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+            let resp = request()
+                .method("GET")
+                .path(&queue_path)
+                .header("Authorization", &admin_token)
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::OK);
+            let reports = serde_json::from_slice::<Vec<Report>>(resp.body())
+                .expect("failed to parse response");
+            assert_eq!(reports.len(), 1);
+            assert_eq!(reports[0].uuid, report.uuid);
+
+            let resolve_path = format!("/api/rooms/{}/reports/{}/resolve", room.uuid, report.uuid);
+            let resp = request()
+                .method("POST")
+                .path(&resolve_path)
+                .header("Authorization", &admin_token)
+                .json(&ResolveReport {
+                    action: ReportAction::DeleteMessage,
+                    reason: None,
+                })
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::OK);
+            let report =
+                serde_json::from_slice::<Report>(resp.body()).expect("failed to parse response");
+            assert_eq!(report.action, Some(ReportAction::DeleteMessage));
+            assert_eq!(report.resolved_by, Some(admin.uuid));
+            let deleted = services::message::get(&mut conn, &room, message.uuid)
+                .await
+                .expect("failed to fetch message");
+            assert!(deleted.is_none());
+
+            let resp = request()
+                .method("POST")
+                .path(&resolve_path)
+                .header("Authorization", &admin_token)
+                .json(&ResolveReport {
+                    action: ReportAction::Dismiss,
+                    reason: None,
+                })
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::CONFLICT);
+
+            // resolved reports leave the open queue
+            let resp = request()
+                .method("GET")
+                .path(&queue_path)
+                .header("Authorization", &admin_token)
+                .reply(&api)
+                .await;
+            let reports = serde_json::from_slice::<Vec<Report>>(resp.body())
+                .expect("failed to parse response");
+            assert!(reports.is_empty());
+
+            let message = send_message(&mut conn, "buy more stuff", &spammer, &room).await;
+            let resp = request()
+                .method("POST")
+                .path(&format!(
+                    "/api/rooms/{}/messages/{}/reports",
+                    room.uuid, message.uuid
+                ))
+                .header("Authorization", &member_token)
+                .json(&data)
+                .reply(&api)
+                .await;
+            let report =
+                serde_json::from_slice::<Report>(resp.body()).expect("failed to parse response");
+
+            let resp = request()
+                .method("POST")
+                .path(&format!(
+                    "/api/rooms/{}/reports/{}/resolve",
+                    room.uuid, report.uuid
+                ))
+                .header("Authorization", &admin_token)
+                .json(&ResolveReport {
+                    action: ReportAction::Ban,
+                    reason: Some("repeated spam".to_string()),
+                })
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::OK);
+            let in_room = services::room::user_in_room(&mut conn, &room, &spammer)
+                .await
+                .expect("failed to check membership");
+            assert!(!in_room);
+        })
+    })
+    .await;
+}
@@ -0,0 +1,395 @@
+use crate::{create_authenticated_user, create_room, create_user, db, join_user};
+use common::payloads::{CreateMessage, CreateRole, RemoveMember, SetMemberRole, UpdateRole};
+use common::{Permissions, Role, RoomMember};
+use warp::http::StatusCode;
+use warp::test::request;
+
+#[tokio::test]
+async fn test_default_roles() {
+    db(|pool| {
+        Box::pin(async {
+            let mut conn = pool.acquire().await.expect("can't acquire pool");
+
+            let (user, token) = create_authenticated_user(&mut conn, "user", "password").await;
+            let room = create_room(&mut conn, "room_name").await;
+            join_user(&mut conn, &user, &room, false).await;
+
+            let api = backend::api(pool);
+
+            let resp = request()
+                .method("GET")
+                .path(&format!("/api/rooms/{}/roles", room.uuid))
+                .header("Authorization", token)
+                .reply(&api)
+                .await;
+
+            let roles =
+                serde_json::from_slice::<Vec<Role>>(resp.body()).expect("failed to parse response");
+
+            assert_eq!(resp.status(), StatusCode::OK);
+            assert_eq!(roles.len(), 2);
+            assert!(roles[0].is_admin);
+            assert_eq!(roles[0].permissions(), Permissions::ALL);
+            assert!(roles[1].is_default);
+            assert_eq!(roles[1].permissions(), Permissions::DEFAULT);
+        })
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_assign_role() {
+    db(|pool| {
+        Box::pin(async {
+            let mut conn = pool.acquire().await.expect("can't acquire pool");
+
+            let (admin, admin_token) =
+                create_authenticated_user(&mut conn, "admin", "password").await;
+            let (moderator, moderator_token) =
+                create_authenticated_user(&mut conn, "moderator", "password").await;
+            let member = create_user(&mut conn, "member", "password").await;
+            let room = create_room(&mut conn, "room_name").await;
+            join_user(&mut conn, &admin, &room, true).await;
+            join_user(&mut conn, &moderator, &room, false).await;
+            join_user(&mut conn, &member, &room, false).await;
+
+            let api = backend::api(pool);
+
+            let kick = |token: &str| {
+                request()
+                    .method("POST")
+                    .path(&format!("/api/rooms/{}/kick", room.uuid))
+                    .header("Authorization", token)
+                    .json(&RemoveMember {
+                        member: member.uuid,
+                        reason: None,
+                    })
+            };
+
+            let resp = kick(&moderator_token).reply(&api).await;
+            assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+            let resp = request()
+                .method("POST")
+                .path(&format!("/api/rooms/{}/roles", room.uuid))
+                .header("Authorization", &admin_token)
+                .json(&CreateRole {
+                    name: "Moderator".to_string(),
+                    permissions: Permissions::DEFAULT | Permissions::KICK,
+                })
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::CREATED);
+            let role =
+                serde_json::from_slice::<Role>(resp.body()).expect("failed to parse response");
+
+            let resp = request()
+                .method("PUT")
+                .path(&format!(
+                    "/api/rooms/{}/members/{}/role",
+                    room.uuid, moderator.uuid
+                ))
+                .header("Authorization", &admin_token)
+                .json(&SetMemberRole { role: role.uuid })
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::OK);
+
+            let resp = kick(&moderator_token).reply(&api).await;
+            assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+
+            let resp = request()
+                .method("GET")
+                .path(&format!("/api/rooms/{}/members", room.uuid))
+                .header("Authorization", &admin_token)
+                .reply(&api)
+                .await;
+            let members = serde_json::from_slice::<Vec<RoomMember>>(resp.body())
+                .expect("failed to parse response");
+            let moderator = members
+                .iter()
+                .find(|it| it.user.uuid == moderator.uuid)
+                .expect("moderator isn't a member");
+            assert_eq!(moderator.role, role);
+            assert!(moderator.can(Permissions::KICK));
+            assert!(!moderator.can(Permissions::BAN));
+        })
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_roles_cant_grant_more_than_own_permissions() {
+    db(|pool| {
+        Box::pin(async {
+            let mut conn = pool.acquire().await.expect("can't acquire pool");
+
+            let (admin, admin_token) =
+                create_authenticated_user(&mut conn, "admin", "password").await;
+            let (manager, manager_token) =
+                create_authenticated_user(&mut conn, "manager", "password").await;
+            let room = create_room(&mut conn, "room_name").await;
+            join_user(&mut conn, &admin, &room, true).await;
+            join_user(&mut conn, &manager, &room, false).await;
+
+            let api = backend::api(pool);
+
+            let resp = request()
+                .method("POST")
+                .path(&format!("/api/rooms/{}/roles", room.uuid))
+                .header("Authorization", &admin_token)
+                .json(&CreateRole {
+                    name: "Manager".to_string(),
+                    permissions: Permissions::DEFAULT | Permissions::MANAGE_ROLES,
+                })
+                .reply(&api)
+                .await;
+            let role =
+                serde_json::from_slice::<Role>(resp.body()).expect("failed to parse response");
+
+            let resp = request()
+                .method("PUT")
+                .path(&format!(
+                    "/api/rooms/{}/members/{}/role",
+                    room.uuid, manager.uuid
+                ))
+                .header("Authorization", &admin_token)
+                .json(&SetMemberRole { role: role.uuid })
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::OK);
+
+            // can't give themselves more permissions
+            let resp = request()
+                .method("PATCH")
+                .path(&format!("/api/rooms/{}/roles/{}", room.uuid, role.uuid))
+                .header("Authorization", &manager_token)
+                .json(&UpdateRole {
+                    name: None,
+                    permissions: Some(Permissions::ALL),
+                })
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+            // can't create roles with more permissions
+            let resp = request()
+                .method("POST")
+                .path(&format!("/api/rooms/{}/roles", room.uuid))
+                .header("Authorization", &manager_token)
+                .json(&CreateRole {
+                    name: "Banner".to_string(),
+                    permissions: Permissions::BAN,
+                })
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+            // can't demote the admin
+            let resp = request()
+                .method("PUT")
+                .path(&format!(
+                    "/api/rooms/{}/members/{}/role",
+                    room.uuid, admin.uuid
+                ))
+                .header("Authorization", &manager_token)
+                .json(&SetMemberRole { role: role.uuid })
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        })
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_send_messages_permission() {
+    db(|pool| {
+        Box::pin(async {
+            let mut conn = pool.acquire().await.expect("can't acquire pool");
+
+            let (admin, admin_token) =
+                create_authenticated_user(&mut conn, "admin", "password").await;
+            let (member, member_token) =
+                create_authenticated_user(&mut conn, "member", "password").await;
+            let room = create_room(&mut conn, "room_name").await;
+            join_user(&mut conn, &admin, &room, true).await;
+            let joined = join_user(&mut conn, &member, &room, false).await;
+
+            let api = backend::api(pool);
+
+            let resp = request()
+                .method("PATCH")
+                .path(&format!(
+                    "/api/rooms/{}/roles/{}",
+                    room.uuid, joined.role.uuid
+                ))
+                .header("Authorization", &admin_token)
+                .json(&UpdateRole {
+                    name: None,
+                    permissions: Some(Permissions::NONE),
+                })
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::OK);
+
+            let resp = request()
+                .method("POST")
+                .path(&format!("/api/rooms/{}/messages", room.uuid))
+                .header("Authorization", &member_token)
+                .json(&CreateMessage {
+                    content: "content".to_string(),
+                })
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+            // reading is still allowed
+            let resp = request()
+                .method("GET")
+                .path(&format!("/api/rooms/{}/messages", room.uuid))
+                .header("Authorization", &member_token)
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::OK);
+        })
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_delete_role() {
+    db(|pool| {
+        Box::pin(async {
+            let mut conn = pool.acquire().await.expect("can't acquire pool");
+
+            let (admin, admin_token) =
+                create_authenticated_user(&mut conn, "admin", "password").await;
+            let member = create_user(&mut conn, "member", "password").await;
+            let room = create_room(&mut conn, "room_name").await;
+            let admin = join_user(&mut conn, &admin, &room, true).await;
+            let member = join_user(&mut conn, &member, &room, false).await;
+
+            let api = backend::api(pool);
+
+            let delete = |role: &Role| {
+                request()
+                    .method("DELETE")
+                    .path(&format!("/api/rooms/{}/roles/{}", room.uuid, role.uuid))
+                    .header("Authorization", &admin_token)
+            };
+
+            let resp = delete(&admin.role).reply(&api).await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+            let resp = delete(&member.role).reply(&api).await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+            let resp = request()
+                .method("POST")
+                .path(&format!("/api/rooms/{}/roles", room.uuid))
+                .header("Authorization", &admin_token)
+                .json(&CreateRole {
+                    name: "Temporary".to_string(),
+                    permissions: Permissions::SEND_MESSAGES,
+                })
+                .reply(&api)
+                .await;
+            let role =
+                serde_json::from_slice::<Role>(resp.body()).expect("failed to parse response");
+
+            request()
+                .method("PUT")
+                .path(&format!(
+                    "/api/rooms/{}/members/{}/role",
+                    room.uuid, member.user.uuid
+                ))
+                .header("Authorization", &admin_token)
+                .json(&SetMemberRole { role: role.uuid })
+                .reply(&api)
+                .await;
+
+            let resp = delete(&role).reply(&api).await;
+            assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+
+            let resp = request()
+                .method("GET")
+                .path(&format!("/api/rooms/{}/members", room.uuid))
+                .header("Authorization", &admin_token)
+                .reply(&api)
+                .await;
+            let members = serde_json::from_slice::<Vec<RoomMember>>(resp.body())
+                .expect("failed to parse response");
+            let moved = members
+                .iter()
+                .find(|it| it.user.uuid == member.user.uuid)
+                .expect("member was removed");
+            assert!(moved.role.is_default);
+        })
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_cant_delete_roles_with_more_permissions() {
+    db(|pool| {
+        Box::pin(async {
+            let mut conn = pool.acquire().await.expect("can't acquire pool");
+
+            let (admin, admin_token) =
+                create_authenticated_user(&mut conn, "admin", "password").await;
+            let (manager, manager_token) =
+                create_authenticated_user(&mut conn, "manager", "password").await;
+            let room = create_room(&mut conn, "room_name").await;
+            join_user(&mut conn, &admin, &room, true).await;
+            join_user(&mut conn, &manager, &room, false).await;
+
+            let api = backend::api(pool);
+
+            let create = |name: &str, permissions: Permissions| {
+                request()
+                    .method("POST")
+                    .path(&format!("/api/rooms/{}/roles", room.uuid))
+                    .header("Authorization", &admin_token)
+                    .json(&CreateRole {
+                        name: name.to_string(),
+                        permissions,
+                    })
+            };
+
+            let resp = create("Manager", Permissions::DEFAULT | Permissions::MANAGE_ROLES)
+                .reply(&api)
+                .await;
+            let manager_role =
+                serde_json::from_slice::<Role>(resp.body()).expect("failed to parse response");
+            let resp = create("Banner", Permissions::BAN).reply(&api).await;
+            let banner_role =
+                serde_json::from_slice::<Role>(resp.body()).expect("failed to parse response");
+
+            let resp = request()
+                .method("PUT")
+                .path(&format!(
+                    "/api/rooms/{}/members/{}/role",
+                    room.uuid, manager.uuid
+                ))
+                .header("Authorization", &admin_token)
+                .json(&SetMemberRole {
+                    role: manager_role.uuid,
+                })
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::OK);
+
+            let resp = request()
+                .method("DELETE")
+                .path(&format!(
+                    "/api/rooms/{}/roles/{}",
+                    room.uuid, banner_role.uuid
+                ))
+                .header("Authorization", &manager_token)
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        })
+    })
+    .await
+}
@@ -1,7 +1,15 @@
-use crate::{create_authenticated_user, create_room, create_user, db, join_user};
-use common::payloads::{CreateRoom, JoinMembers};
-use common::{Room, RoomMember};
+use crate::{
+    create_authenticated_user, create_owned_room, create_public_room, create_room, create_user, db,
+    join_user,
+};
+use backend::services::role as role_service;
+use common::payloads::{
+    CreateMessage, CreateRole, CreateRoom, DirectoryEntry, JoinMembers, RemoveMember,
+    SetMemberRole, TransferOwnership, UpdateRole, UpdateRoom,
+};
+use common::{MessageType, Permissions, Role, Room, RoomMember, RoomVisibility};
 use sqlx::types::Uuid;
+use sqlx::PgConnection;
 use warp::http::StatusCode;
 use warp::test::request;
 
@@ -13,8 +21,9 @@ async fn test_get_room() {
             let username = "user";
             let password = "password";
 
-            let (_, token) = create_authenticated_user(&mut conn, username, password).await;
+            let (user, token) = create_authenticated_user(&mut conn, username, password).await;
             let room = create_room(&mut conn, "room_name").await;
+            join_user(&mut conn, &user, &room, false).await;
 
             let api = backend::api(pool);
 
@@ -83,6 +92,9 @@ async fn test_create_room() {
                 .header("Authorization", token)
                 .json(&CreateRoom {
                     name: room_name.to_string(),
+                    visibility: RoomVisibility::Public,
+                    space: None,
+                    category: None,
                 })
                 .reply(&api)
                 .await;
@@ -93,6 +105,115 @@ async fn test_create_room() {
             assert_eq!(resp.status(), StatusCode::CREATED);
             assert_eq!(room.name, room_name);
             assert_eq!(room.icon, None); // there shouldn't be any icon at first
+            assert!(room.is_public());
+        })
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_private_room_hidden_from_non_members() {
+    db(|pool| {
+        Box::pin(async {
+            let mut conn = pool.acquire().await.expect("can't acquire pool");
+
+            let (_, token) = create_authenticated_user(&mut conn, "user", "password").await;
+            let private = create_room(&mut conn, "private").await;
+            let public = create_public_room(&mut conn, "public").await;
+
+            let api = backend::api(pool);
+
+            let get = |room: &Room| {
+                request()
+                    .method("GET")
+                    .path(&format!("/api/rooms/{}", room.uuid))
+                    .header("Authorization", &token)
+            };
+
+            let resp = get(&private).reply(&api).await;
+            assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+            let resp = get(&public).reply(&api).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+        })
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_public_room_directory() {
+    db(|pool| {
+        Box::pin(async {
+            let mut conn = pool.acquire().await.expect("can't acquire pool");
+
+            let (user, token) = create_authenticated_user(&mut conn, "user", "password").await;
+            let rust = create_public_room(&mut conn, "rust").await;
+            let rustaceans = create_public_room(&mut conn, "rustaceans").await;
+            create_public_room(&mut conn, "go").await;
+            create_room(&mut conn, "rust (private)").await;
+            join_user(&mut conn, &user, &rustaceans, false).await;
+
+            let api = backend::api(pool);
+
+            let list = |query: &str| {
+                request()
+                    .method("GET")
+                    .path(&format!("/api/rooms/public?{}", query))
+                    .header("Authorization", &token)
+            };
+
+            let resp = list("search=rust").reply(&api).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+            let entries = serde_json::from_slice::<Vec<DirectoryEntry>>(resp.body())
+                .expect("failed to parse response");
+            // rooms with more members come first
+            let rooms = entries.iter().map(|it| &it.room).collect::<Vec<_>>();
+            assert_eq!(rooms, vec![&rustaceans, &rust]);
+            assert_eq!(entries[0].member_count, 1);
+
+            let resp = list("offset=1&limit=1").reply(&api).await;
+            let entries = serde_json::from_slice::<Vec<DirectoryEntry>>(resp.body())
+                .expect("failed to parse response");
+            assert_eq!(entries.len(), 1);
+
+            let resp = list("limit=0").reply(&api).await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        })
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_join_public_room() {
+    db(|pool| {
+        Box::pin(async {
+            let mut conn = pool.acquire().await.expect("can't acquire pool");
+
+            let (user, token) = create_authenticated_user(&mut conn, "user", "password").await;
+            let private = create_room(&mut conn, "private").await;
+            let public = create_public_room(&mut conn, "public").await;
+
+            let api = backend::api(pool);
+
+            let join = |room: &Room| {
+                request()
+                    .method("POST")
+                    .path(&format!("/api/rooms/{}/members/me", room.uuid))
+                    .header("Authorization", &token)
+            };
+
+            let resp = join(&private).reply(&api).await;
+            assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+            let resp = join(&public).reply(&api).await;
+            assert_eq!(resp.status(), StatusCode::CREATED);
+            let member = serde_json::from_slice::<RoomMember>(resp.body())
+                .expect("failed to parse response");
+            assert_eq!(member.user.uuid, user.uuid);
+            assert!(member.role.is_default);
+
+            let resp = join(&public).reply(&api).await;
+            assert_eq!(resp.status(), StatusCode::CONFLICT);
         })
     })
     .await
@@ -109,7 +230,7 @@ async fn test_join_room() {
             let (user, token) = create_authenticated_user(&mut conn, username, password).await;
             let user_to_join = create_user(&mut conn, "username_to_join", "password_to_join").await;
             let room = create_room(&mut conn, "room_name").await;
-            join_user(&mut conn, &user, &room, true).await;
+            let admin = join_user(&mut conn, &user, &room, true).await;
             let api = backend::api(pool);
 
             let resp = request()
@@ -118,7 +239,7 @@ async fn test_join_room() {
                 .header("Authorization", token)
                 .json(&JoinMembers {
                     member: user_to_join.uuid,
-                    with_elevated_permissions: true,
+                    role: Some(admin.role.uuid),
                 })
                 .reply(&api)
                 .await;
@@ -129,7 +250,7 @@ async fn test_join_room() {
             assert_eq!(resp.status(), StatusCode::CREATED);
             assert_eq!(body.room.uuid, room.uuid);
             assert_eq!(body.user.uuid, user_to_join.uuid);
-            assert_eq!(body.has_elevated_permissions, true);
+            assert!(body.role.is_admin);
         })
     })
     .await
@@ -203,3 +324,745 @@ async fn test_no_perms_403() {
     })
     .await
 }
+
+#[tokio::test]
+async fn test_member_cant_add_admins() {
+    db(|pool| {
+        Box::pin(async {
+            let mut conn = pool.acquire().await.expect("can't acquire pool");
+
+            let (user, token) = create_authenticated_user(&mut conn, "user", "password").await;
+            let admin = create_user(&mut conn, "admin", "password").await;
+            let invitee = create_user(&mut conn, "invitee", "password").await;
+            let room = create_room(&mut conn, "room_name").await;
+            let admin = join_user(&mut conn, &admin, &room, true).await;
+            join_user(&mut conn, &user, &room, false).await;
+
+            let api = backend::api(pool);
+
+            let join = |role: Option<Uuid>| {
+                request()
+                    .method("POST")
+                    .path(&format!("/api/rooms/{}/join", room.uuid))
+                    .header("Authorization", &token)
+                    .json(&JoinMembers {
+                        member: invitee.uuid,
+                        role,
+                    })
+            };
+
+            let resp = join(Some(admin.role.uuid)).reply(&api).await;
+            assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+            // nothing was applied
+            let resp = request()
+                .method("GET")
+                .path(&format!("/api/rooms/{}/members", room.uuid))
+                .header("Authorization", &token)
+                .reply(&api)
+                .await;
+            let body = serde_json::from_slice::<Vec<RoomMember>>(resp.body())
+                .expect("failed to parse response");
+            assert!(body.iter().all(|it| it.user.uuid != invitee.uuid));
+
+            let resp = join(None).reply(&api).await;
+            assert_eq!(resp.status(), StatusCode::CREATED);
+            let body = serde_json::from_slice::<RoomMember>(resp.body())
+                .expect("failed to parse response");
+            assert!(body.role.is_default);
+            assert!(!body.can(Permissions::KICK));
+        })
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_adding_members_needs_invite_permission() {
+    db(|pool| {
+        Box::pin(async {
+            let mut conn = pool.acquire().await.expect("can't acquire pool");
+
+            let (user, token) = create_authenticated_user(&mut conn, "user", "password").await;
+            let (admin, admin_token) =
+                create_authenticated_user(&mut conn, "admin", "password").await;
+            let invitee = create_user(&mut conn, "invitee", "password").await;
+            let room = create_room(&mut conn, "room_name").await;
+            join_user(&mut conn, &admin, &room, true).await;
+            let member = join_user(&mut conn, &user, &room, false).await;
+
+            let api = backend::api(pool);
+
+            // the default role decides whether ordinary members can add people
+            let resp = request()
+                .method("PATCH")
+                .path(&format!(
+                    "/api/rooms/{}/roles/{}",
+                    room.uuid, member.role.uuid
+                ))
+                .header("Authorization", &admin_token)
+                .json(&UpdateRole {
+                    name: None,
+                    permissions: Some(Permissions::SEND_MESSAGES),
+                })
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::OK);
+
+            let join = |token: &str| {
+                request()
+                    .method("POST")
+                    .path(&format!("/api/rooms/{}/join", room.uuid))
+                    .header("Authorization", token)
+                    .json(&JoinMembers {
+                        member: invitee.uuid,
+                        role: None,
+                    })
+            };
+
+            let resp = join(&token).reply(&api).await;
+            assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+            let resp = join(&admin_token).reply(&api).await;
+            assert_eq!(resp.status(), StatusCode::CREATED);
+        })
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_non_member_cant_add_members() {
+    db(|pool| {
+        Box::pin(async {
+            let mut conn = pool.acquire().await.expect("can't acquire pool");
+
+            let (_, token) = create_authenticated_user(&mut conn, "user", "password").await;
+            let other = create_user(&mut conn, "other", "password").await;
+            let room = create_room(&mut conn, "room_name").await;
+
+            let api = backend::api(pool);
+
+            let resp = request()
+                .method("POST")
+                .path(&format!("/api/rooms/{}/join", room.uuid))
+                .header("Authorization", token)
+                .json(&JoinMembers {
+                    member: other.uuid,
+                    role: None,
+                })
+                .reply(&api)
+                .await;
+
+            assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        })
+    })
+    .await
+}
+
+/// Type of the last message posted in the room
+async fn last_message_type(conn: &mut PgConnection, room: &Room) -> MessageType {
+    sqlx::query_as::<_, (MessageType,)>(
+        "select type from messages where room = $1 order by created_at desc limit 1;",
+    )
+    .bind(room.uuid)
+    .fetch_one(conn)
+    .await
+    .expect("no message in room")
+    .0
+}
+
+#[tokio::test]
+async fn test_leave_room() {
+    db(|pool| {
+        Box::pin(async {
+            let mut conn = pool.acquire().await.expect("can't acquire pool");
+
+            let (user, token) = create_authenticated_user(&mut conn, "user", "password").await;
+            let room = create_room(&mut conn, "room_name").await;
+            join_user(&mut conn, &user, &room, false).await;
+
+            let api = backend::api(pool);
+
+            let resp = request()
+                .method("POST")
+                .path(&format!("/api/rooms/{}/leave", room.uuid))
+                .header("Authorization", &token)
+                .reply(&api)
+                .await;
+
+            assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+            assert_eq!(
+                last_message_type(&mut conn, &room).await,
+                MessageType::RoomLeave
+            );
+
+            let resp = request()
+                .method("POST")
+                .path(&format!("/api/rooms/{}/leave", room.uuid))
+                .header("Authorization", &token)
+                .reply(&api)
+                .await;
+
+            assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        })
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_kick_member() {
+    db(|pool| {
+        Box::pin(async {
+            let mut conn = pool.acquire().await.expect("can't acquire pool");
+
+            let (user, token) = create_authenticated_user(&mut conn, "user", "password").await;
+            let (member, member_token) =
+                create_authenticated_user(&mut conn, "member", "password").await;
+            let room = create_room(&mut conn, "room_name").await;
+            join_user(&mut conn, &user, &room, true).await;
+            join_user(&mut conn, &member, &room, false).await;
+
+            let api = backend::api(pool);
+
+            // members without elevated permissions can't kick
+            let resp = request()
+                .method("POST")
+                .path(&format!("/api/rooms/{}/kick", room.uuid))
+                .header("Authorization", member_token)
+                .json(&RemoveMember {
+                    member: user.uuid,
+                    reason: None,
+                })
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+            let resp = request()
+                .method("POST")
+                .path(&format!("/api/rooms/{}/kick", room.uuid))
+                .header("Authorization", &token)
+                .json(&RemoveMember {
+                    member: user.uuid,
+                    reason: None,
+                })
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+            let resp = request()
+                .method("POST")
+                .path(&format!("/api/rooms/{}/kick", room.uuid))
+                .header("Authorization", &token)
+                .json(&RemoveMember {
+                    member: member.uuid,
+                    reason: Some("spam".to_string()),
+                })
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+            assert_eq!(
+                last_message_type(&mut conn, &room).await,
+                MessageType::RoomKick
+            );
+
+            let resp = request()
+                .method("GET")
+                .path(&format!("/api/rooms/{}/members", room.uuid))
+                .header("Authorization", &token)
+                .reply(&api)
+                .await;
+            let body = serde_json::from_slice::<Vec<RoomMember>>(resp.body())
+                .expect("failed to parse response");
+            assert_eq!(body.len(), 1);
+            assert_eq!(body[0].user.uuid, user.uuid);
+        })
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_ban_member() {
+    db(|pool| {
+        Box::pin(async {
+            let mut conn = pool.acquire().await.expect("can't acquire pool");
+
+            let (user, token) = create_authenticated_user(&mut conn, "user", "password").await;
+            let member = create_user(&mut conn, "member", "password").await;
+            let room = create_room(&mut conn, "room_name").await;
+            join_user(&mut conn, &user, &room, true).await;
+            join_user(&mut conn, &member, &room, false).await;
+
+            let api = backend::api(pool);
+
+            let resp = request()
+                .method("POST")
+                .path(&format!("/api/rooms/{}/bans", room.uuid))
+                .header("Authorization", &token)
+                .json(&RemoveMember {
+                    member: member.uuid,
+                    reason: Some("spam".to_string()),
+                })
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+            assert_eq!(
+                last_message_type(&mut conn, &room).await,
+                MessageType::RoomBan
+            );
+
+            let join = |member: Uuid| {
+                request()
+                    .method("POST")
+                    .path(&format!("/api/rooms/{}/join", room.uuid))
+                    .header("Authorization", &token)
+                    .json(&JoinMembers { member, role: None })
+            };
+
+            let resp = join(member.uuid).reply(&api).await;
+            assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+            let resp = request()
+                .method("DELETE")
+                .path(&format!("/api/rooms/{}/bans/{}", room.uuid, member.uuid))
+                .header("Authorization", &token)
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+
+            let resp = join(member.uuid).reply(&api).await;
+            assert_eq!(resp.status(), StatusCode::CREATED);
+        })
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_update_room() {
+    db(|pool| {
+        Box::pin(async move {
+            let mut conn = pool.acquire().await.expect("can't acquire pool");
+
+            let (admin, admin_token) =
+                create_authenticated_user(&mut conn, "admin", "password").await;
+            let (member, member_token) =
+                create_authenticated_user(&mut conn, "member", "password").await;
+            let room = create_room(&mut conn, "room_name").await;
+            join_user(&mut conn, &admin, &room, true).await;
+            join_user(&mut conn, &member, &room, false).await;
+
+            let api = backend::api(pool.clone());
+
+            let update = |token: &str, data: &UpdateRoom| {
+                request()
+                    .method("PATCH")
+                    .path(&format!("/api/rooms/{}", room.uuid))
+                    .header("Authorization", token)
+                    .json(data)
+            };
+
+            let rename = UpdateRoom {
+                name: Some("new name".to_string()),
+                topic: Some(Some("the topic".to_string())),
+                ..Default::default()
+            };
+
+            let resp = update(&member_token, &rename).reply(&api).await;
+            assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+            let resp = update(&admin_token, &rename).reply(&api).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+            let updated =
+                serde_json::from_slice::<Room>(resp.body()).expect("failed to parse response");
+            assert_eq!(updated.name, "new name");
+            assert_eq!(updated.topic.as_deref(), Some("the topic"));
+            assert_eq!(
+                last_message_type(&mut conn, &room).await,
+                MessageType::RoomRename
+            );
+
+            // a null topic clears it, the name is left alone
+            let resp = request()
+                .method("PATCH")
+                .path(&format!("/api/rooms/{}", room.uuid))
+                .header("Authorization", &admin_token)
+                .json(&serde_json::json!({ "topic": null }))
+                .reply(&api)
+                .await;
+            let updated =
+                serde_json::from_slice::<Room>(resp.body()).expect("failed to parse response");
+            assert_eq!(updated.name, "new name");
+            assert_eq!(updated.topic, None);
+
+            let resp = update(
+                &admin_token,
+                &UpdateRoom {
+                    name: Some(" ".to_string()),
+                    ..Default::default()
+                },
+            )
+            .reply(&api)
+            .await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        })
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_archive_room() {
+    db(|pool| {
+        Box::pin(async {
+            let mut conn = pool.acquire().await.expect("can't acquire pool");
+
+            let (admin, admin_token) =
+                create_authenticated_user(&mut conn, "admin", "password").await;
+            let (member, member_token) =
+                create_authenticated_user(&mut conn, "member", "password").await;
+            let room = create_room(&mut conn, "room_name").await;
+            join_user(&mut conn, &admin, &room, true).await;
+            join_user(&mut conn, &member, &room, false).await;
+
+            let api = backend::api(pool);
+
+            let archive = |method: &str, token: &str| {
+                request()
+                    .method(method)
+                    .path(&format!("/api/rooms/{}/archive", room.uuid))
+                    .header("Authorization", token)
+            };
+            let post = || {
+                request()
+                    .method("POST")
+                    .path(&format!("/api/rooms/{}/messages", room.uuid))
+                    .header("Authorization", &member_token)
+                    .json(&CreateMessage {
+                        content: "content".to_string(),
+                    })
+            };
+
+            let resp = archive("PUT", &member_token).reply(&api).await;
+            assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+            let resp = archive("PUT", &admin_token).reply(&api).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+            let archived =
+                serde_json::from_slice::<Room>(resp.body()).expect("failed to parse response");
+            assert!(archived.is_archived());
+
+            let resp = post().reply(&api).await;
+            assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+            let resp = archive("DELETE", &admin_token).reply(&api).await;
+            let unarchived =
+                serde_json::from_slice::<Room>(resp.body()).expect("failed to parse response");
+            assert!(!unarchived.is_archived());
+
+            let resp = post().reply(&api).await;
+            assert_eq!(resp.status(), StatusCode::CREATED);
+        })
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_delete_room() {
+    db(|pool| {
+        Box::pin(async move {
+            let mut conn = pool.acquire().await.expect("can't acquire pool");
+
+            let (admin, admin_token) =
+                create_authenticated_user(&mut conn, "admin", "password").await;
+            let (member, member_token) =
+                create_authenticated_user(&mut conn, "member", "password").await;
+            let room = create_room(&mut conn, "room_name").await;
+            join_user(&mut conn, &admin, &room, true).await;
+            join_user(&mut conn, &member, &room, false).await;
+
+            let api = backend::api(pool.clone());
+
+            let delete = |token: &str| {
+                request()
+                    .method("DELETE")
+                    .path(&format!("/api/rooms/{}", room.uuid))
+                    .header("Authorization", token)
+            };
+
+            let resp = delete(&member_token).reply(&api).await;
+            assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+            let resp = delete(&admin_token).reply(&api).await;
+            assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+
+            let resp = request()
+                .method("GET")
+                .path(&format!("/api/rooms/{}", room.uuid))
+                .header("Authorization", &admin_token)
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+            // the join messages went along with the room
+            let (messages,) =
+                sqlx::query_as::<_, (i64,)>("select count(*) from messages where room = $1;")
+                    .bind(room.uuid)
+                    .fetch_one(&mut conn)
+                    .await
+                    .expect("failed to count messages");
+            assert_eq!(messages, 0);
+        })
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_room_owner() {
+    db(|pool| {
+        Box::pin(async move {
+            let mut conn = pool.acquire().await.expect("can't acquire pool");
+
+            let (owner, owner_token) =
+                create_authenticated_user(&mut conn, "owner", "password").await;
+            let (admin, admin_token) =
+                create_authenticated_user(&mut conn, "admin", "password").await;
+            let room = create_owned_room(&mut conn, "room_name", &owner).await;
+            join_user(&mut conn, &admin, &room, true).await;
+
+            let api = backend::api(pool.clone());
+
+            let resp = request()
+                .method("GET")
+                .path(&format!("/api/rooms/{}/members", room.uuid))
+                .header("Authorization", &admin_token)
+                .reply(&api)
+                .await;
+            let members = serde_json::from_slice::<Vec<RoomMember>>(resp.body())
+                .expect("failed to parse response");
+            let owner_member = members
+                .iter()
+                .find(|it| it.user.uuid == owner.uuid)
+                .expect("owner isn't a member");
+            assert!(owner_member.role.is_admin);
+
+            // other admins can't get rid of the owner
+            let resp = request()
+                .method("POST")
+                .path(&format!("/api/rooms/{}/kick", room.uuid))
+                .header("Authorization", &admin_token)
+                .json(&RemoveMember {
+                    member: owner.uuid,
+                    reason: None,
+                })
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+            let resp = request()
+                .method("PUT")
+                .path(&format!(
+                    "/api/rooms/{}/members/{}/role",
+                    room.uuid, owner.uuid
+                ))
+                .header("Authorization", &admin_token)
+                .json(&SetMemberRole {
+                    role: owner_member.role.uuid,
+                })
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+            let resp = request()
+                .method("DELETE")
+                .path(&format!("/api/rooms/{}", room.uuid))
+                .header("Authorization", &admin_token)
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+            let leave = |token: &str| {
+                request()
+                    .method("POST")
+                    .path(&format!("/api/rooms/{}/leave", room.uuid))
+                    .header("Authorization", token)
+            };
+            let resp = leave(&owner_token).reply(&api).await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+            let transfer = |token: &str| {
+                request()
+                    .method("PUT")
+                    .path(&format!("/api/rooms/{}/owner", room.uuid))
+                    .header("Authorization", token)
+                    .json(&TransferOwnership { owner: admin.uuid })
+            };
+            let resp = transfer(&admin_token).reply(&api).await;
+            assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+            let resp = transfer(&owner_token).reply(&api).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+            let room =
+                serde_json::from_slice::<Room>(resp.body()).expect("failed to parse response");
+            assert!(room.is_owner(admin.uuid));
+
+            let resp = leave(&owner_token).reply(&api).await;
+            assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        })
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_only_admins_manage_the_admin_role() {
+    db(|pool| {
+        Box::pin(async {
+            let mut conn = pool.acquire().await.expect("can't acquire pool");
+
+            let (admin, admin_token) =
+                create_authenticated_user(&mut conn, "admin", "password").await;
+            let (manager, manager_token) =
+                create_authenticated_user(&mut conn, "manager", "password").await;
+            let user = create_user(&mut conn, "user", "password").await;
+            let room = create_room(&mut conn, "room_name").await;
+            let admin_member = join_user(&mut conn, &admin, &room, true).await;
+            join_user(&mut conn, &manager, &room, false).await;
+            join_user(&mut conn, &user, &room, false).await;
+
+            let api = backend::api(pool);
+
+            let resp = request()
+                .method("POST")
+                .path(&format!("/api/rooms/{}/roles", room.uuid))
+                .header("Authorization", &admin_token)
+                .json(&CreateRole {
+                    name: "Everything".to_string(),
+                    permissions: Permissions::ALL,
+                })
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::CREATED);
+            let role =
+                serde_json::from_slice::<Role>(resp.body()).expect("failed to parse response");
+
+            let set_role = |member: Uuid, role: Uuid, token: &str| {
+                request()
+                    .method("PUT")
+                    .path(&format!("/api/rooms/{}/members/{}/role", room.uuid, member))
+                    .header("Authorization", token)
+                    .json(&SetMemberRole { role })
+            };
+
+            let resp = set_role(manager.uuid, role.uuid, &admin_token)
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::OK);
+
+            // having every permission doesn't make them an admin
+            let resp = set_role(user.uuid, admin_member.role.uuid, &manager_token)
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+            let resp = set_role(admin.uuid, role.uuid, &manager_token)
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+            // admins can do both
+            let resp = set_role(user.uuid, admin_member.role.uuid, &admin_token)
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::OK);
+
+            let resp = set_role(user.uuid, role.uuid, &admin_token)
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::OK);
+        })
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_cant_change_own_role() {
+    db(|pool| {
+        Box::pin(async {
+            let mut conn = pool.acquire().await.expect("can't acquire pool");
+
+            let (admin, admin_token) =
+                create_authenticated_user(&mut conn, "admin", "password").await;
+            let room = create_room(&mut conn, "room_name").await;
+            let member = join_user(&mut conn, &admin, &room, true).await;
+            let default_role = role_service::get_default(&mut conn, &room)
+                .await
+                .expect("failed to get role");
+
+            let api = backend::api(pool);
+
+            for role in &[default_role.uuid, member.role.uuid] {
+                let resp = request()
+                    .method("PUT")
+                    .path(&format!(
+                        "/api/rooms/{}/members/{}/role",
+                        room.uuid, admin.uuid
+                    ))
+                    .header("Authorization", &admin_token)
+                    .json(&SetMemberRole { role: *role })
+                    .reply(&api)
+                    .await;
+                assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+            }
+        })
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_owner_can_remove_admins() {
+    db(|pool| {
+        Box::pin(async {
+            let mut conn = pool.acquire().await.expect("can't acquire pool");
+
+            let (owner, owner_token) =
+                create_authenticated_user(&mut conn, "owner", "password").await;
+            let (admin, admin_token) =
+                create_authenticated_user(&mut conn, "admin", "password").await;
+            let other = create_user(&mut conn, "other", "password").await;
+            let room = create_owned_room(&mut conn, "room_name", &owner).await;
+            join_user(&mut conn, &admin, &room, true).await;
+            join_user(&mut conn, &other, &room, true).await;
+
+            let api = backend::api(pool);
+
+            let remove = |action: &str, token: &str, member: Uuid| {
+                request()
+                    .method("POST")
+                    .path(&format!("/api/rooms/{}/{}", room.uuid, action))
+                    .header("Authorization", token)
+                    .json(&RemoveMember {
+                        member,
+                        reason: None,
+                    })
+            };
+
+            // admins can't remove each other
+            let resp = remove("kick", &admin_token, other.uuid).reply(&api).await;
+            assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+            let resp = remove("bans", &admin_token, other.uuid).reply(&api).await;
+            assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+            let resp = remove("kick", &owner_token, other.uuid).reply(&api).await;
+            assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+            let resp = remove("bans", &owner_token, admin.uuid).reply(&api).await;
+            assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+
+            let resp = request()
+                .method("GET")
+                .path(&format!("/api/rooms/{}/members", room.uuid))
+                .header("Authorization", &owner_token)
+                .reply(&api)
+                .await;
+            let members = serde_json::from_slice::<Vec<RoomMember>>(resp.body())
+                .expect("failed to parse response");
+            assert_eq!(members.len(), 1);
+        })
+    })
+    .await
+}
@@ -0,0 +1,176 @@
+use crate::{create_authenticated_user, db};
+use common::payloads::{CreateCategory, CreateRoom, CreateSpace, DirectoryEntry, JoinMembers};
+use common::{Room, RoomVisibility, Space, SpaceCategory, SpaceMember};
+use warp::http::StatusCode;
+use warp::test::request;
+
+#[tokio::test]
+async fn test_spaces() {
+    db(|pool| {
+        Box::pin(async move {
+            let mut conn = pool.acquire().await.expect("can't acquire pool");
+
+            let (owner, owner_token) =
+                create_authenticated_user(&mut conn, "owner", "password").await;
+            let (member, member_token) =
+                create_authenticated_user(&mut conn, "member", "password").await;
+
+            let api = backend::api(pool.clone());
+
+            let resp = request()
+                .method("POST")
+                .path("/api/spaces")
+                .header("Authorization", &owner_token)
+                .json(&CreateSpace {
+                    name: "space_name".to_string(),
+                })
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::CREATED);
+            let space =
+                serde_json::from_slice::<Space>(resp.body()).expect("failed to parse response");
+            assert!(space.is_owner(owner.uuid));
+
+            let resp = request()
+                .method("POST")
+                .path(&format!("/api/spaces/{}/categories", space.uuid))
+                .header("Authorization", &owner_token)
+                .json(&CreateCategory {
+                    name: "category_name".to_string(),
+                })
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::CREATED);
+            let category = serde_json::from_slice::<SpaceCategory>(resp.body())
+                .expect("failed to parse response");
+
+            let create_room = |name: &str, visibility: RoomVisibility| {
+                request()
+                    .method("POST")
+                    .path("/api/rooms")
+                    .header("Authorization", &owner_token)
+                    .json(&CreateRoom {
+                        name: name.to_string(),
+                        visibility,
+                        space: Some(space.uuid),
+                        category: Some(category.uuid),
+                    })
+            };
+
+            let resp = create_room("public_room", RoomVisibility::Public)
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::CREATED);
+            let public_room =
+                serde_json::from_slice::<Room>(resp.body()).expect("failed to parse response");
+            assert_eq!(public_room.space, Some(space.uuid));
+            assert_eq!(public_room.category, Some(category.uuid));
+
+            let resp = create_room("private_room", RoomVisibility::Private)
+                .reply(&api)
+                .await;
+            let private_room =
+                serde_json::from_slice::<Room>(resp.body()).expect("failed to parse response");
+            assert_eq!(private_room.position, public_room.position + 1);
+
+            // the public rooms of a space are hidden from those outside of it
+            let resp = request()
+                .method("POST")
+                .path(&format!("/api/rooms/{}/members/me", public_room.uuid))
+                .header("Authorization", &member_token)
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+            let resp = request()
+                .method("GET")
+                .path("/api/rooms/public")
+                .header("Authorization", &member_token)
+                .reply(&api)
+                .await;
+            let entries = serde_json::from_slice::<Vec<DirectoryEntry>>(resp.body())
+                .expect("failed to parse response");
+            assert!(entries.is_empty());
+
+            let resp = request()
+                .method("POST")
+                .path(&format!("/api/spaces/{}/members", space.uuid))
+                .header("Authorization", &owner_token)
+                .json(&JoinMembers {
+                    member: member.uuid,
+                    role: None,
+                })
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::CREATED);
+            let space_member = serde_json::from_slice::<SpaceMember>(resp.body())
+                .expect("failed to parse response");
+            assert!(space_member.role.is_default);
+
+            // joining the space joins its public rooms, and only those
+            let get_members = |room: &Room| {
+                request()
+                    .method("GET")
+                    .path(&format!("/api/rooms/{}/members", room.uuid))
+                    .header("Authorization", &member_token)
+            };
+            let resp = get_members(&public_room).reply(&api).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+            let resp = get_members(&private_room).reply(&api).await;
+            assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+            let resp = request()
+                .method("GET")
+                .path(&format!("/api/rooms/public?space={}", space.uuid))
+                .header("Authorization", &member_token)
+                .reply(&api)
+                .await;
+            let entries = serde_json::from_slice::<Vec<DirectoryEntry>>(resp.body())
+                .expect("failed to parse response");
+            assert_eq!(entries.len(), 1);
+            assert_eq!(entries[0].room, public_room);
+
+            let resp = request()
+                .method("POST")
+                .path(&format!("/api/spaces/{}/categories", space.uuid))
+                .header("Authorization", &member_token)
+                .json(&CreateCategory {
+                    name: "other_category".to_string(),
+                })
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+            let resp = request()
+                .method("GET")
+                .path("/api/spaces")
+                .header("Authorization", &member_token)
+                .reply(&api)
+                .await;
+            let spaces = serde_json::from_slice::<Vec<Space>>(resp.body())
+                .expect("failed to parse response");
+            assert_eq!(spaces, vec![space.clone()]);
+            assert_eq!(spaces[0].categories, vec![category]);
+
+            let resp = request()
+                .method("POST")
+                .path(&format!("/api/spaces/{}/leave", space.uuid))
+                .header("Authorization", &member_token)
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+
+            let resp = get_members(&public_room).reply(&api).await;
+            assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+            let resp = request()
+                .method("POST")
+                .path(&format!("/api/spaces/{}/leave", space.uuid))
+                .header("Authorization", &owner_token)
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        })
+    })
+    .await
+}
@@ -1,8 +1,11 @@
-use crate::{create_authenticated_user, create_user, db};
-use common::User;
+use crate::{create_authenticated_user, create_room, create_user, db, join_user, send_message};
+use chrono::{Duration, Utc};
+use common::payloads::{JoinMembers, OpenDm};
+use common::{Message, User};
+use serde_json::{json, Value};
 use sqlx::types::Uuid;
 use warp::http::StatusCode;
-use warp::test::request;
+use warp::test::{request, ws, WsClient};
 
 #[tokio::test]
 async fn test_get_by_uuid() {
@@ -160,3 +163,258 @@ async fn test_get_me_with_bad_token() {
     })
     .await
 }
+
+#[tokio::test]
+async fn test_update_profile() {
+    db(|pool| {
+        Box::pin(async {
+            let mut conn = pool.acquire().await.expect("can't acquire pool");
+            let (user, token) = create_authenticated_user(&mut conn, "user", "password").await;
+
+            let api = backend::api(pool);
+
+            let expires_at = Utc::now() + Duration::hours(1);
+            let resp = request()
+                .method("PATCH")
+                .path("/api/users/me")
+                .header("Authorization", &token)
+                .json(&json!({
+                    "display_name": "User",
+                    "bio": "hello",
+                    "pronouns": "they/them",
+                    "timezone": "Europe/Berlin",
+                    "status": { "text": "busy", "expires_at": expires_at },
+                }))
+                .reply(&api)
+                .await;
+
+            let updated =
+                serde_json::from_slice::<User>(resp.body()).expect("failed to parse response");
+            assert_eq!(resp.status(), StatusCode::OK);
+            assert_eq!(updated.uuid, user.uuid);
+            assert_eq!(updated.display_name.as_deref(), Some("User"));
+            assert_eq!(updated.bio.as_deref(), Some("hello"));
+            assert_eq!(updated.pronouns.as_deref(), Some("they/them"));
+            assert_eq!(updated.timezone.as_deref(), Some("Europe/Berlin"));
+            assert_eq!(updated.status.unwrap().text, "busy");
+
+            // missing fields are kept, null clears them
+            let resp = request()
+                .method("PATCH")
+                .path("/api/users/me")
+                .header("Authorization", &token)
+                .json(&json!({ "bio": null, "status": null }))
+                .reply(&api)
+                .await;
+
+            let updated =
+                serde_json::from_slice::<User>(resp.body()).expect("failed to parse response");
+            assert_eq!(resp.status(), StatusCode::OK);
+            assert_eq!(updated.display_name.as_deref(), Some("User"));
+            assert_eq!(updated.bio, None);
+            assert_eq!(updated.status, None);
+        })
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_update_profile_invalid() {
+    db(|pool| {
+        Box::pin(async {
+            let mut conn = pool.acquire().await.expect("can't acquire pool");
+            let (_, token) = create_authenticated_user(&mut conn, "user", "password").await;
+
+            let api = backend::api(pool);
+
+            for body in vec![
+                json!({ "display_name": "a".repeat(100) }),
+                json!({ "bio": "   " }),
+                json!({ "timezone": "not a timezone" }),
+                json!({ "timezone": "Europe/Atlantis" }),
+                json!({ "status": { "text": "busy", "expires_at": Utc::now() - Duration::hours(1) } }),
+            ] {
+                let resp = request()
+                    .method("PATCH")
+                    .path("/api/users/me")
+                    .header("Authorization", &token)
+                    .json(&body)
+                    .reply(&api)
+                    .await;
+
+                assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+            }
+        })
+    })
+    .await
+}
+
+/// Receives websocket messages until one with the op comes
+async fn recv_op(client: &mut WsClient, op: u32) -> Value {
+    loop {
+        let message = client.recv().await.expect("failed to receive message");
+        if !message.is_text() {
+            continue;
+        }
+
+        let payload = serde_json::from_str::<Value>(message.to_str().unwrap()).unwrap();
+        if payload["op"] == op {
+            return payload;
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_update_profile_notifies_room_members() {
+    db(|pool| {
+        Box::pin(async {
+            let mut conn = pool.acquire().await.expect("can't acquire pool");
+            let (user, token) = create_authenticated_user(&mut conn, "user", "password").await;
+            let (other, other_token) =
+                create_authenticated_user(&mut conn, "other", "password").await;
+            let room = create_room(&mut conn, "room").await;
+            join_user(&mut conn, &user, &room, true).await;
+            join_user(&mut conn, &other, &room, false).await;
+
+            let api = backend::api(pool);
+
+            let mut client = ws()
+                .path("/api/ws")
+                .handshake(api.clone())
+                .await
+                .expect("handshake failed");
+            client
+                .send_text(json!({ "op": 100, "data": { "token": other_token } }).to_string())
+                .await;
+            recv_op(&mut client, 0).await;
+
+            let resp = request()
+                .method("PATCH")
+                .path("/api/users/me")
+                .header("Authorization", &token)
+                .json(&json!({ "display_name": "User" }))
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::OK);
+
+            let payload = recv_op(&mut client, 5).await;
+            assert_eq!(payload["data"]["uuid"], json!(user.uuid));
+            assert_eq!(payload["data"]["display_name"], "User");
+        })
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_block_user() {
+    db(|pool| {
+        Box::pin(async {
+            let mut conn = pool.acquire().await.expect("can't acquire pool");
+            let (alice, alice_token) =
+                create_authenticated_user(&mut conn, "alice", "password").await;
+            let (bob, bob_token) = create_authenticated_user(&mut conn, "bob", "password").await;
+            let room = create_room(&mut conn, "room").await;
+            join_user(&mut conn, &alice, &room, false).await;
+            join_user(&mut conn, &bob, &room, true).await;
+            send_message(&mut conn, "hello", &bob, &room).await;
+            let other_room = create_room(&mut conn, "other_room").await;
+            join_user(&mut conn, &bob, &other_room, true).await;
+
+            let api = backend::api(pool);
+            let block_path = format!("/api/users/me/blocks/{}", bob.uuid);
+            let messages = |token: &str| {
+                request()
+                    .method("GET")
+                    .path(&format!("/api/rooms/{}/messages", room.uuid))
+                    .header("Authorization", token)
+            };
+
+            let resp = request()
+                .method("PUT")
+                .path(&block_path)
+                .header("Authorization", &alice_token)
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+
+            let resp = request()
+                .method("GET")
+                .path("/api/users/me/blocks")
+                .header("Authorization", &alice_token)
+                .reply(&api)
+                .await;
+            let blocked =
+                serde_json::from_slice::<Vec<User>>(resp.body()).expect("failed to parse response");
+            assert_eq!(blocked.len(), 1);
+            assert_eq!(blocked[0].uuid, bob.uuid);
+
+            // the message is still there but without its content
+            let resp = messages(&alice_token).reply(&api).await;
+            let messages_seen = serde_json::from_slice::<Vec<Message>>(resp.body())
+                .expect("failed to parse response");
+            assert!(messages_seen[0].blocked);
+            assert!(messages_seen[0].content.is_empty());
+
+            // bob sees it as it is
+            let resp = messages(&bob_token).reply(&api).await;
+            let messages_seen = serde_json::from_slice::<Vec<Message>>(resp.body())
+                .expect("failed to parse response");
+            assert!(!messages_seen[0].blocked);
+            assert_eq!(messages_seen[0].content, "hello");
+
+            // bob can't reach alice anymore
+            let resp = request()
+                .method("POST")
+                .path("/api/dms")
+                .header("Authorization", &bob_token)
+                .json(&OpenDm {
+                    members: vec![alice.uuid],
+                })
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+            let resp = request()
+                .method("POST")
+                .path(&format!("/api/rooms/{}/join", other_room.uuid))
+                .header("Authorization", &bob_token)
+                .json(&JoinMembers {
+                    member: alice.uuid,
+                    role: None,
+                })
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+            let resp = request()
+                .method("DELETE")
+                .path(&block_path)
+                .header("Authorization", &alice_token)
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+
+            let resp = request()
+                .method("DELETE")
+                .path(&block_path)
+                .header("Authorization", &alice_token)
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+            let resp = messages(&alice_token).reply(&api).await;
+            let messages_seen = serde_json::from_slice::<Vec<Message>>(resp.body())
+                .expect("failed to parse response");
+            assert!(!messages_seen[0].blocked);
+
+            let resp = request()
+                .method("PUT")
+                .path(&format!("/api/users/me/blocks/{}", alice.uuid))
+                .header("Authorization", &alice_token)
+                .reply(&api)
+                .await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        })
+    })
+    .await
+}
@@ -0,0 +1,529 @@
+use crate::{create_authenticated_user, create_room, create_user, db, join_user, send_message};
+use backend::services;
+use backend::websocket::pubsub::{Event, Postgres, PubSub, Recipients};
+use common::payloads::{CreateReport, RemoveMember, SuspendUser};
+use common::websocket::{
+    AuthenticatePayload, Encoding, ErrorCode, ErrorPayload, Frame, MessagePayload, OpCode,
+    PresenceUpdatePayload, UpdatePresencePayload, PROTOCOL_VERSION,
+};
+use common::{Presence, Report, ReportScope, RoomMember};
+use serde_json::{json, Value};
+use sqlx::types::Uuid;
+use sqlx::PgPool;
+use tokio::time::Duration;
+use warp::http::StatusCode;
+use warp::test::{request, ws, WsClient};
+use warp::ws::Message;
+use warp::{Filter, Reply};
+
+async fn recv_json(client: &mut WsClient) -> Value {
+    loop {
+        let message = client.recv().await.expect("failed to receive message");
+        // skip heartbeat pings
+        if message.is_text() {
+            return serde_json::from_str(message.to_str().unwrap())
+                .expect("failed to parse message");
+        }
+    }
+}
+
+/// Waits for the server to close the socket.
+///
+/// The test client swallows the close frame, so its code can't be checked.
+async fn recv_closed(client: &mut WsClient) {
+    while let Ok(message) = client.recv().await {
+        // skip heartbeat pings
+        assert!(
+            message.is_ping(),
+            "received {:?} instead of closing",
+            message
+        );
+    }
+}
+
+async fn recv_payload(client: &mut WsClient) -> MessagePayload<Value> {
+    serde_json::from_value(recv_json(client).await).expect("invalid payload")
+}
+
+fn authenticate_payload(token: &str) -> MessagePayload<AuthenticatePayload> {
+    MessagePayload {
+        op: OpCode::Authenticate,
+        data: AuthenticatePayload {
+            token: token.to_string(),
+        },
+    }
+}
+
+fn authenticate(token: &str) -> String {
+    serde_json::to_string(&authenticate_payload(token)).unwrap()
+}
+
+#[tokio::test]
+async fn test_authenticate() {
+    db(|pool| {
+        Box::pin(async {
+            let mut conn = pool.acquire().await.expect("can't acquire pool");
+            let (_, token) = create_authenticated_user(&mut conn, "user", "password").await;
+
+            let api = backend::api(pool);
+            let mut client = ws()
+                .path(&format!("/api/ws?v={}", PROTOCOL_VERSION))
+                .handshake(api)
+                .await
+                .expect("handshake failed");
+
+            client.send_text(authenticate(&token)).await;
+            let json = recv_json(&mut client).await;
+
+            // opcodes are sent as integers
+            assert_eq!(json["op"], 0);
+            assert_eq!(json["data"]["me"]["username"], "user");
+        })
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_authenticate_with_message_pack() {
+    db(|pool| {
+        Box::pin(async {
+            let mut conn = pool.acquire().await.expect("can't acquire pool");
+            let (_, token) = create_authenticated_user(&mut conn, "user", "password").await;
+
+            let api = backend::api(pool);
+            let mut client = ws()
+                .path("/api/ws?encoding=msgpack")
+                .handshake(api)
+                .await
+                .expect("handshake failed");
+
+            let frame = Encoding::MessagePack
+                .encode(&authenticate_payload(&token))
+                .unwrap();
+            match frame {
+                Frame::Binary(bytes) => client.send(Message::binary(bytes)).await,
+                Frame::Text(_) => panic!("msgpack frames should be binary"),
+            }
+
+            let message = loop {
+                let message = client.recv().await.expect("failed to receive message");
+                if message.is_binary() {
+                    break message;
+                }
+            };
+
+            let payload = Encoding::MessagePack
+                .decode::<MessagePayload<Value>>(message.as_bytes())
+                .expect("failed to decode message");
+
+            assert_eq!(payload.op, OpCode::Authenticated);
+            assert_eq!(payload.data["me"]["username"], "user");
+        })
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_malformed_frame_keeps_connection() {
+    db(|pool| {
+        Box::pin(async {
+            let mut conn = pool.acquire().await.expect("can't acquire pool");
+            let (_, token) = create_authenticated_user(&mut conn, "user", "password").await;
+
+            let api = backend::api(pool);
+            let mut client = ws().path("/api/ws").handshake(api).await.unwrap();
+
+            client.send_text("this isn't json").await;
+            let payload = recv_payload(&mut client).await;
+            let error = serde_json::from_value::<ErrorPayload>(payload.data).unwrap();
+
+            assert_eq!(payload.op, OpCode::BadPayload);
+            assert_eq!(error.code, ErrorCode::MalformedFrame);
+
+            client.send_text(r#"{"op": 5, "data": null}"#).await;
+            let payload = recv_payload(&mut client).await;
+            let error = serde_json::from_value::<ErrorPayload>(payload.data).unwrap();
+
+            assert_eq!(payload.op, OpCode::InvalidOp);
+            assert_eq!(error.code, ErrorCode::UnknownOp);
+
+            // the connection is still usable
+            client.send_text(authenticate(&token)).await;
+            let payload = recv_payload(&mut client).await;
+
+            assert_eq!(payload.op, OpCode::Authenticated);
+        })
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_invalid_token_closes_connection() {
+    db(|pool| {
+        Box::pin(async {
+            let api = backend::api(pool);
+            let mut client = ws().path("/api/ws").handshake(api).await.unwrap();
+
+            client.send_text(authenticate("not a token")).await;
+            let payload = recv_payload(&mut client).await;
+            let error = serde_json::from_value::<ErrorPayload>(payload.data).unwrap();
+
+            assert_eq!(payload.op, OpCode::BadPayload);
+            assert_eq!(error.code, ErrorCode::AuthenticationFailed);
+
+            recv_closed(&mut client).await;
+        })
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_suspension_closes_connection() {
+    db(|pool| {
+        Box::pin(async move {
+            let mut conn = pool.acquire().await.expect("can't acquire pool");
+            let (user, token) = create_authenticated_user(&mut conn, "user", "password").await;
+
+            let api = backend::api(pool.clone());
+            let mut client = ws().path("/api/ws").handshake(api).await.unwrap();
+
+            client.send_text(authenticate(&token)).await;
+            let payload = recv_payload(&mut client).await;
+            assert_eq!(payload.op, OpCode::Authenticated);
+            assert_eq!(recv_presence(&mut client).await.user, user.uuid);
+
+            let (admin, admin_token) =
+                create_authenticated_user(&mut conn, "admin", "password").await;
+            services::admin::set_instance_admin(&mut conn, &admin, true)
+                .await
+                .expect("failed to make user an instance admin");
+            let resp = request()
+                .method("PUT")
+                .path(&format!("/api/admin/users/{}/suspension", user.uuid))
+                .header("Authorization", &admin_token)
+                .json(&SuspendUser { reason: None })
+                .reply(&backend::api(pool.clone()))
+                .await;
+            assert_eq!(resp.status(), StatusCode::OK);
+
+            recv_closed(&mut client).await;
+
+            // and it can't authenticate again
+            let api = backend::api(pool.clone());
+            let mut client = ws().path("/api/ws").handshake(api).await.unwrap();
+
+            client.send_text(authenticate(&token)).await;
+            let payload = recv_payload(&mut client).await;
+            let error = serde_json::from_value::<ErrorPayload>(payload.data).unwrap();
+            assert_eq!(error.code, ErrorCode::AuthenticationFailed);
+        })
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_kick_sends_room_leave() {
+    db(|pool| {
+        Box::pin(async move {
+            let mut conn = pool.acquire().await.expect("can't acquire pool");
+            let (admin, admin_token) =
+                create_authenticated_user(&mut conn, "admin", "password").await;
+            let (member, token) = create_authenticated_user(&mut conn, "member", "password").await;
+            let room = create_room(&mut conn, "room_name").await;
+            join_user(&mut conn, &admin, &room, true).await;
+            join_user(&mut conn, &member, &room, false).await;
+
+            let api = backend::api(pool.clone());
+            let mut client = ws().path("/api/ws").handshake(api).await.unwrap();
+
+            client.send_text(authenticate(&token)).await;
+            let payload = recv_payload(&mut client).await;
+            assert_eq!(payload.op, OpCode::Authenticated);
+            assert_eq!(recv_presence(&mut client).await.user, member.uuid);
+
+            let resp = request()
+                .method("POST")
+                .path(&format!("/api/rooms/{}/kick", room.uuid))
+                .header("Authorization", &admin_token)
+                .json(&RemoveMember {
+                    member: member.uuid,
+                    reason: None,
+                })
+                .reply(&backend::api(pool.clone()))
+                .await;
+            assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+
+            // only sent once the kick is committed
+            let payload = recv_payload(&mut client).await;
+            assert_eq!(payload.op, OpCode::RoomLeave);
+            assert_eq!(payload.data["uuid"], json!(room.uuid));
+        })
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_instance_reports_go_to_instance_admins() {
+    db(|pool| {
+        Box::pin(async move {
+            let mut conn = pool.acquire().await.expect("can't acquire pool");
+            let (admin, admin_token) =
+                create_authenticated_user(&mut conn, "admin", "password").await;
+            services::admin::set_instance_admin(&mut conn, &admin, true)
+                .await
+                .expect("failed to make user an instance admin");
+            let (member, member_token) =
+                create_authenticated_user(&mut conn, "member", "password").await;
+            let spammer = create_user(&mut conn, "spammer", "password").await;
+            let room = create_room(&mut conn, "room_name").await;
+            join_user(&mut conn, &member, &room, false).await;
+            join_user(&mut conn, &spammer, &room, false).await;
+            let message = send_message(&mut conn, "buy cheap stuff", &spammer, &room).await;
+
+            let api = backend::api(pool.clone());
+            let mut client = ws().path("/api/ws").handshake(api).await.unwrap();
+
+            client.send_text(authenticate(&admin_token)).await;
+            let payload = recv_payload(&mut client).await;
+            assert_eq!(payload.op, OpCode::Authenticated);
+            assert_eq!(recv_presence(&mut client).await.user, admin.uuid);
+
+            let resp = request()
+                .method("POST")
+                .path(&format!(
+                    "/api/rooms/{}/messages/{}/reports",
+                    room.uuid, message.uuid
+                ))
+                .header("Authorization", &member_token)
+                .json(&CreateReport {
+                    reason: "spam".to_string(),
+                    scope: ReportScope::Instance,
+                })
+                .reply(&backend::api(pool.clone()))
+                .await;
+            assert_eq!(resp.status(), StatusCode::CREATED);
+            let report =
+                serde_json::from_slice::<Report>(resp.body()).expect("failed to parse response");
+
+            let payload = recv_payload(&mut client).await;
+            assert_eq!(payload.op, OpCode::ReportCreate);
+            assert_eq!(payload.data["uuid"], json!(report.uuid));
+        })
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_unsupported_version_closes_connection() {
+    db(|pool| {
+        Box::pin(async {
+            let api = backend::api(pool);
+            let mut client = ws()
+                .path(&format!("/api/ws?v={}", PROTOCOL_VERSION + 1))
+                .handshake(api)
+                .await
+                .unwrap();
+
+            let payload = recv_payload(&mut client).await;
+            let error = serde_json::from_value::<ErrorPayload>(payload.data).unwrap();
+
+            assert_eq!(error.code, ErrorCode::UnsupportedVersion);
+
+            recv_closed(&mut client).await;
+        })
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_postgres_pubsub_delivers_events() {
+    db(|pool| {
+        Box::pin(async {
+            let mut conn = pool.acquire().await.expect("can't acquire pool");
+            let (user, token) = create_authenticated_user(&mut conn, "user", "password").await;
+
+            let pubsub = Postgres::new(pool.clone()).await.expect("failed to listen");
+
+            let api = backend::api(pool);
+            let mut client = ws().path("/api/ws").handshake(api).await.unwrap();
+            client.send_text(authenticate(&token)).await;
+            let payload = recv_payload(&mut client).await;
+            assert_eq!(payload.op, OpCode::Authenticated);
+            let payload = recv_payload(&mut client).await;
+            assert_eq!(payload.op, OpCode::PresenceUpdate);
+
+            // the second one is too large to fit in a notification
+            for content in vec!["small".to_string(), "large".repeat(5000)] {
+                let event = Event::Dispatch {
+                    recipients: Recipients::Users(vec![user.uuid]),
+                    payload: MessagePayload {
+                        op: OpCode::UserUpdate,
+                        data: json!({ "content": content }),
+                    },
+                };
+                pubsub.publish(event).await.expect("failed to publish");
+
+                let payload = recv_payload(&mut client).await;
+                assert_eq!(payload.op, OpCode::UserUpdate);
+                assert_eq!(payload.data["content"], content);
+            }
+        })
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_queue_metrics() {
+    db(|pool| {
+        Box::pin(async {
+            let mut conn = pool.acquire().await.expect("can't acquire pool");
+            let (user, token) = create_authenticated_user(&mut conn, "user", "password").await;
+
+            let api = backend::api(pool);
+            let mut client = ws().path("/api/ws").handshake(api.clone()).await.unwrap();
+            client.send_text(authenticate(&token)).await;
+            let payload = recv_payload(&mut client).await;
+            assert_eq!(payload.op, OpCode::Authenticated);
+
+            let metrics = || {
+                request()
+                    .method("GET")
+                    .path("/api/ws/metrics")
+                    .header("Authorization", &token)
+            };
+
+            let resp = metrics().reply(&api).await;
+            assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+            services::admin::set_instance_admin(&mut conn, &user, true)
+                .await
+                .expect("failed to make instance admin");
+            let resp = metrics().reply(&api).await;
+            let metrics = serde_json::from_slice::<Value>(resp.body()).unwrap();
+
+            assert_eq!(resp.status(), StatusCode::OK);
+            assert!(metrics["sessions"].as_u64().unwrap() >= 1);
+            assert!(metrics["queued"].as_u64().unwrap() <= metrics["capacity"].as_u64().unwrap());
+        })
+    })
+    .await
+}
+
+async fn recv_presence(client: &mut WsClient) -> PresenceUpdatePayload {
+    let payload = recv_payload(client).await;
+    assert_eq!(payload.op, OpCode::PresenceUpdate);
+    serde_json::from_value(payload.data).expect("invalid presence update")
+}
+
+fn update_presence(idle: bool) -> String {
+    serde_json::to_string(&MessagePayload {
+        op: OpCode::UpdatePresence,
+        data: UpdatePresencePayload { idle },
+    })
+    .unwrap()
+}
+
+/// Waits for the server to have handled the user's session changes
+async fn wait_for_sessions(pool: &PgPool, user: Uuid, sessions: i64, idle: i64) {
+    loop {
+        let counts: (i64, i64) = sqlx::query_as(
+            "select count(*), count(*) filter (where idle) from user_sessions where user_id = $1;",
+        )
+        .bind(user)
+        .fetch_one(pool)
+        .await
+        .expect("failed to count sessions");
+
+        if counts == (sessions, idle) {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+}
+
+async fn member_presence<F>(api: &F, room: Uuid, token: &str, user: Uuid) -> Presence
+where
+    F: Filter + 'static,
+    F::Extract: Reply + Send,
+{
+    let resp = request()
+        .method("GET")
+        .path(&format!("/api/rooms/{}/members", room))
+        .header("Authorization", token)
+        .reply(api)
+        .await;
+
+    serde_json::from_slice::<Vec<RoomMember>>(resp.body())
+        .expect("failed to parse response")
+        .into_iter()
+        .find(|member| member.user.uuid == user)
+        .expect("user isn't a member")
+        .presence
+}
+
+#[tokio::test]
+async fn test_presence() {
+    db(|pool| {
+        Box::pin(async move {
+            let mut conn = pool.acquire().await.expect("can't acquire pool");
+            let (watcher, watcher_token) =
+                create_authenticated_user(&mut conn, "watcher", "password").await;
+            let (user, token) = create_authenticated_user(&mut conn, "user", "password").await;
+            let room = create_room(&mut conn, "room").await;
+            join_user(&mut conn, &watcher, &room, true).await;
+            join_user(&mut conn, &user, &room, false).await;
+
+            let api = backend::api(pool.clone());
+
+            let mut watcher_client = ws().path("/api/ws").handshake(api.clone()).await.unwrap();
+            watcher_client.send_text(authenticate(&watcher_token)).await;
+            assert_eq!(
+                recv_payload(&mut watcher_client).await.op,
+                OpCode::Authenticated
+            );
+            assert_eq!(recv_presence(&mut watcher_client).await.user, watcher.uuid);
+
+            let presence = || member_presence(&api, room.uuid, &watcher_token, user.uuid);
+            assert_eq!(presence().await, Presence::Offline);
+
+            let mut first = ws().path("/api/ws").handshake(api.clone()).await.unwrap();
+            first.send_text(authenticate(&token)).await;
+            assert_eq!(recv_payload(&mut first).await.op, OpCode::Authenticated);
+
+            let update = recv_presence(&mut watcher_client).await;
+            assert_eq!(update.user, user.uuid);
+            assert_eq!(update.presence, Presence::Online);
+            assert_eq!(presence().await, Presence::Online);
+
+            let mut second = ws().path("/api/ws").handshake(api.clone()).await.unwrap();
+            second.send_text(authenticate(&token)).await;
+            assert_eq!(recv_payload(&mut second).await.op, OpCode::Authenticated);
+            wait_for_sessions(&pool, user.uuid, 2, 0).await;
+
+            // the user is only idle once every session is
+            first.send_text(update_presence(true)).await;
+            wait_for_sessions(&pool, user.uuid, 2, 1).await;
+            assert_eq!(presence().await, Presence::Online);
+
+            second.send_text(update_presence(true)).await;
+            let update = recv_presence(&mut watcher_client).await;
+            assert_eq!(update.presence, Presence::Idle);
+            assert_eq!(presence().await, Presence::Idle);
+
+            second.send_text(update_presence(false)).await;
+            let update = recv_presence(&mut watcher_client).await;
+            assert_eq!(update.presence, Presence::Online);
+
+            // and offline once every session is closed
+            drop(first);
+            wait_for_sessions(&pool, user.uuid, 1, 0).await;
+            assert_eq!(presence().await, Presence::Online);
+
+            drop(second);
+            let update = recv_presence(&mut watcher_client).await;
+            assert_eq!(update.user, user.uuid);
+            assert_eq!(update.presence, Presence::Offline);
+        })
+    })
+    .await
+}
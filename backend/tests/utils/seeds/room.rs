@@ -1,33 +1,66 @@
-use backend::services::{message as message_service, room as room_service};
-use common::{Message, Room, RoomMember, User};
+use backend::services::{message as message_service, role as role_service, room as room_service};
+use backend::websocket;
+use common::{Message, Room, RoomMember, RoomVisibility, User};
 use sqlx::PgConnection;
 
 pub async fn create_room(conn: &mut PgConnection, name: &str) -> Room {
     let room = Room::new(name);
-    room_service::create(conn, room)
+    let (room, joined) = room_service::create(conn, room)
         .await
-        .expect("failed to create room")
+        .expect("failed to create room");
+    websocket::send_deferred(joined).await;
+    room
+}
+
+pub async fn create_public_room(conn: &mut PgConnection, name: &str) -> Room {
+    let mut room = Room::new(name);
+    room.visibility = RoomVisibility::Public;
+    let (room, joined) = room_service::create(conn, room)
+        .await
+        .expect("failed to create room");
+    websocket::send_deferred(joined).await;
+    room
+}
+
+/// Creates a room owned by the user, who joins it as an admin
+pub async fn create_owned_room(conn: &mut PgConnection, name: &str, owner: &User) -> Room {
+    let mut room = Room::new(name);
+    room.owner = Some(owner.uuid);
+    let (room, joined) = room_service::create(conn, room)
+        .await
+        .expect("failed to create room");
+    websocket::send_deferred(joined).await;
+    room
 }
 
 pub async fn join_user(
     conn: &mut PgConnection,
     user: &User,
     room: &Room,
-    has_elevated_perms: bool,
+    as_admin: bool,
 ) -> RoomMember {
-    room_service::join(conn, room, user, has_elevated_perms)
+    let role = if as_admin {
+        role_service::get_admin(conn, room).await
+    } else {
+        role_service::get_default(conn, room).await
+    };
+    let role = role.expect("failed to get role");
+
+    let (member, joined) = room_service::join(conn, room, user, &role, user)
         .await
-        .expect("failed to create room")
+        .expect("failed to create room");
+    websocket::send_deferred(Some(joined)).await;
+    member
 }
 
 pub async fn create_room_with_user(
     conn: &mut PgConnection,
     name: &str,
     user: &User,
-    has_elevated_perms: bool,
+    as_admin: bool,
 ) -> (Room, RoomMember) {
     let room = create_room(conn, name).await;
-    let member = join_user(conn, user, &room, has_elevated_perms).await;
+    let member = join_user(conn, user, &room, as_admin).await;
 
     (room, member)
 }
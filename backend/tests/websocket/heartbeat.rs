@@ -0,0 +1,68 @@
+use backend::websocket::heartbeat::{Heartbeat, HeartbeatConfig, Stopped};
+use std::cell::Cell;
+use std::rc::Rc;
+use tokio::time::{Duration, Instant};
+
+fn config() -> HeartbeatConfig {
+    // the timeout isn't a multiple of the interval, so no tick lands right on it
+    HeartbeatConfig {
+        interval: Duration::from_secs(5),
+        timeout: Duration::from_secs(12),
+    }
+}
+
+#[tokio::test]
+async fn test_idle_client_times_out() {
+    tokio::time::pause();
+    let config = config();
+    let start = Instant::now();
+
+    let pings = Rc::new(Cell::new(0));
+    let stopped = {
+        let pings = Rc::clone(&pings);
+        Heartbeat::new(config)
+            .run(move || {
+                pings.set(pings.get() + 1);
+                Ok(())
+            })
+            .await
+    };
+
+    assert_eq!(stopped, Stopped::TimedOut);
+    assert!(start.elapsed() > config.timeout);
+    assert!(start.elapsed() <= config.timeout + config.interval);
+    // pinged right away and on every interval until the timeout
+    assert_eq!(pings.get(), 3);
+}
+
+#[tokio::test]
+async fn test_healthy_client_is_kept() {
+    tokio::time::pause();
+    let config = config();
+
+    let heartbeat = Heartbeat::new(config);
+    let mut task = tokio::task::spawn(heartbeat.clone().run(|| Ok(())));
+
+    // answer every ping for much longer than the timeout
+    for _ in 0..20 {
+        tokio::time::sleep(config.interval).await;
+        heartbeat.beat();
+    }
+    let still_running = tokio::time::timeout(config.interval, &mut task).await;
+    assert!(still_running.is_err());
+
+    // then stop answering
+    let stopped = task.await.expect("heartbeat task panicked");
+    assert_eq!(stopped, Stopped::TimedOut);
+}
+
+#[tokio::test]
+async fn test_stops_when_session_is_closed() {
+    tokio::time::pause();
+
+    let stopped = Heartbeat::new(config())
+        .run(|| Err(anyhow::anyhow!("session closed")))
+        .await;
+
+    assert_eq!(stopped, Stopped::Closed);
+}
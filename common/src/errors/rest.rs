@@ -1,6 +1,7 @@
 use http::StatusCode;
 use serde::{Deserialize, Serialize};
 use std::fmt;
+use std::time::Duration;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ApiError {
@@ -10,6 +11,10 @@ pub struct ApiError {
     #[serde(skip_serializing_if = "String::is_empty")]
     #[serde(default)]
     pub message: String,
+    /// Seconds to wait before trying again, set on `429 Too Many Requests`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub retry_after: Option<f64>,
 }
 
 impl ApiError {
@@ -17,11 +22,7 @@ impl ApiError {
     /// 500 [Internal Server Error][StatusCode::INTERNAL_SERVER_ERROR] error code
     pub fn new_with_message(message: &str) -> Self {
         let status = StatusCode::INTERNAL_SERVER_ERROR;
-        Self {
-            status,
-            message: message.to_string(),
-            title: title(status),
-        }
+        Self::new_with_message_and_status(message, status)
     }
 
     /// Creates a new `ApiError` with provided message and status
@@ -30,6 +31,16 @@ impl ApiError {
             status,
             message: message.to_string(),
             title: title(status),
+            retry_after: None,
+        }
+    }
+
+    /// Creates a new [Too Many Requests][StatusCode::TOO_MANY_REQUESTS] `ApiError`
+    /// telling the client how long to wait
+    pub fn new_rate_limited(message: &str, retry_after: Duration) -> Self {
+        Self {
+            retry_after: Some(retry_after.as_secs_f64()),
+            ..Self::new_with_message_and_status(message, StatusCode::TOO_MANY_REQUESTS)
         }
     }
 }
@@ -52,7 +63,17 @@ impl fmt::Display for ApiError {
 #[cfg(not(target_arch = "wasm32"))]
 impl warp::Reply for ApiError {
     fn into_response(self) -> warp::reply::Response {
-        warp::reply::with_status(warp::reply::json(&self), self.status).into_response()
+        let retry_after = self.retry_after;
+        let mut response =
+            warp::reply::with_status(warp::reply::json(&self), self.status).into_response();
+        if let Some(retry_after) = retry_after {
+            // the header only takes whole seconds
+            response.headers_mut().insert(
+                http::header::RETRY_AFTER,
+                http::HeaderValue::from(retry_after.ceil() as u64),
+            );
+        }
+        response
     }
 }
 
@@ -0,0 +1,50 @@
+use crate::{Room, User};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// A user as instance admins see them, with the state of their account
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct UserAccount {
+    pub user: User,
+    /// When the user was suspended, `None` unless they are
+    pub suspended_at: Option<DateTime<Utc>>,
+    pub suspension_reason: Option<String>,
+    /// The user has to change their password before they can sign in again
+    pub password_reset_required: bool,
+}
+
+impl UserAccount {
+    pub fn is_suspended(&self) -> bool {
+        self.suspended_at.is_some()
+    }
+}
+
+impl PartialEq for UserAccount {
+    fn eq(&self, other: &Self) -> bool {
+        self.user == other.user
+    }
+}
+
+/// A room with how busy it is, for instance admins
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RoomOverview {
+    pub room: Room,
+    pub member_count: i64,
+    pub message_count: i64,
+}
+
+/// Counts across the whole instance
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct InstanceStats {
+    pub users: i64,
+    pub suspended_users: i64,
+    pub rooms: i64,
+    pub spaces: i64,
+    pub messages: i64,
+    /// Messages sent in the last 24 hours
+    pub recent_messages: i64,
+    /// Users with a live websocket session
+    pub online_users: i64,
+    /// Reports escalated to instance admins that are still open
+    pub open_reports: i64,
+}
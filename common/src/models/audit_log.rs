@@ -0,0 +1,68 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Something a member did with their permissions in a room
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AuditLogEntry {
+    pub uuid: Uuid,
+    pub room: Uuid,
+    /// The user who did it, `None` once their account is deleted
+    pub actor: Option<Uuid>,
+    /// The member or role it was done to, if any
+    pub target: Option<Uuid>,
+    pub action: AuditAction,
+    /// Details of the action, changed fields are `{"field": {"old": .., "new": ..}}`
+    pub changes: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+}
+
+impl PartialEq for AuditLogEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.uuid == other.uuid
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(not(target_arch = "wasm32"), derive(sqlx::Type))]
+#[cfg_attr(not(target_arch = "wasm32"), sqlx(rename = "audit_action"))]
+pub enum AuditAction {
+    /// The name, topic, visibility, slow mode or icon of the room changed
+    #[cfg_attr(not(target_arch = "wasm32"), sqlx(rename = "room_update"))]
+    RoomUpdate,
+    #[cfg_attr(not(target_arch = "wasm32"), sqlx(rename = "room_archive"))]
+    RoomArchive,
+    #[cfg_attr(not(target_arch = "wasm32"), sqlx(rename = "room_unarchive"))]
+    RoomUnarchive,
+    /// The target is the new owner
+    #[cfg_attr(not(target_arch = "wasm32"), sqlx(rename = "ownership_transfer"))]
+    OwnershipTransfer,
+    /// The target joined the room, the actor is whoever added them
+    #[cfg_attr(not(target_arch = "wasm32"), sqlx(rename = "member_join"))]
+    MemberJoin,
+    #[cfg_attr(not(target_arch = "wasm32"), sqlx(rename = "member_kick"))]
+    MemberKick,
+    #[cfg_attr(not(target_arch = "wasm32"), sqlx(rename = "member_ban"))]
+    MemberBan,
+    #[cfg_attr(not(target_arch = "wasm32"), sqlx(rename = "member_unban"))]
+    MemberUnban,
+    #[cfg_attr(not(target_arch = "wasm32"), sqlx(rename = "member_role_update"))]
+    MemberRoleUpdate,
+    #[cfg_attr(not(target_arch = "wasm32"), sqlx(rename = "role_create"))]
+    RoleCreate,
+    #[cfg_attr(not(target_arch = "wasm32"), sqlx(rename = "role_update"))]
+    RoleUpdate,
+    #[cfg_attr(not(target_arch = "wasm32"), sqlx(rename = "role_delete"))]
+    RoleDelete,
+    #[cfg_attr(not(target_arch = "wasm32"), sqlx(rename = "invite_create"))]
+    InviteCreate,
+    #[cfg_attr(not(target_arch = "wasm32"), sqlx(rename = "invite_revoke"))]
+    InviteRevoke,
+    /// The target is the author of the message
+    #[cfg_attr(not(target_arch = "wasm32"), sqlx(rename = "message_delete"))]
+    MessageDelete,
+    /// The target is the author of the reported message
+    #[cfg_attr(not(target_arch = "wasm32"), sqlx(rename = "report_resolve"))]
+    ReportResolve,
+}
@@ -0,0 +1,79 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// A content rule new messages are checked against before they're posted
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AutomodRule {
+    pub uuid: Uuid,
+    /// The room the rule is for, `None` for rules of the whole instance
+    pub room: Option<Uuid>,
+    pub kind: AutomodRuleKind,
+    /// The words, regexes or domains of the rule, empty for `MaxMentions`
+    pub patterns: Vec<String>,
+    /// Only set for `MaxMentions`
+    pub max_mentions: Option<i32>,
+    pub action: AutomodAction,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl AutomodRule {
+    /// What a message breaking the rule contains, for errors and reports
+    pub fn describe(&self) -> String {
+        match self.kind {
+            AutomodRuleKind::BannedWords => "a banned word".to_string(),
+            AutomodRuleKind::Regex => "a banned expression".to_string(),
+            AutomodRuleKind::LinkAllowList | AutomodRuleKind::LinkDenyList => {
+                "a link that isn't allowed".to_string()
+            }
+            AutomodRuleKind::MaxMentions => {
+                format!("more than {} mentions", self.max_mentions.unwrap_or(0))
+            }
+        }
+    }
+}
+
+impl PartialEq for AutomodRule {
+    fn eq(&self, other: &Self) -> bool {
+        self.uuid == other.uuid
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(not(target_arch = "wasm32"), derive(sqlx::Type))]
+#[cfg_attr(not(target_arch = "wasm32"), sqlx(rename = "automod_rule_kind"))]
+pub enum AutomodRuleKind {
+    /// Whole words, regardless of case
+    #[cfg_attr(not(target_arch = "wasm32"), sqlx(rename = "banned_words"))]
+    BannedWords,
+    #[cfg_attr(not(target_arch = "wasm32"), sqlx(rename = "regex"))]
+    Regex,
+    /// Links are only allowed to these domains and their subdomains
+    #[cfg_attr(not(target_arch = "wasm32"), sqlx(rename = "link_allow_list"))]
+    LinkAllowList,
+    /// Links to these domains and their subdomains aren't allowed
+    #[cfg_attr(not(target_arch = "wasm32"), sqlx(rename = "link_deny_list"))]
+    LinkDenyList,
+    /// More `@username` mentions than `max_mentions` in a single message
+    #[cfg_attr(not(target_arch = "wasm32"), sqlx(rename = "max_mentions"))]
+    MaxMentions,
+}
+
+/// What happens to messages breaking a rule
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(not(target_arch = "wasm32"), derive(sqlx::Type))]
+#[cfg_attr(not(target_arch = "wasm32"), sqlx(rename = "automod_action"))]
+pub enum AutomodAction {
+    /// The message isn't posted
+    #[cfg_attr(not(target_arch = "wasm32"), sqlx(rename = "reject"))]
+    Reject,
+    /// The offending parts are replaced with `*`
+    #[cfg_attr(not(target_arch = "wasm32"), sqlx(rename = "mask"))]
+    Mask,
+    /// The message is posted and reported for review
+    #[cfg_attr(not(target_arch = "wasm32"), sqlx(rename = "flag"))]
+    Flag,
+}
@@ -0,0 +1,32 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// A code that adds whoever redeems it to a room
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Invite {
+    pub code: String,
+    pub room: Uuid,
+    pub created_by: Option<Uuid>,
+    /// Given to those who join with the invite, the default role of the room if not set
+    pub role: Option<Uuid>,
+    pub max_uses: Option<i32>,
+    pub uses: i32,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Invite {
+    /// Whether the invite has expired or ran out of uses
+    pub fn is_expired(&self) -> bool {
+        let out_of_uses = matches!(self.max_uses, Some(max_uses) if self.uses >= max_uses);
+        let expired = matches!(self.expires_at, Some(expires_at) if expires_at <= Utc::now());
+        out_of_uses || expired
+    }
+}
+
+impl PartialEq for Invite {
+    fn eq(&self, other: &Self) -> bool {
+        self.code == other.code
+    }
+}
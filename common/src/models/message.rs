@@ -3,6 +3,7 @@ use chrono::{DateTime, Utc};
 use serde::export::TryFrom;
 use serde::{Deserialize, Serialize};
 use std::fmt;
+use std::ops::Range;
 use std::str::FromStr;
 use uuid::Uuid;
 
@@ -16,6 +17,9 @@ pub struct Message {
     #[serde(with = "message_type_serializer_deserializer")]
     #[serde(rename = "type")]
     pub type_: MessageType,
+    /// The author is blocked by whoever the message was sent to, its content is left out
+    #[serde(default)]
+    pub blocked: bool,
 }
 
 impl Message {
@@ -27,6 +31,7 @@ impl Message {
             room,
             created_at: Utc::now(),
             type_: MessageType::Default,
+            blocked: false,
         }
     }
 
@@ -38,8 +43,39 @@ impl Message {
             room,
             created_at: Utc::now(),
             type_,
+            blocked: false,
         }
     }
+
+    /// Whether the message mentions the user with `@username`
+    pub fn mentions(&self, user: &User) -> bool {
+        parse_mentions(&self.content).any(|(_, username)| username == user.username)
+    }
+}
+
+/// The `@username` mentions in `content` along with where they are in it.
+///
+/// Mentions start a word and go on for as long as there are letters, digits or underscores.
+pub fn parse_mentions(content: &str) -> impl Iterator<Item = (Range<usize>, &str)> {
+    content
+        .char_indices()
+        .filter(move |(i, c)| {
+            *c == '@'
+                && content[..*i]
+                    .chars()
+                    .next_back()
+                    .map_or(true, char::is_whitespace)
+        })
+        .filter_map(move |(start, _)| {
+            let username = &content[start + 1..];
+            let len = username
+                .find(|c: char| !c.is_alphanumeric() && c != '_')
+                .unwrap_or(username.len());
+            if len == 0 {
+                return None;
+            }
+            Some((start..start + 1 + len, &username[..len]))
+        })
 }
 
 impl PartialEq for Message {
@@ -58,6 +94,13 @@ pub enum MessageType {
     RoomJoin,
     #[cfg_attr(not(target_arch = "wasm32"), sqlx(rename = "room_leave"))]
     RoomLeave,
+    #[cfg_attr(not(target_arch = "wasm32"), sqlx(rename = "room_kick"))]
+    RoomKick,
+    #[cfg_attr(not(target_arch = "wasm32"), sqlx(rename = "room_ban"))]
+    RoomBan,
+    /// The content is the new name of the room
+    #[cfg_attr(not(target_arch = "wasm32"), sqlx(rename = "room_rename"))]
+    RoomRename,
 }
 
 pub struct ParseMessageTypeError(String);
@@ -70,6 +113,9 @@ impl FromStr for MessageType {
             "default" => Ok(MessageType::Default),
             "room_join" => Ok(MessageType::RoomJoin),
             "room_leave" => Ok(MessageType::RoomLeave),
+            "room_kick" => Ok(MessageType::RoomKick),
+            "room_ban" => Ok(MessageType::RoomBan),
+            "room_rename" => Ok(MessageType::RoomRename),
             _ => Err(ParseMessageTypeError(s.to_string())),
         }
     }
@@ -89,6 +135,9 @@ impl fmt::Display for MessageType {
             MessageType::Default => "DEFAULT",
             MessageType::RoomJoin => "ROOM_JOIN",
             MessageType::RoomLeave => "ROOM_LEAVE",
+            MessageType::RoomKick => "ROOM_KICK",
+            MessageType::RoomBan => "ROOM_BAN",
+            MessageType::RoomRename => "ROOM_RENAME",
         };
 
         write!(f, "{}", s)
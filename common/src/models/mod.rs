@@ -1,12 +1,32 @@
+mod admin;
 mod asset;
+mod audit_log;
+mod automod;
+mod invite;
 mod message;
+mod notification;
+mod presence;
+mod report;
+mod role;
 mod room;
 mod room_member;
+mod space;
 mod user;
 pub mod websocket;
 
+pub use admin::{InstanceStats, RoomOverview, UserAccount};
 pub use asset::Asset;
-pub use message::{Message, MessageType};
-pub use room::Room;
+pub use audit_log::{AuditAction, AuditLogEntry};
+pub use automod::{AutomodAction, AutomodRule, AutomodRuleKind};
+pub use invite::Invite;
+pub use message::{parse_mentions, Message, MessageType};
+pub use notification::{NotificationLevel, NotificationSettings};
+pub use presence::Presence;
+pub use report::{Report, ReportAction, ReportScope};
+pub use role::{Permissions, Role};
+pub use room::{
+    PostPermission, Room, RoomKind, RoomVisibility, MAX_GROUP_DM_MEMBERS, MAX_SLOW_MODE,
+};
 pub use room_member::RoomMember;
-pub use user::User;
+pub use space::{Space, SpaceCategory, SpaceMember, SpaceRole};
+pub use user::{CustomStatus, User};
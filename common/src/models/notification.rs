@@ -0,0 +1,73 @@
+use crate::{Message, MessageType, User};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// How a member wants to be notified about a room
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct NotificationSettings {
+    pub room: Uuid,
+    pub level: NotificationLevel,
+    /// The room is muted until then, it stays muted forever if it's far enough away
+    pub muted_until: Option<DateTime<Utc>>,
+    /// Whether the room is left out of unread counts while it's muted
+    pub hide_unread_when_muted: bool,
+}
+
+impl NotificationSettings {
+    /// The settings of members who never changed them
+    pub fn new(room: Uuid) -> Self {
+        Self {
+            room,
+            level: NotificationLevel::All,
+            muted_until: None,
+            hide_unread_when_muted: true,
+        }
+    }
+
+    pub fn is_muted(&self) -> bool {
+        matches!(self.muted_until, Some(muted_until) if muted_until > Utc::now())
+    }
+
+    /// Whether `message` should notify `me`, whatever delivers notifications has to check this
+    pub fn notifies(&self, message: &Message, me: &User) -> bool {
+        if message.author.uuid == me.uuid || message.type_ != MessageType::Default {
+            return false;
+        }
+        if self.is_muted() {
+            return false;
+        }
+
+        match self.level {
+            NotificationLevel::All => true,
+            NotificationLevel::Mentions => message.mentions(me),
+            NotificationLevel::Nothing => false,
+        }
+    }
+
+    /// Whether the unread messages of the room count towards unread badges
+    pub fn counts_unread(&self) -> bool {
+        !(self.hide_unread_when_muted && self.is_muted())
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(not(target_arch = "wasm32"), derive(sqlx::Type))]
+#[cfg_attr(not(target_arch = "wasm32"), sqlx(rename = "notification_level"))]
+pub enum NotificationLevel {
+    /// Every message notifies
+    #[cfg_attr(not(target_arch = "wasm32"), sqlx(rename = "all"))]
+    All,
+    /// Only the messages mentioning the member
+    #[cfg_attr(not(target_arch = "wasm32"), sqlx(rename = "mentions"))]
+    Mentions,
+    #[cfg_attr(not(target_arch = "wasm32"), sqlx(rename = "nothing"))]
+    Nothing,
+}
+
+impl Default for NotificationLevel {
+    fn default() -> Self {
+        NotificationLevel::All
+    }
+}
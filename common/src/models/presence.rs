@@ -0,0 +1,19 @@
+use serde::{Deserialize, Serialize};
+
+/// Whether a user is around, derived from their open websocket sessions
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Presence {
+    /// At least one session is active
+    Online,
+    /// Every session reported the user as idle
+    Idle,
+    /// No session is open
+    Offline,
+}
+
+impl Default for Presence {
+    fn default() -> Self {
+        Presence::Offline
+    }
+}
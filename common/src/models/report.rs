@@ -0,0 +1,73 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// A message a member reported, waiting for someone to act on it
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Report {
+    pub uuid: Uuid,
+    pub room: Uuid,
+    /// `None` once the message is deleted
+    pub message: Option<Uuid>,
+    pub author: Option<Uuid>,
+    /// What the message said when it was reported
+    pub content: String,
+    /// `None` for messages automod flagged
+    pub reporter: Option<Uuid>,
+    pub reason: String,
+    pub scope: ReportScope,
+    pub created_at: DateTime<Utc>,
+    /// What was done about it, `None` while the report is open
+    pub action: Option<ReportAction>,
+    pub resolved_by: Option<Uuid>,
+    pub resolved_at: Option<DateTime<Utc>>,
+}
+
+impl Report {
+    pub fn is_resolved(&self) -> bool {
+        self.resolved_at.is_some()
+    }
+}
+
+impl PartialEq for Report {
+    fn eq(&self, other: &Self) -> bool {
+        self.uuid == other.uuid
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(not(target_arch = "wasm32"), derive(sqlx::Type))]
+#[cfg_attr(not(target_arch = "wasm32"), sqlx(rename = "report_scope"))]
+pub enum ReportScope {
+    /// Handled by the admins of the room
+    #[cfg_attr(not(target_arch = "wasm32"), sqlx(rename = "room"))]
+    Room,
+    /// Goes beyond the room, handled by instance admins
+    #[cfg_attr(not(target_arch = "wasm32"), sqlx(rename = "instance"))]
+    Instance,
+}
+
+impl Default for ReportScope {
+    fn default() -> Self {
+        ReportScope::Room
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(not(target_arch = "wasm32"), derive(sqlx::Type))]
+#[cfg_attr(not(target_arch = "wasm32"), sqlx(rename = "report_action"))]
+pub enum ReportAction {
+    /// Nothing needed doing
+    #[cfg_attr(not(target_arch = "wasm32"), sqlx(rename = "dismiss"))]
+    Dismiss,
+    #[cfg_attr(not(target_arch = "wasm32"), sqlx(rename = "delete_message"))]
+    DeleteMessage,
+    /// Kicks the author of the message from the room
+    #[cfg_attr(not(target_arch = "wasm32"), sqlx(rename = "kick"))]
+    Kick,
+    /// Bans the author of the message from the room
+    #[cfg_attr(not(target_arch = "wasm32"), sqlx(rename = "ban"))]
+    Ban,
+}
@@ -0,0 +1,135 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::ops::{BitAnd, BitOr, Not};
+use uuid::Uuid;
+
+/// What the members of a room are allowed to do, stored as a bitset.
+///
+/// It's sent over the wire as its bits, unknown ones are dropped.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(into = "i64", from = "i64")]
+pub struct Permissions(i64);
+
+impl Permissions {
+    pub const NONE: Permissions = Permissions(0);
+    pub const SEND_MESSAGES: Permissions = Permissions(1);
+    pub const INVITE: Permissions = Permissions(1 << 1);
+    pub const KICK: Permissions = Permissions(1 << 2);
+    pub const BAN: Permissions = Permissions(1 << 3);
+    pub const MANAGE_ROOM: Permissions = Permissions(1 << 4);
+    pub const PIN: Permissions = Permissions(1 << 5);
+    pub const MANAGE_ROLES: Permissions = Permissions(1 << 6);
+
+    pub const ALL: Permissions = Permissions((1 << 7) - 1);
+
+    /// What the default role of a new room allows
+    pub const DEFAULT: Permissions = Permissions(Self::SEND_MESSAGES.0 | Self::INVITE.0);
+
+    /// Every permission with its name, in bit order
+    pub const NAMED: [(Permissions, &'static str); 7] = [
+        (Self::SEND_MESSAGES, "send_messages"),
+        (Self::INVITE, "invite"),
+        (Self::KICK, "kick"),
+        (Self::BAN, "ban"),
+        (Self::MANAGE_ROOM, "manage_room"),
+        (Self::PIN, "pin"),
+        (Self::MANAGE_ROLES, "manage_roles"),
+    ];
+
+    /// Creates the set from its bits, dropping the ones that aren't a known permission
+    pub const fn from_bits_truncate(bits: i64) -> Self {
+        Self(bits & Self::ALL.0)
+    }
+
+    pub const fn bits(self) -> i64 {
+        self.0
+    }
+
+    pub const fn contains(self, other: Permissions) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+}
+
+impl From<i64> for Permissions {
+    fn from(bits: i64) -> Self {
+        Self::from_bits_truncate(bits)
+    }
+}
+
+impl From<Permissions> for i64 {
+    fn from(permissions: Permissions) -> Self {
+        permissions.bits()
+    }
+}
+
+impl BitOr for Permissions {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitAnd for Permissions {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self {
+        Self(self.0 & rhs.0)
+    }
+}
+
+impl Not for Permissions {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        Self(!self.0 & Self::ALL.0)
+    }
+}
+
+impl fmt::Display for Permissions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names = Self::NAMED
+            .iter()
+            .filter(|(permission, _)| self.contains(*permission))
+            .map(|(_, name)| *name)
+            .collect::<Vec<_>>();
+
+        write!(f, "{}", names.join(", "))
+    }
+}
+
+/// A set of permissions given to members of a room
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Role {
+    pub uuid: Uuid,
+    pub room: Uuid,
+    pub name: String,
+    pub permissions: Permissions,
+    /// Given to the members who join without being assigned a role
+    pub is_default: bool,
+    /// Given to the creator of the room, it has every permission and can't be changed
+    pub is_admin: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Role {
+    /// What the role allows, admins being allowed everything
+    pub fn permissions(&self) -> Permissions {
+        if self.is_admin {
+            Permissions::ALL
+        } else {
+            self.permissions
+        }
+    }
+}
+
+impl PartialEq for Role {
+    fn eq(&self, other: &Self) -> bool {
+        self.uuid == other.uuid
+    }
+}
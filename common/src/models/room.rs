@@ -3,23 +3,91 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+/// The number of people a group DM can have, including its creator
+pub const MAX_GROUP_DM_MEMBERS: usize = 10;
+
+/// The longest slow mode a room can have, six hours
+pub const MAX_SLOW_MODE: i32 = 6 * 60 * 60;
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Room {
     pub uuid: Uuid,
     pub name: String,
     pub created_at: DateTime<Utc>,
     pub icon: Option<Asset>,
+    #[serde(default)]
+    pub topic: Option<String>,
+    #[serde(default)]
+    pub kind: RoomKind,
+    #[serde(default)]
+    pub visibility: RoomVisibility,
+    /// Archived rooms are read-only
+    #[serde(default)]
+    pub archived_at: Option<DateTime<Utc>>,
+    /// The user who can't be removed from the room, DMs don't have one
+    #[serde(default)]
+    pub owner: Option<Uuid>,
+    /// Seconds members have to wait between their messages, `0` if slow mode is off
+    #[serde(default)]
+    pub slow_mode: i32,
+    /// Who can post, announcement rooms only let admins do it
+    #[serde(default)]
+    pub post_permission: PostPermission,
+    /// The space the room belongs to, if any
+    #[serde(default)]
+    pub space: Option<Uuid>,
+    /// The category of its space the room is shown in
+    #[serde(default)]
+    pub category: Option<Uuid>,
+    /// Where the room is in its category, lowest first
+    #[serde(default)]
+    pub position: i32,
 }
 
 impl Room {
     pub fn new(name: &str) -> Self {
+        Self::new_with_kind(name, RoomKind::Room)
+    }
+
+    pub fn new_with_kind(name: &str, kind: RoomKind) -> Self {
         Self {
             uuid: Uuid::new_v4(),
             name: name.to_string(),
             created_at: Utc::now(),
             icon: None,
+            topic: None,
+            kind,
+            visibility: RoomVisibility::Private,
+            archived_at: None,
+            owner: None,
+            slow_mode: 0,
+            post_permission: PostPermission::Everyone,
+            space: None,
+            category: None,
+            position: 0,
         }
     }
+
+    pub fn is_dm(&self) -> bool {
+        self.kind != RoomKind::Room
+    }
+
+    pub fn is_public(&self) -> bool {
+        self.visibility == RoomVisibility::Public
+    }
+
+    pub fn is_archived(&self) -> bool {
+        self.archived_at.is_some()
+    }
+
+    /// Whether only admins can post in the room
+    pub fn is_announcement(&self) -> bool {
+        self.post_permission == PostPermission::Admins
+    }
+
+    pub fn is_owner(&self, user: Uuid) -> bool {
+        self.owner == Some(user)
+    }
 }
 
 impl PartialEq for Room {
@@ -27,3 +95,63 @@ impl PartialEq for Room {
         self.uuid == other.uuid
     }
 }
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(not(target_arch = "wasm32"), derive(sqlx::Type))]
+#[cfg_attr(not(target_arch = "wasm32"), sqlx(rename = "room_kind"))]
+pub enum RoomKind {
+    /// A named room that members are added to
+    #[cfg_attr(not(target_arch = "wasm32"), sqlx(rename = "room"))]
+    Room,
+    /// A conversation between two users, there's at most one for every pair
+    #[cfg_attr(not(target_arch = "wasm32"), sqlx(rename = "dm"))]
+    Dm,
+    /// A conversation between a few users, named after them
+    #[cfg_attr(not(target_arch = "wasm32"), sqlx(rename = "group_dm"))]
+    GroupDm,
+}
+
+impl Default for RoomKind {
+    fn default() -> Self {
+        RoomKind::Room
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(not(target_arch = "wasm32"), derive(sqlx::Type))]
+#[cfg_attr(not(target_arch = "wasm32"), sqlx(rename = "room_visibility"))]
+pub enum RoomVisibility {
+    /// Only members can see the room, others have to be added or invited
+    #[cfg_attr(not(target_arch = "wasm32"), sqlx(rename = "private"))]
+    Private,
+    /// Listed in the room directory, anyone can see and join it
+    #[cfg_attr(not(target_arch = "wasm32"), sqlx(rename = "public"))]
+    Public,
+}
+
+impl Default for RoomVisibility {
+    fn default() -> Self {
+        RoomVisibility::Private
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(not(target_arch = "wasm32"), derive(sqlx::Type))]
+#[cfg_attr(not(target_arch = "wasm32"), sqlx(rename = "post_permission"))]
+pub enum PostPermission {
+    /// Every member who can send messages
+    #[cfg_attr(not(target_arch = "wasm32"), sqlx(rename = "everyone"))]
+    Everyone,
+    /// Only admins, the others can just read
+    #[cfg_attr(not(target_arch = "wasm32"), sqlx(rename = "admins"))]
+    Admins,
+}
+
+impl Default for PostPermission {
+    fn default() -> Self {
+        PostPermission::Everyone
+    }
+}
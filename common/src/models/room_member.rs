@@ -1,4 +1,4 @@
-use crate::models::{Room, User};
+use crate::models::{Permissions, Presence, Role, Room, User};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
@@ -6,6 +6,14 @@ use serde::{Deserialize, Serialize};
 pub struct RoomMember {
     pub user: User,
     pub room: Room,
-    pub has_elevated_permissions: bool,
+    pub role: Role,
     pub joined_at: DateTime<Utc>,
+    #[serde(default)]
+    pub presence: Presence,
+}
+
+impl RoomMember {
+    pub fn can(&self, permissions: Permissions) -> bool {
+        self.role.permissions().contains(permissions)
+    }
 }
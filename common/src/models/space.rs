@@ -0,0 +1,97 @@
+use crate::models::{Permissions, User};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// A workspace grouping rooms, with its own members and roles.
+///
+/// Joining a space gives access to its public rooms.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Space {
+    pub uuid: Uuid,
+    pub name: String,
+    pub owner: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    /// Ordered by their position
+    #[serde(default)]
+    pub categories: Vec<SpaceCategory>,
+}
+
+impl Space {
+    pub fn new(name: &str) -> Self {
+        Self {
+            uuid: Uuid::new_v4(),
+            name: name.to_string(),
+            owner: None,
+            created_at: Utc::now(),
+            categories: vec![],
+        }
+    }
+
+    pub fn is_owner(&self, user: Uuid) -> bool {
+        self.owner == Some(user)
+    }
+}
+
+impl PartialEq for Space {
+    fn eq(&self, other: &Self) -> bool {
+        self.uuid == other.uuid
+    }
+}
+
+/// A collapsible group of rooms in a space
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SpaceCategory {
+    pub uuid: Uuid,
+    pub space: Uuid,
+    pub name: String,
+    pub position: i32,
+}
+
+/// A set of permissions given to members of a space.
+///
+/// [`Permissions::INVITE`] lets them add members, [`Permissions::MANAGE_ROOM`] lets them
+/// create and arrange the rooms and categories of the space.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SpaceRole {
+    pub uuid: Uuid,
+    pub space: Uuid,
+    pub name: String,
+    pub permissions: Permissions,
+    /// Given to the members who join without being assigned a role
+    pub is_default: bool,
+    /// Given to the creator of the space, it has every permission
+    pub is_admin: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+impl SpaceRole {
+    /// What the role allows, admins being allowed everything
+    pub fn permissions(&self) -> Permissions {
+        if self.is_admin {
+            Permissions::ALL
+        } else {
+            self.permissions
+        }
+    }
+}
+
+impl PartialEq for SpaceRole {
+    fn eq(&self, other: &Self) -> bool {
+        self.uuid == other.uuid
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SpaceMember {
+    pub user: User,
+    pub space: Uuid,
+    pub role: SpaceRole,
+    pub joined_at: DateTime<Utc>,
+}
+
+impl SpaceMember {
+    pub fn can(&self, permissions: Permissions) -> bool {
+        self.role.permissions().contains(permissions)
+    }
+}
@@ -11,6 +11,30 @@ pub struct User {
     pub password: String,
     pub created_at: DateTime<Utc>,
     pub avatar: Option<Asset>,
+    /// Shown instead of the username when set
+    pub display_name: Option<String>,
+    pub bio: Option<String>,
+    pub pronouns: Option<String>,
+    /// IANA name of the user's timezone, e.g. `Europe/Berlin`
+    pub timezone: Option<String>,
+    pub status: Option<CustomStatus>,
+    /// Can manage the whole instance through `/api/admin`
+    #[serde(default)]
+    pub is_instance_admin: bool,
+}
+
+/// A short message the user sets about what they're up to
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CustomStatus {
+    pub text: String,
+    /// The status is cleared once this passes
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl CustomStatus {
+    pub fn is_expired(&self) -> bool {
+        matches!(self.expires_at, Some(expires_at) if expires_at <= Utc::now())
+    }
 }
 
 impl User {
@@ -21,6 +45,12 @@ impl User {
             password,
             created_at: Utc::now(),
             avatar: None,
+            display_name: None,
+            bio: None,
+            pronouns: None,
+            timezone: None,
+            status: None,
+            is_instance_admin: false,
         }
     }
 
@@ -31,8 +61,19 @@ impl User {
             password: "".to_string(),
             created_at: Utc::now(),
             avatar: None,
+            display_name: None,
+            bio: None,
+            pronouns: None,
+            timezone: None,
+            status: None,
+            is_instance_admin: false,
         }
     }
+
+    /// The display name if the user has one, the username otherwise
+    pub fn name(&self) -> &str {
+        self.display_name.as_deref().unwrap_or(&self.username)
+    }
 }
 
 impl PartialEq for User {
@@ -0,0 +1,101 @@
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+
+/// Encoding of the websocket frames, negotiated with the `encoding` query parameter
+/// when connecting.
+///
+/// MessagePack frames carry the same data model as JSON ones: values are converted
+/// through [`serde_json::Value`] so both encodings produce identical payloads once decoded.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+pub enum Encoding {
+    #[serde(rename = "json")]
+    Json,
+    #[serde(rename = "msgpack")]
+    MessagePack,
+}
+
+impl Default for Encoding {
+    fn default() -> Self {
+        Encoding::Json
+    }
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Encoding::Json => "json",
+            Encoding::MessagePack => "msgpack",
+        };
+
+        write!(f, "{}", s)
+    }
+}
+
+/// An encoded websocket frame
+#[derive(Debug, Clone, PartialEq)]
+pub enum Frame {
+    Text(String),
+    Binary(Vec<u8>),
+}
+
+impl Encoding {
+    pub fn encode<T: Serialize>(self, value: &T) -> Result<Frame, CodecError> {
+        Ok(match self {
+            Encoding::Json => Frame::Text(serde_json::to_string(value)?),
+            Encoding::MessagePack => {
+                let value = serde_json::to_value(value)?;
+                Frame::Binary(rmp_serde::to_vec_named(&value)?)
+            }
+        })
+    }
+
+    /// Decodes the contents of a frame, regardless of whether it was sent as text or binary
+    pub fn decode<T: DeserializeOwned>(self, bytes: &[u8]) -> Result<T, CodecError> {
+        Ok(match self {
+            Encoding::Json => serde_json::from_slice(bytes)?,
+            Encoding::MessagePack => {
+                let value = rmp_serde::from_read_ref::<_, Value>(bytes)?;
+                serde_json::from_value(value)?
+            }
+        })
+    }
+}
+
+#[derive(Debug)]
+pub enum CodecError {
+    Json(serde_json::Error),
+    Encode(rmp_serde::encode::Error),
+    Decode(rmp_serde::decode::Error),
+}
+
+impl fmt::Display for CodecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CodecError::Json(e) => write!(f, "{}", e),
+            CodecError::Encode(e) => write!(f, "{}", e),
+            CodecError::Decode(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for CodecError {}
+
+impl From<serde_json::Error> for CodecError {
+    fn from(e: serde_json::Error) -> Self {
+        CodecError::Json(e)
+    }
+}
+
+impl From<rmp_serde::encode::Error> for CodecError {
+    fn from(e: rmp_serde::encode::Error) -> Self {
+        CodecError::Encode(e)
+    }
+}
+
+impl From<rmp_serde::decode::Error> for CodecError {
+    fn from(e: rmp_serde::decode::Error) -> Self {
+        CodecError::Decode(e)
+    }
+}
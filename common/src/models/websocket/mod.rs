@@ -1,5 +1,18 @@
-use crate::{Room, User};
+mod codec;
+
+pub use codec::*;
+
+use crate::{NotificationSettings, Presence, Room, Space, User};
 use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use std::fmt;
+use uuid::Uuid;
+
+/// Version of the websocket wire protocol.
+///
+/// Clients send it as the `v` query parameter when connecting. Bump it whenever
+/// opcodes or payloads change in a way older clients can't understand.
+pub const PROTOCOL_VERSION: u32 = 1;
 
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(bound(deserialize = "T: for<'a> Deserialize<'a>"))]
@@ -20,9 +33,158 @@ pub struct AuthenticatePayload {
 pub struct AuthenticatedPayload {
     pub me: User,
     pub rooms: Vec<Room>,
+    #[serde(default)]
+    pub spaces: Vec<Space>,
+    /// The notification settings of the rooms the user is in
+    #[serde(default)]
+    pub notification_settings: Vec<NotificationSettings>,
+}
+
+/// Sent by the client to report whether the user is idle
+#[derive(Serialize, Deserialize, Debug)]
+pub struct UpdatePresencePayload {
+    pub idle: bool,
+}
+
+/// Sent to the users sharing a room with `user` when its presence changes
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PresenceUpdatePayload {
+    pub user: Uuid,
+    pub presence: Presence,
+}
+
+/// Data of the error frames, sent with [`OpCode::BadPayload`], [`OpCode::InvalidOp`]
+/// or [`OpCode::ServerError`] depending on [`ErrorCode::op`].
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ErrorPayload {
+    pub code: ErrorCode,
+    pub message: String,
+}
+
+impl ErrorPayload {
+    pub fn new(code: ErrorCode, message: &str) -> Self {
+        Self {
+            code,
+            message: message.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ErrorPayload {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.message, u16::from(self.code))
+    }
+}
+
+impl std::error::Error for ErrorPayload {}
+
+/// Error codes sent in [`ErrorPayload`].
+///
+/// Fatal codes are also used as the close code of the websocket, so they live in the
+/// 4000-4999 range reserved for applications.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+#[serde(into = "u16", try_from = "u16")]
+pub enum ErrorCode {
+    /// The frame could not be decoded at all
+    MalformedFrame,
+    /// The frame was decoded but its data doesn't match what the op expects
+    InvalidData,
+    /// The op isn't known or can't be sent by clients
+    UnknownOp,
+    /// `Authenticate` was sent on an already authenticated connection
+    AlreadyAuthenticated,
+    /// Something went wrong on the server while handling the frame
+    Internal,
+    /// An op requiring authentication was sent before `Authenticate`
+    NotAuthenticated,
+    /// The token sent with `Authenticate` is invalid
+    AuthenticationFailed,
+    /// The client requested a protocol version the server doesn't speak
+    UnsupportedVersion,
+    /// The client didn't read its messages fast enough and missed some of them.
+    ///
+    /// Only ever sent as a close code, the client should reconnect and fetch
+    /// its state again.
+    SlowConsumer,
+    /// An instance admin suspended or deleted the account, or required a new password.
+    ///
+    /// Only ever sent as a close code, the token doesn't work anymore.
+    SessionRevoked,
+}
+
+impl ErrorCode {
+    /// Whether the server closes the connection after sending this error
+    pub fn is_fatal(self) -> bool {
+        matches!(
+            self,
+            ErrorCode::NotAuthenticated
+                | ErrorCode::AuthenticationFailed
+                | ErrorCode::UnsupportedVersion
+                | ErrorCode::SlowConsumer
+                | ErrorCode::SessionRevoked
+        )
+    }
+
+    /// The op of the error frame carrying this code
+    pub fn op(self) -> OpCode {
+        match self {
+            ErrorCode::MalformedFrame
+            | ErrorCode::InvalidData
+            | ErrorCode::AuthenticationFailed
+            | ErrorCode::UnsupportedVersion => OpCode::BadPayload,
+            ErrorCode::UnknownOp
+            | ErrorCode::AlreadyAuthenticated
+            | ErrorCode::NotAuthenticated => OpCode::InvalidOp,
+            ErrorCode::Internal | ErrorCode::SlowConsumer | ErrorCode::SessionRevoked => {
+                OpCode::ServerError
+            }
+        }
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug, Copy, Clone)]
+impl From<ErrorCode> for u16 {
+    fn from(code: ErrorCode) -> Self {
+        match code {
+            // recoverable
+            ErrorCode::MalformedFrame => 4000,
+            ErrorCode::InvalidData => 4001,
+            ErrorCode::UnknownOp => 4002,
+            ErrorCode::AlreadyAuthenticated => 4003,
+            ErrorCode::Internal => 4004,
+
+            // fatal
+            ErrorCode::NotAuthenticated => 4100,
+            ErrorCode::AuthenticationFailed => 4101,
+            ErrorCode::UnsupportedVersion => 4102,
+            ErrorCode::SlowConsumer => 4103,
+            ErrorCode::SessionRevoked => 4104,
+        }
+    }
+}
+
+impl TryFrom<u16> for ErrorCode {
+    type Error = String;
+
+    fn try_from(value: u16) -> Result<Self, Self::Error> {
+        Ok(match value {
+            4000 => ErrorCode::MalformedFrame,
+            4001 => ErrorCode::InvalidData,
+            4002 => ErrorCode::UnknownOp,
+            4003 => ErrorCode::AlreadyAuthenticated,
+            4004 => ErrorCode::Internal,
+            4100 => ErrorCode::NotAuthenticated,
+            4101 => ErrorCode::AuthenticationFailed,
+            4102 => ErrorCode::UnsupportedVersion,
+            4103 => ErrorCode::SlowConsumer,
+            4104 => ErrorCode::SessionRevoked,
+            _ => return Err(format!("unknown error code: {}", value)),
+        })
+    }
+}
+
+/// Opcodes are sent over the wire as integers, see [`u32_to_opcode`] for the mapping.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+#[serde(into = "u32", from = "u32")]
 pub enum OpCode {
     Authenticate,
     Authenticated,
@@ -30,10 +192,19 @@ pub enum OpCode {
     InvalidOp,
     ServerError,
     MessageCreate,
-    RoomCreate,
     RoomUpdate,
     RoomJoin,
+    RoomLeave,
+    RoomDelete,
+    SpaceJoin,
+    SpaceUpdate,
+    SpaceLeave,
+    NotificationSettingsUpdate,
+    ReportCreate,
+    MessageDelete,
     UserUpdate,
+    PresenceUpdate,
+    UpdatePresence,
 }
 
 impl From<u32> for OpCode {
@@ -42,20 +213,66 @@ impl From<u32> for OpCode {
     }
 }
 
+impl From<OpCode> for u32 {
+    fn from(op: OpCode) -> Self {
+        opcode_to_u32(op)
+    }
+}
+
 fn u32_to_opcode(value: u32) -> OpCode {
     match value {
         // server side => receive only for client
         0 => OpCode::Authenticated,
         1 => OpCode::MessageCreate,
-        2 => OpCode::RoomCreate,
+        // 2 was `RoomCreate`, creators get a `RoomJoin` instead
         3 => OpCode::RoomUpdate,
         4 => OpCode::RoomJoin,
         5 => OpCode::UserUpdate,
+        6 => OpCode::PresenceUpdate,
+        7 => OpCode::RoomLeave,
+        8 => OpCode::RoomDelete,
+        9 => OpCode::SpaceJoin,
+        10 => OpCode::SpaceUpdate,
+        11 => OpCode::SpaceLeave,
+        12 => OpCode::NotificationSettingsUpdate,
+        13 => OpCode::ReportCreate,
+        14 => OpCode::MessageDelete,
 
         // client side => send only for client
         100 => OpCode::Authenticate,
+        101 => OpCode::UpdatePresence,
+
+        // errors => receive only for client
+        200 => OpCode::BadPayload,
+        202 => OpCode::ServerError,
 
         // invalid
         _ => OpCode::InvalidOp,
     }
 }
+
+fn opcode_to_u32(op: OpCode) -> u32 {
+    match op {
+        OpCode::Authenticated => 0,
+        OpCode::MessageCreate => 1,
+        OpCode::RoomUpdate => 3,
+        OpCode::RoomJoin => 4,
+        OpCode::UserUpdate => 5,
+        OpCode::PresenceUpdate => 6,
+        OpCode::RoomLeave => 7,
+        OpCode::RoomDelete => 8,
+        OpCode::SpaceJoin => 9,
+        OpCode::SpaceUpdate => 10,
+        OpCode::SpaceLeave => 11,
+        OpCode::NotificationSettingsUpdate => 12,
+        OpCode::ReportCreate => 13,
+        OpCode::MessageDelete => 14,
+
+        OpCode::Authenticate => 100,
+        OpCode::UpdatePresence => 101,
+
+        OpCode::BadPayload => 200,
+        OpCode::InvalidOp => 201,
+        OpCode::ServerError => 202,
+    }
+}
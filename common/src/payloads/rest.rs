@@ -1,4 +1,11 @@
-use serde::{Deserialize, Serialize};
+use crate::{
+    AuditAction, AutomodAction, AutomodRuleKind, CustomStatus, NotificationLevel, Permissions,
+    PostPermission, ReportAction, ReportScope, Room, RoomVisibility, MAX_GROUP_DM_MEMBERS,
+    MAX_SLOW_MODE,
+};
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Deserializer, Serialize};
 use uuid::Uuid;
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -15,6 +22,135 @@ pub struct JwtToken {
 #[derive(Deserialize, Serialize, Debug)]
 pub struct CreateRoom {
     pub name: String,
+    #[serde(default)]
+    pub visibility: RoomVisibility,
+    /// The space to create the room in
+    #[serde(default)]
+    pub space: Option<Uuid>,
+    /// The category of the space to put the room in, it needs `space` to be set
+    #[serde(default)]
+    pub category: Option<Uuid>,
+}
+
+pub const MAX_ROOM_NAME_LENGTH: usize = 64;
+pub const MAX_ROOM_TOPIC_LENGTH: usize = 512;
+
+/// Changes to the settings of a room.
+///
+/// Missing fields are left as they are, a `null` topic clears it.
+#[derive(Deserialize, Serialize, Debug, Default)]
+pub struct UpdateRoom {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(
+        default,
+        deserialize_with = "double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub topic: Option<Option<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub visibility: Option<RoomVisibility>,
+    /// In seconds, `0` turns slow mode off
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slow_mode: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_permission: Option<PostPermission>,
+}
+
+impl UpdateRoom {
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(name) = &self.name {
+            validate_length("room name", name, MAX_ROOM_NAME_LENGTH)?;
+        }
+        if let Some(Some(topic)) = &self.topic {
+            validate_length("topic", topic, MAX_ROOM_TOPIC_LENGTH)?;
+        }
+        if let Some(slow_mode) = self.slow_mode {
+            if !(0..=MAX_SLOW_MODE).contains(&slow_mode) {
+                return Err(format!(
+                    "slow mode must be between 0 and {} seconds",
+                    MAX_SLOW_MODE
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The most rooms a page of the directory can have
+pub const MAX_DIRECTORY_PAGE_SIZE: i64 = 100;
+
+/// Query of `GET /api/rooms/public`
+#[derive(Deserialize, Serialize, Debug, Default)]
+pub struct DirectoryQuery {
+    /// Only rooms with this in their name are returned
+    #[serde(default)]
+    pub search: Option<String>,
+    /// Lists the public rooms of this space instead of those outside of spaces
+    #[serde(default)]
+    pub space: Option<Uuid>,
+    #[serde(default)]
+    pub offset: i64,
+    #[serde(default = "default_directory_page_size")]
+    pub limit: i64,
+}
+
+fn default_directory_page_size() -> i64 {
+    50
+}
+
+impl DirectoryQuery {
+    pub fn validate(&self) -> Result<(), String> {
+        validate_page(self.offset, self.limit, MAX_DIRECTORY_PAGE_SIZE)
+    }
+}
+
+fn validate_page(offset: i64, limit: i64, max_limit: i64) -> Result<(), String> {
+    if offset < 0 {
+        return Err("offset can't be negative".to_string());
+    }
+    if !(1..=max_limit).contains(&limit) {
+        return Err(format!("limit must be between 1 and {}", max_limit));
+    }
+
+    Ok(())
+}
+
+/// A room in the directory
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct DirectoryEntry {
+    pub room: Room,
+    pub member_count: i64,
+}
+
+/// Opens a DM with one user, or a group DM with several
+#[derive(Deserialize, Serialize, Debug)]
+pub struct OpenDm {
+    pub members: Vec<Uuid>,
+}
+
+impl OpenDm {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.members.is_empty() {
+            return Err("at least one member is required".to_string());
+        }
+        if self.members.len() >= MAX_GROUP_DM_MEMBERS {
+            return Err(format!(
+                "group DMs can have at most {} members",
+                MAX_GROUP_DM_MEMBERS
+            ));
+        }
+
+        let mut members = self.members.clone();
+        members.sort_unstable();
+        members.dedup();
+        if members.len() != self.members.len() {
+            return Err("members can't be repeated".to_string());
+        }
+
+        Ok(())
+    }
 }
 
 #[derive(Deserialize, Serialize, Debug)]
@@ -22,8 +158,427 @@ pub struct CreateMessage {
     pub content: String,
 }
 
+/// A user to add to a room
 #[derive(Deserialize, Serialize, Debug)]
 pub struct JoinMembers {
     pub member: Uuid,
-    pub with_elevated_permissions: bool,
+    /// The role to give them, the default role of the room if not provided
+    #[serde(default)]
+    pub role: Option<Uuid>,
+}
+
+/// The most entries a page of the audit log can have
+pub const MAX_AUDIT_LOG_PAGE_SIZE: i64 = 100;
+
+/// Query of `GET /api/rooms/{id}/audit-log`, the newest entries come first
+#[derive(Deserialize, Serialize, Debug, Default)]
+pub struct AuditLogQuery {
+    #[serde(default)]
+    pub action: Option<AuditAction>,
+    #[serde(default)]
+    pub actor: Option<Uuid>,
+    #[serde(default)]
+    pub target: Option<Uuid>,
+    #[serde(default)]
+    pub offset: i64,
+    #[serde(default = "default_audit_log_page_size")]
+    pub limit: i64,
+}
+
+fn default_audit_log_page_size() -> i64 {
+    50
+}
+
+impl AuditLogQuery {
+    pub fn validate(&self) -> Result<(), String> {
+        validate_page(self.offset, self.limit, MAX_AUDIT_LOG_PAGE_SIZE)
+    }
+}
+
+/// A member to kick or ban from a room
+#[derive(Deserialize, Serialize, Debug)]
+pub struct RemoveMember {
+    pub member: Uuid,
+    pub reason: Option<String>,
+}
+
+pub const MAX_REPORT_REASON_LENGTH: usize = 512;
+
+/// Why a message is being reported, and who should look at it
+#[derive(Deserialize, Serialize, Debug)]
+pub struct CreateReport {
+    pub reason: String,
+    #[serde(default)]
+    pub scope: ReportScope,
+}
+
+impl CreateReport {
+    pub fn validate(&self) -> Result<(), String> {
+        validate_length("reason", &self.reason, MAX_REPORT_REASON_LENGTH)
+    }
+}
+
+/// Query of `GET /api/rooms/{id}/reports`, the open reports unless `resolved` is set
+#[derive(Deserialize, Serialize, Debug, Default)]
+pub struct ReportQuery {
+    #[serde(default)]
+    pub resolved: bool,
+}
+
+/// What to do about a report, `reason` is passed on to kicks and bans
+#[derive(Deserialize, Serialize, Debug)]
+pub struct ResolveReport {
+    pub action: ReportAction,
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+pub const MAX_AUTOMOD_PATTERNS: usize = 100;
+pub const MAX_AUTOMOD_PATTERN_LENGTH: usize = 256;
+
+/// A new automod rule, `max_mentions` is needed for `MaxMentions` and `patterns` for the others.
+///
+/// Regexes are checked by the server, they only need to be valid there.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct CreateAutomodRule {
+    pub kind: AutomodRuleKind,
+    #[serde(default)]
+    pub patterns: Vec<String>,
+    #[serde(default)]
+    pub max_mentions: Option<i32>,
+    pub action: AutomodAction,
+}
+
+impl CreateAutomodRule {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.kind == AutomodRuleKind::MaxMentions {
+            return match self.max_mentions {
+                Some(max) if max < 0 => Err("max mentions can't be negative".to_string()),
+                Some(_) if !self.patterns.is_empty() => {
+                    Err("mention limits don't have patterns".to_string())
+                }
+                Some(_) => Ok(()),
+                None => Err("max mentions is needed for mention limits".to_string()),
+            };
+        }
+
+        if self.max_mentions.is_some() {
+            return Err("max mentions is only for mention limits".to_string());
+        }
+        if self.patterns.is_empty() {
+            return Err("patterns can't be empty".to_string());
+        }
+        if self.patterns.len() > MAX_AUTOMOD_PATTERNS {
+            return Err(format!(
+                "a rule can't have more than {} patterns",
+                MAX_AUTOMOD_PATTERNS
+            ));
+        }
+        self.patterns
+            .iter()
+            .try_for_each(|it| validate_length("pattern", it, MAX_AUTOMOD_PATTERN_LENGTH))
+    }
+}
+
+/// The member to hand a room over to
+#[derive(Deserialize, Serialize, Debug)]
+pub struct TransferOwnership {
+    pub owner: Uuid,
+}
+
+pub const MAX_SPACE_NAME_LENGTH: usize = 64;
+pub const MAX_CATEGORY_NAME_LENGTH: usize = 64;
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct CreateSpace {
+    pub name: String,
+}
+
+impl CreateSpace {
+    pub fn validate(&self) -> Result<(), String> {
+        validate_length("space name", &self.name, MAX_SPACE_NAME_LENGTH)
+    }
+}
+
+/// A new category at the end of a space
+#[derive(Deserialize, Serialize, Debug)]
+pub struct CreateCategory {
+    pub name: String,
+}
+
+impl CreateCategory {
+    pub fn validate(&self) -> Result<(), String> {
+        validate_length("category name", &self.name, MAX_CATEGORY_NAME_LENGTH)
+    }
+}
+
+/// Changes to a category, missing fields are left as they are
+#[derive(Deserialize, Serialize, Debug, Default)]
+pub struct UpdateCategory {
+    pub name: Option<String>,
+    pub position: Option<i32>,
+}
+
+impl UpdateCategory {
+    pub fn validate(&self) -> Result<(), String> {
+        match &self.name {
+            Some(name) => validate_length("category name", name, MAX_CATEGORY_NAME_LENGTH),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Where to show a room in its space
+#[derive(Deserialize, Serialize, Debug)]
+pub struct MoveRoom {
+    /// `None` shows it above the categories
+    pub category: Option<Uuid>,
+    pub position: i32,
+}
+
+/// Changes to how the current user is notified about a room.
+///
+/// Missing fields are left as they are, a `null` `muted_until` unmutes the room.
+#[derive(Deserialize, Serialize, Debug, Default)]
+pub struct UpdateNotificationSettings {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub level: Option<NotificationLevel>,
+    #[serde(
+        default,
+        deserialize_with = "double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub muted_until: Option<Option<DateTime<Utc>>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hide_unread_when_muted: Option<bool>,
+}
+
+impl UpdateNotificationSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if matches!(self.muted_until, Some(Some(muted_until)) if muted_until <= Utc::now()) {
+            return Err("mute expiry must be in the future".to_string());
+        }
+        Ok(())
+    }
+}
+
+pub const MAX_ADMIN_PAGE_SIZE: i64 = 100;
+pub const MAX_SUSPENSION_REASON_LENGTH: usize = 512;
+
+/// Query of the user and room lists of `/api/admin`, `search` matches names
+#[derive(Deserialize, Serialize, Debug, Default)]
+pub struct AdminQuery {
+    #[serde(default)]
+    pub search: Option<String>,
+    #[serde(default)]
+    pub offset: i64,
+    #[serde(default = "default_admin_page_size")]
+    pub limit: i64,
+}
+
+fn default_admin_page_size() -> i64 {
+    50
+}
+
+impl AdminQuery {
+    pub fn validate(&self) -> Result<(), String> {
+        validate_page(self.offset, self.limit, MAX_ADMIN_PAGE_SIZE)
+    }
+}
+
+/// Why a user is being suspended, shown to them when they try to sign in
+#[derive(Deserialize, Serialize, Debug, Default)]
+pub struct SuspendUser {
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+impl SuspendUser {
+    pub fn validate(&self) -> Result<(), String> {
+        match &self.reason {
+            Some(reason) => validate_length("reason", reason, MAX_SUSPENSION_REASON_LENGTH),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Signs in with a new password, needed once an admin forced a password reset
+#[derive(Deserialize, Serialize, Debug)]
+pub struct ChangePassword {
+    pub username: String,
+    pub password: String,
+    pub new_password: String,
+}
+
+impl ChangePassword {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.new_password.is_empty() {
+            return Err("new password can't be empty".to_string());
+        }
+        if self.new_password == self.password {
+            return Err("new password must be different from the old one".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Changes to the profile of the current user.
+///
+/// Missing fields are left as they are, `null` clears them.
+#[derive(Deserialize, Serialize, Debug, Default)]
+pub struct UpdateProfile {
+    #[serde(
+        default,
+        deserialize_with = "double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub display_name: Option<Option<String>>,
+    #[serde(
+        default,
+        deserialize_with = "double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub bio: Option<Option<String>>,
+    #[serde(
+        default,
+        deserialize_with = "double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub pronouns: Option<Option<String>>,
+    #[serde(
+        default,
+        deserialize_with = "double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub timezone: Option<Option<String>>,
+    #[serde(
+        default,
+        deserialize_with = "double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub status: Option<Option<CustomStatus>>,
+}
+
+impl UpdateProfile {
+    pub const MAX_DISPLAY_NAME_LENGTH: usize = 32;
+    pub const MAX_BIO_LENGTH: usize = 190;
+    pub const MAX_PRONOUNS_LENGTH: usize = 40;
+    pub const MAX_TIMEZONE_LENGTH: usize = 64;
+    pub const MAX_STATUS_LENGTH: usize = 128;
+
+    /// Checks the new values, returning why they were rejected
+    pub fn validate(&self) -> Result<(), String> {
+        let fields = [
+            (
+                "display name",
+                &self.display_name,
+                Self::MAX_DISPLAY_NAME_LENGTH,
+            ),
+            ("bio", &self.bio, Self::MAX_BIO_LENGTH),
+            ("pronouns", &self.pronouns, Self::MAX_PRONOUNS_LENGTH),
+            ("timezone", &self.timezone, Self::MAX_TIMEZONE_LENGTH),
+        ];
+
+        for (name, value, max_length) in fields.iter() {
+            if let Some(Some(value)) = value {
+                validate_length(name, value, *max_length)?;
+            }
+        }
+
+        if let Some(Some(timezone)) = &self.timezone {
+            if timezone.parse::<Tz>().is_err() {
+                return Err(format!("unknown timezone: {}", timezone));
+            }
+        }
+
+        if let Some(Some(status)) = &self.status {
+            validate_length("status", &status.text, Self::MAX_STATUS_LENGTH)?;
+            if status.is_expired() {
+                return Err("status expiry must be in the future".to_string());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A new role in a room
+#[derive(Deserialize, Serialize, Debug)]
+pub struct CreateRole {
+    pub name: String,
+    #[serde(default)]
+    pub permissions: Permissions,
+}
+
+/// Changes to a role, missing fields are left as they are
+#[derive(Deserialize, Serialize, Debug, Default)]
+pub struct UpdateRole {
+    pub name: Option<String>,
+    pub permissions: Option<Permissions>,
+}
+
+/// The role to give a member
+#[derive(Deserialize, Serialize, Debug)]
+pub struct SetMemberRole {
+    pub role: Uuid,
+}
+
+pub const MAX_ROLE_NAME_LENGTH: usize = 32;
+
+impl CreateRole {
+    pub fn validate(&self) -> Result<(), String> {
+        validate_length("role name", &self.name, MAX_ROLE_NAME_LENGTH)
+    }
+}
+
+impl UpdateRole {
+    pub fn validate(&self) -> Result<(), String> {
+        match &self.name {
+            Some(name) => validate_length("role name", name, MAX_ROLE_NAME_LENGTH),
+            None => Ok(()),
+        }
+    }
+}
+
+/// A new invite to a room, it never expires if neither limit is set
+#[derive(Deserialize, Serialize, Debug, Default)]
+pub struct CreateInvite {
+    pub max_uses: Option<i32>,
+    pub expires_at: Option<DateTime<Utc>>,
+    /// The role to give those who join, the default role of the room if not provided
+    pub role: Option<Uuid>,
+}
+
+impl CreateInvite {
+    pub fn validate(&self) -> Result<(), String> {
+        if matches!(self.max_uses, Some(max_uses) if max_uses < 1) {
+            return Err("an invite must be usable at least once".to_string());
+        }
+        if matches!(self.expires_at, Some(expires_at) if expires_at <= Utc::now()) {
+            return Err("invite expiry must be in the future".to_string());
+        }
+        Ok(())
+    }
+}
+
+fn validate_length(name: &str, value: &str, max_length: usize) -> Result<(), String> {
+    if value.trim().is_empty() {
+        Err(format!("{} can't be empty", name))
+    } else if value.chars().count() > max_length {
+        Err(format!(
+            "{} can't be longer than {} characters",
+            name, max_length
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// Tells a missing field (`None`) apart from an explicit `null` (`Some(None)`)
+fn double_option<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    Deserialize::deserialize(deserializer).map(Some)
 }
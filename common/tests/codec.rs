@@ -0,0 +1,242 @@
+use chrono::Utc;
+use common::websocket::{
+    AuthenticatePayload, AuthenticatedPayload, Encoding, ErrorCode, ErrorPayload, Frame,
+    MessagePayload, OpCode, PresenceUpdatePayload, UpdatePresencePayload,
+};
+use common::{
+    Asset, CustomStatus, Message, MessageType, NotificationLevel, NotificationSettings,
+    Permissions, Presence, Report, ReportAction, ReportScope, Room, Space, SpaceCategory, User,
+};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fmt::Debug;
+
+const ENCODINGS: [Encoding; 2] = [Encoding::Json, Encoding::MessagePack];
+
+fn user() -> User {
+    let mut user = User::new("user".to_string(), "password".to_string());
+    user.avatar = Some(Asset::new(Default::default()));
+    user.display_name = Some("User".to_string());
+    user.status = Some(CustomStatus {
+        text: "status".to_string(),
+        expires_at: Some(Utc::now()),
+    });
+    user
+}
+
+fn room() -> Room {
+    Room::new("room")
+}
+
+fn space() -> Space {
+    let mut space = Space::new("space");
+    space.categories.push(SpaceCategory {
+        uuid: Default::default(),
+        space: space.uuid,
+        name: "category".to_string(),
+        position: 0,
+    });
+    space
+}
+
+fn notification_settings() -> NotificationSettings {
+    let mut settings = NotificationSettings::new(room().uuid);
+    settings.level = NotificationLevel::Mentions;
+    settings.muted_until = Some(Utc::now());
+    settings
+}
+
+fn report() -> Report {
+    Report {
+        uuid: Default::default(),
+        room: room().uuid,
+        message: None,
+        author: Some(user().uuid),
+        content: "content".to_string(),
+        reporter: Some(user().uuid),
+        reason: "reason".to_string(),
+        scope: ReportScope::Instance,
+        created_at: Utc::now(),
+        action: Some(ReportAction::DeleteMessage),
+        resolved_by: Some(user().uuid),
+        resolved_at: Some(Utc::now()),
+    }
+}
+
+/// Encodes and decodes the payload with every encoding and
+/// checks that nothing was lost along the way
+fn assert_round_trip<T>(op: OpCode, data: T)
+where
+    T: Serialize + DeserializeOwned + Debug,
+{
+    let payload = MessagePayload { op, data };
+    let expected = serde_json::to_value(&payload).unwrap();
+
+    for encoding in ENCODINGS.iter() {
+        let frame = encoding.encode(&payload).expect("failed to encode");
+        let bytes = match frame {
+            Frame::Text(text) => {
+                assert_eq!(*encoding, Encoding::Json);
+                text.into_bytes()
+            }
+            Frame::Binary(bytes) => {
+                assert_eq!(*encoding, Encoding::MessagePack);
+                bytes
+            }
+        };
+
+        let decoded = encoding
+            .decode::<MessagePayload<T>>(&bytes)
+            .expect("failed to decode");
+
+        assert_eq!(decoded.op, op);
+        assert_eq!(serde_json::to_value(&decoded).unwrap(), expected);
+    }
+}
+
+#[test]
+fn test_authenticate_round_trip() {
+    assert_round_trip(
+        OpCode::Authenticate,
+        AuthenticatePayload {
+            token: "token".to_string(),
+        },
+    );
+}
+
+#[test]
+fn test_authenticated_round_trip() {
+    assert_round_trip(
+        OpCode::Authenticated,
+        AuthenticatedPayload {
+            me: user(),
+            rooms: vec![room(), room()],
+            spaces: vec![space()],
+            notification_settings: vec![notification_settings()],
+        },
+    );
+}
+
+#[test]
+fn test_error_round_trip() {
+    for code in [
+        ErrorCode::MalformedFrame,
+        ErrorCode::InvalidData,
+        ErrorCode::UnknownOp,
+        ErrorCode::AlreadyAuthenticated,
+        ErrorCode::Internal,
+        ErrorCode::NotAuthenticated,
+        ErrorCode::AuthenticationFailed,
+        ErrorCode::UnsupportedVersion,
+        ErrorCode::SlowConsumer,
+        ErrorCode::SessionRevoked,
+    ]
+    .iter()
+    {
+        assert_round_trip(code.op(), ErrorPayload::new(*code, "error"));
+    }
+}
+
+#[test]
+fn test_message_create_round_trip() {
+    for type_ in [
+        MessageType::Default,
+        MessageType::RoomJoin,
+        MessageType::RoomLeave,
+        MessageType::RoomKick,
+        MessageType::RoomBan,
+        MessageType::RoomRename,
+    ]
+    .iter()
+    {
+        let message = Message::new_with_type(user(), room(), "content".to_string(), *type_);
+        assert_round_trip(OpCode::MessageCreate, message);
+    }
+}
+
+#[test]
+fn test_message_delete_round_trip() {
+    let message = Message::new(user(), room(), "content".to_string());
+    assert_round_trip(OpCode::MessageDelete, message);
+}
+
+#[test]
+fn test_report_round_trip() {
+    assert_round_trip(OpCode::ReportCreate, report());
+}
+
+#[test]
+fn test_room_round_trip() {
+    for op in [
+        OpCode::RoomUpdate,
+        OpCode::RoomJoin,
+        OpCode::RoomLeave,
+        OpCode::RoomDelete,
+    ]
+    .iter()
+    {
+        assert_round_trip(*op, room());
+    }
+}
+
+#[test]
+fn test_space_round_trip() {
+    for op in [OpCode::SpaceJoin, OpCode::SpaceUpdate, OpCode::SpaceLeave].iter() {
+        assert_round_trip(*op, space());
+    }
+}
+
+#[test]
+fn test_notification_settings_round_trip() {
+    assert_round_trip(OpCode::NotificationSettingsUpdate, notification_settings());
+}
+
+#[test]
+fn test_user_update_round_trip() {
+    assert_round_trip(OpCode::UserUpdate, user());
+}
+
+#[test]
+fn test_presence_round_trip() {
+    for presence in [Presence::Online, Presence::Idle, Presence::Offline].iter() {
+        assert_round_trip(
+            OpCode::PresenceUpdate,
+            PresenceUpdatePayload {
+                user: user().uuid,
+                presence: *presence,
+            },
+        );
+    }
+
+    assert_round_trip(OpCode::UpdatePresence, UpdatePresencePayload { idle: true });
+}
+
+#[test]
+fn test_message_pack_is_binary() {
+    let payload = MessagePayload {
+        op: OpCode::RoomJoin,
+        data: room(),
+    };
+
+    let json = match Encoding::Json.encode(&payload).unwrap() {
+        Frame::Text(text) => text,
+        Frame::Binary(_) => panic!("json should be sent as text"),
+    };
+    let msgpack = match Encoding::MessagePack.encode(&payload).unwrap() {
+        Frame::Binary(bytes) => bytes,
+        Frame::Text(_) => panic!("msgpack should be sent as binary"),
+    };
+
+    assert!(msgpack.len() < json.len());
+}
+
+#[test]
+fn test_permissions_are_sent_as_bits() {
+    let permissions = Permissions::SEND_MESSAGES | Permissions::KICK;
+    assert_eq!(serde_json::to_string(&permissions).unwrap(), "5");
+
+    // bits that aren't a known permission are dropped
+    let decoded = serde_json::from_str::<Permissions>("-1").unwrap();
+    assert_eq!(decoded, Permissions::ALL);
+    assert!(decoded.contains(Permissions::MANAGE_ROLES));
+}
@@ -0,0 +1,103 @@
+use chrono::{Duration, Utc};
+use common::{
+    parse_mentions, Message, MessageType, NotificationLevel, NotificationSettings, Room, User,
+};
+
+fn user(username: &str) -> User {
+    User::new(username.to_string(), "password".to_string())
+}
+
+fn message(author: &User, content: &str) -> Message {
+    Message::new(author.clone(), Room::new("room"), content.to_string())
+}
+
+fn settings(level: NotificationLevel, muted_for: Option<Duration>) -> NotificationSettings {
+    let mut settings = NotificationSettings::new(Room::new("room").uuid);
+    settings.level = level;
+    settings.muted_until = muted_for.map(|it| Utc::now() + it);
+    settings
+}
+
+#[test]
+fn test_parse_mentions() {
+    let content = "@bob hi @alice_2, mail@example.com @ @carol's (@dave)";
+    let mentions = parse_mentions(content).collect::<Vec<_>>();
+    assert_eq!(
+        mentions,
+        vec![(0..4, "bob"), (8..16, "alice_2"), (37..43, "carol")]
+    );
+    assert_eq!(&content[mentions[1].0.clone()], "@alice_2");
+}
+
+#[test]
+fn test_message_mentions() {
+    let author = user("author");
+    let bob = user("bob");
+
+    assert!(message(&author, "hey @bob!").mentions(&bob));
+    assert!(!message(&author, "hey @bobby").mentions(&bob));
+    assert!(!message(&author, "hey bob@bob").mentions(&bob));
+}
+
+#[test]
+fn test_notification_levels() {
+    let author = user("author");
+    let me = user("me");
+    let plain = message(&author, "hello");
+    let mention = message(&author, "hello @me");
+
+    let all = settings(NotificationLevel::All, None);
+    assert!(all.notifies(&plain, &me));
+    assert!(all.notifies(&mention, &me));
+
+    let mentions = settings(NotificationLevel::Mentions, None);
+    assert!(!mentions.notifies(&plain, &me));
+    assert!(mentions.notifies(&mention, &me));
+
+    let nothing = settings(NotificationLevel::Nothing, None);
+    assert!(!nothing.notifies(&plain, &me));
+    assert!(!nothing.notifies(&mention, &me));
+}
+
+#[test]
+fn test_own_and_system_messages_dont_notify() {
+    let me = user("me");
+    let all = settings(NotificationLevel::All, None);
+
+    assert!(!all.notifies(&message(&me, "hello @me"), &me));
+
+    let mut join = message(&user("author"), "");
+    join.type_ = MessageType::RoomJoin;
+    assert!(!all.notifies(&join, &me));
+}
+
+#[test]
+fn test_muted_rooms() {
+    let author = user("author");
+    let me = user("me");
+    let mention = message(&author, "hello @me");
+
+    for level in [NotificationLevel::All, NotificationLevel::Mentions].iter() {
+        let muted = settings(*level, Some(Duration::hours(1)));
+        assert!(muted.is_muted());
+        assert!(!muted.notifies(&mention, &me));
+
+        // the mute runs out by itself
+        let expired = settings(*level, Some(-Duration::hours(1)));
+        assert!(!expired.is_muted());
+        assert!(expired.notifies(&mention, &me));
+    }
+}
+
+#[test]
+fn test_counts_unread() {
+    let mut muted = settings(NotificationLevel::All, Some(Duration::hours(1)));
+    assert!(!muted.counts_unread());
+    muted.hide_unread_when_muted = false;
+    assert!(muted.counts_unread());
+
+    let mut unmuted = settings(NotificationLevel::Nothing, None);
+    assert!(unmuted.counts_unread());
+    unmuted.hide_unread_when_muted = false;
+    assert!(unmuted.counts_unread());
+}
@@ -0,0 +1,159 @@
+use crate::services::room::fetch_audit_log;
+use crate::utils::{format_time, use_token};
+use common::{AuditAction, AuditLogEntry, RoomMember};
+use std::rc::Rc;
+use uuid::Uuid;
+use wasm_bindgen_futures::spawn_local;
+use yew::prelude::*;
+use yew_functional::{function_component, use_effect_with_deps, use_state};
+use yew_material::{MatButton, MatIcon};
+
+/// How many entries are loaded at a time
+const PAGE_SIZE: i64 = 20;
+
+#[derive(Clone, Properties, PartialEq)]
+pub struct AuditLogProps {
+    pub room_id: Uuid,
+    /// Used to show names, those who left the room are shown as a former member
+    pub members: Vec<RoomMember>,
+}
+
+/// What the entry says happened, without the actor
+fn describe(entry: &AuditLogEntry, name_of: impl Fn(Uuid) -> String) -> String {
+    let target = entry.target.map(&name_of).unwrap_or_default();
+    let detail = |key: &str| {
+        entry.changes[key]
+            .as_str()
+            .map(|it| it.to_string())
+            .unwrap_or_default()
+    };
+
+    match entry.action {
+        AuditAction::RoomUpdate => {
+            let fields = entry
+                .changes
+                .as_object()
+                .map(|it| {
+                    it.keys()
+                        .map(|field| field.replace('_', " "))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                })
+                .unwrap_or_default();
+            format!("changed the {} of the room", fields)
+        }
+        AuditAction::RoomArchive => "archived the room".to_string(),
+        AuditAction::RoomUnarchive => "unarchived the room".to_string(),
+        AuditAction::OwnershipTransfer => format!("made {} the owner", target),
+        AuditAction::MemberJoin if entry.actor.is_some() && entry.target == entry.actor => {
+            "joined".to_string()
+        }
+        AuditAction::MemberJoin => format!("added {}", target),
+        AuditAction::MemberKick => format!("kicked {}", target),
+        AuditAction::MemberBan => format!("banned {}", target),
+        AuditAction::MemberUnban => format!("unbanned {}", target),
+        AuditAction::MemberRoleUpdate => format!("changed the role of {}", target),
+        AuditAction::RoleCreate => format!("created the role {}", detail("name")),
+        AuditAction::RoleUpdate => "updated a role".to_string(),
+        AuditAction::RoleDelete => format!("deleted the role {}", detail("name")),
+        AuditAction::InviteCreate => format!("created the invite {}", detail("code")),
+        AuditAction::InviteRevoke => format!("revoked the invite {}", detail("code")),
+        AuditAction::MessageDelete => format!("deleted a message by {}", target),
+        AuditAction::ReportResolve => match detail("action").as_str() {
+            "dismiss" => format!("dismissed a report about {}", target),
+            action => format!(
+                "resolved a report about {} ({})",
+                target,
+                action.replace('_', " ")
+            ),
+        },
+    }
+}
+
+#[function_component(AuditLog)]
+pub fn audit_log(props: &AuditLogProps) -> Html {
+    let token = use_token();
+    let (entries, set_entries) = use_state(Vec::<AuditLogEntry>::new);
+    let (has_more, set_has_more) = use_state(|| false);
+
+    // fetches the page after `loaded` entries and puts it after them
+    let load = {
+        let token = Rc::clone(&token);
+        let room_id = props.room_id;
+        let (set_entries, set_has_more) = (Rc::clone(&set_entries), Rc::clone(&set_has_more));
+
+        Rc::new(move |loaded: Vec<AuditLogEntry>| {
+            let token = Rc::clone(&token);
+            let (set_entries, set_has_more) = (Rc::clone(&set_entries), Rc::clone(&set_has_more));
+
+            spawn_local(async move {
+                match fetch_audit_log(&*token, room_id, loaded.len() as i64, PAGE_SIZE).await {
+                    Ok(page) => {
+                        set_has_more(page.len() as i64 == PAGE_SIZE);
+                        set_entries(loaded.into_iter().chain(page).collect());
+                    }
+                    Err(e) => weblog::console_error!(e.to_string()),
+                }
+            })
+        })
+    };
+
+    {
+        let load = Rc::clone(&load);
+        use_effect_with_deps(
+            move |_| {
+                load(vec![]);
+                || {}
+            },
+            props.room_id,
+        );
+    }
+
+    let name_of = |user: Uuid| {
+        props
+            .members
+            .iter()
+            .find(|member| member.user.uuid == user)
+            .map_or_else(
+                || "a former member".to_string(),
+                |member| member.user.name().to_string(),
+            )
+    };
+
+    let items = entries
+        .iter()
+        .map(|entry| {
+            html! {
+                <li>
+                    <strong>{ entry.actor.map_or_else(|| "a deleted user".to_string(), name_of) }</strong>
+                    { " " }
+                    { describe(entry, name_of) }
+                    <time>{ format_time(&entry.created_at) }</time>
+                </li>
+            }
+        })
+        .collect::<Vec<Html>>();
+
+    let load_more = if *has_more {
+        let entries = Rc::clone(&entries);
+        let onclick = Callback::from(move |_| load(entries.to_vec()));
+        html! {
+            <span onclick=onclick>
+                <MatButton label="Load more" />
+            </span>
+        }
+    } else {
+        html!()
+    };
+
+    html! {
+        <section class="room-audit-log">
+            <header>
+                <MatIcon>{ "history" }</MatIcon>
+                <h3>{ "Audit log" }</h3>
+            </header>
+            <ul>{ for items }</ul>
+            { load_more }
+        </section>
+    }
+}
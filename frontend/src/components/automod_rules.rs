@@ -0,0 +1,240 @@
+use crate::services::room::{create_automod_rule, delete_automod_rule, fetch_automod_rules};
+use crate::utils::use_token;
+use common::payloads::CreateAutomodRule;
+use common::{AutomodAction, AutomodRule, AutomodRuleKind};
+use std::rc::Rc;
+use uuid::Uuid;
+use wasm_bindgen_futures::spawn_local;
+use yew::prelude::*;
+use yew_functional::{function_component, use_effect_with_deps, use_state};
+use yew_material::{text_inputs::TextFieldType, MatButton, MatIcon, MatIconButton, MatTextField};
+
+const KINDS: [(AutomodRuleKind, &str); 5] = [
+    (AutomodRuleKind::BannedWords, "Banned words"),
+    (AutomodRuleKind::Regex, "Regexes"),
+    (AutomodRuleKind::LinkAllowList, "Only links to"),
+    (AutomodRuleKind::LinkDenyList, "No links to"),
+    (AutomodRuleKind::MaxMentions, "Mention limit"),
+];
+
+const ACTIONS: [(AutomodAction, &str); 3] = [
+    (AutomodAction::Reject, "Reject"),
+    (AutomodAction::Mask, "Mask"),
+    (AutomodAction::Flag, "Flag for review"),
+];
+
+fn label_of<T: PartialEq>(labels: &[(T, &'static str)], value: &T) -> &'static str {
+    labels
+        .iter()
+        .find(|(it, _)| it == value)
+        .map_or("", |(_, label)| label)
+}
+
+#[derive(Clone, Properties, PartialEq)]
+pub struct AutomodRulesProps {
+    pub room_id: Uuid,
+}
+
+/// The automod rules of the room, for members who can manage it
+#[function_component(AutomodRules)]
+pub fn automod_rules(props: &AutomodRulesProps) -> Html {
+    let token = use_token();
+    let room_id = props.room_id;
+    let (rules, set_rules) = use_state(Vec::<AutomodRule>::new);
+    let (kind, set_kind) = use_state(|| AutomodRuleKind::BannedWords);
+    let (action, set_action) = use_state(|| AutomodAction::Reject);
+    // comma separated patterns, or the number of mentions for a mention limit
+    let (value, set_value) = use_state(String::new);
+    let (error, set_error) = use_state(|| None::<String>);
+
+    {
+        let token = Rc::clone(&token);
+        let set_rules = Rc::clone(&set_rules);
+        use_effect_with_deps(
+            move |room_id| {
+                let room_id = *room_id;
+                spawn_local(async move {
+                    match fetch_automod_rules(&*token, room_id).await {
+                        Ok(rules) => set_rules(rules),
+                        Err(e) => weblog::console_error!(e.to_string()),
+                    }
+                });
+                || {}
+            },
+            room_id,
+        );
+    }
+
+    let delete_callback = |rule: Uuid| {
+        let token = Rc::clone(&token);
+        let (rules, set_rules) = (Rc::clone(&rules), Rc::clone(&set_rules));
+
+        Callback::from(move |_: MouseEvent| {
+            let token = Rc::clone(&token);
+            let (rules, set_rules) = (Rc::clone(&rules), Rc::clone(&set_rules));
+
+            spawn_local(async move {
+                match delete_automod_rule(&*token, room_id, rule).await {
+                    Ok(_) => {
+                        set_rules(rules.iter().filter(|it| it.uuid != rule).cloned().collect())
+                    }
+                    Err(e) => weblog::console_error!(e.to_string()),
+                }
+            })
+        })
+    };
+
+    let create_onclick = {
+        let token = Rc::clone(&token);
+        let (rules, set_rules) = (Rc::clone(&rules), Rc::clone(&set_rules));
+        let (kind, action, value) = (*kind, *action, Rc::clone(&value));
+        let (set_value, set_error) = (Rc::clone(&set_value), Rc::clone(&set_error));
+
+        Callback::from(move |_| {
+            let data = if kind == AutomodRuleKind::MaxMentions {
+                CreateAutomodRule {
+                    kind,
+                    patterns: vec![],
+                    max_mentions: value.trim().parse().ok(),
+                    action,
+                }
+            } else {
+                CreateAutomodRule {
+                    kind,
+                    patterns: value
+                        .split(',')
+                        .map(|it| it.trim().to_string())
+                        .filter(|it| !it.is_empty())
+                        .collect(),
+                    max_mentions: None,
+                    action,
+                }
+            };
+            if let Err(e) = data.validate() {
+                set_error(Some(e));
+                return;
+            }
+
+            let token = Rc::clone(&token);
+            let (rules, set_rules) = (Rc::clone(&rules), Rc::clone(&set_rules));
+            let (set_value, set_error) = (Rc::clone(&set_value), Rc::clone(&set_error));
+            spawn_local(async move {
+                match create_automod_rule(&*token, room_id, &data).await {
+                    Ok(rule) => {
+                        set_rules(rules.iter().cloned().chain(Some(rule)).collect());
+                        set_value(String::new());
+                        set_error(None);
+                    }
+                    Err(e) => set_error(Some(e.to_string())),
+                }
+            })
+        })
+    };
+
+    let items = rules
+        .iter()
+        .map(|rule| {
+            let details = match rule.max_mentions {
+                Some(max) => max.to_string(),
+                None => rule.patterns.join(", "),
+            };
+            html! {
+                <article>
+                    <span>
+                        <strong>{ label_of(&KINDS, &rule.kind) }</strong>
+                        { ": " }{ details }
+                    </span>
+                    <span class="automod-rule-action">{ label_of(&ACTIONS, &rule.action) }</span>
+                    <span title="Delete" onclick=delete_callback(rule.uuid)>
+                        <MatIconButton icon="delete" />
+                    </span>
+                </article>
+            }
+        })
+        .collect::<Vec<Html>>();
+
+    let choices = |options: Vec<(Callback<MouseEvent>, bool, &'static str)>| {
+        options
+            .into_iter()
+            .map(|(onclick, selected, label)| {
+                let icon = if selected {
+                    "radio_button_checked"
+                } else {
+                    "radio_button_unchecked"
+                };
+                html! {
+                    <article onclick=onclick>
+                        <MatIcon>{ icon }</MatIcon>
+                        <span>{ label }</span>
+                    </article>
+                }
+            })
+            .collect::<Vec<Html>>()
+    };
+    let kinds = choices(
+        KINDS
+            .iter()
+            .map(|(it, label)| {
+                let (it, set_kind) = (*it, Rc::clone(&set_kind));
+                (Callback::from(move |_| set_kind(it)), *kind == it, *label)
+            })
+            .collect(),
+    );
+    let actions = choices(
+        ACTIONS
+            .iter()
+            .map(|(it, label)| {
+                let (it, set_action) = (*it, Rc::clone(&set_action));
+                (
+                    Callback::from(move |_| set_action(it)),
+                    *action == it,
+                    *label,
+                )
+            })
+            .collect(),
+    );
+
+    let value_field = if *kind == AutomodRuleKind::MaxMentions {
+        html! {
+            <MatTextField
+                outlined=true
+                field_type=TextFieldType::Number
+                label="Max mentions"
+                value=&*value
+                oninput=Callback::from(move |e: InputData| set_value(e.value))
+            />
+        }
+    } else {
+        html! {
+            <MatTextField
+                outlined=true
+                label="Patterns"
+                helper="Separated by commas, domains for link lists"
+                value=&*value
+                oninput=Callback::from(move |e: InputData| set_value(e.value))
+            />
+        }
+    };
+    let error = match &*error {
+        Some(e) => html! { <span class="automod-error">{ e }</span> },
+        None => html!(),
+    };
+
+    html! {
+        <section class="room-automod-rules">
+            <header>
+                <MatIcon>{ "gavel" }</MatIcon>
+                <h3>{ "Automod" }</h3>
+            </header>
+            { for items }
+            <h4>{ "New rule" }</h4>
+            { for kinds }
+            { value_field }
+            { for actions }
+            { error }
+            <span onclick=create_onclick>
+                <MatButton label="Add rule" />
+            </span>
+        </section>
+    }
+}
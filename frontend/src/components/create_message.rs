@@ -1,5 +1,6 @@
+use crate::services::request::RateLimited;
 use crate::services::room::send_message;
-use crate::utils::use_token;
+use crate::utils::{sleep, use_token};
 use common::payloads::CreateMessage as CreateMessagePayload;
 use common::Room;
 use std::rc::Rc;
@@ -13,26 +14,38 @@ pub struct CreateMessageProps {
     pub room: Room,
 }
 
+/// Counts the seconds left until messages can be sent again down to zero
+async fn count_down(set_cooldown: Rc<dyn Fn(u32)>, seconds: u32) {
+    for remaining in (1..=seconds).rev() {
+        set_cooldown(remaining);
+        sleep(1000).await;
+    }
+    set_cooldown(0);
+}
+
 #[function_component(CreateMessage)]
 pub fn create_message(props: &CreateMessageProps) -> Html {
     let (message, set_message) = use_state(|| "".to_string());
     let token = use_token();
     let (error, set_error) = use_state(|| None);
+    let (cooldown, set_cooldown) = use_state(|| 0u32);
 
     let onclick = {
         let message = Rc::clone(&message);
         let set_message = Rc::clone(&set_message);
         let room_id = props.room.uuid;
+        let cooling_down = *cooldown > 0;
 
         Callback::from(move |_| {
             let message = Rc::clone(&message);
-            if message.is_empty() {
+            if message.is_empty() || cooling_down {
                 return;
             }
 
             let token = Rc::clone(&token);
             let set_message = Rc::clone(&set_message);
             let set_error = Rc::clone(&set_error);
+            let set_cooldown = Rc::clone(&set_cooldown);
 
             spawn_local(async move {
                 let result = send_message(
@@ -45,7 +58,12 @@ pub fn create_message(props: &CreateMessageProps) -> Html {
                 .await;
                 match result {
                     Ok(_) => set_message(String::new()),
-                    Err(e) => set_error(Some(e)),
+                    Err(e) => match e.downcast_ref::<RateLimited>() {
+                        Some(limited) => {
+                            count_down(set_cooldown, limited.retry_after.ceil() as u32).await
+                        }
+                        None => set_error(Some(e)),
+                    },
                 }
             });
         })
@@ -78,6 +96,23 @@ pub fn create_message(props: &CreateMessageProps) -> Html {
         html!()
     };
 
+    let cooling_down = *cooldown > 0;
+    let notice = if cooling_down {
+        html! {
+            <span class="message-cooldown">
+                { format!("You can send another message in {}s", *cooldown) }
+            </span>
+        }
+    } else if props.room.slow_mode > 0 {
+        html! {
+            <span class="message-cooldown">
+                { format!("Slow mode is on: one message every {}s", props.room.slow_mode) }
+            </span>
+        }
+    } else {
+        html!()
+    };
+
     html! {<>
         <article class="new-message-form-container">
             <MatFormfield>
@@ -89,8 +124,9 @@ pub fn create_message(props: &CreateMessageProps) -> Html {
                  />
             </MatFormfield>
             { error_node }
+            { notice }
             <span onclick=onclick>
-                <MatIconButton icon="send"/>
+                <MatIconButton icon="send" disabled=cooling_down />
             </span>
         </article>
     </>}
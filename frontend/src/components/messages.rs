@@ -5,6 +5,7 @@ use crate::websocket::{internal_events, InternalEventBus};
 use common::{Message, Room};
 use std::cell::Ref;
 use std::rc::Rc;
+use uuid::Uuid;
 use wasm_bindgen_futures::spawn_local;
 use yew::prelude::*;
 use yew_functional::{function_component, use_effect, use_effect_with_deps, use_ref, use_state};
@@ -12,6 +13,9 @@ use yew_functional::{function_component, use_effect, use_effect_with_deps, use_r
 #[derive(Clone, Properties, PartialEq)]
 pub struct MessagesProps {
     pub room: Room,
+    /// The current user, who can't report their own messages
+    #[prop_or_default]
+    pub me: Option<Uuid>,
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -70,6 +74,14 @@ pub fn show_room_messages(props: &MessagesProps) -> Html {
                         set_state(LoadingState::Loaded)
                     }
                 }
+                internal_events::Response::DeleteMessage(msg) => {
+                    if msg.room.uuid == current_uuid {
+                        messages.borrow_mut().retain(|it| it.uuid != msg.uuid);
+                        set_state(LoadingState::Loaded)
+                    }
+                }
+                internal_events::Response::PresenceUpdate(_)
+                | internal_events::Response::NewReport(_) => {}
             }));
 
             || drop(producer)
@@ -82,7 +94,7 @@ pub fn show_room_messages(props: &MessagesProps) -> Html {
             messages.borrow_mut().clear();
             html!("loading")
         }
-        LoadingState::Loaded => display_messages(messages.borrow()),
+        LoadingState::Loaded => display_messages(messages.borrow(), props.me),
         LoadingState::Error(e) => html!(e.to_string()),
     };
 
@@ -93,10 +105,10 @@ pub fn show_room_messages(props: &MessagesProps) -> Html {
     }
 }
 
-fn display_messages(messages: Ref<Vec<Message>>) -> Html {
-    let messages = messages
-        .iter()
-        .map(|message| html! { <SingleMessage key=message.uuid.to_string() message=message /> });
+fn display_messages(messages: Ref<Vec<Message>>, me: Option<Uuid>) -> Html {
+    let messages = messages.iter().map(|message| {
+        html! { <SingleMessage key=message.uuid.to_string() message=message me=me /> }
+    });
 
     html! { for messages }
 }
@@ -1,17 +1,27 @@
+mod audit_log;
 mod auth;
+mod automod_rules;
 mod create_message;
 mod messages;
+mod notification_settings;
+mod reports;
 mod room;
 mod rooms_list;
 mod single_message;
+mod space_rooms;
 mod update_profile;
 mod user_avatar;
 
+pub use audit_log::AuditLog;
 pub use auth::Auth;
+pub use automod_rules::AutomodRules;
 pub use create_message::CreateMessage;
 pub use messages::RoomMessages;
+pub use notification_settings::NotificationSettingsSection;
+pub use reports::ReportQueue;
 pub use room::Room;
 pub use rooms_list::RoomsList;
 pub use single_message::SingleMessage;
+pub use space_rooms::{muted_icon, room_list_item, unread_badge, SpaceRooms};
 pub use update_profile::UpdateProfile;
-pub use user_avatar::{UserAvatar, UserProfileDialog};
+pub use user_avatar::{PresenceDot, UserAvatar, UserProfileDialog};
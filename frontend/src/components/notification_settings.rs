@@ -0,0 +1,142 @@
+use crate::services::room::update_notification_settings;
+use crate::utils::{format_time, use_token};
+use chrono::{DateTime, Duration, Utc};
+use common::payloads::UpdateNotificationSettings;
+use common::{NotificationLevel, NotificationSettings};
+use std::rc::Rc;
+use wasm_bindgen_futures::spawn_local;
+use yew::prelude::*;
+use yew_functional::function_component;
+use yew_material::MatIcon;
+
+/// How long the room can be muted for, in hours
+const MUTE_DURATIONS: [(&str, i64); 3] = [("1 hour", 1), ("8 hours", 8), ("1 week", 7 * 24)];
+
+/// Far enough away that the room never gets unmuted by itself
+fn forever() -> DateTime<Utc> {
+    Utc::now() + Duration::days(100 * 365)
+}
+
+#[derive(Clone, Properties, PartialEq)]
+pub struct NotificationSettingsProps {
+    pub settings: NotificationSettings,
+}
+
+/// The notification settings of the current user for a room, they show up again
+/// through `NotificationSettingsUpdate` once saved
+#[function_component(NotificationSettingsSection)]
+pub fn notification_settings_section(props: &NotificationSettingsProps) -> Html {
+    let token = use_token();
+    let room_id = props.settings.room;
+
+    let update = |data: UpdateNotificationSettings| {
+        let token = Rc::clone(&token);
+        let data = Rc::new(data);
+        Callback::from(move |_: MouseEvent| {
+            let token = Rc::clone(&token);
+            let data = Rc::clone(&data);
+            spawn_local(async move {
+                if let Err(e) = update_notification_settings(&*token, room_id, &data).await {
+                    weblog::console_error!(e.to_string());
+                }
+            })
+        })
+    };
+
+    let levels = [
+        (NotificationLevel::All, "All messages"),
+        (NotificationLevel::Mentions, "Only mentions"),
+        (NotificationLevel::Nothing, "Nothing"),
+    ]
+    .iter()
+    .map(|(level, label)| {
+        let icon = if props.settings.level == *level {
+            "radio_button_checked"
+        } else {
+            "radio_button_unchecked"
+        };
+        let onclick = update(UpdateNotificationSettings {
+            level: Some(*level),
+            ..Default::default()
+        });
+
+        html! {
+            <article onclick=onclick>
+                <MatIcon>{ icon }</MatIcon>
+                <span>{ label }</span>
+            </article>
+        }
+    })
+    .collect::<Vec<Html>>();
+
+    let mute = if props.settings.is_muted() {
+        let muted_until = props.settings.muted_until.unwrap_or_else(Utc::now);
+        let label = if muted_until > Utc::now() + Duration::days(365) {
+            "Muted".to_string()
+        } else {
+            format!("Muted until {}", format_time(&muted_until))
+        };
+        let unmute_onclick = update(UpdateNotificationSettings {
+            muted_until: Some(None),
+            ..Default::default()
+        });
+
+        html! {
+            <article onclick=unmute_onclick>
+                <MatIcon>{ "notifications_active" }</MatIcon>
+                <span>{ label }{ ", unmute" }</span>
+            </article>
+        }
+    } else {
+        let durations = MUTE_DURATIONS.iter().map(|(label, hours)| {
+            let onclick = update(UpdateNotificationSettings {
+                muted_until: Some(Some(Utc::now() + Duration::hours(*hours))),
+                ..Default::default()
+            });
+            html! {
+                <article onclick=onclick>
+                    <MatIcon>{ "notifications_paused" }</MatIcon>
+                    <span>{ format!("Mute for {}", label) }</span>
+                </article>
+            }
+        });
+        let forever_onclick = update(UpdateNotificationSettings {
+            muted_until: Some(Some(forever())),
+            ..Default::default()
+        });
+
+        html! {<>
+            { for durations }
+            <article onclick=forever_onclick>
+                <MatIcon>{ "notifications_off" }</MatIcon>
+                <span>{ "Mute until I unmute it" }</span>
+            </article>
+        </>}
+    };
+
+    let hide_unread = props.settings.hide_unread_when_muted;
+    let hide_unread_onclick = update(UpdateNotificationSettings {
+        hide_unread_when_muted: Some(!hide_unread),
+        ..Default::default()
+    });
+    let hide_unread_icon = if hide_unread {
+        "check_box"
+    } else {
+        "check_box_outline_blank"
+    };
+
+    html! {
+        <section class="room-notification-settings">
+            <header>
+                <MatIcon>{ "notifications" }</MatIcon>
+                <h3>{ "Notifications" }</h3>
+            </header>
+            { for levels }
+            { mute }
+            <article onclick=hide_unread_onclick>
+                <MatIcon>{ hide_unread_icon }</MatIcon>
+                <span>{ "Leave out of unread counts while muted" }</span>
+            </article>
+        </section>
+    }
+}
@@ -0,0 +1,156 @@
+use crate::services::room::{fetch_reports, resolve_report};
+use crate::utils::{format_time, use_token};
+use crate::websocket::{internal_events, InternalEventBus};
+use common::{Report, ReportAction, RoomMember};
+use std::rc::Rc;
+use uuid::Uuid;
+use wasm_bindgen_futures::spawn_local;
+use yew::prelude::*;
+use yew_functional::{function_component, use_effect, use_effect_with_deps, use_state};
+use yew_material::{MatIcon, MatIconButton};
+
+/// What admins can do about a report, with the icon and label of its button
+const ACTIONS: [(ReportAction, &str, &str); 4] = [
+    (ReportAction::Dismiss, "done", "Dismiss"),
+    (ReportAction::DeleteMessage, "delete", "Delete message"),
+    (ReportAction::Kick, "person_remove", "Kick author"),
+    (ReportAction::Ban, "block", "Ban author"),
+];
+
+#[derive(Clone, Properties, PartialEq)]
+pub struct ReportQueueProps {
+    pub room_id: Uuid,
+    /// Used to show names, those who left the room are shown as a former member
+    pub members: Vec<RoomMember>,
+}
+
+/// The open reports of the room, new ones come in through `ReportCreate`
+#[function_component(ReportQueue)]
+pub fn report_queue(props: &ReportQueueProps) -> Html {
+    let token = use_token();
+    let (reports, set_reports) = use_state(Vec::<Report>::new);
+
+    {
+        let token = Rc::clone(&token);
+        let set_reports = Rc::clone(&set_reports);
+        use_effect_with_deps(
+            move |room_id| {
+                let room_id = *room_id;
+                spawn_local(async move {
+                    match fetch_reports(&*token, room_id).await {
+                        Ok(reports) => set_reports(reports),
+                        Err(e) => weblog::console_error!(e.to_string()),
+                    }
+                });
+                || {}
+            },
+            props.room_id,
+        );
+    }
+
+    {
+        let (reports, set_reports) = (Rc::clone(&reports), Rc::clone(&set_reports));
+        let room_id = props.room_id;
+
+        use_effect(move || {
+            let bridge = InternalEventBus::bridge(Callback::from(move |msg| {
+                if let internal_events::Response::NewReport(report) = msg {
+                    if report.room == room_id {
+                        let reports = std::iter::once((*report).clone())
+                            .chain(reports.iter().cloned())
+                            .collect();
+                        set_reports(reports);
+                    }
+                }
+            }));
+
+            || drop(bridge)
+        });
+    }
+
+    // the report leaves the queue once the server carried out the action
+    let resolve_callback = |report: Uuid, action: ReportAction| {
+        let token = Rc::clone(&token);
+        let (reports, set_reports) = (Rc::clone(&reports), Rc::clone(&set_reports));
+        let room_id = props.room_id;
+
+        Callback::from(move |_: MouseEvent| {
+            let token = Rc::clone(&token);
+            let (reports, set_reports) = (Rc::clone(&reports), Rc::clone(&set_reports));
+
+            spawn_local(async move {
+                match resolve_report(&*token, room_id, report, action).await {
+                    Ok(_) => set_reports(
+                        reports
+                            .iter()
+                            .filter(|it| it.uuid != report)
+                            .cloned()
+                            .collect(),
+                    ),
+                    Err(e) => weblog::console_error!(e.to_string()),
+                }
+            })
+        })
+    };
+
+    let name_of = |user: Option<Uuid>| {
+        props
+            .members
+            .iter()
+            .find(|member| Some(member.user.uuid) == user)
+            .map_or_else(
+                || "a former member".to_string(),
+                |member| member.user.name().to_string(),
+            )
+    };
+
+    let items = reports
+        .iter()
+        .map(|report| {
+            let actions = ACTIONS.iter().map(|(action, icon, label)| {
+                html! {
+                    <span title=*label onclick=resolve_callback(report.uuid, *action)>
+                        <MatIconButton icon=*icon />
+                    </span>
+                }
+            });
+
+            // what automod flags doesn't have a reporter, nor reports of deleted users
+            let reporter = if report.reporter.is_none() && report.reason.starts_with("automod:") {
+                "Automod".to_string()
+            } else {
+                name_of(report.reporter)
+            };
+
+            html! {
+                <li>
+                    <section>
+                        <strong>{ reporter }</strong>
+                        { " reported " }
+                        <strong>{ name_of(report.author) }</strong>
+                        <time>{ format_time(&report.created_at) }</time>
+                    </section>
+                    <blockquote>{ &report.content }</blockquote>
+                    <span class="report-reason">{ &report.reason }</span>
+                    <section class="report-actions">{ for actions }</section>
+                </li>
+            }
+        })
+        .collect::<Vec<Html>>();
+
+    let list = if items.is_empty() {
+        html! { <span class="report-queue-empty">{ "Nothing to review" }</span> }
+    } else {
+        html! { <ul>{ for items }</ul> }
+    };
+
+    html! {
+        <section class="room-reports">
+            <header>
+                <MatIcon>{ "flag" }</MatIcon>
+                <h3>{ "Reports" }</h3>
+            </header>
+            { list }
+        </section>
+    }
+}
@@ -1,13 +1,23 @@
-use crate::components::{CreateMessage, RoomMessages};
-use crate::services::room::{fetch_room_members, join_room};
+use crate::components::{
+    AuditLog, AutomodRules, CreateMessage, NotificationSettingsSection, PresenceDot, ReportQueue,
+    RoomMessages,
+};
+use crate::services::room::{
+    archive_room, ban_member, delete_room, fetch_room_members, join_room, kick_member, leave_room,
+    open_dm, transfer_ownership, update_room,
+};
+use crate::services::user::block_user;
 use crate::utils::{asset_url, format_time, use_token};
-use crate::{DATA_THEME_ATTR, PREFERS_DARK_KEY};
-use common::User;
+use crate::websocket::{internal_events, InternalEventBus};
+use crate::{AppRoute, DATA_THEME_ATTR, PREFERS_DARK_KEY};
+use common::payloads::UpdateRoom;
+use common::{NotificationSettings, Permissions, PostPermission, Presence, RoomKind, User};
 use std::rc::Rc;
+use uuid::Uuid;
 use wasm_bindgen_futures::spawn_local;
 use yew::prelude::*;
 use yew::services::storage::Area;
-use yew::services::StorageService;
+use yew::services::{DialogService, StorageService};
 use yew_functional::{function_component, use_effect, use_effect_with_deps, use_ref, use_state};
 use yew_material::{
     dialog::{ActionType, MatDialogAction},
@@ -18,6 +28,8 @@ use yew_material::{
     MatButton, MatDialog, MatIcon, MatIconButton, MatIconButtonToggle, MatTextField,
     WeakComponentLink,
 };
+use yew_router::agent::RouteRequest;
+use yew_router::prelude::*;
 
 #[derive(Clone, Properties, PartialEq)]
 struct TopRoomBarProps {
@@ -65,6 +77,11 @@ fn top_room_bar(props: &TopRoomBarProps) -> Html {
         .map(|it| &it.name)
         .unwrap_or(&default_room_name);
 
+    let topic = match props.room.as_ref().and_then(|it| it.topic.as_ref()) {
+        Some(topic) => html! { <span class="room-topic">{ topic }</span> },
+        None => html!(),
+    };
+
     let storage_service = use_ref(|| StorageService::new(Area::Local).unwrap());
     let on_theme_change = Callback::from(move |state: bool| {
         let theme = if state {
@@ -95,6 +112,7 @@ fn top_room_bar(props: &TopRoomBarProps) -> Html {
                 <span class="room-name" onclick=room_name_click>
                     { room_name }
                 </span>
+                { topic }
             </MatTopAppBarTitle>
 
             <MatTopAppBarActionItems>
@@ -118,14 +136,30 @@ fn top_room_bar(props: &TopRoomBarProps) -> Html {
 #[derive(Clone, Properties, PartialEq)]
 struct UserCardProps {
     user: User,
+    presence: Presence,
+    #[prop_or_default]
+    is_owner: bool,
+    #[prop_or_default]
+    actions: Html,
 }
 
 #[function_component(UserCard)]
 fn user_card(props: &UserCardProps) -> Html {
     html! {
         <article>
-            <img src=asset_url(props.user.avatar.as_ref()) />
-            <span>{ &props.user.username }</span>
+            <span class="user-card-avatar">
+                <img src=asset_url(props.user.avatar.as_ref()) />
+                <PresenceDot presence=props.presence />
+            </span>
+            <span>{ props.user.name() }</span>
+            {
+                if props.is_owner {
+                    html! { <span class="owner-badge" title="Owner"><MatIcon>{ "star" }</MatIcon></span> }
+                } else {
+                    html!()
+                }
+            }
+            <span class="user-card-actions">{ props.actions.clone() }</span>
         </article>
     }
 }
@@ -133,6 +167,9 @@ fn user_card(props: &UserCardProps) -> Html {
 #[derive(Clone, Properties, PartialEq)]
 pub struct ShowRoomProps {
     pub room: Option<common::Room>,
+    pub me: Option<User>,
+    /// How the user is notified about the room
+    pub notification_settings: Option<NotificationSettings>,
     pub user_avatar_action: Html,
     pub onnavigationiconclick: Option<Callback<()>>,
 }
@@ -199,14 +236,244 @@ pub fn show_room(props: &ShowRoomProps) -> Html {
         );
     }
 
+    {
+        let members = Rc::clone(&members);
+        let set_members = Rc::clone(&set_members);
+
+        use_effect(move || {
+            let bridge = InternalEventBus::bridge(Callback::from(move |msg| {
+                if let internal_events::Response::PresenceUpdate(update) = msg {
+                    if members.iter().any(|member| member.user.uuid == update.user) {
+                        let members = members
+                            .iter()
+                            .cloned()
+                            .map(|mut member| {
+                                if member.user.uuid == update.user {
+                                    member.presence = update.presence;
+                                }
+                                member
+                            })
+                            .collect();
+                        set_members(members);
+                    }
+                }
+            }));
+
+            || drop(bridge)
+        });
+    }
+
+    let me = props.me.as_ref().map(|me| me.uuid);
+    let my_member = members.iter().find(|member| Some(member.user.uuid) == me);
+    let can_kick = my_member.map_or(false, |it| it.can(Permissions::KICK));
+    let can_ban = my_member.map_or(false, |it| it.can(Permissions::BAN));
+    let can_manage_room = my_member.map_or(false, |it| it.can(Permissions::MANAGE_ROOM));
+    let is_admin = my_member.map_or(false, |it| it.role.is_admin);
+    let is_owner = me.map_or(false, |me| room.is_owner(me));
+    // rooms without an owner are left to their admins
+    let can_delete = if room.owner.is_some() {
+        is_owner
+    } else {
+        is_admin
+    };
+
+    // kicked and banned members are taken out of the list once the request succeeds
+    let remove_member_callback = |member: Uuid, ban: bool| {
+        let token = Rc::clone(&token);
+        let (members, set_members) = (Rc::clone(&members), Rc::clone(&set_members));
+
+        Callback::from(move |_| {
+            let token = Rc::clone(&token);
+            let (members, set_members) = (Rc::clone(&members), Rc::clone(&set_members));
+
+            spawn_local(async move {
+                let result = if ban {
+                    ban_member(&*token, room_id, member).await
+                } else {
+                    kick_member(&*token, room_id, member).await
+                };
+
+                match result {
+                    Ok(()) => set_members(
+                        members
+                            .iter()
+                            .filter(|it| it.user.uuid != member)
+                            .cloned()
+                            .collect(),
+                    ),
+                    Err(e) => weblog::console_error!(e.to_string()),
+                }
+            })
+        })
+    };
+
+    let router = use_ref(RouteAgentDispatcher::<()>::new);
+    let message_callback = |member: Uuid| {
+        let token = Rc::clone(&token);
+        let router = Rc::clone(&router);
+
+        Callback::from(move |_| {
+            let token = Rc::clone(&token);
+            let router = Rc::clone(&router);
+
+            spawn_local(async move {
+                match open_dm(&*token, vec![member]).await {
+                    Ok(dm) => {
+                        let route = Route::from(AppRoute::Rooms(dm.uuid));
+                        router.borrow_mut().send(RouteRequest::ChangeRoute(route));
+                    }
+                    Err(e) => weblog::console_error!(e.to_string()),
+                }
+            })
+        })
+    };
+
+    // their messages show up collapsed once the room is opened again
+    let block_callback = |member: &User| {
+        let token = Rc::clone(&token);
+        let (uuid, name) = (member.uuid, member.name().to_string());
+
+        Callback::from(move |_| {
+            let confirmed = DialogService::confirm(&format!(
+                "Block {}? They won't be able to message you or add you to rooms.",
+                name
+            ));
+            if !confirmed {
+                return;
+            }
+
+            let token = Rc::clone(&token);
+            spawn_local(async move {
+                if let Err(e) = block_user(&*token, uuid).await {
+                    weblog::console_error!(e.to_string());
+                }
+            })
+        })
+    };
+
+    // the new owner becomes an admin, the room itself changes through `RoomUpdate`
+    let transfer_callback = |member: &User| {
+        let token = Rc::clone(&token);
+        let (members, set_members) = (Rc::clone(&members), Rc::clone(&set_members));
+        let admin_role = my_member.map(|it| it.role.clone());
+        let (uuid, name, room_name) = (member.uuid, member.name().to_string(), room.name.clone());
+
+        Callback::from(move |_| {
+            let confirmed = DialogService::confirm(&format!(
+                "Make {} the owner of {}? You will stay an admin.",
+                name, room_name
+            ));
+            if !confirmed {
+                return;
+            }
+
+            let token = Rc::clone(&token);
+            let (members, set_members) = (Rc::clone(&members), Rc::clone(&set_members));
+            let admin_role = admin_role.clone();
+
+            spawn_local(async move {
+                match transfer_ownership(&*token, room_id, uuid).await {
+                    Ok(_) => set_members(
+                        members
+                            .iter()
+                            .cloned()
+                            .map(|mut it| {
+                                match &admin_role {
+                                    Some(role) if it.user.uuid == uuid => it.role = role.clone(),
+                                    _ => {}
+                                }
+                                it
+                            })
+                            .collect(),
+                    ),
+                    Err(e) => weblog::console_error!(e.to_string()),
+                }
+            })
+        })
+    };
+
     let user_cards = match &*member_fetch_error {
         Some(e) => vec![html!(e.to_string())],
         None => members
             .iter()
-            .map(|member| html! { <UserCard user=&member.user /> })
+            .map(|member| {
+                let uuid = member.user.uuid;
+                // the server has the final say, this only hides what would be refused
+                let removable = Some(uuid) != me && !member.role.is_admin;
+                let kick = if can_kick && removable {
+                    html! {
+                        <span title="Kick" onclick=remove_member_callback(uuid, false)>
+                            <MatIconButton icon="person_remove" />
+                        </span>
+                    }
+                } else {
+                    html!()
+                };
+                let ban = if can_ban && removable {
+                    html! {
+                        <span title="Ban" onclick=remove_member_callback(uuid, true)>
+                            <MatIconButton icon="block" />
+                        </span>
+                    }
+                } else {
+                    html!()
+                };
+                let message = if Some(uuid) != me && room.kind != RoomKind::Dm {
+                    html! {
+                        <span title="Message" onclick=message_callback(uuid)>
+                            <MatIconButton icon="chat" />
+                        </span>
+                    }
+                } else {
+                    html!()
+                };
+                let transfer = if is_owner && Some(uuid) != me {
+                    html! {
+                        <span title="Make owner" onclick=transfer_callback(&member.user)>
+                            <MatIconButton icon="star_outline" />
+                        </span>
+                    }
+                } else {
+                    html!()
+                };
+                let block = if Some(uuid) != me {
+                    html! {
+                        <span title="Block" onclick=block_callback(&member.user)>
+                            <MatIconButton icon="do_not_disturb_on" />
+                        </span>
+                    }
+                } else {
+                    html!()
+                };
+                let actions = html! {<>{ message }{ transfer }{ kick }{ ban }{ block }</>};
+
+                html! {
+                    <UserCard
+                        user=&member.user
+                        presence=member.presence
+                        is_owner=room.is_owner(uuid)
+                        actions=actions
+                    />
+                }
+            })
             .collect::<Vec<Html>>(),
     };
 
+    let leave_onclick = {
+        let token = Rc::clone(&token);
+
+        // the room is taken out of the list when the server sends `RoomLeave`
+        Callback::from(move |_| {
+            let token = Rc::clone(&token);
+
+            spawn_local(async move {
+                if let Err(e) = leave_room(&*token, room_id).await {
+                    weblog::console_error!(e.to_string());
+                }
+            })
+        })
+    };
+
     let (invitee_username, set_invitee_username) = use_state(String::new);
     let (invite_dialog_link, _) = use_state(WeakComponentLink::<MatDialog>::default);
     let invite_onclick = {
@@ -239,6 +506,241 @@ pub fn show_room(props: &ShowRoomProps) -> Html {
         })
     };
 
+    let (new_name, set_new_name) = use_state(|| room.name.clone());
+    let (new_topic, set_new_topic) = use_state(|| room.topic.clone().unwrap_or_default());
+    let (new_slow_mode, set_new_slow_mode) = use_state(|| room.slow_mode.to_string());
+
+    {
+        let (set_new_name, set_new_topic) = (Rc::clone(&set_new_name), Rc::clone(&set_new_topic));
+        let set_new_slow_mode = Rc::clone(&set_new_slow_mode);
+
+        // start over from what the room has whenever it changes
+        use_effect_with_deps(
+            move |(_, name, topic, slow_mode)| {
+                set_new_name(name.clone());
+                set_new_topic(topic.clone().unwrap_or_default());
+                set_new_slow_mode(slow_mode.to_string());
+                || {}
+            },
+            (
+                room_id,
+                room.name.clone(),
+                room.topic.clone(),
+                room.slow_mode,
+            ),
+        );
+    }
+
+    // everyone gets the changes through `RoomUpdate`
+    let save_settings_onclick = {
+        let token = Rc::clone(&token);
+        let (new_name, new_topic) = (Rc::clone(&new_name), Rc::clone(&new_topic));
+        let new_slow_mode = Rc::clone(&new_slow_mode);
+        let is_dm = room.is_dm();
+
+        Callback::from(move |_| {
+            let token = Rc::clone(&token);
+            let topic = new_topic.trim();
+            let data = UpdateRoom {
+                name: if is_dm {
+                    None
+                } else {
+                    Some((*new_name).clone())
+                },
+                topic: Some(if topic.is_empty() {
+                    None
+                } else {
+                    Some(topic.to_string())
+                }),
+                // an empty field turns it off
+                slow_mode: match new_slow_mode.trim() {
+                    "" => Some(0),
+                    slow_mode => slow_mode.parse().ok(),
+                },
+                ..Default::default()
+            };
+
+            spawn_local(async move {
+                if let Err(e) = update_room(&*token, room_id, &data).await {
+                    weblog::console_error!(e.to_string());
+                }
+            })
+        })
+    };
+
+    let settings = if can_manage_room {
+        let name_field = if room.is_dm() {
+            html!()
+        } else {
+            html! {
+                <MatTextField
+                    outlined=true
+                    label="Name"
+                    value=&*new_name
+                    oninput=Callback::from(move |e: InputData| set_new_name(e.value))
+                />
+            }
+        };
+
+        html! {<>
+            <section class="room-settings">
+                <header>
+                    <MatIcon>{ "settings" }</MatIcon>
+                    <h3>{ "Settings" }</h3>
+                </header>
+                { name_field }
+                <MatTextField
+                    outlined=true
+                    label="Topic"
+                    value=&*new_topic
+                    oninput=Callback::from(move |e: InputData| set_new_topic(e.value))
+                />
+                <MatTextField
+                    outlined=true
+                    field_type=TextFieldType::Number
+                    label="Slow mode (seconds)"
+                    helper="Members without the manage room permission wait this long between messages"
+                    value=&*new_slow_mode
+                    oninput=Callback::from(move |e: InputData| set_new_slow_mode(e.value))
+                />
+                <span onclick=save_settings_onclick>
+                    <MatButton label="Save" />
+                </span>
+            </section>
+            <AutomodRules room_id=room_id />
+        </>}
+    } else {
+        html!()
+    };
+
+    // both show up through the `RoomUpdate` and `RoomDelete` events
+    let admin_actions = if is_admin {
+        let archive_onclick = {
+            let token = Rc::clone(&token);
+            let archived = !room.is_archived();
+            Callback::from(move |_| {
+                let token = Rc::clone(&token);
+                spawn_local(async move {
+                    if let Err(e) = archive_room(&*token, room_id, archived).await {
+                        weblog::console_error!(e.to_string());
+                    }
+                })
+            })
+        };
+        // announcement rooms only let admins post, the others just read
+        let post_permission_onclick = {
+            let token = Rc::clone(&token);
+            let post_permission = if room.is_announcement() {
+                PostPermission::Everyone
+            } else {
+                PostPermission::Admins
+            };
+            Callback::from(move |_| {
+                let token = Rc::clone(&token);
+                let data = UpdateRoom {
+                    post_permission: Some(post_permission),
+                    ..Default::default()
+                };
+                spawn_local(async move {
+                    if let Err(e) = update_room(&*token, room_id, &data).await {
+                        weblog::console_error!(e.to_string());
+                    }
+                })
+            })
+        };
+        let post_permission_button = if room.is_dm() {
+            html!()
+        } else {
+            let label = if room.is_announcement() {
+                "Let everyone post"
+            } else {
+                "Only let admins post"
+            };
+            html! {
+                <article>
+                    <MatIcon>{ "campaign" }</MatIcon>
+                    <span onclick=post_permission_onclick>{ label }</span>
+                </article>
+            }
+        };
+        let delete_onclick = {
+            let token = Rc::clone(&token);
+            let name = room.name.clone();
+            Callback::from(move |_| {
+                let confirmed = DialogService::confirm(&format!(
+                    "Delete {} and all of its messages? This can't be undone.",
+                    name
+                ));
+                if !confirmed {
+                    return;
+                }
+
+                let token = Rc::clone(&token);
+                spawn_local(async move {
+                    if let Err(e) = delete_room(&*token, room_id).await {
+                        weblog::console_error!(e.to_string());
+                    }
+                })
+            })
+        };
+        let delete_button = if can_delete {
+            html! {
+                <article class="delete-room-button-container">
+                    <MatIcon>{ "delete_forever" }</MatIcon>
+                    <span onclick=delete_onclick>{ "Delete room" }</span>
+                </article>
+            }
+        } else {
+            html!()
+        };
+        let archive_label = if room.is_archived() {
+            "Unarchive room"
+        } else {
+            "Archive room"
+        };
+
+        html! {
+            <section class="room-admin-actions">
+                <article>
+                    <MatIcon>{ "archive" }</MatIcon>
+                    <span onclick=archive_onclick>{ archive_label }</span>
+                </article>
+                { post_permission_button }
+                { delete_button }
+            </section>
+        }
+    } else {
+        html!()
+    };
+
+    let composer = if room.is_archived() {
+        html! { <p class="archived-notice">{ "This room is archived, nothing new can be posted in it." }</p> }
+    } else if room.is_announcement() && !is_admin {
+        html! { <p class="announcement-notice">{ "Only admins can post here." }</p> }
+    } else {
+        html! { <CreateMessage room=room /> }
+    };
+
+    // DMs are between the same two people for good
+    let (add_member_button, leave_button) = if room.kind == RoomKind::Dm {
+        (html!(), html!())
+    } else {
+        (
+            html! {
+                <article class="add-user-button-container">
+                    <MatIcon>{ "person_add" }</MatIcon>
+                    <span onclick=invite_onclick>{ "Add member" }</span>
+                </article>
+            },
+            html! {
+                <article class="leave-room-button-container">
+                    <MatIcon>{ "logout" }</MatIcon>
+                    <span onclick=leave_onclick>{ "Leave room" }</span>
+                </article>
+            },
+        )
+    };
+
     html! {<>
         <TopRoomBar
             onnavigationiconclick=&props.onnavigationiconclick
@@ -247,8 +749,8 @@ pub fn show_room(props: &ShowRoomProps) -> Html {
             room=room.clone()
         />
         <section class="room-content">
-            <RoomMessages room=room />
-            <CreateMessage room=room />
+            <RoomMessages room=room me=me />
+            { composer }
         </section>
 
         <MatDialog
@@ -265,13 +767,32 @@ pub fn show_room(props: &ShowRoomProps) -> Html {
                         <MatIcon>{ "people" }</MatIcon>
                         <h3>{ "Members" }</h3>
                     </header>
-                    <article class="add-user-button-container">
-                        <MatIcon>{ "person_add" }</MatIcon>
-                        <span onclick=invite_onclick>{ "Add member" }</span>
-                    </article>
+                    { add_member_button }
                     { for user_cards }
+                    { leave_button }
                 </section>
 
+                {
+                    match &props.notification_settings {
+                        Some(settings) => html! { <NotificationSettingsSection settings=settings /> },
+                        None => html!(),
+                    }
+                }
+                { settings }
+                { admin_actions }
+                {
+                    if is_admin {
+                        html! {
+                            <>
+                                <ReportQueue room_id=room_id members=(*members).clone() />
+                                <AuditLog room_id=room_id members=(*members).clone() />
+                            </>
+                        }
+                    } else {
+                        html!()
+                    }
+                }
+
                 <section class="room-timestamp">
                     <header>
                         <MatIcon>{ "access_time" }</MatIcon>
@@ -1,47 +1,153 @@
-use crate::services::room::create_room;
-use crate::utils::asset_url;
+use crate::components::{muted_icon, room_list_item, unread_badge, SpaceRooms};
+use crate::services::room::{
+    create_room, fetch_public_rooms, fetch_room_members, join_public_room,
+};
+use crate::services::space::create_space;
+use crate::utils::{asset_url, use_token};
 use crate::{AppRoute, AppState};
+use common::payloads::DirectoryEntry;
+use common::{NotificationSettings, Room, RoomKind, RoomVisibility, User};
+use std::collections::HashMap;
 use std::rc::Rc;
+use uuid::Uuid;
 use wasm_bindgen_futures::spawn_local;
 use weblog::console_log;
 use yew::prelude::*;
 use yew::services::DialogService;
-use yew_functional::{function_component, use_ref, use_state};
+use yew_functional::{function_component, use_effect_with_deps, use_ref, use_state};
 use yew_material::{
     dialog::{ActionType, MatDialogAction},
     list::GraphicType,
-    MatButton, MatDialog, MatList, MatListItem, MatTextField, WeakComponentLink,
+    MatButton, MatCheckbox, MatDialog, MatFormfield, MatList, MatListItem, MatTextField,
+    WeakComponentLink,
 };
 use yew_router::agent::RouteRequest;
 use yew_router::prelude::*;
 use yew_state::SharedHandle;
 
+#[derive(Clone, Properties, PartialEq)]
+struct DmListItemProps {
+    room: Room,
+    me: Option<User>,
+    muted: bool,
+    unread: u32,
+}
+
+/// A DM is shown as the other user, group DMs keep the name made from their members
+#[function_component(DmListItem)]
+fn dm_list_item(props: &DmListItemProps) -> Html {
+    let token = use_token();
+    let (other, set_other) = use_state(|| None::<User>);
+
+    {
+        let me = props.me.as_ref().map(|me| me.uuid);
+        let room = props.room.clone();
+
+        use_effect_with_deps(
+            move |_| {
+                if room.kind == RoomKind::Dm {
+                    spawn_local(async move {
+                        match fetch_room_members(&*token, room.uuid).await {
+                            Ok(members) => set_other(
+                                members
+                                    .into_iter()
+                                    .map(|member| member.user)
+                                    .find(|user| Some(user.uuid) != me),
+                            ),
+                            Err(e) => weblog::console_error!(e.to_string()),
+                        }
+                    });
+                }
+
+                || {}
+            },
+            props.room.uuid,
+        );
+    }
+
+    let (name, avatar) = match &*other {
+        Some(user) => (user.name().to_string(), asset_url(user.avatar.as_ref())),
+        None => (props.room.name.clone(), asset_url(props.room.icon.as_ref())),
+    };
+
+    html! {
+        <MatListItem graphic=GraphicType::Avatar>
+            { name }
+            { muted_icon(props.muted) }
+            { unread_badge(props.unread) }
+            <img slot="graphic" src=avatar />
+        </MatListItem>
+    }
+}
+
 #[function_component(RoomsList)]
 pub fn rooms_list(handle: &SharedHandle<AppState>) -> Html {
     let router = use_ref(RouteAgentDispatcher::<()>::new);
 
-    let rooms = handle
-        .state()
-        .rooms
+    let (filter, set_filter) = use_state(String::new);
+
+    let state = handle.state();
+    let all_rooms = state.rooms.borrow();
+    let settings = state.notification_settings.borrow();
+    let muted = settings
+        .iter()
+        .filter(|it| it.is_muted())
+        .map(|it| it.room)
+        .collect::<Vec<Uuid>>();
+    // muted rooms can be left out of the unread counts
+    let unread = state
+        .unread
         .borrow()
         .iter()
-        .map(|room| {
-            let onclick = {
-                let router = Rc::clone(&router);
-                let uuid = room.uuid;
-                Callback::from(move |_| {
-                    let route = Route::from(AppRoute::Rooms(uuid));
-                    router.borrow_mut().send(RouteRequest::ChangeRoute(route));
-                })
-            };
+        .filter(|(room, _)| {
+            settings
+                .iter()
+                .find(|it| it.room == **room)
+                .map_or(true, NotificationSettings::counts_unread)
+        })
+        .map(|(room, count)| (*room, *count))
+        .collect::<HashMap<Uuid, u32>>();
+    let unread_in = |room: &Room| unread.get(&room.uuid).copied().unwrap_or(0);
+    // archived rooms only show up when looked for
+    let filter_lowercase = filter.trim().to_lowercase();
+    let shown_rooms = all_rooms
+        .iter()
+        .filter(|room| {
+            if filter_lowercase.is_empty() {
+                !room.is_archived()
+            } else {
+                room.name.to_lowercase().contains(&filter_lowercase)
+            }
+        })
+        .collect::<Vec<&Room>>();
+    let (dms, rooms): (Vec<&Room>, Vec<&Room>) = shown_rooms
+        .iter()
+        .copied()
+        .filter(|room| room.space.is_none())
+        .partition(|room| room.is_dm());
+    let onselect = {
+        let router = Rc::clone(&router);
+        Callback::from(move |uuid: Uuid| {
+            let route = Route::from(AppRoute::Rooms(uuid));
+            router.borrow_mut().send(RouteRequest::ChangeRoute(route));
+        })
+    };
+    let onclick = |room: &Room| {
+        let uuid = room.uuid;
+        onselect.reform(move |_| uuid)
+    };
+
+    let rooms = rooms
+        .into_iter()
+        .map(|room| room_list_item(room, muted.contains(&room.uuid), unread_in(room), &onselect))
+        .collect::<Vec<Html>>();
 
+    let dms = dms
+        .into_iter()
+        .map(|room| {
             html! {
-                // MatListItem must be outside for activatable to work
-                <span onclick=&onclick>
-                     <MatListItem graphic=GraphicType::Avatar>
-                        { &room.name }
-                        <img slot="graphic" src=asset_url(room.icon.as_ref()) />
-                     </MatListItem>
+                <span onclick=onclick(room)>
+                    <DmListItem room=room.clone() me=state.me.clone() muted=muted.contains(&room.uuid) unread=unread_in(room) />
                 </span>
             }
         })
@@ -54,30 +160,169 @@ pub fn rooms_list(handle: &SharedHandle<AppState>) -> Html {
         Callback::from(move |_| dialog_link.show())
     };
 
+    let (is_public, set_is_public) = use_state(|| false);
+
     let on_room_create_click = {
         let room_name = Rc::clone(&room_name);
+        let is_public = Rc::clone(&is_public);
         let token = handle.state().token.clone();
         Callback::from(move |_| {
             console_log!("room name: ", &*room_name);
 
             let room_name = Rc::clone(&room_name);
             let token = token.as_ref().unwrap().clone();
+            let visibility = if *is_public {
+                RoomVisibility::Public
+            } else {
+                RoomVisibility::Private
+            };
 
             spawn_local(async move {
                 let token = token;
-                if let Err(e) = create_room(&token, &**room_name).await {
+                if let Err(e) = create_room(&token, &**room_name, visibility, None, None).await {
                     DialogService::alert(&format!("Error creating room: {}", e))
                 };
             })
         })
     };
 
+    let (directory, set_directory) = use_state(Vec::<DirectoryEntry>::new);
+    let (directory_link, _) = use_state(WeakComponentLink::<MatDialog>::default);
+    // the space whose public rooms are browsed, `None` for the rooms outside of spaces
+    let directory_space = use_ref(|| None::<Uuid>);
+
+    let search_directory = {
+        let token = handle.state().token.clone();
+        let directory_space = Rc::clone(&directory_space);
+        Rc::new(move |search: String| {
+            let token = token.as_ref().unwrap().clone();
+            let set_directory = Rc::clone(&set_directory);
+            let space = *directory_space.borrow();
+
+            spawn_local(async move {
+                match fetch_public_rooms(&token, &search, space).await {
+                    Ok(entries) => set_directory(entries),
+                    Err(e) => weblog::console_error!(e.to_string()),
+                }
+            })
+        })
+    };
+
+    let browse = {
+        let directory_link = Rc::clone(&directory_link);
+        let search_directory = Rc::clone(&search_directory);
+        let directory_space = Rc::clone(&directory_space);
+        move |space: Option<Uuid>| {
+            *directory_space.borrow_mut() = space;
+            search_directory(String::new());
+            directory_link.show();
+        }
+    };
+    let browse_onclick = {
+        let browse = browse.clone();
+        Callback::from(move |_| browse(None))
+    };
+    let onbrowse = Callback::from(move |space: Uuid| browse(Some(space)));
+
+    let new_space_onclick = {
+        let token = handle.state().token.clone();
+        Callback::from(move |_| {
+            let name = match DialogService::prompt("Space name", None) {
+                Some(name) if !name.trim().is_empty() => name,
+                _ => return,
+            };
+
+            let token = token.as_ref().unwrap().clone();
+            spawn_local(async move {
+                if let Err(e) = create_space(&token, name.trim()).await {
+                    DialogService::alert(&format!("Error creating space: {}", e))
+                }
+            })
+        })
+    };
+
+    // the rooms of a space show up under it once the server sends `SpaceJoin`
+    let spaces = state
+        .spaces
+        .borrow()
+        .iter()
+        .map(|space| {
+            let rooms = shown_rooms
+                .iter()
+                .filter(|room| room.space == Some(space.uuid))
+                .map(|room| (*room).clone())
+                .collect::<Vec<Room>>();
+
+            html! {
+                <SpaceRooms
+                    space=space.clone()
+                    rooms=rooms
+                    me=state.me.clone()
+                    muted=muted.clone()
+                    unread=unread.clone()
+                    onselect=onselect.clone()
+                    onbrowse=onbrowse.clone()
+                />
+            }
+        })
+        .collect::<Vec<Html>>();
+
+    // joined rooms show up in the list once the server sends `RoomJoin`
+    let directory_entries = directory
+        .iter()
+        .map(|entry| {
+            let token = handle.state().token.clone();
+            let uuid = entry.room.uuid;
+            let join_onclick = Callback::from(move |_| {
+                let token = token.as_ref().unwrap().clone();
+                spawn_local(async move {
+                    if let Err(e) = join_public_room(&token, uuid).await {
+                        DialogService::alert(&format!("Error joining room: {}", e))
+                    }
+                })
+            });
+            let joined = all_rooms.iter().any(|room| room.uuid == uuid);
+
+            html! {
+                <article class="directory-entry">
+                    <img src=asset_url(entry.room.icon.as_ref()) />
+                    <span>{ &entry.room.name }</span>
+                    <span class="member-count">{ format!("{} members", entry.member_count) }</span>
+                    <span onclick=join_onclick>
+                        <MatButton label="Join" disabled=joined />
+                    </span>
+                </article>
+            }
+        })
+        .collect::<Vec<Html>>();
+
     html! {<>
+        <section class="rooms-filter">
+            <MatTextField
+                label="Find a room"
+                value=&*filter
+                oninput=Callback::from(move |e: InputData| set_filter(e.value))
+            />
+        </section>
+
         <MatList activatable=true>
             { for rooms }
             <span class="new-room" onclick=new_onclick>
                 <MatButton label="New" />
             </span>
+            <span class="new-room" onclick=browse_onclick>
+                <MatButton label="Browse" />
+            </span>
+            <span class="new-room" onclick=new_space_onclick>
+                <MatButton label="New space" />
+            </span>
+        </MatList>
+
+        { for spaces }
+
+        <h2>{"Direct messages"}</h2>
+        <MatList activatable=true>
+            { for dms }
         </MatList>
 
         <MatDialog
@@ -91,6 +336,9 @@ pub fn rooms_list(handle: &SharedHandle<AppState>) -> Html {
                     value=&*room_name
                     oninput=Callback::from(move |e: InputData| set_room_name(e.value))
                 />
+                <MatFormfield label="List in the room directory">
+                    <MatCheckbox onchange=Callback::from(move |state| set_is_public(state)) />
+                </MatFormfield>
             </section>
 
             <MatDialogAction action_type=ActionType::Primary action="create">
@@ -102,5 +350,19 @@ pub fn rooms_list(handle: &SharedHandle<AppState>) -> Html {
                 <MatButton label="Cancel" />
             </MatDialogAction>
         </MatDialog>
+
+        <MatDialog heading="Public rooms" dialog_link=&*directory_link>
+            <section class="room-directory">
+                <MatTextField
+                    label="Search"
+                    oninput=Callback::from(move |e: InputData| search_directory(e.value))
+                />
+                { for directory_entries }
+            </section>
+
+            <MatDialogAction action_type=ActionType::Secondary action="close">
+                <MatButton label="Close" />
+            </MatDialogAction>
+        </MatDialog>
     </>}
 }
@@ -1,13 +1,21 @@
 use crate::components::{UserAvatar, UserProfileDialog};
-use crate::utils::format_time;
+use crate::services::room::report_message;
+use crate::utils::{format_time, use_token};
 use common::{Message, MessageType};
+use std::rc::Rc;
+use uuid::Uuid;
+use wasm_bindgen_futures::spawn_local;
 use yew::prelude::*;
+use yew::services::DialogService;
 use yew_functional::{function_component, use_state};
+use yew_material::MatIconButton;
 use yew_md::Markdown;
 
 #[derive(Clone, Properties, PartialEq)]
 pub struct SingleMessageProp {
     pub message: Message,
+    #[prop_or_default]
+    pub me: Option<Uuid>,
 }
 
 #[function_component(SingleMessage)]
@@ -22,30 +30,102 @@ pub fn show_single_message(props: &SingleMessageProp) -> Html {
 
     let on_dialog_closed = Callback::from(move |_| set_dialog_open(false));
 
+    // the admins of the room see it in their queue, nothing changes for the reporter
+    let report_click = {
+        let token = use_token();
+        let (room_id, message_id) = (message.room.uuid, message.uuid);
+
+        Callback::from(move |_| {
+            let reason = match DialogService::prompt("Why are you reporting this message?", None) {
+                Some(reason) if !reason.trim().is_empty() => reason,
+                _ => return,
+            };
+
+            let token = Rc::clone(&token);
+            spawn_local(async move {
+                if let Err(e) = report_message(&*token, room_id, message_id, &reason).await {
+                    weblog::console_error!(e.to_string());
+                }
+            })
+        })
+    };
+    let report = if props.me.is_some() && props.me != Some(message.author.uuid) {
+        html! {
+            <span class="message-actions" title="Report" onclick=report_click>
+                <MatIconButton icon="flag" />
+            </span>
+        }
+    } else {
+        html!()
+    };
+
     let time = format_time(&props.message.created_at);
+    // the server leaves out what users the reader blocked have to say
+    if message.blocked {
+        return html! {
+            <article class="message-card" data_type="blocked">
+                <span class="blocked-message">{ "Blocked message" }</span>
+                <span class="timestamp">{ time }</span>
+            </article>
+        };
+    }
+
     match message.type_ {
         MessageType::Default => html! {
             <article class="message-card" data_type="default">
                 <UserAvatar user=&message.author />
                 <section class="content-container">
                     <section>
-                        <span class="author">{ &message.author.username }</span>
+                        <span class="author">{ message.author.name() }</span>
                         <span class="timestamp">{ time }</span>
                     </section>
                     <span class="content">
                         <Markdown content=&message.content />
                     </span>
                 </section>
+                { report }
             </article>
         },
         MessageType::RoomJoin => html! {
             <article class="message-card" data_type="join" onclick=join_click>
                 <UserAvatar user=&message.author show_details_on_click=false />
-                <span>{ &message.author.username }{ " just joined" }</span>
+                <span>{ message.author.name() }{ " just joined" }</span>
+                <span class="timestamp">{ time }</span>
+                <UserProfileDialog user=&message.author open=*dialog_open onclosed=on_dialog_closed />
+            </article>
+        },
+        MessageType::RoomLeave | MessageType::RoomKick | MessageType::RoomBan => {
+            let action = match message.type_ {
+                MessageType::RoomLeave => " left",
+                MessageType::RoomKick => " was kicked",
+                _ => " was banned",
+            };
+            let reason = if message.content.is_empty() {
+                html!()
+            } else {
+                html! { <span class="reason">{ &message.content }</span> }
+            };
+
+            html! {
+                <article class="message-card" data_type="leave" onclick=join_click>
+                    <UserAvatar user=&message.author show_details_on_click=false />
+                    <span>{ message.author.name() }{ action }</span>
+                    { reason }
+                    <span class="timestamp">{ time }</span>
+                    <UserProfileDialog user=&message.author open=*dialog_open onclosed=on_dialog_closed />
+                </article>
+            }
+        }
+        MessageType::RoomRename => html! {
+            <article class="message-card" data_type="rename" onclick=join_click>
+                <UserAvatar user=&message.author show_details_on_click=false />
+                <span>
+                    { message.author.name() }{ " renamed the room to " }
+                    <strong>{ &message.content }</strong>
+                </span>
                 <span class="timestamp">{ time }</span>
                 <UserProfileDialog user=&message.author open=*dialog_open onclosed=on_dialog_closed />
             </article>
         },
-        MessageType::RoomLeave => html! {},
     }
 }
@@ -0,0 +1,321 @@
+use crate::services::room::create_room;
+use crate::services::space::{add_space_member, create_category, fetch_space_members, leave_space};
+use crate::utils::{asset_url, use_token};
+use common::{Permissions, Room, RoomVisibility, Space, User};
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+use uuid::Uuid;
+use wasm_bindgen_futures::spawn_local;
+use yew::prelude::*;
+use yew::services::DialogService;
+use yew_functional::{function_component, use_effect_with_deps, use_state};
+use yew_material::{list::GraphicType, MatIcon, MatList, MatListItem};
+
+#[derive(Clone, Properties, PartialEq)]
+pub struct SpaceRoomsProps {
+    pub space: Space,
+    /// The rooms of the space the user is in
+    pub rooms: Vec<Room>,
+    pub me: Option<User>,
+    /// The rooms the user muted
+    pub muted: Vec<Uuid>,
+    /// How many unread messages are counted for the rooms
+    pub unread: HashMap<Uuid, u32>,
+    pub onselect: Callback<Uuid>,
+    /// Opens the directory of the public rooms of the space
+    pub onbrowse: Callback<Uuid>,
+}
+
+/// A room in the sidebar, the archived ones are labelled as such
+pub fn room_list_item(room: &Room, muted: bool, unread: u32, onselect: &Callback<Uuid>) -> Html {
+    let uuid = room.uuid;
+    let onclick = onselect.reform(move |_| uuid);
+    let archived = if room.is_archived() {
+        html! { <span class="archived-label">{ " (archived)" }</span> }
+    } else {
+        html!()
+    };
+
+    html! {
+        // MatListItem must be outside for activatable to work
+        <span onclick=onclick>
+             <MatListItem graphic=GraphicType::Avatar>
+                { &room.name }
+                { archived }
+                { muted_icon(muted) }
+                { unread_badge(unread) }
+                <img slot="graphic" src=asset_url(room.icon.as_ref()) />
+             </MatListItem>
+        </span>
+    }
+}
+
+/// Shown next to the name of muted rooms
+pub fn muted_icon(muted: bool) -> Html {
+    if muted {
+        html! {
+            <span class="muted-icon">
+                <MatIcon>{ "notifications_off" }</MatIcon>
+            </span>
+        }
+    } else {
+        html!()
+    }
+}
+
+/// Shown next to the name of rooms with unread messages
+pub fn unread_badge(count: u32) -> Html {
+    if count > 0 {
+        html! { <span class="unread-badge">{ count }</span> }
+    } else {
+        html!()
+    }
+}
+
+/// The rooms of a space, the ones without a category first and the others under
+/// their collapsible category
+#[function_component(SpaceRooms)]
+pub fn space_rooms(props: &SpaceRoomsProps) -> Html {
+    let token = use_token();
+    let space_id = props.space.uuid;
+
+    let (permissions, set_permissions) = use_state(|| Permissions::NONE);
+    {
+        let token = Rc::clone(&token);
+        let me = props.me.as_ref().map(|me| me.uuid);
+
+        use_effect_with_deps(
+            move |_| {
+                spawn_local(async move {
+                    match fetch_space_members(&*token, space_id).await {
+                        Ok(members) => {
+                            let permissions = members
+                                .iter()
+                                .find(|member| Some(member.user.uuid) == me)
+                                .map_or(Permissions::NONE, |member| member.role.permissions());
+                            set_permissions(permissions);
+                        }
+                        Err(e) => weblog::console_error!(e.to_string()),
+                    }
+                });
+
+                || {}
+            },
+            space_id,
+        );
+    }
+    let can_manage = permissions.contains(Permissions::MANAGE_ROOM);
+    let can_invite = permissions.contains(Permissions::INVITE);
+
+    let (collapsed, set_collapsed) = use_state(HashSet::<Uuid>::new);
+
+    let mut rooms = props.rooms.iter().collect::<Vec<_>>();
+    rooms.sort_by(|a, b| (a.position, &a.name).cmp(&(b.position, &b.name)));
+    let rooms_in = |category: Option<Uuid>| {
+        rooms
+            .iter()
+            .filter(|room| room.category == category)
+            .map(|room| {
+                let unread = props.unread.get(&room.uuid).copied().unwrap_or(0);
+                room_list_item(
+                    room,
+                    props.muted.contains(&room.uuid),
+                    unread,
+                    &props.onselect,
+                )
+            })
+            .collect::<Vec<Html>>()
+    };
+
+    // rooms made here are public so that everyone in the space can find them
+    let new_room_onclick = |category: Option<Uuid>| {
+        let token = Rc::clone(&token);
+        Callback::from(move |e: MouseEvent| {
+            e.stop_propagation();
+            let name = match DialogService::prompt("Room name", None) {
+                Some(name) if !name.trim().is_empty() => name,
+                _ => return,
+            };
+
+            let token = Rc::clone(&token);
+            spawn_local(async move {
+                let res = create_room(
+                    &*token,
+                    name.trim(),
+                    RoomVisibility::Public,
+                    Some(space_id),
+                    category,
+                )
+                .await;
+                if let Err(e) = res {
+                    DialogService::alert(&format!("Error creating room: {}", e))
+                }
+            })
+        })
+    };
+
+    let categories = props
+        .space
+        .categories
+        .iter()
+        .map(|category| {
+            let uuid = category.uuid;
+            let is_collapsed = collapsed.contains(&uuid);
+            let toggle_onclick = {
+                let (collapsed, set_collapsed) = (Rc::clone(&collapsed), Rc::clone(&set_collapsed));
+                Callback::from(move |_| {
+                    let mut collapsed = (*collapsed).clone();
+                    if !collapsed.remove(&uuid) {
+                        collapsed.insert(uuid);
+                    }
+                    set_collapsed(collapsed);
+                })
+            };
+            let icon = if is_collapsed {
+                "chevron_right"
+            } else {
+                "expand_more"
+            };
+            let new_room_button = if can_manage {
+                html! {
+                    <span class="new-space-room" onclick=new_room_onclick(Some(uuid))>
+                        <MatIcon>{ "add" }</MatIcon>
+                    </span>
+                }
+            } else {
+                html!()
+            };
+            let rooms = if is_collapsed {
+                html!()
+            } else {
+                html! {
+                    <MatList activatable=true>
+                        { for rooms_in(Some(uuid)) }
+                    </MatList>
+                }
+            };
+
+            html! {
+                <section class="space-category">
+                    <header onclick=toggle_onclick>
+                        <MatIcon>{ icon }</MatIcon>
+                        <span>{ &category.name }</span>
+                        { new_room_button }
+                    </header>
+                    { rooms }
+                </section>
+            }
+        })
+        .collect::<Vec<Html>>();
+
+    let browse_onclick = props.onbrowse.reform(move |_| space_id);
+
+    let manage_actions = if can_manage {
+        let new_category_onclick = {
+            let token = Rc::clone(&token);
+            Callback::from(move |_| {
+                let name = match DialogService::prompt("Category name", None) {
+                    Some(name) if !name.trim().is_empty() => name,
+                    _ => return,
+                };
+
+                let token = Rc::clone(&token);
+                spawn_local(async move {
+                    if let Err(e) = create_category(&*token, space_id, name.trim()).await {
+                        DialogService::alert(&format!("Error creating category: {}", e))
+                    }
+                })
+            })
+        };
+
+        html! {<>
+            <span onclick=new_room_onclick(None)>
+                <MatIcon>{ "add" }</MatIcon>
+            </span>
+            <span onclick=new_category_onclick>
+                <MatIcon>{ "create_new_folder" }</MatIcon>
+            </span>
+        </>}
+    } else {
+        html!()
+    };
+
+    let add_member_action = if can_invite {
+        let add_member_onclick = {
+            let token = Rc::clone(&token);
+            Callback::from(move |_| {
+                let username = match DialogService::prompt("Username", None) {
+                    Some(username) if !username.trim().is_empty() => username,
+                    _ => return,
+                };
+
+                let token = Rc::clone(&token);
+                spawn_local(async move {
+                    if let Err(e) = add_space_member(&*token, space_id, username.trim()).await {
+                        DialogService::alert(&format!("Error adding member: {}", e))
+                    }
+                })
+            })
+        };
+
+        html! {
+            <span onclick=add_member_onclick>
+                <MatIcon>{ "person_add" }</MatIcon>
+            </span>
+        }
+    } else {
+        html!()
+    };
+
+    let is_owner = props
+        .me
+        .as_ref()
+        .map_or(false, |me| props.space.is_owner(me.uuid));
+    // the space goes away from the sidebar with `SpaceLeave`
+    let leave_action = if is_owner {
+        html!()
+    } else {
+        let token = Rc::clone(&token);
+        let name = props.space.name.clone();
+        let leave_onclick = Callback::from(move |_| {
+            let confirmed =
+                DialogService::confirm(&format!("Leave {} and all of its rooms?", name));
+            if !confirmed {
+                return;
+            }
+
+            let token = Rc::clone(&token);
+            spawn_local(async move {
+                if let Err(e) = leave_space(&*token, space_id).await {
+                    DialogService::alert(&format!("Error leaving space: {}", e))
+                }
+            })
+        });
+
+        html! {
+            <span onclick=leave_onclick>
+                <MatIcon>{ "logout" }</MatIcon>
+            </span>
+        }
+    };
+
+    html! {
+        <section class="space">
+            <header>
+                <h2>{ &props.space.name }</h2>
+                <span class="space-actions">
+                    <span onclick=browse_onclick>
+                        <MatIcon>{ "explore" }</MatIcon>
+                    </span>
+                    { manage_actions }
+                    { add_member_action }
+                    { leave_action }
+                </span>
+            </header>
+            <MatList activatable=true>
+                { for rooms_in(None) }
+            </MatList>
+            { for categories }
+        </section>
+    }
+}
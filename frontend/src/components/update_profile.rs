@@ -1,13 +1,16 @@
 use crate::utils::{asset_url, use_token};
 use crate::{services, AppState};
+use chrono::{Duration, Utc};
+use common::payloads::UpdateProfile;
+use common::{CustomStatus, User};
 use std::rc::Rc;
 use wasm_bindgen_futures::spawn_local;
 use web_sys::HtmlElement;
 use yew::prelude::*;
 use yew::services::reader::File;
-use yew_functional::{function_component, use_state};
+use yew_functional::{function_component, use_effect_with_deps, use_state};
 use yew_material::text_inputs::TextFieldType;
-use yew_material::{MatIcon, MatTextField};
+use yew_material::{MatButton, MatIcon, MatTextField};
 use yew_state::SharedHandle;
 
 #[function_component(UpdateProfile)]
@@ -90,6 +93,8 @@ pub fn update_profile(handle: &SharedHandle<AppState>) -> Html {
         }
     };
 
+    let profile_form = html! { <ProfileForm user=user.clone() /> };
+
     let (hidden, set_hidden) = use_state(|| true);
 
     let on_avatar_mouseover = {
@@ -160,8 +165,225 @@ pub fn update_profile(handle: &SharedHandle<AppState>) -> Html {
                 { edit_username }
 
                 { edit_password }
+
+                { profile_form }
+
+                <BlockedUsers />
             </div>
             <input id="avatar-input" type="file" style="display: none;" ref=(*avatar_input_ref).clone() onchange=on_file_change />
         </div>
     }
 }
+
+#[derive(Clone, Properties, PartialEq)]
+struct ProfileFormProps {
+    user: common::User,
+}
+
+/// `None` for fields left empty so they're cleared
+fn optional(value: &str) -> Option<String> {
+    let value = value.trim();
+    if value.is_empty() {
+        None
+    } else {
+        Some(value.to_string())
+    }
+}
+
+#[function_component(ProfileForm)]
+fn profile_form(props: &ProfileFormProps) -> Html {
+    let user = &props.user;
+    let token = use_token();
+
+    let (display_name, set_display_name) = {
+        let value = user.display_name.clone().unwrap_or_default();
+        use_state(|| value)
+    };
+    let (pronouns, set_pronouns) = {
+        let value = user.pronouns.clone().unwrap_or_default();
+        use_state(|| value)
+    };
+    let (bio, set_bio) = {
+        let value = user.bio.clone().unwrap_or_default();
+        use_state(|| value)
+    };
+    let (timezone, set_timezone) = {
+        let value = user.timezone.clone().unwrap_or_default();
+        use_state(|| value)
+    };
+    let (status, set_status) = {
+        let value = user.status.as_ref().map(|it| it.text.clone());
+        use_state(|| value.unwrap_or_default())
+    };
+    let (status_hours, set_status_hours) = use_state(String::new);
+    let (message, set_message) = use_state(|| None::<String>);
+
+    let onsave = {
+        let display_name = Rc::clone(&display_name);
+        let pronouns = Rc::clone(&pronouns);
+        let bio = Rc::clone(&bio);
+        let timezone = Rc::clone(&timezone);
+        let status = Rc::clone(&status);
+        let status_hours = Rc::clone(&status_hours);
+
+        Callback::from(move |_| {
+            let status = optional(&status).map(|text| CustomStatus {
+                text,
+                expires_at: status_hours
+                    .parse::<i64>()
+                    .ok()
+                    .map(|hours| Utc::now() + Duration::hours(hours)),
+            });
+
+            let profile = UpdateProfile {
+                display_name: Some(optional(&display_name)),
+                bio: Some(optional(&bio)),
+                pronouns: Some(optional(&pronouns)),
+                timezone: Some(optional(&timezone)),
+                status: Some(status),
+            };
+
+            if let Err(e) = profile.validate() {
+                return set_message(Some(e));
+            }
+
+            let token = Rc::clone(&token);
+            let set_message = Rc::clone(&set_message);
+            spawn_local(async move {
+                // the websocket updates the user everywhere once saved
+                match services::user::update_profile(&*token, &profile).await {
+                    Ok(_) => set_message(Some("Profile saved".to_string())),
+                    Err(e) => set_message(Some(e.to_string())),
+                }
+            });
+        })
+    };
+
+    html! {
+        <section class="profile-form">
+            <MatTextField
+                outlined=true
+                field_type=TextFieldType::Text
+                label="Display name"
+                value=&*display_name
+                oninput=Callback::from(move |e: InputData| set_display_name(e.value))
+            />
+            <MatTextField
+                outlined=true
+                field_type=TextFieldType::Text
+                label="Pronouns"
+                value=&*pronouns
+                oninput=Callback::from(move |e: InputData| set_pronouns(e.value))
+            />
+            <MatTextField
+                outlined=true
+                field_type=TextFieldType::Text
+                label="Bio"
+                value=&*bio
+                oninput=Callback::from(move |e: InputData| set_bio(e.value))
+            />
+            <MatTextField
+                outlined=true
+                field_type=TextFieldType::Text
+                label="Timezone (e.g. Europe/Berlin)"
+                value=&*timezone
+                oninput=Callback::from(move |e: InputData| set_timezone(e.value))
+            />
+            <MatTextField
+                outlined=true
+                field_type=TextFieldType::Text
+                label="Status"
+                value=&*status
+                oninput=Callback::from(move |e: InputData| set_status(e.value))
+            />
+            <MatTextField
+                outlined=true
+                field_type=TextFieldType::Number
+                label="Clear status after (hours)"
+                value=&*status_hours
+                oninput=Callback::from(move |e: InputData| set_status_hours(e.value))
+            />
+
+            <span onclick=onsave>
+                <MatButton label="Save profile" raised=true />
+            </span>
+            { (*message).as_ref().map(|message| html! { <span>{ message }</span> }).unwrap_or_default() }
+        </section>
+    }
+}
+
+/// The users the current user blocked, each of whom can be unblocked
+#[function_component(BlockedUsers)]
+fn blocked_users() -> Html {
+    let token = use_token();
+    let (blocked, set_blocked) = use_state(Vec::<User>::new);
+
+    {
+        let token = Rc::clone(&token);
+        let set_blocked = Rc::clone(&set_blocked);
+
+        use_effect_with_deps(
+            move |_| {
+                spawn_local(async move {
+                    match services::user::fetch_blocks(&*token).await {
+                        Ok(blocked) => set_blocked(blocked),
+                        Err(e) => weblog::console_error!(e.to_string()),
+                    }
+                });
+
+                || {}
+            },
+            (),
+        );
+    }
+
+    let users = blocked
+        .iter()
+        .map(|user| {
+            let uuid = user.uuid;
+            let unblock_onclick = {
+                let token = Rc::clone(&token);
+                let (blocked, set_blocked) = (Rc::clone(&blocked), Rc::clone(&set_blocked));
+                Callback::from(move |_| {
+                    let token = Rc::clone(&token);
+                    let (blocked, set_blocked) = (Rc::clone(&blocked), Rc::clone(&set_blocked));
+                    spawn_local(async move {
+                        match services::user::unblock_user(&*token, uuid).await {
+                            Ok(()) => set_blocked(
+                                blocked
+                                    .iter()
+                                    .filter(|it| it.uuid != uuid)
+                                    .cloned()
+                                    .collect(),
+                            ),
+                            Err(e) => weblog::console_error!(e.to_string()),
+                        }
+                    })
+                })
+            };
+
+            html! {
+                <li>
+                    <img src=asset_url(user.avatar.as_ref()) />
+                    <span>{ user.name() }</span>
+                    <span onclick=unblock_onclick>
+                        <MatButton label="Unblock" />
+                    </span>
+                </li>
+            }
+        })
+        .collect::<Vec<Html>>();
+
+    let list = if users.is_empty() {
+        html! { <p>{ "You haven't blocked anyone." }</p> }
+    } else {
+        html! { <ul>{ for users }</ul> }
+    };
+
+    html! {
+        <section class="blocked-users">
+            <h3>{ "Blocked users" }</h3>
+            { list }
+        </section>
+    }
+}
@@ -1,10 +1,10 @@
 use crate::utils::asset_url;
-use common::User;
+use common::{Presence, User};
 use yew::prelude::*;
 use yew_functional::{function_component, use_effect, use_state};
 use yew_material::{
     dialog::{ActionType, MatDialogAction},
-    MatButton, MatDialog, MatIconButton,
+    MatButton, MatDialog, MatIcon, MatIconButton,
 };
 
 #[derive(Clone, Properties, PartialEq)]
@@ -16,6 +16,9 @@ pub struct UserAvatarProps {
     pub onclick: Option<Callback<MouseEvent>>,
     #[prop_or_default]
     pub onload: Callback<NodeRef>,
+    /// Shown as a dot over the avatar when known
+    #[prop_or_default]
+    pub presence: Option<Presence>,
 }
 
 #[function_component(UserAvatar)]
@@ -55,11 +58,30 @@ pub fn user_avatar(props: &UserAvatarProps) -> Html {
             <MatIconButton>
                 <img src=asset_url(props.user.avatar.as_ref()) />
             </MatIconButton>
+            { props.presence.map(|presence| html! { <PresenceDot presence=presence /> }).unwrap_or_default() }
         </span>
         <UserProfileDialog user=&props.user open=*open onclosed=on_dialog_closed />
     </>}
 }
 
+#[derive(Clone, Properties, PartialEq)]
+pub struct PresenceDotProps {
+    pub presence: Presence,
+}
+
+#[function_component(PresenceDot)]
+pub fn presence_dot(props: &PresenceDotProps) -> Html {
+    let (presence, title) = match props.presence {
+        Presence::Online => ("online", "Online"),
+        Presence::Idle => ("idle", "Idle"),
+        Presence::Offline => ("offline", "Offline"),
+    };
+
+    html! {
+        <span class="presence-dot" data-presence=presence title=title />
+    }
+}
+
 #[derive(Clone, Properties, PartialEq)]
 pub struct UserProfileDialogProps {
     pub user: User,
@@ -69,6 +91,41 @@ pub struct UserProfileDialogProps {
 
 #[function_component(UserProfileDialog)]
 pub fn user_profile_dialog(props: &UserProfileDialogProps) -> Html {
+    let user = &props.user;
+
+    let username = if user.display_name.is_some() {
+        html! { <span class="username">{ format!("@{}", user.username) }</span> }
+    } else {
+        html!()
+    };
+
+    let pronouns = match &user.pronouns {
+        Some(pronouns) => html! { <span class="pronouns">{ pronouns }</span> },
+        None => html!(),
+    };
+
+    let status = match &user.status {
+        Some(status) if !status.is_expired() => html! {
+            <span class="status">{ &status.text }</span>
+        },
+        _ => html!(),
+    };
+
+    let bio = match &user.bio {
+        Some(bio) => html! { <p class="bio">{ bio }</p> },
+        None => html!(),
+    };
+
+    let timezone = match &user.timezone {
+        Some(timezone) => html! {
+            <span class="timezone">
+                <MatIcon>{ "schedule" }</MatIcon>
+                { timezone }
+            </span>
+        },
+        None => html!(),
+    };
+
     html! {
         <span class="user-profile-dialog-container">
             <MatDialog
@@ -76,8 +133,13 @@ pub fn user_profile_dialog(props: &UserProfileDialogProps) -> Html {
                 open=props.open
             >
                 <section class="profile-dialog-container">
-                    <img src=asset_url(props.user.avatar.as_ref()) />
-                    <span>{ &props.user.username }</span>
+                    <img src=asset_url(user.avatar.as_ref()) />
+                    <span class="display-name">{ user.name() }</span>
+                    { username }
+                    { pronouns }
+                    { status }
+                    { bio }
+                    { timezone }
                 </section>
 
                 <MatDialogAction action_type=ActionType::Secondary action="cancel">
@@ -5,17 +5,23 @@ mod websocket;
 
 use components::{Auth, Room as ShowRoom, RoomsList, UpdateProfile, UserAvatar};
 
-use crate::utils::{asset_url, is_on_mobile};
+use crate::services::room::redeem_invite;
+use crate::utils::{asset_url, is_on_mobile, sleep};
 use crate::websocket::{Connection, InternalEventBus, Request, Response};
-use common::websocket::{AuthenticatedPayload, OpCode};
-use common::{Message, Room, User};
+use common::websocket::{
+    AuthenticatedPayload, Encoding, ErrorCode, ErrorPayload, OpCode, PresenceUpdatePayload,
+};
+use common::{Message, NotificationSettings, Report, Room, Space, User};
 use serde::{Deserialize, Serialize};
 use std::cell::RefCell;
+use std::collections::HashMap;
 use std::rc::Rc;
 use std::str::FromStr;
 use uuid::Uuid;
-use wasm_bindgen::JsValue;
-use weblog::console_log;
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::spawn_local;
+use weblog::{console_error, console_log};
 use yew::format::Text;
 use yew::prelude::*;
 use yew::services::storage::Area;
@@ -40,6 +46,10 @@ static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
 pub struct AppState {
     token: Option<String>,
     rooms: Rc<RefCell<Vec<Room>>>,
+    spaces: Rc<RefCell<Vec<Space>>>,
+    notification_settings: Rc<RefCell<Vec<NotificationSettings>>>,
+    /// How many messages that notify the user came in each room since it was last open
+    unread: Rc<RefCell<HashMap<Uuid, u32>>>,
     me: Option<User>,
     force_render: u32,
     prefers_dark: bool,
@@ -47,9 +57,14 @@ pub struct AppState {
 
 const TOKEN_KEY: &str = "token";
 const PREFERS_DARK_KEY: &str = "prefersDark";
+/// Where to go once signed in, set when a page that needs an account is opened while signed out
+const REDIRECT_KEY: &str = "redirectTo";
 
 const DATA_THEME_ATTR: &str = "data-theme";
 
+/// How long to wait before reconnecting after the websocket closes
+const RECONNECT_DELAY_MILLIS: i32 = 1000;
+
 impl Default for AppState {
     fn default() -> Self {
         let service = StorageService::new(Area::Local).expect("can't initialize StorageService");
@@ -69,6 +84,9 @@ impl Default for AppState {
         Self {
             token,
             rooms: Rc::new(RefCell::new(vec![])),
+            spaces: Rc::new(RefCell::new(vec![])),
+            notification_settings: Rc::new(RefCell::new(vec![])),
+            unread: Rc::new(RefCell::new(HashMap::new())),
             me: None,
             force_render: 0,
             prefers_dark,
@@ -76,10 +94,12 @@ impl Default for AppState {
     }
 }
 
-#[derive(Switch, Clone, Debug, Copy)]
+#[derive(Switch, Clone, Debug)]
 pub enum AppRoute {
     #[to = "/profile/update"]
     UpdateProfile,
+    #[to = "/invite/{code}"]
+    Invite(String),
     #[to = "/login"]
     Auth,
     #[to = "/room/{id}"]
@@ -128,6 +148,33 @@ fn home(props: &HomeProps) -> Html {
         None
     };
 
+    // opening a room reads its messages
+    {
+        let read = props.handle.reduce_callback_with(|state, room: Uuid| {
+            if state.unread.borrow_mut().remove(&room).is_some() {
+                state.force_render += 1;
+            }
+        });
+        use_effect_with_deps(
+            move |room| {
+                if let Some(room) = room {
+                    read.emit(*room);
+                }
+                || ()
+            },
+            props.room,
+        );
+    }
+
+    let notification_settings = current.and_then(|room| {
+        state
+            .notification_settings
+            .borrow()
+            .iter()
+            .find(|it| it.room == room.uuid)
+            .cloned()
+    });
+
     let (menu_link, _) = use_state(WeakComponentLink::<MatMenu>::default);
 
     let user_avatar_action = if let Some(user) = &state.me {
@@ -149,6 +196,9 @@ fn home(props: &HomeProps) -> Html {
             let reset_callback = props.handle.reduce_callback(move |state| {
                 state.token = None;
                 state.rooms = Rc::new(RefCell::new(vec![]));
+                state.spaces = Rc::new(RefCell::new(vec![]));
+                state.notification_settings = Rc::new(RefCell::new(vec![]));
+                state.unread = Rc::new(RefCell::new(HashMap::new()));
                 state.me = None;
             });
 
@@ -214,7 +264,13 @@ fn home(props: &HomeProps) -> Html {
     };
 
     let room = html! {
-        <ShowRoom room=current.cloned() user_avatar_action=user_avatar_action onnavigationiconclick=on_nav_click />
+        <ShowRoom
+            room=current.cloned()
+            me=state.me.clone()
+            notification_settings=notification_settings
+            user_avatar_action=user_avatar_action
+            onnavigationiconclick=on_nav_click
+        />
     };
 
     let drawer_type = if is_on_mobile { "modal" } else { "" };
@@ -277,6 +333,7 @@ fn main_application(handle: &SharedHandle<AppState>) -> Html {
         let dispatcher = Rc::clone(&dispatcher);
         let router = Rc::clone(&router);
         let route_service = Rc::clone(&route_service);
+        let set_has_sent_connect = Rc::clone(&set_has_sent_connect);
         let handle = handle.clone();
 
         use_effect(move || {
@@ -295,17 +352,25 @@ fn main_application(handle: &SharedHandle<AppState>) -> Html {
                                         .unwrap();
 
                                 state.rooms = Rc::new(RefCell::new(data.rooms));
+                                state.spaces = Rc::new(RefCell::new(data.spaces));
+                                state.notification_settings =
+                                    Rc::new(RefCell::new(data.notification_settings));
                                 state.me = Some(data.me);
                                 set_has_authenticated(true);
 
-                                let uuid = route_service
-                                    .borrow()
-                                    .get_route()
-                                    .route
-                                    .replace("/room/", "");
-                                let uuid = Uuid::from_str(&uuid);
-
                                 let current_route = route_service.borrow().get_route().route;
+                                let current_route = if current_route.starts_with("/login") {
+                                    let mut storage = StorageService::new(Area::Session)
+                                        .expect("can't initialize StorageService");
+                                    let redirect = storage.restore::<Text>(REDIRECT_KEY).ok();
+                                    storage.remove(REDIRECT_KEY);
+                                    redirect.unwrap_or(current_route)
+                                } else {
+                                    current_route
+                                };
+
+                                let uuid = Uuid::from_str(&current_route.replace("/room/", ""));
+
                                 // todo remove this when using query params -- see next todo
                                 #[allow(clippy::if_same_then_else)]
                                 let route = if current_route.starts_with("/room") {
@@ -315,8 +380,25 @@ fn main_application(handle: &SharedHandle<AppState>) -> Html {
                                     }
                                 } else if current_route.starts_with("/profile/update") {
                                     AppRoute::UpdateProfile
+                                } else if let Some(code) = current_route.strip_prefix("/invite/") {
+                                    let token = state.token.clone().unwrap();
+                                    let code = code.to_string();
+                                    let router = Rc::clone(&router);
+                                    spawn_local(async move {
+                                        let route = match redeem_invite(&token, &code).await {
+                                            Ok(room) => AppRoute::Rooms(room.uuid),
+                                            Err(e) => {
+                                                console_error!(format!("invite: {}", e));
+                                                AppRoute::Home
+                                            }
+                                        };
+                                        router
+                                            .borrow_mut()
+                                            .send(RouteRequest::ChangeRoute(Route::from(route)));
+                                    });
+                                    AppRoute::Home
                                 } else if current_route.starts_with("/login") {
-                                    AppRoute::Home // todo check for redirect query
+                                    AppRoute::Home
                                 } else if current_route == "/" {
                                     AppRoute::Home
                                 } else {
@@ -331,16 +413,120 @@ fn main_application(handle: &SharedHandle<AppState>) -> Html {
                             OpCode::RoomJoin => {
                                 let data = serde_json::from_value::<Room>(m.data.clone()).unwrap();
 
+                                // members start with the defaults, they're sent along on reconnect
+                                state
+                                    .notification_settings
+                                    .borrow_mut()
+                                    .push(NotificationSettings::new(data.uuid));
                                 state.rooms.borrow_mut().push(data);
                                 state.force_render += 1;
                             }
+                            OpCode::RoomUpdate => {
+                                let data = serde_json::from_value::<Room>(m.data.clone()).unwrap();
+
+                                for room in state.rooms.borrow_mut().iter_mut() {
+                                    if room.uuid == data.uuid {
+                                        *room = data.clone();
+                                    }
+                                }
+                                state.force_render += 1;
+                            }
+                            OpCode::RoomLeave | OpCode::RoomDelete => {
+                                let data = serde_json::from_value::<Room>(m.data.clone()).unwrap();
+
+                                state.rooms.borrow_mut().retain(|it| it.uuid != data.uuid);
+                                state
+                                    .notification_settings
+                                    .borrow_mut()
+                                    .retain(|it| it.room != data.uuid);
+                                state.unread.borrow_mut().remove(&data.uuid);
+                                state.force_render += 1;
+
+                                let current_route = route_service.borrow().get_route().route;
+                                if current_route == format!("/room/{}", data.uuid) {
+                                    let route = Route::from(AppRoute::Home);
+                                    router.borrow_mut().send(RouteRequest::ChangeRoute(route));
+                                }
+                            }
+                            OpCode::SpaceJoin => {
+                                let data = serde_json::from_value::<Space>(m.data.clone()).unwrap();
+
+                                state.spaces.borrow_mut().push(data);
+                                state.force_render += 1;
+                            }
+                            OpCode::SpaceUpdate => {
+                                let data = serde_json::from_value::<Space>(m.data.clone()).unwrap();
+
+                                for space in state.spaces.borrow_mut().iter_mut() {
+                                    if space.uuid == data.uuid {
+                                        *space = data.clone();
+                                    }
+                                }
+                                state.force_render += 1;
+                            }
+                            OpCode::SpaceLeave => {
+                                let data = serde_json::from_value::<Space>(m.data.clone()).unwrap();
+
+                                state.spaces.borrow_mut().retain(|it| it.uuid != data.uuid);
+                                state.force_render += 1;
+                            }
+                            OpCode::NotificationSettingsUpdate => {
+                                let data =
+                                    serde_json::from_value::<NotificationSettings>(m.data.clone())
+                                        .unwrap();
+
+                                {
+                                    let mut settings = state.notification_settings.borrow_mut();
+                                    settings.retain(|it| it.room != data.room);
+                                    settings.push(data);
+                                }
+                                state.force_render += 1;
+                            }
                             OpCode::MessageCreate => {
                                 let data =
                                     serde_json::from_value::<Message>(m.data.clone()).unwrap();
+
+                                // the messages of the open room are read as they come in
+                                let current_route = route_service.borrow().get_route().route;
+                                let is_open = current_route == format!("/room/{}", data.room.uuid);
+                                let settings = state
+                                    .notification_settings
+                                    .borrow()
+                                    .iter()
+                                    .find(|it| it.room == data.room.uuid)
+                                    .cloned()
+                                    .unwrap_or_else(|| NotificationSettings::new(data.room.uuid));
+                                let notifies = state
+                                    .me
+                                    .as_ref()
+                                    .map_or(false, |me| settings.notifies(&data, me));
+                                if !is_open && notifies {
+                                    *state
+                                        .unread
+                                        .borrow_mut()
+                                        .entry(data.room.uuid)
+                                        .or_default() += 1;
+                                    state.force_render += 1;
+                                }
+
                                 events_dispatcher
                                     .borrow_mut()
                                     .send(websocket::internal_events::Request::NewMessage(data))
                             }
+                            OpCode::MessageDelete => {
+                                let data =
+                                    serde_json::from_value::<Message>(m.data.clone()).unwrap();
+                                events_dispatcher
+                                    .borrow_mut()
+                                    .send(websocket::internal_events::Request::DeleteMessage(data))
+                            }
+                            OpCode::ReportCreate => {
+                                let data =
+                                    serde_json::from_value::<Report>(m.data.clone()).unwrap();
+                                events_dispatcher
+                                    .borrow_mut()
+                                    .send(websocket::internal_events::Request::NewReport(data))
+                            }
                             OpCode::UserUpdate => {
                                 let data = serde_json::from_value::<User>(m.data.clone()).unwrap();
                                 if let Some(me) = &state.me {
@@ -349,7 +535,34 @@ fn main_application(handle: &SharedHandle<AppState>) -> Html {
                                     }
                                 }
                             }
-                            _ => panic!("fucked"),
+                            OpCode::PresenceUpdate => {
+                                let data =
+                                    serde_json::from_value::<PresenceUpdatePayload>(m.data.clone())
+                                        .unwrap();
+                                events_dispatcher
+                                    .borrow_mut()
+                                    .send(websocket::internal_events::Request::PresenceUpdate(data))
+                            }
+                            OpCode::BadPayload | OpCode::InvalidOp | OpCode::ServerError => {
+                                let error =
+                                    serde_json::from_value::<ErrorPayload>(m.data.clone()).unwrap();
+                                console_error!(format!("websocket error: {}", error));
+
+                                // the token doesn't work anymore, e.g. the account was
+                                // suspended, so reconnecting won't help
+                                if matches!(
+                                    error.code,
+                                    ErrorCode::AuthenticationFailed | ErrorCode::SessionRevoked
+                                ) {
+                                    StorageService::new(Area::Local)
+                                        .expect("can't initialize StorageService")
+                                        .remove(TOKEN_KEY);
+                                    state.token = None;
+                                    state.me = None;
+                                    set_has_sent_connect(false);
+                                }
+                            }
+                            _ => console_error!(format!("unexpected op: {:?}", m.op)),
                         }
                         console_log!(JsValue::from_serde(&*m).unwrap());
                     }
@@ -359,6 +572,15 @@ fn main_application(handle: &SharedHandle<AppState>) -> Html {
                     }
                     Response::Closed => {
                         dispatcher.borrow_mut().send(Request::Disconnect);
+
+                        // events may have been missed (slow clients get disconnected
+                        // by the server) so reconnect and authenticate again to resync
+                        set_has_authenticated(false);
+                        let set_has_sent_connect = Rc::clone(&set_has_sent_connect);
+                        spawn_local(async move {
+                            sleep(RECONNECT_DELAY_MILLIS).await;
+                            set_has_sent_connect(false);
+                        });
                     }
                 },
             ));
@@ -367,6 +589,45 @@ fn main_application(handle: &SharedHandle<AppState>) -> Html {
         })
     };
 
+    {
+        let dispatcher = Rc::clone(&dispatcher);
+
+        // the user is idle while the page is hidden
+        use_effect_with_deps(
+            move |has_authenticated| {
+                let document = yew::utils::document();
+                let report_idle = move || {
+                    let hidden = yew::utils::document().hidden();
+                    dispatcher.borrow_mut().send(Request::SetIdle(hidden));
+                };
+
+                let listener = if *has_authenticated {
+                    report_idle();
+                    let listener = Closure::wrap(Box::new(report_idle) as Box<dyn Fn()>);
+                    document
+                        .add_event_listener_with_callback(
+                            "visibilitychange",
+                            listener.as_ref().unchecked_ref(),
+                        )
+                        .expect("failed to listen for visibility changes");
+                    Some(listener)
+                } else {
+                    None
+                };
+
+                move || {
+                    if let Some(listener) = listener {
+                        let _ = document.remove_event_listener_with_callback(
+                            "visibilitychange",
+                            listener.as_ref().unchecked_ref(),
+                        );
+                    }
+                }
+            },
+            *has_authenticated,
+        );
+    }
+
     match &handle.state().token {
         Some(_) => {
             if !*has_sent_connect {
@@ -379,16 +640,28 @@ fn main_application(handle: &SharedHandle<AppState>) -> Html {
                     "ws"
                 };
 
-                dispatcher
-                    .borrow_mut()
-                    .send(websocket::Request::Connect(format!(
-                        "{}://{}/api/ws",
-                        ws_protocol, base
-                    )));
+                // mobile clients get the more compact binary encoding
+                let encoding = if is_on_mobile() {
+                    Encoding::MessagePack
+                } else {
+                    Encoding::Json
+                };
+
+                dispatcher.borrow_mut().send(websocket::Request::Connect(
+                    format!("{}://{}/api/ws", ws_protocol, base),
+                    encoding,
+                ));
                 set_has_sent_connect(true)
             }
         }
         None => {
+            let current_route = route_service.borrow().get_route().route;
+            if current_route.starts_with("/invite/") {
+                let mut storage =
+                    StorageService::new(Area::Session).expect("can't initialize StorageService");
+                storage.store(REDIRECT_KEY, Ok(current_route));
+            }
+
             let route = Route::from(AppRoute::Auth);
             router.borrow_mut().send(RouteRequest::ChangeRoute(route));
         }
@@ -415,6 +688,8 @@ fn switch(switch: AppRoute) -> Html {
     match switch {
         AppRoute::UpdateProfile => html! { <SharedStateComponent<UpdateProfile> /> },
         AppRoute::Auth => html! { <Auth /> },
+        // redeemed once the websocket has authenticated, see `Main`
+        AppRoute::Invite(_) => html! { <SharedStateComponent<Home> /> },
         AppRoute::Rooms(room) => html! { <SharedStateComponent<Home> room=room /> },
         AppRoute::Home => html! { <SharedStateComponent<Home> /> },
     }
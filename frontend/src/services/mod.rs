@@ -1,4 +1,5 @@
 pub mod auth;
-mod request;
+pub mod request;
 pub mod room;
+pub mod space;
 pub mod user;
@@ -32,6 +32,31 @@ impl fmt::Display for NoContent {
     }
 }
 
+/// The server answered `429`, the request can be made again after `retry_after` seconds
+#[derive(Debug, Clone)]
+pub struct RateLimited {
+    pub message: String,
+    pub retry_after: f64,
+}
+
+impl fmt::Display for RateLimited {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for RateLimited {}
+
+/// Some actions don't need a body, like leaving a room
+fn with_json_body<T: Serialize>(builder: Request, body: Option<&T>) -> anyhow::Result<Request> {
+    Ok(match body {
+        Some(body) => builder
+            .body(serde_json::to_string(body)?)
+            .header("Content-Type", "application/json"),
+        None => builder,
+    })
+}
+
 pub async fn request<T: Serialize, R: DeserializeOwned>(
     url: impl Into<String>,
     method: Method,
@@ -41,11 +66,11 @@ pub async fn request<T: Serialize, R: DeserializeOwned>(
 ) -> anyhow::Result<R> {
     let url = &url.into();
     let mut builder = match method {
-        Method::POST => Request::post(url)
-            .body(serde_json::to_string(body.as_ref().unwrap())?)
-            .header("Content-Type", "application/json"),
+        Method::POST => with_json_body(Request::post(url), body)?,
+        Method::PATCH => with_json_body(Request::new(url).method(Method::PATCH), body)?,
         Method::GET => Request::get(url),
         Method::PUT => Request::put(url),
+        Method::DELETE => Request::new(url).method(Method::DELETE),
         _ => unreachable!(),
     };
 
@@ -70,7 +95,15 @@ pub async fn request<T: Serialize, R: DeserializeOwned>(
         }
     } else {
         let error = resp.json::<ApiError>().await?;
-        Err(anyhow::anyhow!("{}", to_sentence_case(&error.message)))
+        let message = to_sentence_case(&error.message);
+        match error.retry_after {
+            Some(retry_after) => Err(RateLimited {
+                message,
+                retry_after,
+            }
+            .into()),
+            None => Err(anyhow::anyhow!("{}", message)),
+        }
     }
 }
 
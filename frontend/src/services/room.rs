@@ -1,12 +1,28 @@
 use crate::request;
 use crate::services::request::NoContent;
-use common::payloads::{CreateMessage, CreateRoom, JoinMembers};
-use common::{Message, Room, RoomMember, User};
+use common::payloads::{
+    CreateAutomodRule, CreateMessage, CreateReport, CreateRoom, DirectoryEntry, JoinMembers,
+    OpenDm, RemoveMember, ResolveReport, TransferOwnership, UpdateNotificationSettings, UpdateRoom,
+};
+use common::{
+    AuditLogEntry, AutomodRule, Message, NotificationSettings, Report, ReportAction, ReportScope,
+    Room, RoomMember, RoomVisibility, User,
+};
 use uuid::Uuid;
 
-pub async fn create_room(token: &str, name: &str) -> anyhow::Result<Room> {
+/// Creates a room, in the given space and category if there's one
+pub async fn create_room(
+    token: &str,
+    name: &str,
+    visibility: RoomVisibility,
+    space: Option<Uuid>,
+    category: Option<Uuid>,
+) -> anyhow::Result<Room> {
     let data = CreateRoom {
         name: name.to_string(),
+        visibility,
+        space,
+        category,
     };
 
     request!(
@@ -37,7 +53,7 @@ pub async fn join_room(token: &str, room_id: Uuid, username: &str) -> anyhow::Re
 
     let body = JoinMembers {
         member: user.uuid,
-        with_elevated_permissions: false,
+        role: None,
     };
 
     let member = request!(
@@ -51,6 +67,181 @@ pub async fn join_room(token: &str, room_id: Uuid, username: &str) -> anyhow::Re
     Ok(member)
 }
 
+pub async fn update_room(token: &str, room_id: Uuid, data: &UpdateRoom) -> anyhow::Result<Room> {
+    request!(
+        method = PATCH,
+        url = format!("/api/rooms/{}", room_id),
+        body = data,
+        token = token
+    )
+    .await
+}
+
+/// Changes how the user is notified about the room, their other devices get it through
+/// `NotificationSettingsUpdate`
+pub async fn update_notification_settings(
+    token: &str,
+    room_id: Uuid,
+    data: &UpdateNotificationSettings,
+) -> anyhow::Result<NotificationSettings> {
+    request!(
+        method = PATCH,
+        url = format!("/api/rooms/{}/notifications", room_id),
+        body = data,
+        token = token
+    )
+    .await
+}
+
+/// Archives the room, or brings it back if `archived` is false
+pub async fn archive_room(token: &str, room_id: Uuid, archived: bool) -> anyhow::Result<Room> {
+    let url = format!("/api/rooms/{}/archive", room_id);
+    if archived {
+        request!(method = PUT, url = url, token = token).await
+    } else {
+        request!(method = DELETE, url = url, token = token).await
+    }
+}
+
+pub async fn delete_room(token: &str, room_id: Uuid) -> anyhow::Result<()> {
+    no_content(
+        request!(
+            method = DELETE,
+            url = format!("/api/rooms/{}", room_id),
+            token = token
+        )
+        .await,
+    )
+}
+
+/// Hands the room over to another member, the current owner stays an admin
+pub async fn transfer_ownership(token: &str, room_id: Uuid, owner: Uuid) -> anyhow::Result<Room> {
+    let body = TransferOwnership { owner };
+
+    request!(
+        method = PUT,
+        url = format!("/api/rooms/{}/owner", room_id),
+        body = &body,
+        token = token
+    )
+    .await
+}
+
+/// A page of the audit log of the room, the newest entries first
+pub async fn fetch_audit_log(
+    token: &str,
+    room_id: Uuid,
+    offset: i64,
+    limit: i64,
+) -> anyhow::Result<Vec<AuditLogEntry>> {
+    request!(
+        method = GET,
+        url = format!(
+            "/api/rooms/{}/audit-log?offset={}&limit={}",
+            room_id, offset, limit
+        ),
+        token = token
+    )
+    .await
+}
+
+/// The first page of public rooms whose name has `search` in it,
+/// those of the space if there's one and those outside of spaces otherwise
+pub async fn fetch_public_rooms(
+    token: &str,
+    search: &str,
+    space: Option<Uuid>,
+) -> anyhow::Result<Vec<DirectoryEntry>> {
+    let search = String::from(js_sys::encode_uri_component(search));
+    let url = match space {
+        Some(space) => format!("/api/rooms/public?search={}&space={}", search, space),
+        None => format!("/api/rooms/public?search={}", search),
+    };
+    request!(method = GET, url = url, token = token).await
+}
+
+pub async fn join_public_room(token: &str, room_id: Uuid) -> anyhow::Result<RoomMember> {
+    request!(
+        method = POST,
+        url = format!("/api/rooms/{}/members/me", room_id),
+        token = token
+    )
+    .await
+}
+
+/// The DM with the given users, it's created if there isn't one already
+pub async fn open_dm(token: &str, members: Vec<Uuid>) -> anyhow::Result<Room> {
+    let body = OpenDm { members };
+
+    request!(method = POST, url = "/api/dms", body = &body, token = token).await
+}
+
+/// Joins the room the invite is for, or just returns it if the user is already a member
+pub async fn redeem_invite(token: &str, code: &str) -> anyhow::Result<Room> {
+    request!(
+        method = POST,
+        url = format!("/api/invites/{}", code),
+        token = token
+    )
+    .await
+}
+
+/// These requests answer with `204 No Content`, which fails to parse as a body
+pub(crate) fn no_content(res: anyhow::Result<()>) -> anyhow::Result<()> {
+    match res {
+        Ok(()) => Ok(()),
+        Err(e) => match e.downcast::<NoContent>() {
+            Ok(_) => Ok(()),
+            Err(e) => Err(e),
+        },
+    }
+}
+
+pub async fn leave_room(token: &str, room_id: Uuid) -> anyhow::Result<()> {
+    no_content(
+        request!(
+            method = POST,
+            url = format!("/api/rooms/{}/leave", room_id),
+            token = token
+        )
+        .await,
+    )
+}
+
+pub async fn kick_member(token: &str, room_id: Uuid, member: Uuid) -> anyhow::Result<()> {
+    let body = RemoveMember {
+        member,
+        reason: None,
+    };
+
+    no_content(
+        request!(
+            method = POST,
+            url = format!("/api/rooms/{}/kick", room_id),
+            body = &body,
+            token = token
+        )
+        .await,
+    )
+}
+
+pub async fn ban_member(token: &str, room_id: Uuid, member: Uuid) -> anyhow::Result<()> {
+    let body = RemoveMember {
+        member,
+        reason: None,
+    };
+
+    no_content(
+        request!(
+            method = POST,
+            url = format!("/api/rooms/{}/bans", room_id),
+            body = &body,
+            token = token
+        )
+        .await,
+    )
+}
+
 pub async fn fetch_room_messages(token: &str, room_id: Uuid) -> anyhow::Result<Vec<Message>> {
     let res = request!(
         method = GET,
@@ -82,3 +273,89 @@ pub async fn send_message(
     )
     .await
 }
+
+/// Reports the message to the admins of its room
+pub async fn report_message(
+    token: &str,
+    room_id: Uuid,
+    message_id: Uuid,
+    reason: &str,
+) -> anyhow::Result<Report> {
+    let body = CreateReport {
+        reason: reason.to_string(),
+        scope: ReportScope::Room,
+    };
+
+    request!(
+        method = POST,
+        url = format!("/api/rooms/{}/messages/{}/reports", room_id, message_id),
+        body = &body,
+        token = token
+    )
+    .await
+}
+
+/// The reports of the room nobody took care of yet, the newest first
+pub async fn fetch_reports(token: &str, room_id: Uuid) -> anyhow::Result<Vec<Report>> {
+    request!(
+        method = GET,
+        url = format!("/api/rooms/{}/reports", room_id),
+        token = token
+    )
+    .await
+}
+
+pub async fn resolve_report(
+    token: &str,
+    room_id: Uuid,
+    report_id: Uuid,
+    action: ReportAction,
+) -> anyhow::Result<Report> {
+    let body = ResolveReport {
+        action,
+        reason: None,
+    };
+
+    request!(
+        method = POST,
+        url = format!("/api/rooms/{}/reports/{}/resolve", room_id, report_id),
+        body = &body,
+        token = token
+    )
+    .await
+}
+
+/// The automod rules of the room, those of the instance aren't included
+pub async fn fetch_automod_rules(token: &str, room_id: Uuid) -> anyhow::Result<Vec<AutomodRule>> {
+    request!(
+        method = GET,
+        url = format!("/api/rooms/{}/automod-rules", room_id),
+        token = token
+    )
+    .await
+}
+
+pub async fn create_automod_rule(
+    token: &str,
+    room_id: Uuid,
+    data: &CreateAutomodRule,
+) -> anyhow::Result<AutomodRule> {
+    request!(
+        method = POST,
+        url = format!("/api/rooms/{}/automod-rules", room_id),
+        body = data,
+        token = token
+    )
+    .await
+}
+
+pub async fn delete_automod_rule(token: &str, room_id: Uuid, rule_id: Uuid) -> anyhow::Result<()> {
+    no_content(
+        request!(
+            method = DELETE,
+            url = format!("/api/rooms/{}/automod-rules/{}", room_id, rule_id),
+            token = token
+        )
+        .await,
+    )
+}
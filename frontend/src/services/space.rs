@@ -0,0 +1,84 @@
+use crate::request;
+use crate::services::room::no_content;
+use common::payloads::{CreateCategory, CreateSpace, JoinMembers};
+use common::{Space, SpaceCategory, SpaceMember, User};
+use uuid::Uuid;
+
+pub async fn create_space(token: &str, name: &str) -> anyhow::Result<Space> {
+    let data = CreateSpace {
+        name: name.to_string(),
+    };
+
+    request!(
+        method = POST,
+        url = "/api/spaces",
+        body = &data,
+        token = token
+    )
+    .await
+}
+
+pub async fn fetch_space_members(token: &str, space_id: Uuid) -> anyhow::Result<Vec<SpaceMember>> {
+    request!(
+        method = GET,
+        url = format!("/api/spaces/{}/members", space_id),
+        token = token
+    )
+    .await
+}
+
+/// Adds the user to the space, they join its public rooms along with it
+pub async fn add_space_member(
+    token: &str,
+    space_id: Uuid,
+    username: &str,
+) -> anyhow::Result<SpaceMember> {
+    let user: User = request!(
+        method = GET,
+        url = format!("/api/users/by_username/{}", username),
+        token = token
+    )
+    .await?;
+
+    let body = JoinMembers {
+        member: user.uuid,
+        role: None,
+    };
+
+    request!(
+        method = POST,
+        url = format!("/api/spaces/{}/members", space_id),
+        body = &body,
+        token = token
+    )
+    .await
+}
+
+pub async fn leave_space(token: &str, space_id: Uuid) -> anyhow::Result<()> {
+    no_content(
+        request!(
+            method = POST,
+            url = format!("/api/spaces/{}/leave", space_id),
+            token = token
+        )
+        .await,
+    )
+}
+
+pub async fn create_category(
+    token: &str,
+    space_id: Uuid,
+    name: &str,
+) -> anyhow::Result<SpaceCategory> {
+    let data = CreateCategory {
+        name: name.to_string(),
+    };
+
+    request!(
+        method = POST,
+        url = format!("/api/spaces/{}/categories", space_id),
+        body = &data,
+        token = token
+    )
+    .await
+}
@@ -1,7 +1,11 @@
+use crate::request;
 use crate::services::request::request;
+use crate::services::room::no_content;
 use crate::utils::js_to_anyhow;
-use common::Asset;
+use common::payloads::UpdateProfile;
+use common::{Asset, User};
 use reqwasm::Method;
+use uuid::Uuid;
 use web_sys::{File, FormData};
 
 pub async fn update_avatar(token: &str, file: File) -> anyhow::Result<Asset> {
@@ -22,3 +26,40 @@ pub async fn update_avatar(token: &str, file: File) -> anyhow::Result<Asset> {
     )
     .await
 }
+
+pub async fn update_profile(token: &str, profile: &UpdateProfile) -> anyhow::Result<User> {
+    request!(
+        method = PATCH,
+        url = "/api/users/me",
+        body = profile,
+        token = token
+    )
+    .await
+}
+
+/// The users the current user blocked
+pub async fn fetch_blocks(token: &str) -> anyhow::Result<Vec<User>> {
+    request!(method = GET, url = "/api/users/me/blocks", token = token).await
+}
+
+pub async fn block_user(token: &str, user_id: Uuid) -> anyhow::Result<()> {
+    no_content(
+        request!(
+            method = PUT,
+            url = format!("/api/users/me/blocks/{}", user_id),
+            token = token
+        )
+        .await,
+    )
+}
+
+pub async fn unblock_user(token: &str, user_id: Uuid) -> anyhow::Result<()> {
+    no_content(
+        request!(
+            method = DELETE,
+            url = format!("/api/users/me/blocks/{}", user_id),
+            token = token
+        )
+        .await,
+    )
+}
@@ -56,6 +56,16 @@ pub fn is_on_mobile() -> bool {
         .matches()
 }
 
+/// Resolves after the given number of milliseconds
+pub async fn sleep(millis: i32) {
+    let promise = js_sys::Promise::new(&mut |resolve, _| {
+        yew::utils::window()
+            .set_timeout_with_callback_and_timeout_and_arguments_0(&resolve, millis)
+            .expect("failed to set timeout");
+    });
+    let _ = wasm_bindgen_futures::JsFuture::from(promise).await;
+}
+
 pub fn format_time(time: &DateTime<Utc>) -> String {
     let local = time.with_timezone(&Local).naive_local();
     let now = Local::now().naive_local();
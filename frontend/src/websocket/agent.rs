@@ -1,17 +1,22 @@
-use common::websocket::{AuthenticatePayload, MessagePayload, OpCode};
+use common::websocket::{
+    AuthenticatePayload, Encoding, Frame, MessagePayload, OpCode, UpdatePresencePayload,
+    PROTOCOL_VERSION,
+};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashSet;
 use std::rc::Rc;
-use yew::format::Text;
+use yew::format::{Binary, Text};
 use yew::services::websocket::{WebSocketStatus, WebSocketTask};
 use yew::services::WebSocketService;
 use yew::worker::*;
 
 #[derive(Serialize, Deserialize, Debug)]
 pub enum Request {
-    Connect(String),
+    Connect(String, Encoding),
     Authenticate(String),
+    /// Reports whether the user is idle
+    SetIdle(bool),
     Disconnect,
 }
 
@@ -25,6 +30,7 @@ pub enum Response {
 
 pub enum Message {
     Text(Text),
+    Binary(Binary),
     StatusNotification(WebSocketStatus),
 }
 
@@ -32,6 +38,7 @@ pub struct Connection {
     link: AgentLink<Self>,
     subscribers: HashSet<HandlerId>,
     task: Option<WebSocketTask>,
+    encoding: Encoding,
 }
 
 impl Agent for Connection {
@@ -45,24 +52,14 @@ impl Agent for Connection {
             link,
             task: None,
             subscribers: HashSet::new(),
+            encoding: Encoding::default(),
         }
     }
 
     fn update(&mut self, msg: Self::Message) {
         match msg {
-            Message::Text(msg) => {
-                let msg = match parse_message(msg) {
-                    Ok(msg) => msg,
-                    Err(e) => {
-                        let error = Rc::new(e);
-                        return self.send_to_all_subs(|| Response::Error(error.clone()));
-                    }
-                };
-
-                let msg = Rc::new(msg);
-
-                self.send_to_all_subs(move || Response::Message(msg.clone()));
-            }
+            Message::Text(msg) => self.receive(msg.map(String::into_bytes)),
+            Message::Binary(msg) => self.receive(msg),
             Message::StatusNotification(status) => match status {
                 WebSocketStatus::Opened => self.send_to_all_subs(|| Response::Connected),
                 WebSocketStatus::Error => self.send_to_all_subs(|| {
@@ -79,12 +76,21 @@ impl Agent for Connection {
 
     fn handle_input(&mut self, msg: Self::Input, _id: HandlerId) {
         match msg {
-            Request::Connect(url) => {
-                let task = WebSocketService::connect_text(
-                    &url,
-                    self.link.callback(Message::Text),
-                    self.link.callback(Message::StatusNotification),
-                )
+            Request::Connect(url, encoding) => {
+                let url = format!("{}?v={}&encoding={}", url, PROTOCOL_VERSION, encoding);
+                let notification = self.link.callback(Message::StatusNotification);
+                let task = match encoding {
+                    Encoding::Json => WebSocketService::connect_text(
+                        &url,
+                        self.link.callback(Message::Text),
+                        notification,
+                    ),
+                    Encoding::MessagePack => WebSocketService::connect_binary(
+                        &url,
+                        self.link.callback(Message::Binary),
+                        notification,
+                    ),
+                }
                 .map_err(anyhow::Error::from);
 
                 let task = match task {
@@ -96,7 +102,8 @@ impl Agent for Connection {
                     }
                 };
 
-                self.task = Some(task)
+                self.task = Some(task);
+                self.encoding = encoding;
             }
             Request::Authenticate(token) => {
                 weblog::console_log!("sending auth");
@@ -105,6 +112,14 @@ impl Agent for Connection {
                     data: AuthenticatePayload { token },
                 });
             }
+            Request::SetIdle(idle) => {
+                if self.task.is_some() {
+                    self.send_to_ws(&MessagePayload {
+                        op: OpCode::UpdatePresence,
+                        data: UpdatePresencePayload { idle },
+                    });
+                }
+            }
             Request::Disconnect => {
                 // TODO yew limitation
             }
@@ -123,20 +138,35 @@ impl Connection {
         }
     }
 
+    fn receive(&self, msg: Binary) {
+        let msg = match parse_message(self.encoding, msg) {
+            Ok(msg) => msg,
+            Err(e) => {
+                let error = Rc::new(e);
+                return self.send_to_all_subs(|| Response::Error(error.clone()));
+            }
+        };
+
+        let msg = Rc::new(msg);
+
+        self.send_to_all_subs(move || Response::Message(msg.clone()));
+    }
+
     fn send_to_ws<T>(&mut self, message: &MessagePayload<T>)
     where
         T: for<'de> Deserialize<'de> + Serialize,
     {
-        let payload = serde_json::to_string(&message).unwrap();
-        self.task
-            .as_mut()
-            .expect("no connection to websocket")
-            .send(Ok(payload))
+        let frame = self.encoding.encode(&message).unwrap();
+        let task = self.task.as_mut().expect("no connection to websocket");
+        match frame {
+            Frame::Text(text) => task.send(Ok(text)),
+            Frame::Binary(bytes) => task.send_binary(Ok(bytes)),
+        }
     }
 }
 
-fn parse_message(msg: Text) -> anyhow::Result<MessagePayload<Value>> {
+fn parse_message(encoding: Encoding, msg: Binary) -> anyhow::Result<MessagePayload<Value>> {
     let msg = msg?;
-    let payload = serde_json::from_str::<MessagePayload<Value>>(&msg)?;
+    let payload = encoding.decode::<MessagePayload<Value>>(&msg)?;
     Ok(payload)
 }
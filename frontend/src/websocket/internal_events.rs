@@ -1,4 +1,5 @@
-use common::Message;
+use common::websocket::PresenceUpdatePayload;
+use common::{Message, Report};
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 use std::rc::Rc;
@@ -7,11 +8,17 @@ use yew::worker::*;
 #[derive(Serialize, Deserialize, Debug)]
 pub enum Request {
     NewMessage(Message),
+    DeleteMessage(Message),
+    PresenceUpdate(PresenceUpdatePayload),
+    NewReport(Report),
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub enum Response {
     NewMessage(Rc<Message>),
+    DeleteMessage(Rc<Message>),
+    PresenceUpdate(Rc<PresenceUpdatePayload>),
+    NewReport(Rc<Report>),
 }
 
 pub struct InternalEventBus {
@@ -47,6 +54,26 @@ impl Agent for InternalEventBus {
                         .respond(*sub, Response::NewMessage(message.clone()));
                 }
             }
+            Request::DeleteMessage(message) => {
+                let message = Rc::new(message);
+                for sub in self.subscribers.iter() {
+                    self.link
+                        .respond(*sub, Response::DeleteMessage(message.clone()));
+                }
+            }
+            Request::PresenceUpdate(update) => {
+                let update = Rc::new(update);
+                for sub in self.subscribers.iter() {
+                    self.link
+                        .respond(*sub, Response::PresenceUpdate(update.clone()));
+                }
+            }
+            Request::NewReport(report) => {
+                let report = Rc::new(report);
+                for sub in self.subscribers.iter() {
+                    self.link.respond(*sub, Response::NewReport(report.clone()));
+                }
+            }
         }
     }
 